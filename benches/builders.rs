@@ -0,0 +1,36 @@
+// Building a string out of 100k pieces with a builder, and with `let s = cat(s, ...)`.
+//
+//     cargo bench --bench builders
+//
+// The cat version copies everything built so far on every append, and takes seconds where the
+// builder takes a fraction of one, so each is timed once here rather than the hundreds of times
+// the #[bench] harness would run it.
+
+use std::time::{Duration, Instant};
+use zac_lib::interp::Interpreter;
+use zac_lib::parser::parse_program;
+
+const APPENDS: usize = 100_000;
+
+fn time_program(body: &str, result: &str) -> Duration {
+    let source = format!(
+        "let s = \"\"\nlet b = builder()\nlet i = 0\nwhile (i < {}) {{\n  {}\n  let i = i + 1\n}}\nlen({})\n",
+        APPENDS, body, result
+    );
+    let program = parse_program(&source).unwrap();
+    let mut interp = Interpreter::new();
+    let start = Instant::now();
+    interp.run_program(&program).unwrap();
+    start.elapsed()
+}
+
+fn main() {
+    // cargo test builds and runs benches without --bench, and doesn't need to wait for this
+    if !std::env::args().any(|arg| arg == "--bench") {
+        return;
+    }
+    let builder = time_program("builder_push(b, \"piece\")", "builder_finish(b)");
+    println!("builder: {:?}", builder);
+    let cat = time_program("let s = cat(s, \"piece\")", "s");
+    println!("cat:     {:?}", cat);
+}
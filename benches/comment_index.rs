@@ -0,0 +1,41 @@
+// Listing the comments in a large file, from the index next to it and by parsing the whole thing.
+//
+//     cargo bench --bench comment_index
+
+#![feature(test)]
+
+extern crate test;
+
+use std::fs;
+use std::path::PathBuf;
+use test::Bencher;
+use zac_lib::comment_index::{build_index, index_path};
+use zac_lib::parser::load_or_build_index;
+
+// 20k lines, about a third of them named comments
+fn large_source() -> String {
+    (0..5_000)
+        .map(|i| format!("// #note{0}\n// what {0} is for\nlet x{0} = add({0}, 1)\n\n", i))
+        .collect()
+}
+
+fn large_file() -> PathBuf {
+    let path = std::env::temp_dir().join(format!("zac-bench-{}.zac", std::process::id()));
+    fs::write(&path, large_source()).unwrap();
+    let _ = fs::remove_file(index_path(&path));
+    path
+}
+
+#[bench]
+fn from_the_index(b: &mut Bencher) {
+    let path = large_file();
+    // the first one writes the index, and every one after that reads it
+    load_or_build_index(&path).unwrap();
+    b.iter(|| load_or_build_index(&path).unwrap());
+}
+
+#[bench]
+fn by_parsing(b: &mut Bencher) {
+    let source = large_source();
+    b.iter(|| build_index(&source).unwrap());
+}
@@ -0,0 +1,36 @@
+// Running a Zac program from Rust, with a function the Rust program provides. Prints the program
+// with its comments filled in:
+//
+//     cargo run --example host_functions
+
+use anyhow::bail;
+use std::collections::BTreeMap;
+use zac_lib::interp::{Interpreter, Value};
+use zac_lib::parser::parse_program;
+
+const PROGRAM: &str = r#"// #config
+// filled in from the host's settings
+
+let #config = cat("serving ", fetch_config("name"), " on port ", fetch_config("port"))
+"#;
+
+fn main() -> anyhow::Result<()> {
+    let settings = [("name", "zac"), ("port", "8080")]
+        .into_iter()
+        .collect::<BTreeMap<_, _>>();
+
+    let mut interp = Interpreter::new();
+    // fetch_config("name") is the setting's value, or null if there's no such setting
+    interp.register_fn("fetch_config", move |args| match args {
+        [Value::String(key)] => Ok(settings
+            .get(key.as_str())
+            .map_or(Value::Null, |value| Value::String(value.to_string()))),
+        _ => bail!("fetch_config takes the name of a setting"),
+    });
+
+    let program = parse_program(PROGRAM)?;
+    interp.set_source(PROGRAM);
+    interp.run_program(&program)?;
+    print!("{}", interp.writeback(&program)?);
+    Ok(())
+}
@@ -0,0 +1,38 @@
+// Any program, valid or not, should come back as a value or an error. Anything else (a panic, a
+// stack overflow, running out of memory) is a bug, and the input that found it goes in
+// tests/fuzz_regressions.rs.
+//
+//     cargo +nightly fuzz run parse_and_run fuzz/corpus/parse_and_run spec
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use zac_lib::interp::InterpreterBuilder;
+use zac_lib::parser::parse_program;
+use zac_lib::vfs::RealFs;
+
+// enough for the spec programs, but an endless loop stops quickly
+const FUEL: u64 = 20_000;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(source) = std::str::from_utf8(data) else {
+        return;
+    };
+    let Ok(program) = parse_program(source) else {
+        return;
+    };
+    // everything but the time and random builtins, so an input does the same thing every run
+    // and sleep can't slow it down
+    let mut interp = InterpreterBuilder::new()
+        .core_builtins()
+        .io_builtins()
+        .env_builtins()
+        .fs_builtins()
+        .build();
+    interp.set_fuel(Some(FUEL));
+    interp.set_output(Box::new(std::io::sink()));
+    interp.set_fs(Box::new(RealFs::new(false)));
+    interp.set_env(Box::new(|_| None));
+    interp.set_source(source);
+    let _ = interp.run_program(&program);
+});
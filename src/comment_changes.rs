@@ -0,0 +1,94 @@
+// A summary of the comments a run changed, and asking which of those changes to keep before
+// they're written back into the source file (`--confirm-writes`).
+
+use crate::interp::CommentMutation;
+use std::collections::BTreeSet;
+use std::io::{BufRead, Write};
+
+const PREVIEW_LEN: usize = 30;
+
+pub fn render_table(mutations: &[CommentMutation]) -> String {
+    let mut rows = vec![[
+        "comment".to_string(),
+        "writes".to_string(),
+        "before".to_string(),
+        "after".to_string(),
+    ]];
+    for mutation in mutations {
+        rows.push([
+            format!("#{}", mutation.name),
+            mutation.writes.to_string(),
+            preview(&mutation.old_body),
+            preview(&mutation.new_body),
+        ]);
+    }
+    let widths = (0..4)
+        .map(|i| {
+            rows.iter()
+                .map(|row| row[i].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect::<Vec<_>>();
+    let mut table = String::new();
+    for row in rows {
+        let line = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:width$}", cell, width = width))
+            .collect::<Vec<_>>()
+            .join("  ");
+        table.push_str(line.trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+// the first line, cut short
+fn preview(body: &str) -> String {
+    let first_line = body.lines().next().unwrap_or("");
+    if first_line.is_empty() {
+        return "(empty)".to_string();
+    }
+    let mut preview = first_line.chars().take(PREVIEW_LEN).collect::<String>();
+    if first_line.chars().count() > PREVIEW_LEN || body.lines().count() > 1 {
+        preview.push_str("...");
+    }
+    preview
+}
+
+// Asks about each change in turn, and returns the names of the comments whose changes should be
+// kept. Answering "a" keeps this change and all the ones after it. Running out of input keeps
+// nothing else, so a closed stdin never writes anything it wasn't told to.
+pub fn confirm_writes(
+    mutations: &[CommentMutation],
+    input: &mut dyn BufRead,
+    output: &mut dyn Write,
+) -> anyhow::Result<BTreeSet<String>> {
+    let mut accepted = BTreeSet::new();
+    let mut accept_all = false;
+    for mutation in mutations {
+        while !accept_all {
+            write!(output, "write #{}? [y]es/[n]o/[a]ll: ", mutation.name)?;
+            output.flush()?;
+            let mut answer = String::new();
+            if input.read_line(&mut answer)? == 0 {
+                writeln!(output)?;
+                return Ok(accepted);
+            }
+            match answer.trim() {
+                "y" | "yes" => {
+                    accepted.insert(mutation.name.clone());
+                    break;
+                }
+                "n" | "no" => break,
+                "a" | "all" => accept_all = true,
+                _ => continue,
+            }
+        }
+        if accept_all {
+            accepted.insert(mutation.name.clone());
+        }
+    }
+    Ok(accepted)
+}
@@ -0,0 +1,132 @@
+// A sidecar index of a file's named comments (`file.zac.idx`), so listing comments in a large
+// file doesn't need a full parse every time. The index remembers a hash of the source it was
+// built from, and it's rebuilt whenever that doesn't match, or when the index can't be read.
+// `zac comments file.zac` lists them this way, and benches/comment_index.rs compares it with
+// parsing the file.
+
+use crate::json::Json;
+use crate::parser::{find_comments_mut, parse_program, CommentStyle};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const INDEX_VERSION: i128 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommentIndex {
+    pub source_hash: u64,
+    pub comments: Vec<IndexedComment>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexedComment {
+    pub name: String,
+    pub style: CommentStyle,
+}
+
+pub fn index_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_owned();
+    file_name.push(".idx");
+    path.with_file_name(file_name)
+}
+
+pub fn load_or_build_index(path: impl AsRef<Path>) -> anyhow::Result<CommentIndex> {
+    let path = path.as_ref();
+    let source = fs::read_to_string(path)?;
+    let source_hash = hash_source(&source);
+    let index_path = index_path(path);
+    // a missing, corrupt or outdated index isn't an error, we just build a new one
+    if let Some(index) = fs::read_to_string(&index_path)
+        .ok()
+        .and_then(|s| decode_index(&s))
+    {
+        if index.source_hash == source_hash {
+            return Ok(index);
+        }
+    }
+    let index = build_index(&source)?;
+    let tmp_path = index_path.with_extension("idx.tmp");
+    fs::write(&tmp_path, encode_index(&index))?;
+    fs::rename(&tmp_path, &index_path)?;
+    Ok(index)
+}
+
+pub fn build_index(source: &str) -> anyhow::Result<CommentIndex> {
+    let mut program = parse_program(source)?;
+    let mut comments = find_comments_mut(&mut program)?
+        .into_iter()
+        .map(|(name, comment)| IndexedComment {
+            name,
+            style: comment.style,
+        })
+        .collect::<Vec<_>>();
+    comments.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(CommentIndex {
+        source_hash: hash_source(source),
+        comments,
+    })
+}
+
+// FNV-1a, because the hash has to stay the same across builds and Rust versions
+fn hash_source(source: &str) -> u64 {
+    source.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+fn encode_index(index: &CommentIndex) -> String {
+    Json::Object(vec![
+        ("version".into(), Json::Int(INDEX_VERSION)),
+        (
+            "hash".into(),
+            Json::String(format!("{:016x}", index.source_hash)),
+        ),
+        (
+            "comments".into(),
+            Json::Array(
+                index
+                    .comments
+                    .iter()
+                    .map(|comment| {
+                        Json::Object(vec![
+                            ("name".into(), Json::String(comment.name.clone())),
+                            ("style".into(), Json::String(comment.style.name().into())),
+                        ])
+                    })
+                    .collect(),
+            ),
+        ),
+    ])
+    .to_string()
+}
+
+fn decode_index(s: &str) -> Option<CommentIndex> {
+    let json = Json::parse(s).ok()?;
+    if json.get("version")? != &Json::Int(INDEX_VERSION) {
+        return None;
+    }
+    let source_hash = match json.get("hash")? {
+        Json::String(hash) => u64::from_str_radix(hash, 16).ok()?,
+        _ => return None,
+    };
+    let comments = match json.get("comments")? {
+        Json::Array(comments) => comments
+            .iter()
+            .map(|comment| {
+                let name = match comment.get("name")? {
+                    Json::String(name) => name.clone(),
+                    _ => return None,
+                };
+                let style = match comment.get("style")? {
+                    Json::String(style) => CommentStyle::from_name(style)?,
+                    _ => return None,
+                };
+                Some(IndexedComment { name, style })
+            })
+            .collect::<Option<Vec<_>>>()?,
+        _ => return None,
+    };
+    Some(CommentIndex {
+        source_hash,
+        comments,
+    })
+}
@@ -0,0 +1,216 @@
+// Stepping through a program, for debuggers built on the interpreter. Interpreter::debug gives
+// back a DebugSession straight away, before any of the program has run, and the program runs one
+// expression at a time as the session asks for steps. So a program that prints prints as it's
+// stepped through, and one that never ends can still be stepped through for as long as you like.
+//
+//     let mut session = interp.debug(&program);
+//     session.add_breakpoint(Breakpoint::Comment("config".into()));
+//     while let Some(step) = session.continue_to_breakpoint() {
+//         println!("line {:?}: {} with {:?}", step.line, step.source, session.scope());
+//     }
+//
+// Evaluating is recursive, and builtins like map call back into the interpreter, so there's no
+// one place to stop and pick up again from. Instead evaluating is written as futures, which the
+// compiler turns into continuations: before each expression the program waits on a Pause, which
+// isn't ready the first time it's polled, so polling the program returns with everything it was
+// in the middle of kept in the future, map's loop included. The next step polls it again, on the
+// same thread, and it carries on from there. Running a program normally polls the same futures
+// until they're done, and with no session there's nothing to pause at, so that's the first poll.
+// Nothing here needs a second thread, so host functions and hooks run on the thread that steps,
+// and debugging works the same in wasm.
+
+use crate::error::SoldierError;
+use crate::interp::{visible_vars, Interpreter, Scope, Value};
+use crate::parser::Span;
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt::Debug;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::rc::Rc;
+use std::task::{Context, Poll, Waker};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Breakpoint {
+    // a named comment, like the #config in `// #config`, reached as an expression
+    Comment(String),
+    // the first expression on this line each time the program gets to it, counting from 1. only
+    // works when the interpreter was given the source, see Interpreter::set_source
+    Line(usize),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepEvent {
+    // the expression's type from the program description format, see description.rs
+    pub expr: &'static str,
+    // the first line of the expression
+    pub source: String,
+    pub span: Option<Span>,
+    pub line: Option<usize>,
+    // the comment's name, when the expression is a named comment
+    pub comment: Option<String>,
+}
+
+// the program being debugged, run up to its next step each time it's called
+pub(crate) type Resume<'a> = Box<dyn FnMut() -> Poll<Result<Value, SoldierError>> + 'a>;
+
+pub struct DebugSession<'a> {
+    // dropping this, with the session, stops a program that hasn't finished
+    resume: Resume<'a>,
+    debuggee: Rc<Debuggee>,
+    // the line of the last step that was on one, since not every expression keeps its position
+    line: Option<usize>,
+    breakpoints: Vec<Breakpoint>,
+    // None until the program finishes
+    result: Option<Result<Value, SoldierError>>,
+    // the program shares the interpreter's variables, comments and output, so nothing else can
+    // use the interpreter until the session is over
+    interp: PhantomData<&'a mut Interpreter>,
+}
+
+impl Debug for DebugSession<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DebugSession")
+            .field("line", &self.line)
+            .field("breakpoints", &self.breakpoints)
+            .field("result", &self.result)
+            .finish()
+    }
+}
+
+impl DebugSession<'_> {
+    pub(crate) fn new(debuggee: Rc<Debuggee>, resume: Resume<'_>) -> DebugSession<'_> {
+        DebugSession {
+            resume,
+            debuggee,
+            line: None,
+            breakpoints: vec![],
+            result: None,
+            interp: PhantomData,
+        }
+    }
+
+    pub fn add_breakpoint(&mut self, breakpoint: Breakpoint) {
+        self.breakpoints.push(breakpoint);
+    }
+
+    // runs the program up to the next expression and gives it back without evaluating it, or
+    // gives back None when the program has finished
+    pub fn step(&mut self) -> Option<StepEvent> {
+        if self.result.is_some() {
+            return None;
+        }
+        match (self.resume)() {
+            Poll::Pending => {
+                let step = self.debuggee.paused.borrow_mut().take();
+                let step = step.expect("the program only stops before an expression");
+                self.line = step.line.or(self.line);
+                Some(step)
+            }
+            Poll::Ready(result) => {
+                self.result = Some(result);
+                None
+            }
+        }
+    }
+
+    // steps until a breakpoint, and gives back the step it stopped on, or None when the program
+    // ends without reaching another one
+    pub fn continue_to_breakpoint(&mut self) -> Option<StepEvent> {
+        loop {
+            // a line has several expressions on it, and only arriving there counts
+            let prev_line = self.line;
+            let step = self.step()?;
+            if self
+                .breakpoints
+                .iter()
+                .any(|bp| breaks_at(bp, &step, prev_line))
+            {
+                return Some(step);
+            }
+        }
+    }
+
+    // copies of the variables the next expression can see, except the builtins. after the program
+    // finishes there aren't any
+    pub fn scope(&self) -> BTreeMap<String, Value> {
+        if self.result.is_some() {
+            return BTreeMap::new();
+        }
+        visible_vars(&self.debuggee.scope.borrow(), &self.debuggee.builtins)
+    }
+
+    // what running the program gave back, once it's finished
+    pub fn result(&self) -> Option<&Result<Value, SoldierError>> {
+        self.result.as_ref()
+    }
+}
+
+fn breaks_at(breakpoint: &Breakpoint, step: &StepEvent, prev_line: Option<usize>) -> bool {
+    match breakpoint {
+        Breakpoint::Comment(name) => step.comment.as_ref() == Some(name),
+        Breakpoint::Line(line) => step.line == Some(*line) && prev_line != Some(*line),
+    }
+}
+
+// the program's end of a session, kept on the interpreter it runs with
+#[derive(Debug)]
+pub(crate) struct Debuggee {
+    // the expression the program has stopped before, until the session takes it
+    paused: RefCell<Option<StepEvent>>,
+    // the scope of the last expression it stopped before, or the global one before the first
+    scope: RefCell<Rc<RefCell<Scope>>>,
+    // the global scope before the program ran, so the builtins can be left out of scopes
+    builtins: BTreeMap<String, Value>,
+}
+
+impl Debuggee {
+    pub(crate) fn new(global: Rc<RefCell<Scope>>, builtins: BTreeMap<String, Value>) -> Self {
+        Self {
+            paused: RefCell::new(None),
+            scope: RefCell::new(global),
+            builtins,
+        }
+    }
+
+    // what the program waits on before evaluating the expression step is about, which gives
+    // control back to the session
+    pub(crate) fn pause(&self, step: StepEvent, scope: &Rc<RefCell<Scope>>) -> Pause {
+        *self.paused.borrow_mut() = Some(step);
+        *self.scope.borrow_mut() = Rc::clone(scope);
+        Pause(false)
+    }
+}
+
+// not ready the first time it's polled, and ready the next
+pub(crate) struct Pause(bool);
+
+impl Future for Pause {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            Poll::Ready(())
+        } else {
+            self.0 = true;
+            Poll::Pending
+        }
+    }
+}
+
+// nothing the interpreter waits on needs waking, it's only ever waiting for the next poll
+pub(crate) fn poll<F: Future + ?Sized>(future: Pin<&mut F>) -> Poll<F::Output> {
+    future.poll(&mut Context::from_waker(Waker::noop()))
+}
+
+// polls until the future is done. outside of a debug session nothing pauses, so that's the first
+// poll, and inside one, whatever's run this way goes straight through without stopping
+pub(crate) fn run_to_end<F: Future>(future: F) -> F::Output {
+    let mut future = Box::pin(future);
+    loop {
+        if let Poll::Ready(output) = poll(future.as_mut()) {
+            return output;
+        }
+    }
+}
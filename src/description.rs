@@ -0,0 +1,680 @@
+// The "program description" format: a JSON mirror of the AST for tools that generate Zac
+// programs, so they don't have to produce (and escape) Zac source text.
+//
+// Version 1 looks like this:
+//
+//   {"version": 1, "block": [<expr>, ...]}
+//
+// where every <expr> is an object tagged by "type":
+//
+//   {"type": "block", "body": [<expr>, ...]}
+//   {"type": "var", "name": "x"}
+//   {"type": "comment_ref", "name": "notes"}
+//   {"type": "comment", "name": "notes" | null, "body": "...",
+//    "style": "line" | "block" | "trailing"}
+//    ("style" is optional and defaults to "line")
+//   {"type": "assign", "target": <var or comment_ref>, "value": <expr>}
+//   {"type": "index_assign", "target": <var or comment_ref>, "index": <expr>, "value": <expr>}
+//   {"type": "destructure", "pattern": {"type": "map" | "list", "names": ["a", ...]},
+//    "value": <expr>}
+//    (a "_" name in a list pattern skips that item)
+//   {"type": "int", "value": 5}
+//   {"type": "string", "value": "..."}
+//   {"type": "multi_line_string", "value": "..."}
+//   {"type": "interpolation", "parts": ["literal text", <expr>, ...]}
+//   {"type": "list", "items": [<expr>, ...]}
+//   {"type": "defn", "name": "f", "args": ["a", ...], "body": [<expr>, ...]}
+//   {"type": "call", "target": <var or comment_ref>, "args": [<expr>, ...]}
+//   {"type": "field", "expr": <expr>, "field": "name"}
+//   {"type": "while" | "do_while" | "if", "cond": <expr>, "body": [<expr>, ...]}
+//    ("do_while" is `do { ... } while (cond)`, which checks cond after running the body)
+//   {"type": "loop", "body": [<expr>, ...]}
+//   {"type": "bin_op", "op": "+", "lhs": <expr>, "rhs": <expr>}
+//   {"type": "result_comment", "expr": <expr>}
+//   {"type": "trailing_comment", "expr": <expr>, "comment": <comment>}
+//
+// Blank lines aren't part of the description: each expression of a block goes on its own line.
+
+use crate::json::Json;
+use crate::parser::{
+    next_id, Assignment, BinOp, Block, BlockEl, Comment, CommentStyle, Destructure, Expr,
+    FieldAccess, FuncDef, FunctionCall, If, IndexAssignment, InterpolationPart, Op, Pattern,
+    Program, Ref, Span, While,
+};
+use std::fmt::{Display, Formatter};
+
+pub const DESCRIPTION_VERSION: i128 = 1;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DescError {
+    // JSON path of the offending value, like `$.block[2].cond`
+    pub path: String,
+    pub message: String,
+}
+
+impl Display for DescError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+impl std::error::Error for DescError {}
+
+fn desc_err<T>(path: &str, message: impl Into<String>) -> Result<T, DescError> {
+    Err(DescError {
+        path: path.to_owned(),
+        message: message.into(),
+    })
+}
+
+pub fn from_description(json: &str) -> Result<Program, DescError> {
+    let json = Json::parse(json).or_else(|e| desc_err("$", format!("invalid JSON: {}", e)))?;
+    program_from_json(&json)
+}
+
+// from_description, for tools that think of the description as the AST in JSON. a program they
+// hand back goes through reassemble::output_code like any other
+pub fn parse_json(json: &str) -> Result<Program, DescError> {
+    from_description(json)
+}
+
+fn program_from_json(json: &Json) -> Result<Program, DescError> {
+    match field(json, "$", "version")? {
+        Json::Int(DESCRIPTION_VERSION) => {}
+        Json::Int(n) => return desc_err("$.version", format!("unsupported version {}", n)),
+        otherwise => return desc_err("$.version", expected("a number", otherwise)),
+    }
+    Ok(Program {
+        block: block_from_json(field(json, "$", "block")?, "$.block")?,
+    })
+}
+
+pub fn to_description(program: &Program) -> String {
+    program_to_json(program).to_string()
+}
+
+fn program_to_json(program: &Program) -> Json {
+    Json::Object(vec![
+        ("version".into(), Json::Int(DESCRIPTION_VERSION)),
+        ("block".into(), block_to_json(&program.block)),
+    ])
+}
+
+fn expected(what: &str, got: &Json) -> String {
+    format!("expected {}, got {}", what, got.type_name())
+}
+
+fn field<'a>(json: &'a Json, path: &str, name: &str) -> Result<&'a Json, DescError> {
+    match json {
+        Json::Object(_) => json
+            .get(name)
+            .map_or_else(|| desc_err(path, format!("missing field {:?}", name)), Ok),
+        otherwise => desc_err(path, expected("an object", otherwise)),
+    }
+}
+
+fn str_field<'a>(json: &'a Json, path: &str, name: &str) -> Result<&'a str, DescError> {
+    match field(json, path, name)? {
+        Json::String(s) => Ok(s),
+        otherwise => desc_err(
+            &format!("{}.{}", path, name),
+            expected("a string", otherwise),
+        ),
+    }
+}
+
+fn array_field<'a>(json: &'a Json, path: &str, name: &str) -> Result<&'a [Json], DescError> {
+    match field(json, path, name)? {
+        Json::Array(items) => Ok(items),
+        otherwise => desc_err(
+            &format!("{}.{}", path, name),
+            expected("an array", otherwise),
+        ),
+    }
+}
+
+fn names_field(json: &Json, path: &str, name: &str) -> Result<Vec<String>, DescError> {
+    array_field(json, path, name)?
+        .iter()
+        .enumerate()
+        .map(|(i, item)| match item {
+            Json::String(s) => Ok(s.clone()),
+            otherwise => desc_err(
+                &format!("{}.{}[{}]", path, name, i),
+                expected("a string", otherwise),
+            ),
+        })
+        .collect()
+}
+
+fn exprs_field(json: &Json, path: &str, name: &str) -> Result<Vec<Expr>, DescError> {
+    array_field(json, path, name)?
+        .iter()
+        .enumerate()
+        .map(|(i, item)| expr_from_json(item, &format!("{}.{}[{}]", path, name, i)))
+        .collect()
+}
+
+fn expr_field(json: &Json, path: &str, name: &str) -> Result<Box<Expr>, DescError> {
+    Ok(Box::new(expr_from_json(
+        field(json, path, name)?,
+        &format!("{}.{}", path, name),
+    )?))
+}
+
+fn block_from_exprs(exprs: Vec<Expr>) -> Block {
+    Block(
+        exprs
+            .into_iter()
+            .flat_map(|expr| [BlockEl::Expr(expr), BlockEl::NewLine])
+            .collect(),
+    )
+}
+
+fn block_from_json(json: &Json, path: &str) -> Result<Block, DescError> {
+    match json {
+        Json::Array(items) => Ok(block_from_exprs(
+            items
+                .iter()
+                .enumerate()
+                .map(|(i, item)| expr_from_json(item, &format!("{}[{}]", path, i)))
+                .collect::<Result<_, _>>()?,
+        )),
+        otherwise => desc_err(path, expected("an array", otherwise)),
+    }
+}
+
+fn ref_from_json(json: &Json, path: &str) -> Result<Ref, DescError> {
+    match str_field(json, path, "type")? {
+        "var" => Ok(Ref::VarRef(
+            str_field(json, path, "name")?.into(),
+            Span::default(),
+        )),
+        "comment_ref" => Ok(Ref::CommentRef(
+            str_field(json, path, "name")?.into(),
+            Span::default(),
+        )),
+        otherwise => desc_err(
+            &format!("{}.type", path),
+            format!("expected \"var\" or \"comment_ref\", got {:?}", otherwise),
+        ),
+    }
+}
+
+fn op_from_str(op: &str) -> Option<Op> {
+    Some(match op {
+        "+" => Op::Add,
+        "-" => Op::Sub,
+        "*" => Op::Mul,
+        "/" => Op::Div,
+        "==" => Op::Eq,
+        "!=" => Op::Neq,
+        "<" => Op::Lt,
+        ">" => Op::Gt,
+        "<=" => Op::Lte,
+        ">=" => Op::Gte,
+        "&&" => Op::And,
+        "||" => Op::Or,
+        _ => return None,
+    })
+}
+
+fn op_to_str(op: Op) -> &'static str {
+    match op {
+        Op::Add => "+",
+        Op::Sub => "-",
+        Op::Mul => "*",
+        Op::Div => "/",
+        Op::Eq => "==",
+        Op::Neq => "!=",
+        Op::Lt => "<",
+        Op::Gt => ">",
+        Op::Lte => "<=",
+        Op::Gte => ">=",
+        Op::And => "&&",
+        Op::Or => "||",
+    }
+}
+
+fn expr_from_json(json: &Json, path: &str) -> Result<Expr, DescError> {
+    let typ = str_field(json, path, "type")?;
+    Ok(match typ {
+        "block" => Expr::Block(block_from_exprs(exprs_field(json, path, "body")?)),
+        "var" | "comment_ref" => Expr::Ref(ref_from_json(json, path)?),
+        "comment" => Expr::Comment(Comment {
+            name: match field(json, path, "name")? {
+                Json::Null => None,
+                Json::String(name) => Some(name.clone()),
+                otherwise => {
+                    return desc_err(
+                        &format!("{}.name", path),
+                        expected("a string or null", otherwise),
+                    )
+                }
+            },
+            body: str_field(json, path, "body")?.into(),
+            style: match json.get("style") {
+                None => CommentStyle::Line,
+                Some(Json::String(style)) => CommentStyle::from_name(style).map_or_else(
+                    || {
+                        desc_err(
+                            &format!("{}.style", path),
+                            format!("unknown style {:?}", style),
+                        )
+                    },
+                    Ok,
+                )?,
+                Some(otherwise) => {
+                    return desc_err(&format!("{}.style", path), expected("a string", otherwise))
+                }
+            },
+            span: Span::default(),
+        }),
+        "assign" => Expr::Assignment(Assignment {
+            r#ref: ref_from_json(field(json, path, "target")?, &format!("{}.target", path))?,
+            expr: expr_field(json, path, "value")?,
+            span: Span::default(),
+        }),
+        "index_assign" => Expr::IndexAssignment(IndexAssignment {
+            r#ref: ref_from_json(field(json, path, "target")?, &format!("{}.target", path))?,
+            index: expr_field(json, path, "index")?,
+            expr: expr_field(json, path, "value")?,
+        }),
+        "destructure" => {
+            let pattern_path = format!("{}.pattern", path);
+            let pattern = field(json, path, "pattern")?;
+            let names = names_field(pattern, &pattern_path, "names")?;
+            Expr::Destructure(Destructure {
+                pattern: match str_field(pattern, &pattern_path, "type")? {
+                    "map" => Pattern::Map(names),
+                    "list" => Pattern::List(names),
+                    otherwise => {
+                        return desc_err(
+                            &format!("{}.type", pattern_path),
+                            format!("expected \"map\" or \"list\", got {:?}", otherwise),
+                        )
+                    }
+                },
+                expr: expr_field(json, path, "value")?,
+                span: Span::default(),
+            })
+        }
+        "int" => match field(json, path, "value")? {
+            Json::Int(n) => Expr::IntLiteral(*n, Span::default()),
+            otherwise => {
+                return desc_err(&format!("{}.value", path), expected("a number", otherwise))
+            }
+        },
+        "string" => Expr::StringLiteral(str_field(json, path, "value")?.into(), Span::default()),
+        "multi_line_string" => {
+            Expr::MultiLineString(str_field(json, path, "value")?.into(), Span::default())
+        }
+        "interpolation" => Expr::Interpolation(
+            array_field(json, path, "parts")?
+                .iter()
+                .enumerate()
+                .map(|(i, part)| match part {
+                    Json::String(s) => Ok(InterpolationPart::Literal(s.clone())),
+                    part => Ok(InterpolationPart::Expr(expr_from_json(
+                        part,
+                        &format!("{}.parts[{}]", path, i),
+                    )?)),
+                })
+                .collect::<Result<_, _>>()?,
+            Span::default(),
+        ),
+        "list" => Expr::ListLiteral(exprs_field(json, path, "items")?, Span::default()),
+        "defn" => Expr::FuncDef(FuncDef {
+            name: str_field(json, path, "name")?.into(),
+            arg_names: names_field(json, path, "args")?,
+            block: block_from_exprs(exprs_field(json, path, "body")?),
+            span: Span::default(),
+        }),
+        "call" => Expr::FunctionCall(FunctionCall {
+            r#ref: ref_from_json(field(json, path, "target")?, &format!("{}.target", path))?,
+            args: exprs_field(json, path, "args")?,
+            span: Span::default(),
+        }),
+        "field" => Expr::FieldAccess(FieldAccess {
+            expr: expr_field(json, path, "expr")?,
+            field: str_field(json, path, "field")?.into(),
+            span: Span::default(),
+        }),
+        "while" => Expr::While(While {
+            cond: expr_field(json, path, "cond")?,
+            block: block_from_exprs(exprs_field(json, path, "body")?),
+            check_first: true,
+            span: Span::default(),
+        }),
+        "do_while" => Expr::While(While {
+            cond: expr_field(json, path, "cond")?,
+            block: block_from_exprs(exprs_field(json, path, "body")?),
+            check_first: false,
+            span: Span::default(),
+        }),
+        "loop" => Expr::Loop(
+            block_from_exprs(exprs_field(json, path, "body")?),
+            Span::default(),
+        ),
+        "if" => Expr::If(If {
+            cond: expr_field(json, path, "cond")?,
+            block: block_from_exprs(exprs_field(json, path, "body")?),
+            span: Span::default(),
+        }),
+        "bin_op" => {
+            let op = str_field(json, path, "op")?;
+            Expr::BinOp(BinOp {
+                op: op_from_str(op).map_or_else(
+                    || {
+                        desc_err(
+                            &format!("{}.op", path),
+                            format!("unknown operator {:?}", op),
+                        )
+                    },
+                    Ok,
+                )?,
+                lhs: expr_field(json, path, "lhs")?,
+                rhs: expr_field(json, path, "rhs")?,
+                span: Span::default(),
+            })
+        }
+        "result_comment" => {
+            Expr::ResultComment(next_id(), expr_field(json, path, "expr")?, Span::default())
+        }
+        "trailing_comment" => {
+            let comment_path = format!("{}.comment", path);
+            match expr_from_json(field(json, path, "comment")?, &comment_path)? {
+                Expr::Comment(comment) => {
+                    Expr::TrailingComment(expr_field(json, path, "expr")?, comment)
+                }
+                _ => return desc_err(&comment_path, "expected a comment"),
+            }
+        }
+        otherwise => {
+            return desc_err(
+                &format!("{}.type", path),
+                format!("unknown expression type {:?}", otherwise),
+            )
+        }
+    })
+}
+
+fn tagged(typ: &str, fields: Vec<(&str, Json)>) -> Json {
+    Json::Object(
+        [("type".to_owned(), Json::String(typ.into()))]
+            .into_iter()
+            .chain(fields.into_iter().map(|(k, v)| (k.to_owned(), v)))
+            .collect(),
+    )
+}
+
+// the "type" an expression is tagged with
+pub(crate) fn expr_type(expr: &Expr) -> &'static str {
+    match expr {
+        Expr::Block(_) => "block",
+        Expr::Ref(Ref::VarRef(..)) => "var",
+        Expr::Ref(Ref::CommentRef(..)) => "comment_ref",
+        Expr::Comment(_) => "comment",
+        Expr::Assignment(_) => "assign",
+        Expr::IndexAssignment(_) => "index_assign",
+        Expr::Destructure(_) => "destructure",
+        Expr::IntLiteral(..) => "int",
+        Expr::StringLiteral(..) => "string",
+        Expr::MultiLineString(..) => "multi_line_string",
+        Expr::Interpolation(..) => "interpolation",
+        Expr::ListLiteral(..) => "list",
+        Expr::FuncDef(_) => "defn",
+        Expr::FunctionCall(_) => "call",
+        Expr::FieldAccess(_) => "field",
+        Expr::While(While {
+            check_first: true, ..
+        }) => "while",
+        Expr::While(_) => "do_while",
+        Expr::Loop(..) => "loop",
+        Expr::If(_) => "if",
+        Expr::BinOp(_) => "bin_op",
+        Expr::ResultComment(..) => "result_comment",
+        Expr::TrailingComment(_, _) => "trailing_comment",
+    }
+}
+
+fn strings_to_json(strings: &[String]) -> Json {
+    Json::Array(strings.iter().map(|s| Json::String(s.clone())).collect())
+}
+
+fn exprs_to_json<'a>(exprs: impl Iterator<Item = &'a Expr>) -> Json {
+    Json::Array(exprs.map(expr_to_json).collect())
+}
+
+fn block_to_json(block: &Block) -> Json {
+    exprs_to_json(block.exprs())
+}
+
+fn ref_to_json(r#ref: &Ref) -> Json {
+    match r#ref {
+        Ref::VarRef(name, _) => tagged("var", vec![("name", Json::String(name.clone()))]),
+        Ref::CommentRef(name, _) => {
+            tagged("comment_ref", vec![("name", Json::String(name.clone()))])
+        }
+    }
+}
+
+fn expr_to_json(expr: &Expr) -> Json {
+    match expr {
+        Expr::Block(block) => tagged("block", vec![("body", block_to_json(block))]),
+        Expr::Ref(r#ref) => ref_to_json(r#ref),
+        Expr::Comment(Comment {
+            name, body, style, ..
+        }) => tagged(
+            "comment",
+            vec![
+                ("name", name.clone().map_or(Json::Null, Json::String)),
+                ("body", Json::String(body.clone())),
+                ("style", Json::String(style.name().into())),
+            ],
+        ),
+        Expr::Assignment(Assignment { r#ref, expr, .. }) => tagged(
+            "assign",
+            vec![
+                ("target", ref_to_json(r#ref)),
+                ("value", expr_to_json(expr)),
+            ],
+        ),
+        Expr::IndexAssignment(IndexAssignment { r#ref, index, expr }) => tagged(
+            "index_assign",
+            vec![
+                ("target", ref_to_json(r#ref)),
+                ("index", expr_to_json(index)),
+                ("value", expr_to_json(expr)),
+            ],
+        ),
+        Expr::Destructure(Destructure { pattern, expr, .. }) => {
+            let (typ, names) = match pattern {
+                Pattern::Map(names) => ("map", names),
+                Pattern::List(names) => ("list", names),
+            };
+            tagged(
+                "destructure",
+                vec![
+                    (
+                        "pattern",
+                        tagged(typ, vec![("names", strings_to_json(names))]),
+                    ),
+                    ("value", expr_to_json(expr)),
+                ],
+            )
+        }
+        Expr::IntLiteral(n, _) => tagged("int", vec![("value", Json::Int(*n))]),
+        Expr::StringLiteral(s, _) => tagged("string", vec![("value", Json::String(s.clone()))]),
+        Expr::MultiLineString(s, _) => tagged(
+            "multi_line_string",
+            vec![("value", Json::String(s.clone()))],
+        ),
+        Expr::Interpolation(parts, _) => tagged(
+            "interpolation",
+            vec![(
+                "parts",
+                Json::Array(
+                    parts
+                        .iter()
+                        .map(|part| match part {
+                            InterpolationPart::Literal(s) => Json::String(s.clone()),
+                            InterpolationPart::Expr(expr) => expr_to_json(expr),
+                        })
+                        .collect(),
+                ),
+            )],
+        ),
+        Expr::ListLiteral(items, _) => tagged("list", vec![("items", exprs_to_json(items.iter()))]),
+        Expr::FuncDef(FuncDef {
+            name,
+            arg_names,
+            block,
+            ..
+        }) => tagged(
+            "defn",
+            vec![
+                ("name", Json::String(name.clone())),
+                ("args", strings_to_json(arg_names)),
+                ("body", block_to_json(block)),
+            ],
+        ),
+        Expr::FunctionCall(FunctionCall { r#ref, args, .. }) => tagged(
+            "call",
+            vec![
+                ("target", ref_to_json(r#ref)),
+                ("args", exprs_to_json(args.iter())),
+            ],
+        ),
+        Expr::FieldAccess(FieldAccess { expr, field, .. }) => tagged(
+            "field",
+            vec![
+                ("expr", expr_to_json(expr)),
+                ("field", Json::String(field.clone())),
+            ],
+        ),
+        Expr::While(While {
+            cond,
+            block,
+            check_first,
+            ..
+        }) => tagged(
+            if *check_first { "while" } else { "do_while" },
+            vec![("cond", expr_to_json(cond)), ("body", block_to_json(block))],
+        ),
+        Expr::Loop(block, _) => tagged("loop", vec![("body", block_to_json(block))]),
+        Expr::If(If { cond, block, .. }) => tagged(
+            "if",
+            vec![("cond", expr_to_json(cond)), ("body", block_to_json(block))],
+        ),
+        Expr::BinOp(BinOp { op, lhs, rhs, .. }) => tagged(
+            "bin_op",
+            vec![
+                ("op", Json::String(op_to_str(*op).into())),
+                ("lhs", expr_to_json(lhs)),
+                ("rhs", expr_to_json(rhs)),
+            ],
+        ),
+        Expr::ResultComment(_, expr, _) => {
+            tagged("result_comment", vec![("expr", expr_to_json(expr))])
+        }
+        Expr::TrailingComment(expr, comment) => tagged(
+            "trailing_comment",
+            vec![
+                ("expr", expr_to_json(expr)),
+                ("comment", expr_to_json(&Expr::Comment(comment.clone()))),
+            ],
+        ),
+    }
+}
+
+// With the serde feature, the AST serializes to the description format too: a Program is the
+// whole {"version": 1, "block": [...]} object, a Block is the array of its expressions, and an
+// Expr, or a part of one like an Assignment, is the object tagged by "type" it would be inside a
+// block. Positions aren't kept, the same as from_description.
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use super::*;
+    use crate::json::Json;
+    use serde::de::Error;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    impl Serialize for Program {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            program_to_json(self).serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Program {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            program_from_json(&Json::deserialize(deserializer)?).map_err(D::Error::custom)
+        }
+    }
+
+    impl Serialize for Block {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            block_to_json(self).serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Block {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            block_from_json(&Json::deserialize(deserializer)?, "$").map_err(D::Error::custom)
+        }
+    }
+
+    impl Serialize for Expr {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            expr_to_json(self).serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Expr {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            expr_from_json(&Json::deserialize(deserializer)?, "$").map_err(D::Error::custom)
+        }
+    }
+
+    impl Serialize for Ref {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            ref_to_json(self).serialize(serializer)
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Ref {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            ref_from_json(&Json::deserialize(deserializer)?, "$").map_err(D::Error::custom)
+        }
+    }
+
+    // the parts of an expression serialize as the expression they'd be
+    macro_rules! serde_as_expr {
+        ($($part:ident => $variant:ident,)*) => {$(
+            impl Serialize for $part {
+                fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                    expr_to_json(&Expr::$variant(self.clone())).serialize(serializer)
+                }
+            }
+
+            impl<'de> Deserialize<'de> for $part {
+                fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                    match Expr::deserialize(deserializer)? {
+                        Expr::$variant(part) => Ok(part),
+                        otherwise => Err(D::Error::custom(format!(
+                            "$.type: expected a {} expression, got {:?}",
+                            stringify!($part),
+                            expr_type(&otherwise)
+                        ))),
+                    }
+                }
+            }
+        )*};
+    }
+
+    serde_as_expr! {
+        Comment => Comment,
+        Assignment => Assignment,
+        FunctionCall => FunctionCall,
+        While => While,
+    }
+}
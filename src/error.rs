@@ -0,0 +1,262 @@
+// The ways running a program can fail, for code embedding Zac that needs to tell them apart.
+// Builtins still return anyhow errors: one made from a SoldierError turns back into the same
+// variant when it comes out of interp, and anything else becomes Other. Display doesn't say where
+// an error happened, render_error does.
+
+use crate::parser;
+use crate::parser::{Op, ParseDiagnostic, Span};
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug)]
+pub enum SoldierError {
+    Parse(ParseDiagnostic),
+    UndefinedName {
+        name: String,
+        span: Span,
+    },
+    UndefinedComment {
+        name: String,
+        span: Span,
+    },
+    DuplicateComment {
+        name: String,
+        span: Option<Span>,
+    },
+    // expected is a description like "an integer", and got is the {:?} of the value
+    TypeMismatch {
+        expected: String,
+        got: String,
+        span: Option<Span>,
+    },
+    // an operator used on values it doesn't work for, like 1 + "a"
+    BadOperands {
+        op: Op,
+        lhs: String,
+        rhs: String,
+        span: Option<Span>,
+    },
+    NotCallable {
+        got: String,
+        span: Span,
+    },
+    Arity {
+        needed: usize,
+        got: usize,
+        span: Option<Span>,
+    },
+    // name is the builtin that was called, like div or mod
+    DivisionByZero {
+        name: String,
+        dividend: i128,
+        span: Option<Span>,
+    },
+    // call is the call that overflowed, like mul(2, 3)
+    IntegerOverflow {
+        call: String,
+        span: Option<Span>,
+    },
+    // used is how much fuel the program burned before it ran out, see Interpreter::set_fuel
+    FuelExhausted {
+        used: u64,
+        span: Option<Span>,
+    },
+    // expressions were nested more than max deep, see Interpreter::set_max_depth
+    MaxDepthExceeded {
+        max: usize,
+        span: Option<Span>,
+    },
+    // an assert or assert_eq that didn't hold. message is the one given to assert, or what the
+    // two sides of an assert_eq were
+    AssertionFailed {
+        message: Option<String>,
+        span: Option<Span>,
+    },
+    Other {
+        error: anyhow::Error,
+        span: Option<Span>,
+    },
+    // not a failure as such: the program called exit(code), which stops it where it is
+    Exited(i32),
+}
+
+impl SoldierError {
+    // where in the source the error happened. errors from a builtin get the span of the call
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            SoldierError::Parse(_) | SoldierError::Exited(_) => None,
+            SoldierError::UndefinedName { span, .. }
+            | SoldierError::UndefinedComment { span, .. }
+            | SoldierError::NotCallable { span, .. } => Some(*span),
+            SoldierError::DuplicateComment { span, .. }
+            | SoldierError::TypeMismatch { span, .. }
+            | SoldierError::BadOperands { span, .. }
+            | SoldierError::Arity { span, .. }
+            | SoldierError::DivisionByZero { span, .. }
+            | SoldierError::IntegerOverflow { span, .. }
+            | SoldierError::FuelExhausted { span, .. }
+            | SoldierError::MaxDepthExceeded { span, .. }
+            | SoldierError::AssertionFailed { span, .. }
+            | SoldierError::Other { span, .. } => *span,
+        }
+    }
+
+    // errors are made without a span wherever there isn't one to hand, and get the span of the
+    // innermost expression that has one on the way out
+    pub(crate) fn or_span(mut self, new_span: Span) -> Self {
+        match &mut self {
+            SoldierError::DuplicateComment { span, .. }
+            | SoldierError::TypeMismatch { span, .. }
+            | SoldierError::BadOperands { span, .. }
+            | SoldierError::Arity { span, .. }
+            | SoldierError::DivisionByZero { span, .. }
+            | SoldierError::IntegerOverflow { span, .. }
+            | SoldierError::FuelExhausted { span, .. }
+            | SoldierError::MaxDepthExceeded { span, .. }
+            | SoldierError::AssertionFailed { span, .. }
+            | SoldierError::Other { span, .. } => {
+                span.get_or_insert(new_span);
+            }
+            SoldierError::Parse(_)
+            | SoldierError::UndefinedName { .. }
+            | SoldierError::UndefinedComment { .. }
+            | SoldierError::NotCallable { .. }
+            | SoldierError::Exited(_) => (),
+        }
+        self
+    }
+
+    // for an error that happened in other source than the program's, like code given to eval,
+    // so that it gets the span of where it came out into the program instead
+    pub(crate) fn without_span(mut self) -> Self {
+        match &mut self {
+            SoldierError::DuplicateComment { span, .. }
+            | SoldierError::TypeMismatch { span, .. }
+            | SoldierError::BadOperands { span, .. }
+            | SoldierError::Arity { span, .. }
+            | SoldierError::DivisionByZero { span, .. }
+            | SoldierError::IntegerOverflow { span, .. }
+            | SoldierError::FuelExhausted { span, .. }
+            | SoldierError::MaxDepthExceeded { span, .. }
+            | SoldierError::AssertionFailed { span, .. }
+            | SoldierError::Other { span, .. } => *span = None,
+            SoldierError::Parse(_)
+            | SoldierError::UndefinedName { .. }
+            | SoldierError::UndefinedComment { .. }
+            | SoldierError::NotCallable { .. }
+            | SoldierError::Exited(_) => (),
+        }
+        self
+    }
+}
+
+impl Display for SoldierError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SoldierError::Parse(diagnostic) => write!(f, "{}", diagnostic),
+            SoldierError::UndefinedName { name, .. } => write!(f, "undefined name {}", name),
+            SoldierError::UndefinedComment { name, .. } => write!(f, "undefined comment {}", name),
+            SoldierError::DuplicateComment { name, .. } => write!(f, "duplicate comment: {}", name),
+            SoldierError::TypeMismatch { expected, got, .. } => {
+                write!(f, "{} is not {}", got, expected)
+            }
+            SoldierError::BadOperands { op, lhs, rhs, .. } => match op {
+                Op::Add => write!(f, "can't add {} and {}", lhs, rhs),
+                Op::Sub => write!(f, "can't subtract {} and {}", lhs, rhs),
+                Op::Mul => write!(f, "can't multiply {} and {}", lhs, rhs),
+                Op::Div => write!(f, "can't divide {} and {}", lhs, rhs),
+                Op::Gte => write!(f, "can't compare {} >= {}", lhs, rhs),
+                Op::Gt => write!(f, "can't compare {} > {}", lhs, rhs),
+                Op::Lte => write!(f, "can't compare {} <= {}", lhs, rhs),
+                Op::Lt => write!(f, "can't compare {} < {}", lhs, rhs),
+                Op::And | Op::Or | Op::Eq | Op::Neq => {
+                    write!(f, "can't use {:?} on {} and {}", op, lhs, rhs)
+                }
+            },
+            SoldierError::NotCallable { got, .. } => write!(f, "tried to call a {}", got),
+            SoldierError::Arity { needed, got, .. } => write!(
+                f,
+                "not enough arguments, needed {} but only {} were provided",
+                needed, got
+            ),
+            SoldierError::DivisionByZero { name, dividend, .. } => write!(
+                f,
+                "can't take {}({}, 0), the divisor can't be zero",
+                name, dividend
+            ),
+            SoldierError::IntegerOverflow { call, .. } => write!(f, "integer overflow in {}", call),
+            SoldierError::FuelExhausted { used, .. } => {
+                write!(f, "ran out of fuel after {} steps", used)
+            }
+            SoldierError::MaxDepthExceeded { max, .. } => {
+                write!(f, "expressions are nested more than {} deep", max)
+            }
+            SoldierError::AssertionFailed { message, .. } => match message {
+                Some(message) => write!(f, "assertion failed: {}", message),
+                None => write!(f, "assertion failed"),
+            },
+            SoldierError::Other { error, .. } => write!(f, "{}", error),
+            SoldierError::Exited(code) => write!(f, "exited with code {}", code),
+        }
+    }
+}
+
+impl std::error::Error for SoldierError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SoldierError::Other { error, .. } => error.source(),
+            _ => None,
+        }
+    }
+}
+
+impl From<ParseDiagnostic> for SoldierError {
+    fn from(diagnostic: ParseDiagnostic) -> Self {
+        SoldierError::Parse(diagnostic)
+    }
+}
+
+impl From<anyhow::Error> for SoldierError {
+    fn from(err: anyhow::Error) -> Self {
+        match err.downcast::<SoldierError>() {
+            Ok(err) => err,
+            Err(err) => match err.downcast::<ParseDiagnostic>() {
+                Ok(diagnostic) => SoldierError::Parse(diagnostic),
+                Err(error) => SoldierError::Other { error, span: None },
+            },
+        }
+    }
+}
+
+// The error with the line it happened on, the same way parse errors are shown:
+//
+// line 5, col 1: tried to call a Int(3)
+//   x(2)
+//   ^^^^
+pub fn render_error(source: &str, err: &SoldierError) -> String {
+    let span = match err.span() {
+        Some(span) => span,
+        None => return err.to_string(),
+    };
+    let (line, col) = parser::line_col(source, span);
+    let line_text = source.lines().nth(line - 1).unwrap_or("");
+    let before = line_text.chars().take(col - 1);
+    // tabs stay tabs so the caret lines up however wide they're shown
+    let padding = before
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect::<String>();
+    // up to the end of the line, for a span that goes over more than one
+    let width = source
+        .get(span.start..span.end)
+        .and_then(|spanned| spanned.lines().next())
+        .map_or(0, |first_line| first_line.chars().count())
+        .max(1);
+    format!(
+        "line {}, col {}: {}\n  {}\n  {}{}",
+        line,
+        col,
+        err,
+        line_text,
+        padding,
+        "^".repeat(width)
+    )
+}
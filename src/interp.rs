@@ -1,26 +1,158 @@
 use anyhow::{anyhow, bail};
-use dyn_partial_eq::*;
-use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 
+use crate::debugger::{run_to_end, DebugSession, Debuggee, StepEvent};
+use crate::description::expr_type;
+use crate::error::SoldierError;
+use crate::json::Json;
 use crate::parser::{
-    Assignment, BinOp, Block, Comment, Expr, ExprID, FunctionCall, If, Op, Ref, While,
+    parse_program, walk_exprs, Assignment, BinOp, Block, Comment, Destructure, Expr, ExprID,
+    FieldAccess, FunctionCall, If, IndexAssignment, InterpolationPart, Op, Pattern, Program, Ref,
+    Span, While,
 };
-use crate::{parser, wrapping};
+use crate::rng::Rng;
+use crate::trace::{TraceKind, Tracer};
+use crate::vfs::{EnvFn, EnvLookup, RealFs, VirtualFs};
+use crate::{debugger, parser, reassemble, text, trace, wrapping};
 use dyn_clone::DynClone;
-use itertools::Itertools;
+use itertools::{EitherOrBoth, Itertools};
 use lazy_static::lazy_static;
+use std::any::{Any, TypeId};
 use std::cell::RefCell;
 use std::cmp::Ordering;
 use std::fmt::Debug;
+use std::future::Future;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
+use std::pin::Pin;
 use std::rc::Rc;
-use std::str::from_utf8;
-use std::sync::Mutex;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 #[derive(Debug, Clone)]
 pub struct Interpreter {
     scope: Rc<RefCell<Scope>>,
     comments: Rc<RefCell<BTreeMap<String, String>>>,
     pub(crate) result_comments: Rc<RefCell<HashMap<ExprID, Value>>>,
+    diagnostics: Rc<RefCell<Diagnostics>>,
+    tracer: Option<Rc<RefCell<Tracer>>>,
+    fs: Rc<RefCell<Box<dyn VirtualFs>>>,
+    env: Rc<RefCell<EnvLookup>>,
+    // what args() returns, see set_args
+    args: Rc<RefCell<Vec<String>>>,
+    // for rand and rand_range, see rand_seed
+    rng: Rc<RefCell<Rng>>,
+    #[cfg(feature = "unicode")]
+    grapheme_strings: bool,
+    comment_mutations: Rc<RefCell<BTreeMap<String, CommentMutation>>>,
+    output: Rc<RefCell<Output>>,
+    // the program's source, so errors can say where they happened
+    source: Option<Rc<str>>,
+    assign_hooks: Rc<RefCell<AssignHooks>>,
+    // see register_display
+    display_hooks: DisplayHooks,
+    // see set_trace
+    trace_evaluation: bool,
+    // set on the interpreter Interpreter::debug runs a program with
+    debuggee: Option<Rc<Debuggee>>,
+    // lines given to eval_line that don't make a whole program yet
+    pending_input: String,
+}
+
+// called after every `let`, see on_assign
+type AssignHook = Box<dyn FnMut(&Ref, &Value)>;
+
+#[derive(Default)]
+struct AssignHooks(Vec<AssignHook>);
+
+impl Debug for AssignHooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "AssignHooks({})", self.0.len())
+    }
+}
+
+// where print writes to
+struct Output(Box<dyn Write>);
+
+impl Debug for Output {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Output")
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommentMutation {
+    pub name: String,
+    // before the first write
+    pub old_body: String,
+    // after the last write
+    pub new_body: String,
+    pub writes: usize,
+}
+
+// what eval_line did with a line of input
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvalOutcome {
+    Value(Value),
+    // the input so far stops part way through, like a `while (x) {` without its `}`
+    NeedMoreInput,
+}
+
+const DEFAULT_MAX_SCOPE_DEPTH: usize = 128;
+const DEFAULT_MAX_SCOPE_BINDINGS: usize = 1000;
+// how many expressions can be inside each other while they're evaluated. each one takes about
+// 15KB of stack in a debug build and 3KB in a release one, so this fits in 4MB either way. a
+// function call uses up several of these, so recursion usually stops here before
+// DEFAULT_MAX_SCOPE_DEPTH
+const DEFAULT_MAX_DEPTH: usize = 256;
+// `let s = cat(s, ...)` copies all of s every time, so past this many we suggest a builder
+const CAT_APPEND_WARNING_THRESHOLD: usize = 10_000;
+// how many of the most recent calls are listed when the depth limit is hit
+const REPORTED_FRAMES: usize = 10;
+// how much of an expression's source set_trace shows
+const TRACE_SOURCE_LEN: usize = 40;
+// wider than any table, so a wider pad is a mistake that would otherwise use up all the memory
+const MAX_PAD_WIDTH: usize = 1 << 20;
+// the longest string repeat will make, so a typo in the count is an error instead of using up
+// all the memory
+const MAX_REPEAT_LEN: usize = 100_000_000;
+// and the biggest list, counting every value in every copy. a value takes far more memory than
+// a character of a string does
+const MAX_REPEAT_LIST_SIZE: usize = 1_000_000;
+
+// see Interpreter::leaving
+struct Leaving {
+    diagnostics: Rc<RefCell<Diagnostics>>,
+    leave: fn(&mut Diagnostics),
+}
+
+impl Drop for Leaving {
+    fn drop(&mut self) {
+        (self.leave)(&mut self.diagnostics.borrow_mut());
+    }
+}
+
+#[derive(Debug)]
+struct Diagnostics {
+    call_stack: Vec<String>,
+    peak_depth: usize,
+    max_depth: usize,
+    max_scope_bindings: usize,
+    cat_appends: usize,
+    warnings: Vec<String>,
+    // what's left of the fuel, None for no limit, and how much has been burned since it was set
+    fuel: Option<u64>,
+    fuel_used: u64,
+    eval_depth: usize,
+    max_eval_depth: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Stats {
+    pub depth: usize,
+    pub peak_depth: usize,
+    // number of bindings in each scope, innermost first
+    pub frame_bindings: Vec<usize>,
 }
 
 const BUILTIN_COMMENTS: &[&str; 2] = &["help", "example-function"];
@@ -54,37 +186,555 @@ lazy_static! {
         let mut map = BTreeMap::new();
         map.insert("true".to_string(), Value::Bool(true));
         map.insert("false".to_string(), Value::Bool(false));
+        map.insert("null".to_string(), Value::Null);
         Mutex::new(map)
     };
 }
 
-impl Interpreter {
+// Builds an interpreter with a chosen set of builtins, for example one that can only compute for
+// running programs that aren't trusted:
+//
+//     InterpreterBuilder::new().core_builtins().without("globals").build()
+//
+// Interpreter::new() has all of them but the environment, time and file builtins, which a host
+// has to ask for:
+//
+//     InterpreterBuilder::new()
+//         .default_builtins()
+//         .env_builtins()
+//         .time_builtins()
+//         .fs_builtins()
+//         .build()
+#[derive(Default)]
+pub struct InterpreterBuilder {
+    builtins: Vec<Box<dyn Function>>,
+    without: BTreeSet<String>,
+}
+
+impl InterpreterBuilder {
     pub fn new() -> Self {
+        Self::default()
+    }
+
+    // what Interpreter::new() has: every group but env_builtins, time_builtins and fs_builtins
+    pub fn default_builtins(self) -> Self {
+        self.core_builtins().io_builtins().random_builtins()
+    }
+
+    // everything that can't reach outside the interpreter: arithmetic, comparisons, strings,
+    // lists and maps
+    pub fn core_builtins(mut self) -> Self {
+        self.builtins.extend(core_builtins());
+        self
+    }
+
+    // print, debug and exit, which ends the whole process when zac is run from the command line
+    pub fn io_builtins(mut self) -> Self {
+        self.builtins.extend(io_builtins());
+        self
+    }
+
+    // env and args, for what the program was run with
+    pub fn env_builtins(mut self) -> Self {
+        self.builtins.extend(env_builtins());
+        self
+    }
+
+    // rand, rand_range and rand_seed
+    pub fn random_builtins(mut self) -> Self {
+        self.builtins.extend(random_builtins());
+        self
+    }
+
+    // now, now_mono and sleep
+    pub fn time_builtins(mut self) -> Self {
+        self.builtins.extend(time_builtins());
+        self
+    }
+
+    // read_file, write_file, append_file, file_exists and list_dir. they still can't get to any
+    // files until set_fs is given a filesystem that allows them
+    pub fn fs_builtins(mut self) -> Self {
+        self.builtins.extend(fs_builtins());
+        self
+    }
+
+    // leaves out the builtin called name, whether it was added before or after this
+    pub fn without(mut self, name: &str) -> Self {
+        self.without.insert(name.into());
+        self
+    }
+
+    pub fn build(self) -> Interpreter {
         let mut scope = Scope::new(None);
-        scope.insert("set".into(), Value::Function(Box::new(SetBuiltin {})));
-        scope.insert("add".into(), Value::Function(Box::new(AddBuiltin {})));
-        scope.insert("mul".into(), Value::Function(Box::new(MulBuiltin {})));
-        scope.insert("eq".into(), Value::Function(Box::new(EqBuiltin {})));
-        scope.insert("lt".into(), Value::Function(Box::new(LtBuiltin {})));
-        scope.insert("gt".into(), Value::Function(Box::new(GtBuiltin {})));
-        scope.insert("not".into(), Value::Function(Box::new(NotBuiltin {})));
-        scope.insert("and".into(), Value::Function(Box::new(AndBuiltin {})));
-        scope.insert("or".into(), Value::Function(Box::new(OrBuiltin {})));
-        scope.insert("print".into(), Value::Function(Box::new(PrintBuiltin {})));
-        scope.insert("show".into(), Value::Function(Box::new(ShowBuiltin {})));
-        scope.insert("chr".into(), Value::Function(Box::new(ChrBuiltin {})));
-        scope.insert("cat".into(), Value::Function(Box::new(CatBuiltin {})));
+        for builtin in self.builtins {
+            if !self.without.contains(builtin.name()) {
+                scope.insert(builtin.name().into(), Value::Function(builtin));
+            }
+        }
         BUILTIN_CONSTANTS.lock().unwrap().iter().for_each(|(k, v)| {
             scope.insert(k.clone(), v.clone());
         });
 
-        Self {
+        Interpreter {
             result_comments: Rc::new(RefCell::new(HashMap::new())),
             scope: Rc::new(RefCell::new(scope)),
             comments: Rc::new(RefCell::new(BTreeMap::new())),
+            diagnostics: Rc::new(RefCell::new(Diagnostics {
+                call_stack: vec![],
+                peak_depth: 1,
+                max_depth: DEFAULT_MAX_SCOPE_DEPTH,
+                max_scope_bindings: DEFAULT_MAX_SCOPE_BINDINGS,
+                cat_appends: 0,
+                warnings: vec![],
+                fuel: None,
+                fuel_used: 0,
+                eval_depth: 0,
+                max_eval_depth: DEFAULT_MAX_DEPTH,
+            })),
+            tracer: None,
+            fs: Rc::new(RefCell::new(Box::new(RealFs::new(false)))),
+            env: Rc::new(RefCell::new(EnvLookup::real())),
+            args: Rc::new(RefCell::new(vec![])),
+            rng: Rc::new(RefCell::new(Rng::from_entropy())),
+            #[cfg(feature = "unicode")]
+            grapheme_strings: false,
+            comment_mutations: Rc::new(RefCell::new(BTreeMap::new())),
+            output: Rc::new(RefCell::new(Output(Box::new(std::io::stdout())))),
+            source: None,
+            assign_hooks: Default::default(),
+            display_hooks: Default::default(),
+            trace_evaluation: false,
+            debuggee: None,
+            pending_input: String::new(),
+        }
+    }
+}
+
+fn core_builtins() -> Vec<Box<dyn Function>> {
+    let mut builtins: Vec<Box<dyn Function>> = vec![
+        Box::new(SetBuiltin {}),
+        Box::new(AddBuiltin {}),
+        Box::new(SubBuiltin {}),
+        Box::new(MulBuiltin {}),
+        Box::new(DivBuiltin {}),
+        Box::new(PowBuiltin {}),
+        Box::new(MinBuiltin {}),
+        Box::new(MaxBuiltin {}),
+        Box::new(AbsBuiltin {}),
+        Box::new(DivmodBuiltin {}),
+        Box::new(BandBuiltin {}),
+        Box::new(BorBuiltin {}),
+        Box::new(BxorBuiltin {}),
+        Box::new(BnotBuiltin {}),
+        Box::new(ShlBuiltin {}),
+        Box::new(ShrBuiltin {}),
+        Box::new(ModBuiltin {}),
+        Box::new(EqBuiltin {}),
+        Box::new(LtBuiltin {}),
+        Box::new(GtBuiltin {}),
+        Box::new(NotBuiltin {}),
+        Box::new(AndBuiltin {}),
+        Box::new(OrBuiltin {}),
+        Box::new(ShowBuiltin {}),
+        Box::new(ToStrBuiltin {}),
+        Box::new(ParseIntBuiltin {}),
+        Box::new(ToIntBuiltin {}),
+        Box::new(ChrBuiltin {}),
+        Box::new(OrdBuiltin {}),
+        Box::new(CatBuiltin {}),
+        Box::new(DocBuiltin {}),
+        Box::new(ToPairsBuiltin {}),
+        Box::new(FromPairsBuiltin {}),
+        Box::new(KeysBuiltin {}),
+        Box::new(ValuesBuiltin {}),
+        Box::new(HasBuiltin {}),
+        Box::new(RemoveBuiltin {}),
+        Box::new(MergeBuiltin {}),
+        Box::new(EntriesBuiltin {}),
+        Box::new(BuilderBuiltin {}),
+        Box::new(BuilderPushBuiltin {}),
+        Box::new(BuilderFinishBuiltin {}),
+        Box::new(LenBuiltin {}),
+        Box::new(SliceBuiltin {}),
+        Box::new(SubstrBuiltin {}),
+        Box::new(SplitBuiltin {}),
+        Box::new(JoinBuiltin {}),
+        Box::new(ReplaceBuiltin {}),
+        Box::new(ContainsBuiltin {}),
+        Box::new(StartsWithBuiltin {}),
+        Box::new(EndsWithBuiltin {}),
+        Box::new(FindBuiltin {}),
+        Box::new(PushBuiltin {}),
+        Box::new(PopBuiltin {}),
+        Box::new(InsertBuiltin {}),
+        Box::new(RemoveAtBuiltin {}),
+        Box::new(ConcatBuiltin {}),
+        Box::new(MapBuiltin {}),
+        Box::new(FilterBuiltin {}),
+        Box::new(ReduceBuiltin {}),
+        Box::new(SortBuiltin {}),
+        Box::new(SortByBuiltin {}),
+        Box::new(SumBuiltin {}),
+        Box::new(CountBuiltin {}),
+        Box::new(EnumerateBuiltin {}),
+        Box::new(ZipBuiltin {}),
+        Box::new(ZipLongestBuiltin {}),
+        Box::new(RepeatBuiltin {}),
+        Box::new(FormatBuiltin {}),
+        Box::new(ReverseBuiltin {}),
+        Box::new(PadBuiltin {}),
+        Box::new(GlobalsBuiltin {}),
+        Box::new(TypeofBuiltin {}),
+        Box::new(AssertBuiltin {}),
+        Box::new(AssertEqBuiltin {}),
+        Box::new(EvalBuiltin {}),
+        Box::new(JsonParseBuiltin {}),
+        Box::new(JsonShowBuiltin {}),
+    ];
+    for kind in ValueKind::ALL {
+        builtins.push(Box::new(IsKindBuiltin { kind }));
+    }
+    builtins
+}
+
+fn io_builtins() -> Vec<Box<dyn Function>> {
+    vec![
+        Box::new(PrintBuiltin {}),
+        Box::new(DebugBuiltin {}),
+        Box::new(ExitBuiltin {}),
+    ]
+}
+
+fn env_builtins() -> Vec<Box<dyn Function>> {
+    vec![Box::new(EnvBuiltin {}), Box::new(ArgsBuiltin {})]
+}
+
+fn random_builtins() -> Vec<Box<dyn Function>> {
+    vec![
+        Box::new(RandBuiltin {}),
+        Box::new(RandRangeBuiltin {}),
+        Box::new(RandSeedBuiltin {}),
+    ]
+}
+
+fn time_builtins() -> Vec<Box<dyn Function>> {
+    vec![
+        Box::new(NowBuiltin {}),
+        Box::new(NowMonoBuiltin {}),
+        Box::new(SleepBuiltin {}),
+    ]
+}
+
+fn fs_builtins() -> Vec<Box<dyn Function>> {
+    vec![
+        Box::new(ReadFileBuiltin {}),
+        Box::new(WriteFileBuiltin {}),
+        Box::new(AppendFileBuiltin {}),
+        Box::new(FileExistsBuiltin {}),
+        Box::new(ListDirBuiltin {}),
+    ]
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        InterpreterBuilder::new().default_builtins().build()
+    }
+
+    // string indexing, len, slice, reverse and pad count grapheme clusters instead of chars, and
+    // pad counts wide characters as 2 columns
+    #[cfg(feature = "unicode")]
+    pub fn set_grapheme_strings(&mut self, grapheme_strings: bool) {
+        self.grapheme_strings = grapheme_strings;
+    }
+
+    fn graphemes(&self) -> bool {
+        #[cfg(feature = "unicode")]
+        return self.grapheme_strings;
+        #[cfg(not(feature = "unicode"))]
+        false
+    }
+
+    // file builtins go through this instead of the real filesystem. the default is the real
+    // filesystem with access turned off
+    pub fn set_fs(&mut self, fs: Box<dyn VirtualFs>) {
+        *self.fs.borrow_mut() = fs;
+    }
+
+    // makes f callable by name from programs this interpreter runs, and lists it in #help along
+    // with the builtins. a builtin with the same name is replaced
+    pub fn register_builtin(&mut self, name: &str, f: impl Function + 'static) {
+        Scope::root(&self.scope)
+            .borrow_mut()
+            .insert(name.into(), Value::Function(Box::new(f)));
+    }
+
+    // register_builtin for a closure, for functions that only need their arguments
+    pub fn register_fn(
+        &mut self,
+        name: &str,
+        f: impl Fn(&[Value]) -> anyhow::Result<Value> + Send + Sync + 'static,
+    ) {
+        self.register_builtin(
+            name,
+            HostFn {
+                name: name.into(),
+                f: Arc::new(f),
+            },
+        );
+    }
+
+    // show, print, traces and error messages display opaque values holding a T with f, while
+    // this interpreter (or one made from it with new_scope) is running. other interpreters
+    // don't see the hook
+    pub fn register_display<T: Any + Send>(
+        &mut self,
+        f: impl Fn(&T) -> String + Send + Sync + 'static,
+    ) {
+        let hook: DisplayHook = Arc::new(move |value| match value.downcast_ref::<T>() {
+            Some(value) => f(value),
+            None => String::new(),
+        });
+        self.display_hooks
+            .0
+            .borrow_mut()
+            .insert(TypeId::of::<T>(), hook);
+    }
+
+    // what show would give for val, with this interpreter's display hooks
+    pub fn show(&self, val: &Value) -> String {
+        let _hooks = self.display_hooks.activate();
+        val.to_string()
+    }
+
+    // the source the program was parsed from. without it, errors don't say where they happened
+    pub fn set_source(&mut self, source: &str) {
+        self.source = Some(source.into());
+    }
+
+    // print writes here instead of to stdout
+    pub fn set_output(&mut self, output: Box<dyn Write>) {
+        *self.output.borrow_mut() = Output(output);
+    }
+
+    pub fn set_env(&mut self, env: Box<EnvFn>) {
+        *self.env.borrow_mut() = EnvLookup(env);
+    }
+
+    // the program's command line arguments, for args() to return. there aren't any by default
+    pub fn set_args(&mut self, args: Vec<String>) {
+        *self.args.borrow_mut() = args;
+    }
+
+    // callback is called with the variable or comment and its new value after every `let x = ...`
+    // or `let #x = ...`, in the order they happen. setting a key with `let m(k) = ...` and
+    // destructuring don't call it. there can be any number of these, and they're called in the
+    // order they were added
+    pub fn on_assign(&mut self, callback: impl FnMut(&Ref, &Value) + 'static) {
+        self.assign_hooks.borrow_mut().0.push(Box::new(callback));
+    }
+
+    // A session that runs program one step at a time, as it's asked to. Nothing runs until the
+    // first step. See debugger.rs
+    pub fn debug(&mut self, program: &Program) -> DebugSession<'_> {
+        let global = Scope::root(&self.scope);
+        let builtins = global.borrow().this.clone();
+        let debuggee = Rc::new(Debuggee::new(global, builtins));
+        let mut interp = self.clone();
+        interp.debuggee = Some(Rc::clone(&debuggee));
+        let program = Program {
+            block: program.block.clone(),
+        };
+        let mut running = Box::pin(async move { interp.eval_program(&program).await });
+        let display_hooks = self.display_hooks.clone();
+        let resume = Box::new(move || {
+            // the caller's own code runs between steps, so the hooks are only there during them
+            let _hooks = display_hooks.activate();
+            debugger::poll(running.as_mut())
+        });
+        DebugSession::new(debuggee, resume)
+    }
+
+    // Writes a line to the output (see set_output) for every expression after it's evaluated,
+    // indented by how deeply it's nested, with its type, its source and its value:
+    //
+    //       int 1 => 1
+    //     assign let x = 1 => 1
+    //
+    // The expressions inside something come before it, since it isn't finished until they are.
+    pub fn set_trace(&mut self, trace: bool) {
+        self.trace_evaluation = trace;
+    }
+
+    pub fn set_tracer(&mut self, tracer: Rc<RefCell<Tracer>>) {
+        self.tracer = Some(tracer);
+    }
+
+    fn trace(&self, kind: TraceKind, value: Option<&Value>) {
+        if let Some(tracer) = &self.tracer {
+            tracer.borrow_mut().record(kind, value);
+        }
+    }
+
+    // every expression evaluated burns one unit of fuel, so a loop costs at least one per
+    // iteration. a program that runs out stops with FuelExhausted. None, the default, is no limit
+    pub fn set_fuel(&mut self, fuel: Option<u64>) {
+        let mut diagnostics = self.diagnostics.borrow_mut();
+        diagnostics.fuel = fuel;
+        diagnostics.fuel_used = 0;
+    }
+
+    pub fn fuel_remaining(&self) -> Option<u64> {
+        self.diagnostics.borrow().fuel
+    }
+
+    fn burn_fuel(&self) -> Result<(), SoldierError> {
+        let mut diagnostics = self.diagnostics.borrow_mut();
+        match diagnostics.fuel {
+            Some(0) => Err(SoldierError::FuelExhausted {
+                used: diagnostics.fuel_used,
+                span: None,
+            }),
+            Some(fuel) => {
+                diagnostics.fuel = Some(fuel - 1);
+                diagnostics.fuel_used += 1;
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    // evaluating an expression inside another one takes Rust stack, so programs nested deeper
+    // than this stop with MaxDepthExceeded instead of overflowing it
+    pub fn set_max_depth(&mut self, max_depth: usize) {
+        self.diagnostics.borrow_mut().max_eval_depth = max_depth;
+    }
+
+    fn enter_expr(&self) -> Result<Leaving, SoldierError> {
+        let mut diagnostics = self.diagnostics.borrow_mut();
+        if diagnostics.eval_depth >= diagnostics.max_eval_depth {
+            return Err(SoldierError::MaxDepthExceeded {
+                max: diagnostics.max_eval_depth,
+                span: None,
+            });
+        }
+        diagnostics.eval_depth += 1;
+        Ok(self.leaving(|diagnostics| diagnostics.eval_depth -= 1))
+    }
+
+    // undoes what entering an expression or a call did when it's dropped, which is also what
+    // happens to the ones a debug session is stopped in the middle of
+    fn leaving(&self, leave: fn(&mut Diagnostics)) -> Leaving {
+        Leaving {
+            diagnostics: Rc::clone(&self.diagnostics),
+            leave,
+        }
+    }
+
+    pub fn set_max_scope_depth(&mut self, max_depth: usize) {
+        self.diagnostics.borrow_mut().max_depth = max_depth;
+    }
+
+    pub fn set_max_scope_bindings(&mut self, max_scope_bindings: usize) {
+        self.diagnostics.borrow_mut().max_scope_bindings = max_scope_bindings;
+    }
+
+    pub fn warnings(&self) -> Vec<String> {
+        self.diagnostics.borrow().warnings.clone()
+    }
+
+    pub fn peak_depth(&self) -> usize {
+        self.diagnostics.borrow().peak_depth
+    }
+
+    pub fn stats(&self) -> Stats {
+        let mut frame_bindings = vec![self.scope.borrow().this.len()];
+        let mut prev = self.scope.borrow().prev.clone();
+        while let Some(scope) = prev {
+            frame_bindings.push(scope.borrow().this.len());
+            prev = scope.borrow().prev.clone();
+        }
+        Stats {
+            depth: frame_bindings.len(),
+            peak_depth: self.peak_depth(),
+            frame_bindings,
+        }
+    }
+
+    fn enter_call(&self, name: &str) -> anyhow::Result<Leaving> {
+        let mut diagnostics = self.diagnostics.borrow_mut();
+        let depth = self.scope.borrow().depth + 1;
+        if depth > diagnostics.max_depth {
+            let frames = diagnostics
+                .call_stack
+                .iter()
+                .rev()
+                .take(REPORTED_FRAMES)
+                .map(|name| format!("  in {}", name))
+                .join("\n");
+            bail!(
+                "maximum scope depth of {} exceeded calling {}, most recent calls first:\n{}",
+                diagnostics.max_depth,
+                name,
+                frames
+            );
+        }
+        diagnostics.peak_depth = diagnostics.peak_depth.max(depth);
+        diagnostics.call_stack.push(name.to_owned());
+        drop(diagnostics);
+        self.trace(
+            TraceKind::CallEnter {
+                name: name.to_owned(),
+            },
+            None,
+        );
+        Ok(self.leaving(|diagnostics| {
+            diagnostics.call_stack.pop();
+        }))
+    }
+
+    fn count_cat_append(&self) {
+        let mut diagnostics = self.diagnostics.borrow_mut();
+        diagnostics.cat_appends += 1;
+        if diagnostics.cat_appends == CAT_APPEND_WARNING_THRESHOLD {
+            diagnostics.warnings.push(format!(
+                "`let s = cat(s, ...)` ran {} times, and copies all of s each time. \
+                builder(), builder_push(b, s) and builder_finish(b) build strings faster",
+                CAT_APPEND_WARNING_THRESHOLD
+            ));
+        }
+    }
+
+    fn bind(&mut self, name: String, val: Value) {
+        self.trace(TraceKind::VarWrite { name: name.clone() }, Some(&val));
+        let mut scope = self.scope.borrow_mut();
+        // inside a while or an if, `let` updates a variable from outside the block if there is
+        // one, and otherwise makes a new one that goes away at the end of the block
+        let val = if scope.is_block {
+            match scope.assign_existing(&name, val) {
+                Ok(()) => return,
+                Err(val) => val,
+            }
+        } else {
+            val
+        };
+        scope.insert(name, val);
+        let max_scope_bindings = self.diagnostics.borrow().max_scope_bindings;
+        if scope.this.len() > max_scope_bindings && !scope.warned_about_size {
+            scope.warned_about_size = true;
+            self.diagnostics.borrow_mut().warnings.push(format!(
+                "a single scope has more than {} bindings, are names being generated in a loop?",
+                max_scope_bindings
+            ));
         }
     }
 
+    async fn interp_block_scope(&mut self, block: &Block) -> anyhow::Result<Value> {
+        let mut inner = self.clone();
+        inner.scope = Rc::new(RefCell::new(Scope::new_block(Rc::clone(&self.scope))));
+        inner.eval_block(block).await
+    }
+
     pub fn new_scope(&self) -> Self {
         let new_scope = Scope::new(Some(Rc::clone(&self.scope)));
         let mut new_interp = self.clone();
@@ -92,6 +742,29 @@ impl Interpreter {
         new_interp
     }
 
+    // The top level variables of the program, for passing values into it before it runs and
+    // reading them back afterwards. These always go to the global scope, wherever the
+    // interpreter is in the program.
+    pub fn set_var(&mut self, name: &str, value: Value) {
+        Scope::root(&self.scope)
+            .borrow_mut()
+            .insert(name.into(), value);
+    }
+
+    pub fn get_var(&self, name: &str) -> Option<Value> {
+        Scope::root(&self.scope).borrow().this.get(name).cloned()
+    }
+
+    // every top level variable, builtins included, in name order
+    pub fn vars(&self) -> Vec<(String, Value)> {
+        Scope::root(&self.scope)
+            .borrow()
+            .this
+            .iter()
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect()
+    }
+
     pub fn comments(&self) -> Vec<(String, String)> {
         self.comments
             .borrow()
@@ -100,469 +773,3900 @@ impl Interpreter {
             .collect()
     }
 
-    pub fn add_comment(&mut self, comment: &Comment) -> anyhow::Result<()> {
+    // The source program parsed into, with every named comment holding what it holds now. When
+    // there's a source (see set_source), only the changed comments are written into it and
+    // everything else stays as it was, otherwise the whole program is written out again.
+    pub fn writeback(&self, program: &Program) -> anyhow::Result<String> {
+        // result comments are shown here, after the run
+        let _hooks = self.display_hooks.activate();
+        let mut program = Program {
+            block: program.block.clone(),
+        };
+        crate::replace_comments_in_source_code(&mut program, self)?;
+        Ok(match &self.source {
+            Some(source) => reassemble::patch_program(source, &program, self),
+            None => reassemble::output_code(&program, self),
+        })
+    }
+
+    fn comment_body(&self, name: &str) -> anyhow::Result<String> {
+        self.comments
+            .borrow()
+            .get(name)
+            .cloned()
+            .ok_or_else(|| anyhow!("couldn't find comment with name {}", name))
+    }
+
+    fn write_comment(&self, name: &str, val: &Value) -> anyhow::Result<()> {
+        let new_body = wrapping::stringify(val);
+        let mut comments = self.comments.borrow_mut();
+        let comment = comments
+            .get_mut(name)
+            .ok_or_else(|| anyhow!("couldn't find comment with name {}", name))?;
+        let old_body = std::mem::replace(comment, new_body.clone());
+        drop(comments);
+        let mut mutations = self.comment_mutations.borrow_mut();
+        let mutation = mutations
+            .entry(name.to_owned())
+            .or_insert_with(|| CommentMutation {
+                name: name.to_owned(),
+                old_body,
+                new_body: String::new(),
+                writes: 0,
+            });
+        mutation.new_body = new_body;
+        mutation.writes += 1;
+        drop(mutations);
+        self.trace(
+            TraceKind::CommentWrite {
+                name: name.to_owned(),
+            },
+            Some(val),
+        );
+        Ok(())
+    }
+
+    // every comment the program wrote to, in name order
+    pub fn comment_mutations(&self) -> Vec<CommentMutation> {
+        self.comment_mutations.borrow().values().cloned().collect()
+    }
+
+    // puts back what the comment said before the program ran, so the write is never saved
+    pub fn revert_comment_mutation(&mut self, name: &str) -> anyhow::Result<()> {
+        let mutation = self
+            .comment_mutations
+            .borrow_mut()
+            .remove(name)
+            .ok_or_else(|| anyhow!("the program didn't write to comment {}", name))?;
+        self.comments
+            .borrow_mut()
+            .insert(name.to_owned(), mutation.old_body);
+        Ok(())
+    }
+
+    pub fn add_comment(&mut self, comment: &Comment) -> Result<(), SoldierError> {
         if let Some(name) = &comment.name {
             let mut comments = self.comments.borrow_mut();
             if comments.contains_key(name) {
-                bail!("duplicate comment: {}", name);
+                return Err(SoldierError::DuplicateComment {
+                    name: name.clone(),
+                    span: Some(comment.span),
+                });
             }
             comments.insert(name.into(), comment.body.clone());
         }
         Ok(())
     }
 
-    pub fn interp(&mut self, expr: &Expr) -> anyhow::Result<Value> {
-        let val = match expr {
-            Expr::Block(block) => {
-                let mut exprs = block.exprs();
-                let first = exprs
-                    .next()
-                    .ok_or_else(|| anyhow!("a block can't be empty"))?;
-                let mut res = self.interp(first)?;
-                for expr in exprs {
-                    res = self.interp(expr)?;
+    // Registers the program's named comments and then runs it, returning the value of the last
+    // expression. All the comments are registered first, so a duplicate stops the program before
+    // any of it has run.
+    pub fn run_program(&mut self, program: &Program) -> Result<Value, SoldierError> {
+        let _hooks = self.display_hooks.activate();
+        run_to_end(self.eval_program(program))
+    }
+
+    async fn eval_program(&mut self, program: &Program) -> Result<Value, SoldierError> {
+        let mut comments = vec![];
+        for expr in program.block.exprs() {
+            walk_exprs(expr, &mut |expr| {
+                if let Expr::Comment(comment) | Expr::TrailingComment(_, comment) = expr {
+                    comments.push(comment.clone());
                 }
-                res
-            }
-            Expr::Comment(Comment { name: _, body }) => Value::String(body.into()),
-            Expr::Assignment(Assignment { r#ref, expr }) => {
-                let val = self.interp(expr)?;
-                match r#ref {
-                    Ref::CommentRef(comment_name) => {
-                        let mut comments = self.comments.borrow_mut();
-                        let comment = comments.get_mut(comment_name).ok_or_else(|| {
-                            anyhow!("couldn't find comment with name {}", comment_name)
-                        })?;
-                        *comment = wrapping::stringify(&val);
-                    }
-                    Ref::VarRef(name) => {
-                        self.scope.borrow_mut().insert(name.into(), val.clone());
-                    }
+            });
+        }
+        for comment in &comments {
+            self.add_comment(comment)?;
+        }
+        Ok(self.eval_block(&program.block).await?)
+    }
+
+    // run_program for source code, which also becomes the source errors and writeback refer to
+    pub fn run_source(&mut self, source: &str) -> Result<Value, SoldierError> {
+        let program = parse_program(source)?;
+        self.set_source(source);
+        self.run_program(&program)
+    }
+
+    // For a REPL: adds a line of input to the ones before it, and runs them once they make a whole
+    // program. Variables and comments carry over from one run to the next. Input that stops part
+    // way through waits for more lines, and so does a comment, which ends at an empty line so it
+    // can go over several. Input that can't be finished is an error, and is thrown away.
+    pub fn eval_line(&mut self, line: &str) -> Result<EvalOutcome, SoldierError> {
+        self.pending_input.push_str(line);
+        if !line.ends_with('\n') {
+            self.pending_input.push('\n');
+        }
+        let input = self.pending_input.clone();
+        if input.trim().is_empty() {
+            self.pending_input.clear();
+            return Ok(EvalOutcome::Value(Value::Null));
+        }
+        let last_line = input.lines().last().unwrap_or("");
+        if last_line.trim_start().starts_with("//") {
+            return Ok(EvalOutcome::NeedMoreInput);
+        }
+        let program = match parse_program(&input) {
+            Ok(program) => program,
+            Err(diagnostic) => {
+                let end = Span {
+                    start: input.len(),
+                    end: input.len(),
+                };
+                // the parser got all the way to the end, so more input could finish it
+                if (diagnostic.line, diagnostic.column) == parser::line_col(&input, end) {
+                    return Ok(EvalOutcome::NeedMoreInput);
                 }
-                val
+                self.pending_input.clear();
+                return Err(diagnostic.into());
             }
-            Expr::IntLiteral(n) => Value::Int(*n),
-            Expr::Ref(r#ref) => self.get_ref(r#ref)?,
-            // XXX:
-            // this is lols but we'll use func call syntax to index into strings and maps
-            // (don't have lists yet)
-            Expr::FunctionCall(FunctionCall { r#ref, args }) => {
-                let var = self.get_ref(r#ref)?;
-                let args = args
-                    .iter()
-                    .map(|e| self.interp(e))
-                    .collect::<anyhow::Result<Vec<_>>>()?;
-                match var {
-                    Value::Function(func) => func.call(self, &args)?,
-                    Value::String(s) => {
-                        let index = get_arg(&args, 0)?.as_num()?;
-                        if index < 0 {
-                            Value::Bool(false)
-                        } else {
-                            s.chars()
-                                .nth(index as usize)
-                                .map(|c| Value::String(c.into()))
-                                .unwrap_or(Value::Bool(false))
-                        }
-                    }
-                    Value::Map(map) => {
-                        let key = get_arg(&args, 0)?;
-                        map.get(key).cloned().unwrap_or(Value::Bool(false))
-                    }
-                    Value::Bool(_) | Value::Int(_) => {
-                        bail!("tried to call a {:?}", var)
-                    }
-                    Value::List(vals) => {
-                        let index = get_arg(&args, 0)?.as_num()?;
-                        vals.get(index as usize)
-                            .cloned()
-                            .unwrap_or(Value::Bool(false))
-                    }
-                }
+        };
+        self.pending_input.clear();
+        self.set_source(&input);
+        self.run_program(&program).map(EvalOutcome::Value)
+    }
+
+    // forgets the lines eval_line is waiting to finish, for when the user gives up on them
+    pub fn discard_input(&mut self) {
+        self.pending_input.clear();
+    }
+
+    pub fn interp(&mut self, expr: &Expr) -> Result<Value, SoldierError> {
+        let _hooks = self.display_hooks.activate();
+        Ok(run_to_end(self.eval(expr))?)
+    }
+
+    // boxed, since evaluating an expression means evaluating the ones inside it, and a future
+    // can't hold another one of its own type inside it
+    fn eval<'a>(&'a mut self, expr: &'a Expr) -> Evaluation<'a> {
+        Box::pin(async move {
+            if let Some(debuggee) = &self.debuggee {
+                debuggee.pause(self.step_event(expr), &self.scope).await;
             }
-            Expr::While(While { cond, block }) => {
-                // TODO: need to make aa new scope for a new block
-                let mut count = 0;
-                while self.interp(cond)?.as_bool()? {
-                    self.interp(&Expr::Block(block.clone()))?;
-                    count += 1;
+            let result = match self.entering(expr_type(expr)) {
+                Ok(_leaving) => self.eval_expr(expr).await,
+                Err(err) => Err(err.into()),
+            };
+            let val = self.leaving_with(expr_type(expr), expr.span(), result)?;
+            if self.trace_evaluation {
+                self.write_eval_trace(expr, &val)?;
+            }
+            Ok(val)
+        })
+    }
+
+    fn step_event(&self, expr: &Expr) -> StepEvent {
+        let span = expr.span();
+        StepEvent {
+            expr: expr_type(expr),
+            source: reassemble::expr_source(expr, self),
+            span,
+            line: span
+                .zip(self.source.as_ref())
+                .map(|(span, source)| parser::line_col(source, span).0),
+            comment: match expr {
+                Expr::Comment(Comment { name, .. }) => name.clone(),
+                _ => None,
+            },
+        }
+    }
+
+    fn write_eval_trace(&self, expr: &Expr, val: &Value) -> anyhow::Result<()> {
+        let depth = self.diagnostics.borrow().eval_depth;
+        writeln!(
+            self.output.borrow_mut().0,
+            "{}{} {} => {}",
+            "  ".repeat(depth),
+            expr_type(expr),
+            trace::truncate(&reassemble::expr_source(expr, self), TRACE_SOURCE_LEN),
+            trace::truncate(&wrapping::stringify(val), trace::DEFAULT_MAX_VALUE_LEN),
+        )?;
+        Ok(())
+    }
+
+    // the same as evaluating an Expr::Block, without having to make one
+    async fn eval_block(&mut self, block: &Block) -> anyhow::Result<Value> {
+        let result = match self.entering("block") {
+            Ok(_leaving) => self.eval_block_exprs(block).await,
+            Err(err) => Err(err.into()),
+        };
+        self.leaving_with("block", None, result)
+    }
+
+    // everything evaluating an expression involves besides working out its value: fuel, depth,
+    // tracing, and pointing errors at the innermost expression with a span. this is the part
+    // before, and leaving_with the part after
+    fn entering(&self, expr_type: &'static str) -> Result<Leaving, SoldierError> {
+        self.burn_fuel()?;
+        let leaving = self.enter_expr()?;
+        self.trace(TraceKind::ExprStart { expr: expr_type }, None);
+        Ok(leaving)
+    }
+
+    fn leaving_with(
+        &self,
+        expr_type: &'static str,
+        span: Option<Span>,
+        result: anyhow::Result<Value>,
+    ) -> anyhow::Result<Value> {
+        if let Ok(val) = &result {
+            self.trace(TraceKind::ExprEnd { expr: expr_type }, Some(val));
+        }
+        result.map_err(|err| match span {
+            Some(span) => SoldierError::from(err).or_span(span).into(),
+            None => err,
+        })
+    }
+
+    async fn eval_block_exprs(&mut self, block: &Block) -> anyhow::Result<Value> {
+        let mut exprs = block.exprs();
+        let first = exprs
+            .next()
+            .ok_or_else(|| anyhow!("a block can't be empty"))?;
+        let mut res = self.eval(first).await?;
+        for expr in exprs {
+            res = self.eval(expr).await?;
+        }
+        Ok(res)
+    }
+
+    // in a debug build the stack frame of an async fn has room for everything that happens
+    // anywhere in it, and nested expressions take one of these frames each, so anything bigger
+    // than a line or two is in a method of its own, here and in the ones after it
+    async fn eval_expr(&mut self, expr: &Expr) -> anyhow::Result<Value> {
+        Ok(match expr {
+            Expr::Block(block) => self.eval_block_exprs(block).await?,
+            Expr::Comment(Comment { body, .. }) => Value::String(body.into()),
+            Expr::Assignment(Assignment { r#ref, expr, .. }) => {
+                if is_cat_onto_itself(r#ref, expr) {
+                    self.count_cat_append();
                 }
-                Value::Int(count)
+                let val = self.eval(expr).await?;
+                self.assign(r#ref, val)?
+            }
+            Expr::IndexAssignment(IndexAssignment { r#ref, index, expr }) => {
+                let index = self.eval(index).await?;
+                let val = self.eval(expr).await?;
+                self.assign_index(r#ref, index, val)?
+            }
+            Expr::Destructure(Destructure { pattern, expr, .. }) => {
+                let val = self.eval(expr).await?;
+                self.destructure(pattern, val)?
             }
-            Expr::If(If { cond, block }) => {
-                // TODO: need to make aa new scope for a new block
-                let b = self.interp(cond)?.as_bool()?;
-                if b {
-                    self.interp(&Expr::Block(block.clone()))?;
+            Expr::IntLiteral(n, _) => Value::Int(*n),
+            Expr::Ref(r#ref) => self.get_ref(r#ref)?,
+            Expr::FunctionCall(call) => self.eval_call(call).await?,
+            Expr::FieldAccess(FieldAccess { expr, field, .. }) => {
+                let val = self.eval(expr).await?;
+                field_of(&val, field)?
+            }
+            Expr::While(r#while) => self.eval_while(r#while).await?,
+            Expr::Loop(block, _) => self.eval_loop(block).await?,
+            // like while, the value of the body, or false if it didn't run
+            Expr::If(If { cond, block, .. }) => {
+                if self.eval(cond).await?.as_bool()? {
+                    self.interp_block_scope(block).await?
+                } else {
+                    Value::Bool(false)
                 }
-                Value::Bool(b)
             }
-            Expr::FuncDef(func_def) => {
-                let val = Value::Function(Box::new(FuncDef::from_expr(func_def.clone())));
-                self.scope
-                    .borrow_mut()
-                    .insert(func_def.name.clone(), val.clone());
-                val
+            Expr::FuncDef(func_def) => self.define(func_def),
+            Expr::ListLiteral(exprs, _) => Value::List(self.eval_all(exprs).await?),
+            Expr::BinOp(BinOp { op, lhs, rhs, .. }) => {
+                let lhs = self.eval(lhs).await?;
+                let rhs = self.eval(rhs).await?;
+                eval_bin_op(lhs, *op, rhs)?
             }
-            Expr::ListLiteral(exprs) => Value::List(
-                exprs
-                    .iter()
-                    .map(|expr| self.interp(expr))
-                    .collect::<anyhow::Result<Vec<_>>>()?,
-            ),
-            Expr::BinOp(BinOp { op, lhs, rhs }) => self.eval_bin_op(lhs, *op, rhs)?,
-            Expr::StringLiteral(s) => Value::String(s.into()),
-            Expr::ResultComment(id, expr) => {
-                let val = self.interp(expr)?;
+            Expr::StringLiteral(s, _) | Expr::MultiLineString(s, _) => Value::String(s.into()),
+            Expr::Interpolation(parts, _) => self.eval_interpolation(parts).await?,
+            Expr::TrailingComment(expr, _) => self.eval(expr).await?,
+            Expr::ResultComment(id, expr, _) => {
+                let val = self.eval(expr).await?;
                 let mut comments = self.result_comments.borrow_mut();
                 comments.insert(id.clone(), val.clone());
                 val
             }
-        };
-        Ok(val)
+        })
     }
 
-    fn eval_bin_op(&mut self, lhs: &Box<Expr>, op: Op, rhs: &Box<Expr>) -> anyhow::Result<Value> {
-        let lhs = self.interp(lhs)?;
-        let rhs = self.interp(rhs)?;
-        Ok(match op {
-            Op::Add => match (lhs, rhs) {
-                (Value::Int(l), Value::Int(r)) => Value::Int(l + r),
-                (Value::String(l), Value::String(r)) => Value::String(l + &r),
-                (Value::List(l), Value::List(r)) => Value::List(l.into_iter().chain(r).collect()),
-                (Value::Map(l), Value::Map(r)) => Value::Map(l.into_iter().chain(r).collect()),
-                (Value::Bool(l), Value::Bool(r)) => Value::Bool(l || r),
-                (l, r) => bail!("can't add {:?} and {:?}", l, r),
-            },
-            Op::Sub => match (lhs, rhs) {
-                (Value::Int(l), Value::Int(r)) => Value::Int(l - r),
-                (l, r) => bail!("can't subtract {:?} and {:?}", l, r),
-            },
-            Op::Div => match (lhs, rhs) {
-                (Value::Int(l), Value::Int(r)) => Value::Int(l / r),
-                (l, r) => bail!("can't divide {:?} and {:?}", l, r),
-            },
-            Op::Mul => match (lhs, rhs) {
-                (Value::Int(l), Value::Int(r)) => Value::Int(l * r),
-                (l, r) => bail!("can't multiply {:?} and {:?}", l, r),
-            },
-            Op::And => Value::Bool(lhs.as_bool()? && rhs.as_bool()?),
-            Op::Or => Value::Bool(lhs.as_bool()? || rhs.as_bool()?),
-            Op::Eq => Value::Bool(lhs == rhs),
-            Op::Neq => Value::Bool(lhs != rhs),
-            Op::Gte => match (lhs, rhs) {
-                (Value::Int(l), Value::Int(r)) => Value::Bool(l >= r),
-                (l, r) => bail!("can't compare {:?} >= {:?}", l, r),
-            },
-            Op::Gt => match (lhs, rhs) {
-                (Value::Int(l), Value::Int(r)) => Value::Bool(l > r),
-                (l, r) => bail!("can't compare {:?} > {:?}", l, r),
-            },
-            Op::Lte => match (lhs, rhs) {
-                (Value::Int(l), Value::Int(r)) => Value::Bool(l <= r),
-                (l, r) => bail!("can't compare {:?} <= {:?}", l, r),
-            },
-            Op::Lt => match (lhs, rhs) {
-                (Value::Int(l), Value::Int(r)) => Value::Bool(l < r),
-                (l, r) => bail!("can't compare {:?} < {:?}", l, r),
-            },
-        })
+    async fn eval_all(&mut self, exprs: &[Expr]) -> anyhow::Result<Vec<Value>> {
+        let mut vals = Vec::with_capacity(exprs.len());
+        for expr in exprs {
+            vals.push(self.eval(expr).await?);
+        }
+        Ok(vals)
     }
 
-    // TODO: this should probably be a refcell
-    fn get_ref(&self, r#ref: &Ref) -> anyhow::Result<Value> {
-        match r#ref {
-            Ref::CommentRef(name) => {
-                let comment_body = self
+    async fn eval_call(&mut self, call: &FunctionCall) -> anyhow::Result<Value> {
+        let FunctionCall { r#ref, args, span } = call;
+        let var = self.get_ref(r#ref)?;
+        let args = self.eval_all(args).await?;
+        match var {
+            Value::Function(func) => func.call_resumable(self, &args).await,
+            var => self.index_by_call(var, &args, *span),
+        }
+    }
+
+    // the value of the body the last time it ran, or false if it never did
+    async fn eval_while(&mut self, r#while: &While) -> anyhow::Result<Value> {
+        let While {
+            cond,
+            block,
+            check_first,
+            ..
+        } = r#while;
+        let mut count = 0;
+        let mut last = Value::Bool(false);
+        loop {
+            let skip_cond = count == 0 && !check_first;
+            if !skip_cond && !self.eval(cond).await?.as_bool()? {
+                break;
+            }
+            self.trace(TraceKind::LoopIteration, Some(&Value::Int(count)));
+            last = self.interp_block_scope(block).await?;
+            count += 1;
+        }
+        Ok(last)
+    }
+
+    async fn eval_loop(&mut self, block: &Block) -> anyhow::Result<Value> {
+        let mut count = 0;
+        loop {
+            self.trace(TraceKind::LoopIteration, Some(&Value::Int(count)));
+            self.interp_block_scope(block).await?;
+            count += 1;
+        }
+    }
+
+    async fn eval_interpolation(&mut self, parts: &[InterpolationPart]) -> anyhow::Result<Value> {
+        let mut acc = String::new();
+        for part in parts {
+            match part {
+                InterpolationPart::Literal(s) => acc.push_str(s),
+                InterpolationPart::Expr(expr) => {
+                    let val = self.eval(expr).await?;
+                    acc.push_str(&wrapping::stringify(&val))
+                }
+            }
+        }
+        Ok(Value::String(acc))
+    }
+
+    fn assign(&mut self, r#ref: &Ref, val: Value) -> anyhow::Result<Value> {
+        match r#ref {
+            Ref::CommentRef(comment_name, _) => self.write_comment(comment_name, &val)?,
+            Ref::VarRef(name, _) => {
+                self.bind(name.into(), val.clone());
+            }
+        }
+        for hook in self.assign_hooks.borrow_mut().0.iter_mut() {
+            hook(r#ref, &val);
+        }
+        Ok(val)
+    }
+
+    fn assign_index(&mut self, r#ref: &Ref, index: Value, val: Value) -> anyhow::Result<Value> {
+        match r#ref {
+            Ref::CommentRef(comment_name, _) => {
+                let mut body = Value::String(self.comment_body(comment_name)?);
+                set_index(&mut body, index, val.clone(), self.graphemes())?;
+                self.write_comment(comment_name, &body)?;
+            }
+            Ref::VarRef(name, span) => {
+                // this has to write into the scope the variable lives in, rather than into a
+                // copy from get_ref
+                let graphemes = self.graphemes();
+                self.scope
+                    .borrow_mut()
+                    .update(name, |target| {
+                        set_index(target, index, val.clone(), graphemes)
+                    })
+                    .ok_or_else(|| undefined_name(name, *span))??;
+            }
+        }
+        Ok(val)
+    }
+
+    fn destructure(&mut self, pattern: &Pattern, val: Value) -> anyhow::Result<Value> {
+        // everything is looked up before anything is bound, so a pattern that doesn't match
+        // leaves all the names as they were
+        let bindings = match pattern {
+            Pattern::Map(names) => {
+                let map = val.as_map()?;
+                names
+                    .iter()
+                    .map(|name| {
+                        let field = map.get(&Value::String(name.clone())).ok_or_else(|| {
+                            anyhow!("can't destructure, map has no key {:?}", name)
+                        })?;
+                        Ok((name, field.clone()))
+                    })
+                    .collect::<anyhow::Result<Vec<_>>>()?
+            }
+            Pattern::List(names) => {
+                let vals = val.as_list()?;
+                if vals.len() != names.len() {
+                    bail!(
+                        "can't destructure, expected a list of {} items but got {}",
+                        names.len(),
+                        vals.len()
+                    );
+                }
+                names
+                    .iter()
+                    .zip(vals)
+                    .filter(|(name, _)| *name != "_")
+                    .map(|(name, item)| (name, item.clone()))
+                    .collect()
+            }
+        };
+        for (name, field) in bindings {
+            self.bind(name.into(), field);
+        }
+        Ok(val)
+    }
+
+    // XXX:
+    // this is lols but we'll use func call syntax to index into strings and maps
+    // (don't have lists yet)
+    fn index_by_call(&self, var: Value, args: &[Value], span: Span) -> anyhow::Result<Value> {
+        Ok(match var {
+            Value::String(s) => {
+                let index = get_arg(args, 0)?.as_num()?;
+                let chars = text::char_indices(&s, self.graphemes());
+                resolve_index(index, chars.len())
+                    .map(|i| Value::String(chars[i].1.to_string()))
+                    .unwrap_or(Value::Null)
+            }
+            Value::Map(map) => {
+                let key = get_arg(args, 0)?;
+                map.get(key).cloned().unwrap_or(Value::Null)
+            }
+            Value::List(vals) => {
+                let index = get_arg(args, 0)?.as_num()?;
+                resolve_index(index, vals.len())
+                    .map(|i| vals[i].clone())
+                    .unwrap_or(Value::Null)
+            }
+            Value::Function(_)
+            | Value::Bool(_)
+            | Value::Int(_)
+            | Value::Opaque(_)
+            | Value::Null => {
+                return Err(SoldierError::NotCallable {
+                    got: format!("{:?}", var),
+                    span,
+                }
+                .into())
+            }
+        })
+    }
+
+    fn define(&mut self, func_def: &parser::FuncDef) -> Value {
+        let val = Value::Function(Box::new(FuncDef::from_expr(func_def.clone())));
+        self.bind(func_def.name.clone(), val.clone());
+        val
+    }
+
+    // TODO: this should probably be a refcell
+    fn get_ref(&self, r#ref: &Ref) -> anyhow::Result<Value> {
+        match r#ref {
+            Ref::CommentRef(name, span) => {
+                let comment_body = self
                     .comments
                     .borrow()
                     .get(name)
-                    .ok_or_else(|| anyhow!("undefined comment {}", name))?
+                    .ok_or_else(|| SoldierError::UndefinedComment {
+                        name: name.clone(),
+                        span: *span,
+                    })?
                     .clone();
                 Ok(Value::String(comment_body))
             }
-            Ref::VarRef(name) => self
-                .scope
-                .borrow()
-                .get(name)
-                .ok_or_else(|| anyhow!("undefined name {}", name))
-                .map(|val| val.clone()),
+            Ref::VarRef(name, span) => self
+                .scope
+                .borrow()
+                .get(name)
+                .ok_or_else(|| undefined_name(name, *span).into())
+                .map(|val| val.clone()),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Scope {
+    prev: Option<Rc<RefCell<Scope>>>,
+    this: BTreeMap<String, Value>,
+    depth: usize,
+    warned_about_size: bool,
+    // the body of a while or an if, rather than a function call or the whole program
+    is_block: bool,
+}
+
+impl Scope {
+    fn new(prev: Option<Rc<RefCell<Scope>>>) -> Self {
+        let depth = prev.as_ref().map_or(0, |prev| prev.borrow().depth) + 1;
+        Self {
+            prev,
+            this: Default::default(),
+            depth,
+            warned_about_size: false,
+            is_block: false,
+        }
+    }
+
+    // a block is inside the same call as its parent, so it doesn't count towards the depth
+    fn new_block(prev: Rc<RefCell<Scope>>) -> Self {
+        let depth = prev.borrow().depth;
+        Self {
+            prev: Some(prev),
+            this: Default::default(),
+            depth,
+            warned_about_size: false,
+            is_block: true,
+        }
+    }
+
+    // sets name in the nearest scope of the current call that already has it, or gives the value
+    // back if there isn't one
+    fn assign_existing(&mut self, name: &str, val: Value) -> Result<(), Value> {
+        if let Some(existing) = self.this.get_mut(name) {
+            *existing = val;
+            return Ok(());
+        }
+        match &self.prev {
+            Some(prev) if self.is_block => prev.borrow_mut().assign_existing(name, val),
+            _ => Err(val),
+        }
+    }
+
+    // the scope of the whole program, where the builtins and top level variables live
+    fn root(scope: &Rc<RefCell<Scope>>) -> Rc<RefCell<Scope>> {
+        match &scope.borrow().prev {
+            Some(prev) => Scope::root(prev),
+            None => Rc::clone(scope),
+        }
+    }
+
+    pub fn insert(&mut self, name: String, val: Value) {
+        self.this.insert(name, val);
+    }
+
+    pub fn get(&self, name: &str) -> Option<Value> {
+        if let Some(val) = self.this.get(name) {
+            return Some(val.clone());
+        }
+
+        self.prev
+            .as_ref()
+            .and_then(|scope| scope.borrow().get(name))
+    }
+
+    pub fn update<T>(&mut self, name: &str, f: impl FnOnce(&mut Value) -> T) -> Option<T> {
+        if let Some(val) = self.this.get_mut(name) {
+            return Some(f(val));
+        }
+
+        self.prev
+            .as_ref()
+            .and_then(|scope| scope.borrow_mut().update(name, f))
+    }
+}
+
+// a binary operator's value, once both sides have been evaluated
+fn eval_bin_op(lhs: Value, op: Op, rhs: Value) -> anyhow::Result<Value> {
+    Ok(match op {
+        Op::Add => match (lhs, rhs) {
+            (Value::Int(l), Value::Int(r)) => {
+                Value::Int(checked_int_op("add", l, r, i128::checked_add)?)
+            }
+            (Value::String(l), Value::String(r)) => Value::String(l + &r),
+            (Value::List(l), Value::List(r)) => Value::List(l.into_iter().chain(r).collect()),
+            (Value::Map(l), Value::Map(r)) => Value::Map(l.into_iter().chain(r).collect()),
+            (Value::Bool(l), Value::Bool(r)) => Value::Bool(l || r),
+            (l, r) => return Err(bad_operands(Op::Add, l, r)),
+        },
+        Op::Sub => match (lhs, rhs) {
+            (Value::Int(l), Value::Int(r)) => {
+                Value::Int(checked_int_op("sub", l, r, i128::checked_sub)?)
+            }
+            (l, r) => return Err(bad_operands(Op::Sub, l, r)),
+        },
+        Op::Div => match (lhs, rhs) {
+            (Value::Int(l), Value::Int(r)) => Value::Int(checked_div(l, r)?),
+            (l, r) => return Err(bad_operands(Op::Div, l, r)),
+        },
+        Op::Mul => match (lhs, rhs) {
+            (Value::Int(l), Value::Int(r)) => {
+                Value::Int(checked_int_op("mul", l, r, i128::checked_mul)?)
+            }
+            (l, r) => return Err(bad_operands(Op::Mul, l, r)),
+        },
+        Op::And => Value::Bool(lhs.as_bool()? && rhs.as_bool()?),
+        Op::Or => Value::Bool(lhs.as_bool()? || rhs.as_bool()?),
+        Op::Eq => Value::Bool(lhs == rhs),
+        Op::Neq => Value::Bool(lhs != rhs),
+        Op::Gte => match (lhs, rhs) {
+            (Value::Int(l), Value::Int(r)) => Value::Bool(l >= r),
+            (l, r) => return Err(bad_operands(Op::Gte, l, r)),
+        },
+        Op::Gt => match (lhs, rhs) {
+            (Value::Int(l), Value::Int(r)) => Value::Bool(l > r),
+            (l, r) => return Err(bad_operands(Op::Gt, l, r)),
+        },
+        Op::Lte => match (lhs, rhs) {
+            (Value::Int(l), Value::Int(r)) => Value::Bool(l <= r),
+            (l, r) => return Err(bad_operands(Op::Lte, l, r)),
+        },
+        Op::Lt => match (lhs, rhs) {
+            (Value::Int(l), Value::Int(r)) => Value::Bool(l < r),
+            (l, r) => return Err(bad_operands(Op::Lt, l, r)),
+        },
+    })
+}
+
+// what `expr.field` is, where val is what expr evaluated to
+fn field_of(val: &Value, field: &str) -> anyhow::Result<Value> {
+    let map = val.as_map()?;
+    map.get(&Value::String(field.to_owned()))
+        .cloned()
+        .ok_or_else(|| {
+            anyhow!(
+                "map has no key {:?}, its keys are: {}",
+                field,
+                map.keys().map(wrapping::stringify).join(", ")
+            )
+        })
+}
+
+// copies of the variables in scope, the innermost one where a name is used twice, leaving out the
+// builtins unless the program has changed them. for DebugSession::scope
+pub(crate) fn visible_vars(
+    scope: &Rc<RefCell<Scope>>,
+    builtins: &BTreeMap<String, Value>,
+) -> BTreeMap<String, Value> {
+    let mut vars = BTreeMap::new();
+    let mut next = Some(Rc::clone(scope));
+    while let Some(current) = next {
+        let current = current.borrow();
+        for (name, val) in &current.this {
+            if builtins.get(name) != Some(val) {
+                vars.entry(name.clone()).or_insert_with(|| val.clone());
+            }
+        }
+        next = current.prev.clone();
+    }
+    vars
+}
+
+fn is_cat_onto_itself(target: &Ref, expr: &Expr) -> bool {
+    match expr {
+        Expr::FunctionCall(FunctionCall {
+            r#ref: Ref::VarRef(func_name, _),
+            args,
+            ..
+        }) => {
+            func_name == "cat" && matches!(args.first(), Some(Expr::Ref(r)) if same_name(r, target))
+        }
+        _ => false,
+    }
+}
+
+// whether two refs are to the same variable or comment, wherever they are in the source
+fn same_name(a: &Ref, b: &Ref) -> bool {
+    match (a, b) {
+        (Ref::VarRef(a, _), Ref::VarRef(b, _)) | (Ref::CommentRef(a, _), Ref::CommentRef(b, _)) => {
+            a == b
+        }
+        _ => false,
+    }
+}
+
+// where index is in a string or list of length len, or None if it's out of range. negative
+// indexes count back from the end, so -1 is the last one
+fn resolve_index(index: i128, len: usize) -> Option<usize> {
+    let len = len as i128;
+    let index = if index < 0 { index + len } else { index };
+    (0..len).contains(&index).then_some(index as usize)
+}
+
+// like resolve_index, but an index past either end is that end instead of out of range
+fn clamp_index(index: i128, len: usize) -> usize {
+    let len = len as i128;
+    let index = if index < 0 { index + len } else { index };
+    index.clamp(0, len) as usize
+}
+
+// start to end in something of length len, empty if end comes before start
+fn clamp_range(start: i128, end: i128, len: usize) -> std::ops::Range<usize> {
+    let end = clamp_index(end, len);
+    clamp_index(start, len).min(end)..end
+}
+
+fn set_index(target: &mut Value, index: Value, val: Value, graphemes: bool) -> anyhow::Result<()> {
+    match target {
+        Value::Map(map) => {
+            map.insert(index, val);
+        }
+        Value::List(vals) => {
+            let len = vals.len();
+            let i = index.as_num()?;
+            let item = resolve_index(i, len)
+                .map(|i| &mut vals[i])
+                .ok_or_else(|| anyhow!("index {} out of range for a list of length {}", i, len))?;
+            *item = val;
+        }
+        Value::String(s) => {
+            let i = index.as_num()?;
+            let new = val.as_str()?;
+            let chars = text::char_indices(s, graphemes);
+            let (byte_index, old_len) = resolve_index(i, chars.len())
+                .map(|i| (chars[i].0, chars[i].1.len()))
+                .ok_or_else(|| anyhow!("index {} out of range for a string", i))?;
+            s.replace_range(byte_index..byte_index + old_len, new);
+        }
+        otherwise => bail!("can't assign into an index of {:?}", otherwise),
+    }
+    Ok(())
+}
+
+// Functions are compared, ordered and hashed by their name, so two functions registered under
+// the same name are considered the same function. This keeps `eq(handler, add)` and maps keyed
+// by functions stable across clones and runs.
+pub trait Function: Debug + DynClone + Send {
+    fn name(&self) -> &str;
+    // shown by the doc() builtin
+    fn doc(&self) -> &str {
+        ""
+    }
+    fn call(&self, interp: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value>;
+    // what the interpreter calls, so a debug session can stop part way through. functions that
+    // call back into the interpreter, like map, implement this instead, and have call run it to
+    // the end. see debugger.rs
+    fn call_resumable<'a>(
+        &'a self,
+        interp: &'a mut Interpreter,
+        args: &'a [Value],
+    ) -> Evaluation<'a> {
+        Box::pin(async move { self.call(interp, args) })
+    }
+}
+
+// evaluating something, as a future a debug session can suspend. see debugger.rs
+pub type Evaluation<'a, T = Value> = Pin<Box<dyn Future<Output = anyhow::Result<T>> + 'a>>;
+
+dyn_clone::clone_trait_object!(Function);
+
+impl PartialEq for Box<dyn Function> {
+    fn eq(&self, other: &Self) -> bool {
+        self.name() == other.name()
+    }
+}
+
+#[derive(Clone, PartialEq)]
+pub enum Value {
+    String(String),
+    Map(BTreeMap<Value, Value>),
+    Int(i128),
+    Function(Box<dyn Function>),
+    Bool(bool),
+    List(Vec<Value>),
+    Opaque(Opaque),
+    // what indexing gives back when there's nothing there
+    Null,
+}
+
+// Error messages show values with {:?}, and a value can be nested deeper than the stack can
+// recurse, so past this depth the inside of a value is shown as `...`
+const MAX_DEBUG_DEPTH: usize = 32;
+
+impl Debug for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        DepthLimited(self, 0).fmt(f)
+    }
+}
+
+// how print and show write a value: strings without quotes, and long lists and maps wrapped to
+// fit in a comment
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&wrapping::stringify(self))
+    }
+}
+
+// the same as a derived Debug would print, down to MAX_DEBUG_DEPTH
+struct DepthLimited<'a>(&'a Value, usize);
+
+impl Debug for DepthLimited<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let DepthLimited(val, depth) = *self;
+        if depth >= MAX_DEBUG_DEPTH {
+            return write!(f, "...");
+        }
+        match val {
+            Value::String(s) => f.debug_tuple("String").field(s).finish(),
+            Value::Map(map) => f
+                .debug_tuple("Map")
+                .field(&DebugMap(map, depth + 1))
+                .finish(),
+            Value::Int(n) => f.debug_tuple("Int").field(n).finish(),
+            Value::Function(func) => f.debug_tuple("Function").field(func).finish(),
+            Value::Bool(b) => f.debug_tuple("Bool").field(b).finish(),
+            Value::List(vals) => f
+                .debug_tuple("List")
+                .field(&DebugList(vals, depth + 1))
+                .finish(),
+            Value::Opaque(opaque) => f.debug_tuple("Opaque").field(opaque).finish(),
+            Value::Null => write!(f, "Null"),
+        }
+    }
+}
+
+struct DebugMap<'a>(&'a BTreeMap<Value, Value>, usize);
+
+impl Debug for DebugMap<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let DebugMap(map, depth) = *self;
+        if depth >= MAX_DEBUG_DEPTH {
+            return write!(f, "{{...}}");
+        }
+        f.debug_map()
+            .entries(
+                map.iter()
+                    .map(|(k, v)| (DepthLimited(k, depth), DepthLimited(v, depth))),
+            )
+            .finish()
+    }
+}
+
+struct DebugList<'a>(&'a [Value], usize);
+
+impl Debug for DebugList<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let DebugList(vals, depth) = *self;
+        if depth >= MAX_DEBUG_DEPTH {
+            return write!(f, "[...]");
+        }
+        f.debug_list()
+            .entries(vals.iter().map(|v| DepthLimited(v, depth)))
+            .finish()
+    }
+}
+
+// A host value that scripts can pass around but not look inside, like a string builder.
+// Copies of an opaque value share the same underlying value, and they're only equal to each
+// other.
+#[derive(Clone)]
+pub struct Opaque {
+    type_name: &'static str,
+    value: Arc<Mutex<dyn Any + Send>>,
+}
+
+impl Opaque {
+    pub fn new<T: Any + Send>(type_name: &'static str, value: T) -> Self {
+        Self {
+            type_name,
+            value: Arc::new(Mutex::new(value)),
+        }
+    }
+
+    pub fn type_name(&self) -> &str {
+        self.type_name
+    }
+
+    pub fn with<T: Any, R>(&self, f: impl FnOnce(&mut T) -> R) -> anyhow::Result<R> {
+        let mut value = self.value.lock().unwrap();
+        match value.downcast_mut::<T>() {
+            Some(value) => Ok(f(value)),
+            None => {
+                // showing the value needs the lock
+                drop(value);
+                bail!("expected a different kind of value than {:?}", self)
+            }
+        }
+    }
+
+    // what show, print, traces and error messages display for this value: the output of the
+    // display hook registered for its type, or otherwise just its type name
+    pub fn display(&self) -> String {
+        let addr = self.addr();
+        // a hook that ends up showing the value it's in the middle of showing
+        if !SHOWING_OPAQUES.with(|showing| showing.borrow_mut().insert(addr)) {
+            return "<...>".to_string();
+        }
+        let shown = match self.value.try_lock() {
+            Ok(value) => {
+                let hook = ACTIVE_DISPLAY_HOOKS.with(|active| {
+                    active
+                        .borrow()
+                        .last()
+                        .and_then(|hooks| hooks.0.borrow().get(&(*value).type_id()).cloned())
+                });
+                hook.map(|hook| hook(&*value))
+            }
+            // already locked further up the stack, by `with`
+            Err(_) => None,
+        };
+        SHOWING_OPAQUES.with(|showing| showing.borrow_mut().remove(&addr));
+        shown.unwrap_or_else(|| format!("<{}>", self.type_name))
+    }
+
+    fn addr(&self) -> usize {
+        Arc::as_ptr(&self.value) as *const () as usize
+    }
+}
+
+impl Debug for Opaque {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.display())
+    }
+}
+
+type DisplayHook = Arc<dyn Fn(&(dyn Any + Send)) -> String + Send + Sync>;
+
+// the hooks an interpreter has registered, see register_display
+#[derive(Default, Clone)]
+struct DisplayHooks(Rc<RefCell<HashMap<TypeId, DisplayHook>>>);
+
+impl Debug for DisplayHooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "DisplayHooks({})", self.0.borrow().len())
+    }
+}
+
+impl DisplayHooks {
+    // values are displayed deep inside Debug impls and builtins that have no interpreter to
+    // ask, so the running interpreter's hooks are put where Opaque::display can find them until
+    // the guard is dropped
+    fn activate(&self) -> ActiveDisplayHooks {
+        ACTIVE_DISPLAY_HOOKS.with(|active| active.borrow_mut().push(self.clone()));
+        ActiveDisplayHooks
+    }
+}
+
+struct ActiveDisplayHooks;
+
+impl Drop for ActiveDisplayHooks {
+    fn drop(&mut self) {
+        ACTIVE_DISPLAY_HOOKS.with(|active| active.borrow_mut().pop());
+    }
+}
+
+thread_local! {
+    static SHOWING_OPAQUES: RefCell<HashSet<usize>> = RefCell::new(HashSet::new());
+    // innermost last, an interpreter can run another from a host function
+    static ACTIVE_DISPLAY_HOOKS: RefCell<Vec<DisplayHooks>> = const { RefCell::new(vec![]) };
+}
+
+impl PartialEq for Opaque {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.value, &other.value)
+    }
+}
+
+impl Eq for Value {}
+
+impl PartialOrd<Self> for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Value {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Value::Int(a), Value::Int(b)) => a.cmp(b),
+            (Value::String(a), Value::String(b)) => a.cmp(b),
+            (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+            (Value::List(a), Value::List(b)) => a.cmp(b),
+            (Value::Map(a), Value::Map(b)) => a.cmp(b),
+            (Value::Function(a), Value::Function(b)) => a.name().cmp(b.name()),
+            (Value::Opaque(a), Value::Opaque(b)) => {
+                (a.type_name(), a.addr()).cmp(&(b.type_name(), b.addr()))
+            }
+            // values of different kinds are ordered by kind so that maps with mixed keys
+            // still have a total order
+            (a, b) => a.kind().cmp(&b.kind()),
+        }
+    }
+}
+
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.kind().hash(state);
+        match self {
+            Value::String(s) => s.hash(state),
+            Value::Map(map) => {
+                for (k, v) in map {
+                    k.hash(state);
+                    v.hash(state);
+                }
+            }
+            Value::Int(n) => n.hash(state),
+            Value::Function(func) => func.name().hash(state),
+            Value::Bool(b) => b.hash(state),
+            Value::List(vals) => vals.hash(state),
+            Value::Opaque(opaque) => opaque.addr().hash(state),
+            Value::Null => {}
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct FuncDef {
+    name: String,
+    block: Block,
+    arg_names: Vec<String>,
+}
+
+impl FuncDef {
+    fn from_expr(func_def: parser::FuncDef) -> Self {
+        Self {
+            name: func_def.name,
+            block: func_def.block,
+            arg_names: func_def.arg_names,
+        }
+    }
+}
+
+impl Function for FuncDef {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn call(&self, interp: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        run_to_end(self.call_resumable(interp, args))
+    }
+
+    fn call_resumable<'a>(
+        &'a self,
+        interp: &'a mut Interpreter,
+        args: &'a [Value],
+    ) -> Evaluation<'a> {
+        Box::pin(async move {
+            let leaving = interp.enter_call(&self.name)?;
+            let mut new_interp = interp.new_scope();
+            for (name, val) in self.arg_names.iter().zip(args) {
+                new_interp.bind(name.to_owned(), val.clone());
+            }
+            let result = new_interp.eval_block(&self.block).await;
+            drop(leaving);
+            let name = self.name.clone();
+            interp.trace(TraceKind::CallExit { name }, result.as_ref().ok());
+            result
+        })
+    }
+}
+
+// What typeof returns and the is_ builtins check. Values of different kinds sort in this order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ValueKind {
+    Bool,
+    Int,
+    String,
+    List,
+    Map,
+    Function,
+    // anything the host made, whatever it is
+    Opaque,
+    Null,
+}
+
+impl ValueKind {
+    pub const ALL: [ValueKind; 8] = [
+        ValueKind::Bool,
+        ValueKind::Int,
+        ValueKind::String,
+        ValueKind::List,
+        ValueKind::Map,
+        ValueKind::Function,
+        ValueKind::Opaque,
+        ValueKind::Null,
+    ];
+
+    pub fn name(self) -> &'static str {
+        self.describe().0
+    }
+
+    // the name, and the builtin that checks for it with its doc
+    fn describe(self) -> (&'static str, &'static str, &'static str) {
+        match self {
+            ValueKind::Bool => (
+                "bool",
+                "is_bool",
+                "is_bool(x) returns true if x is true or false",
+            ),
+            ValueKind::Int => ("int", "is_int", "is_int(x) returns true if x is an integer"),
+            ValueKind::String => (
+                "string",
+                "is_string",
+                "is_string(x) returns true if x is a string",
+            ),
+            ValueKind::List => ("list", "is_list", "is_list(x) returns true if x is a list"),
+            ValueKind::Map => ("map", "is_map", "is_map(x) returns true if x is a map"),
+            ValueKind::Function => (
+                "function",
+                "is_fn",
+                "is_fn(x) returns true if x is a function, a builtin or one made with defn",
+            ),
+            ValueKind::Opaque => (
+                "opaque",
+                "is_opaque",
+                "is_opaque(x) returns true if x is a value made by the program zac is running in, \
+                like a builder",
+            ),
+            ValueKind::Null => (
+                "null",
+                "is_null",
+                "is_null(x) returns true if x is null, which is what indexing past the end of a \
+                string or list, or with a key a map doesn't have, gives back",
+            ),
+        }
+    }
+}
+
+// ALL has every kind in order, so a new one can't be left out of it and go without an is_ builtin
+const _: () = {
+    assert!(ValueKind::ALL.len() == ValueKind::Null as usize + 1);
+    let mut i = 0;
+    while i < ValueKind::ALL.len() {
+        assert!(ValueKind::ALL[i] as usize == i);
+        i += 1;
+    }
+};
+
+impl Value {
+    pub fn kind(&self) -> ValueKind {
+        match self {
+            Value::String(_) => ValueKind::String,
+            Value::Map(_) => ValueKind::Map,
+            Value::Int(_) => ValueKind::Int,
+            Value::Function(_) => ValueKind::Function,
+            Value::Bool(_) => ValueKind::Bool,
+            Value::List(_) => ValueKind::List,
+            Value::Opaque(_) => ValueKind::Opaque,
+            Value::Null => ValueKind::Null,
+        }
+    }
+
+    fn as_func(&self) -> anyhow::Result<&dyn Function> {
+        match self {
+            Value::Function(f) => Ok(f.as_ref()),
+            otherwise => Err(type_mismatch("a function", otherwise).into()),
+        }
+    }
+
+    pub fn as_num(&self) -> anyhow::Result<i128> {
+        match self {
+            Value::Int(i) => Ok(*i),
+            otherwise => Err(type_mismatch("an integer", otherwise).into()),
+        }
+    }
+
+    pub fn as_bool(&self) -> anyhow::Result<bool> {
+        match self {
+            Value::Bool(b) => Ok(*b),
+            otherwise => Err(type_mismatch("a bool", otherwise).into()),
+        }
+    }
+
+    pub fn as_str(&self) -> anyhow::Result<&str> {
+        match self {
+            Value::String(s) => Ok(s),
+            otherwise => Err(type_mismatch("a String", otherwise).into()),
+        }
+    }
+
+    fn as_map(&self) -> anyhow::Result<&BTreeMap<Value, Value>> {
+        match self {
+            Value::Map(m) => Ok(m),
+            otherwise => Err(type_mismatch("a Map", otherwise).into()),
+        }
+    }
+
+    fn as_builder(&self) -> anyhow::Result<&Opaque> {
+        match self {
+            Value::Opaque(opaque) if opaque.type_name() == BUILDER_TYPE_NAME => Ok(opaque),
+            otherwise => Err(type_mismatch("a builder", otherwise).into()),
+        }
+    }
+
+    fn as_list(&self) -> anyhow::Result<&[Value]> {
+        match self {
+            Value::List(vals) => Ok(vals),
+            otherwise => Err(type_mismatch("a List", otherwise).into()),
+        }
+    }
+}
+
+// Conversions for code embedding Zac. Ints convert from every Rust integer type that always fits
+// in an i128, and back into any of them as long as the value fits.
+macro_rules! int_conversions {
+    ($($int:ty),*) => {
+        $(
+            impl From<$int> for Value {
+                fn from(n: $int) -> Self {
+                    Value::Int(n as i128)
+                }
+            }
+
+            impl TryFrom<&Value> for $int {
+                type Error = SoldierError;
+
+                fn try_from(value: &Value) -> Result<Self, SoldierError> {
+                    match value {
+                        Value::Int(n) => <$int>::try_from(*n).map_err(|_| {
+                            type_mismatch(
+                                concat!("an integer that fits in a ", stringify!($int)),
+                                value,
+                            )
+                        }),
+                        otherwise => Err(type_mismatch("an integer", otherwise)),
+                    }
+                }
+            }
+
+            impl TryFrom<Value> for $int {
+                type Error = SoldierError;
+
+                fn try_from(value: Value) -> Result<Self, SoldierError> {
+                    Self::try_from(&value)
+                }
+            }
+        )*
+    };
+}
+
+int_conversions!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, usize);
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.to_owned())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
+
+impl From<BTreeMap<Value, Value>> for Value {
+    fn from(map: BTreeMap<Value, Value>) -> Self {
+        Value::Map(map)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(vals: Vec<T>) -> Self {
+        Value::List(vals.into_iter().map(Into::into).collect())
+    }
+}
+
+impl TryFrom<&Value> for bool {
+    type Error = SoldierError;
+
+    fn try_from(value: &Value) -> Result<Self, SoldierError> {
+        match value {
+            Value::Bool(b) => Ok(*b),
+            otherwise => Err(type_mismatch("a bool", otherwise)),
+        }
+    }
+}
+
+impl TryFrom<Value> for bool {
+    type Error = SoldierError;
+
+    fn try_from(value: Value) -> Result<Self, SoldierError> {
+        Self::try_from(&value)
+    }
+}
+
+impl<'a> TryFrom<&'a Value> for &'a str {
+    type Error = SoldierError;
+
+    fn try_from(value: &'a Value) -> Result<Self, SoldierError> {
+        match value {
+            Value::String(s) => Ok(s),
+            otherwise => Err(type_mismatch("a String", otherwise)),
+        }
+    }
+}
+
+impl TryFrom<&Value> for String {
+    type Error = SoldierError;
+
+    fn try_from(value: &Value) -> Result<Self, SoldierError> {
+        <&str>::try_from(value).map(str::to_owned)
+    }
+}
+
+impl TryFrom<Value> for String {
+    type Error = SoldierError;
+
+    fn try_from(value: Value) -> Result<Self, SoldierError> {
+        match value {
+            Value::String(s) => Ok(s),
+            otherwise => Err(type_mismatch("a String", &otherwise)),
+        }
+    }
+}
+
+impl TryFrom<&Value> for BTreeMap<Value, Value> {
+    type Error = SoldierError;
+
+    fn try_from(value: &Value) -> Result<Self, SoldierError> {
+        match value {
+            Value::Map(map) => Ok(map.clone()),
+            otherwise => Err(type_mismatch("a Map", otherwise)),
+        }
+    }
+}
+
+impl TryFrom<Value> for BTreeMap<Value, Value> {
+    type Error = SoldierError;
+
+    fn try_from(value: Value) -> Result<Self, SoldierError> {
+        match value {
+            Value::Map(map) => Ok(map),
+            otherwise => Err(type_mismatch("a Map", &otherwise)),
+        }
+    }
+}
+
+impl TryFrom<&Value> for Vec<Value> {
+    type Error = SoldierError;
+
+    fn try_from(value: &Value) -> Result<Self, SoldierError> {
+        match value {
+            Value::List(vals) => Ok(vals.clone()),
+            otherwise => Err(type_mismatch("a List", otherwise)),
+        }
+    }
+}
+
+impl TryFrom<Value> for Vec<Value> {
+    type Error = SoldierError;
+
+    fn try_from(value: Value) -> Result<Self, SoldierError> {
+        match value {
+            Value::List(vals) => Ok(vals),
+            otherwise => Err(type_mismatch("a List", &otherwise)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SetBuiltin {}
+impl Function for SetBuiltin {
+    fn name(&self) -> &str {
+        "set"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let str = get_arg(args, 0)?.as_str()?;
+        let index = get_arg(args, 1)?.as_num()?;
+        let new = get_arg(args, 2)?.as_str()?;
+        let at = usize::try_from(index)
+            .ok()
+            .filter(|&at| at < str.len() && str.is_char_boundary(at))
+            .ok_or_else(|| {
+                anyhow!(
+                    "{} isn't where a character starts in a string of length {}",
+                    index,
+                    str.len()
+                )
+            })?;
+        let (left, right) = str.split_at(at);
+        // the character being replaced
+        let mut rest = right.chars();
+        rest.next();
+        Ok(Value::String(format!("{}{}{}", left, new, rest.as_str())))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct AddBuiltin {}
+impl Function for AddBuiltin {
+    fn name(&self) -> &str {
+        "add"
+    }
+
+    fn doc(&self) -> &str {
+        "add(a, b) adds a and b. It's an error if the result overflows"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let lhs = get_arg(args, 0)?.as_num()?;
+        let rhs = get_arg(args, 1)?.as_num()?;
+        Ok(Value::Int(checked_int_op(
+            "add",
+            lhs,
+            rhs,
+            i128::checked_add,
+        )?))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct MulBuiltin {}
+impl Function for MulBuiltin {
+    fn name(&self) -> &str {
+        "mul"
+    }
+
+    fn doc(&self) -> &str {
+        "mul(a, b) multiplies a by b. It's an error if the result overflows"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let lhs = get_arg(args, 0)?.as_num()?;
+        let rhs = get_arg(args, 1)?.as_num()?;
+        Ok(Value::Int(checked_int_op(
+            "mul",
+            lhs,
+            rhs,
+            i128::checked_mul,
+        )?))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SubBuiltin {}
+impl Function for SubBuiltin {
+    fn name(&self) -> &str {
+        "sub"
+    }
+
+    fn doc(&self) -> &str {
+        "sub(a, b) subtracts b from a. It's an error if the result overflows"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let lhs = get_arg(args, 0)?.as_num()?;
+        let rhs = get_arg(args, 1)?.as_num()?;
+        Ok(Value::Int(checked_int_op(
+            "sub",
+            lhs,
+            rhs,
+            i128::checked_sub,
+        )?))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct DivBuiltin {}
+impl Function for DivBuiltin {
+    fn name(&self) -> &str {
+        "div"
+    }
+
+    fn doc(&self) -> &str {
+        "div(a, b) divides a by b, rounding down when b is positive and up when it's negative, \
+        so that what's left over is mod(a, b) and never negative. div(-7, 2) is -4 and \
+        div(7, -2) is -3"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let lhs = get_arg(args, 0)?.as_num()?;
+        let rhs = get_arg(args, 1)?.as_num()?;
+        Ok(Value::Int(checked_div(lhs, rhs)?))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ModBuiltin {}
+impl Function for ModBuiltin {
+    fn name(&self) -> &str {
+        "mod"
+    }
+
+    fn doc(&self) -> &str {
+        "mod(a, b) returns the remainder of a divided by b. It's never negative, even when a or b \
+        are, so mod(-1, 3) is 2 and mod(7, -3) is 1. It goes with div, so a is \
+        div(a, b) * b + mod(a, b)"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let lhs = get_arg(args, 0)?.as_num()?;
+        let rhs = get_arg(args, 1)?.as_num()?;
+        if rhs == 0 {
+            return Err(division_by_zero("mod", lhs));
+        }
+        Ok(Value::Int(checked_int_op(
+            "mod",
+            lhs,
+            rhs,
+            i128::checked_rem_euclid,
+        )?))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct PowBuiltin {}
+impl Function for PowBuiltin {
+    fn name(&self) -> &str {
+        "pow"
+    }
+
+    fn doc(&self) -> &str {
+        "pow(base, exp) returns base multiplied by itself exp times. exp can't be negative, and \
+        pow(0, 0) is 1"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let base = get_arg(args, 0)?.as_num()?;
+        let exp = get_arg(args, 1)?.as_num()?;
+        if exp < 0 {
+            bail!(
+                "can't take pow({}, {}), the exponent can't be negative",
+                base,
+                exp
+            );
+        }
+        let result = match u32::try_from(exp) {
+            Ok(small_exp) => base.checked_pow(small_exp),
+            // anything but 0, 1 and -1 overflows well before the exponent stops fitting in a u32
+            Err(_) => match base {
+                0 | 1 => Some(base),
+                -1 => Some(if exp % 2 == 0 { 1 } else { -1 }),
+                _ => None,
+            },
+        }
+        .ok_or_else(|| integer_overflow(format!("pow({}, {})", base, exp)))?;
+        Ok(Value::Int(result))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct MinBuiltin {}
+impl Function for MinBuiltin {
+    fn name(&self) -> &str {
+        "min"
+    }
+
+    fn doc(&self) -> &str {
+        "min(a, b, ...) returns the smallest of two or more numbers, or the first of two or more \
+        strings in alphabetical order"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        Ok(comparable_args("min", args)?.min().unwrap().clone())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct MaxBuiltin {}
+impl Function for MaxBuiltin {
+    fn name(&self) -> &str {
+        "max"
+    }
+
+    fn doc(&self) -> &str {
+        "max(a, b, ...) returns the largest of two or more numbers, or the last of two or more \
+        strings in alphabetical order"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        Ok(comparable_args("max", args)?.max().unwrap().clone())
+    }
+}
+
+// at least two arguments, and either all numbers or all strings
+fn comparable_args<'a>(
+    name: &str,
+    args: &'a [Value],
+) -> anyhow::Result<impl Iterator<Item = &'a Value>> {
+    if args.len() < 2 {
+        bail!("{} needs at least 2 arguments, got {}", name, args.len());
+    }
+    let all_ints = args.iter().all(|arg| matches!(arg, Value::Int(_)));
+    let all_strings = args.iter().all(|arg| matches!(arg, Value::String(_)));
+    if !all_ints && !all_strings {
+        bail!("{} needs all numbers or all strings, got {:?}", name, args);
+    }
+    Ok(args.iter())
+}
+
+#[derive(Debug, Clone)]
+struct AbsBuiltin {}
+impl Function for AbsBuiltin {
+    fn name(&self) -> &str {
+        "abs"
+    }
+
+    fn doc(&self) -> &str {
+        "abs(n) returns n without its sign. It's an error for the smallest integer, which is one \
+        further from zero than the largest"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let n = get_arg(args, 0)?.as_num()?;
+        let abs = n
+            .checked_abs()
+            .ok_or_else(|| integer_overflow(format!("abs({})", n)))?;
+        Ok(Value::Int(abs))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct DivmodBuiltin {}
+impl Function for DivmodBuiltin {
+    fn name(&self) -> &str {
+        "divmod"
+    }
+
+    fn doc(&self) -> &str {
+        "divmod(a, b) returns [div(a, b), mod(a, b)], for use with `let [q, r] = divmod(a, b)`. \
+        Like mod, r is never negative, so divmod(-7, 2) is [-4, 1] and divmod(7, -2) is [-3, 1]"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let lhs = get_arg(args, 0)?.as_num()?;
+        let rhs = get_arg(args, 1)?.as_num()?;
+        if rhs == 0 {
+            return Err(division_by_zero("divmod", lhs));
+        }
+        let quotient = checked_int_op("divmod", lhs, rhs, i128::checked_div_euclid)?;
+        let remainder = checked_int_op("divmod", lhs, rhs, i128::checked_rem_euclid)?;
+        Ok(Value::List(vec![
+            Value::Int(quotient),
+            Value::Int(remainder),
+        ]))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct BandBuiltin {}
+impl Function for BandBuiltin {
+    fn name(&self) -> &str {
+        "band"
+    }
+
+    fn doc(&self) -> &str {
+        "band(a, b) returns the bits set in both a and b"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let lhs = get_arg(args, 0)?.as_num()?;
+        let rhs = get_arg(args, 1)?.as_num()?;
+        Ok(Value::Int(lhs & rhs))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct BorBuiltin {}
+impl Function for BorBuiltin {
+    fn name(&self) -> &str {
+        "bor"
+    }
+
+    fn doc(&self) -> &str {
+        "bor(a, b) returns the bits set in either a or b"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let lhs = get_arg(args, 0)?.as_num()?;
+        let rhs = get_arg(args, 1)?.as_num()?;
+        Ok(Value::Int(lhs | rhs))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct BxorBuiltin {}
+impl Function for BxorBuiltin {
+    fn name(&self) -> &str {
+        "bxor"
+    }
+
+    fn doc(&self) -> &str {
+        "bxor(a, b) returns the bits set in exactly one of a and b"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let lhs = get_arg(args, 0)?.as_num()?;
+        let rhs = get_arg(args, 1)?.as_num()?;
+        Ok(Value::Int(lhs ^ rhs))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct BnotBuiltin {}
+impl Function for BnotBuiltin {
+    fn name(&self) -> &str {
+        "bnot"
+    }
+
+    fn doc(&self) -> &str {
+        "bnot(a) flips every bit of a, so bnot(0) is -1"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        Ok(Value::Int(!get_arg(args, 0)?.as_num()?))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ShlBuiltin {}
+impl Function for ShlBuiltin {
+    fn name(&self) -> &str {
+        "shl"
+    }
+
+    fn doc(&self) -> &str {
+        "shl(a, n) shifts the bits of a left by n places, from 0 to 127. Bits shifted past the \
+        top are dropped"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let val = get_arg(args, 0)?.as_num()?;
+        let amount = shift_amount("shl", val, get_arg(args, 1)?.as_num()?)?;
+        Ok(Value::Int(val << amount))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ShrBuiltin {}
+impl Function for ShrBuiltin {
+    fn name(&self) -> &str {
+        "shr"
+    }
+
+    fn doc(&self) -> &str {
+        "shr(a, n) shifts the bits of a right by n places, from 0 to 127. Negative numbers stay \
+        negative, so shr(-8, 1) is -4"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let val = get_arg(args, 0)?.as_num()?;
+        let amount = shift_amount("shr", val, get_arg(args, 1)?.as_num()?)?;
+        Ok(Value::Int(val >> amount))
+    }
+}
+
+// shifting an i128 by 128 or more bits panics
+fn shift_amount(name: &str, val: i128, amount: i128) -> anyhow::Result<u32> {
+    match u32::try_from(amount) {
+        Ok(amount) if amount < i128::BITS => Ok(amount),
+        _ => bail!(
+            "can't take {}({}, {}), the shift has to be from 0 to {}",
+            name,
+            val,
+            amount,
+            i128::BITS - 1
+        ),
+    }
+}
+
+// i128 arithmetic panics (or wraps, in release builds) at the extremes, so every operation goes
+// through here and reports the operands instead
+fn checked_int_op(
+    name: &str,
+    lhs: i128,
+    rhs: i128,
+    op: impl FnOnce(i128, i128) -> Option<i128>,
+) -> anyhow::Result<i128> {
+    op(lhs, rhs).ok_or_else(|| integer_overflow(format!("{}({}, {})", name, lhs, rhs)))
+}
+
+fn undefined_name(name: &str, span: Span) -> SoldierError {
+    SoldierError::UndefinedName {
+        name: name.to_owned(),
+        span,
+    }
+}
+
+fn integer_overflow(call: String) -> anyhow::Error {
+    SoldierError::IntegerOverflow { call, span: None }.into()
+}
+
+fn division_by_zero(name: &str, dividend: i128) -> anyhow::Error {
+    SoldierError::DivisionByZero {
+        name: name.to_owned(),
+        dividend,
+        span: None,
+    }
+    .into()
+}
+
+fn bad_operands(op: Op, lhs: Value, rhs: Value) -> anyhow::Error {
+    SoldierError::BadOperands {
+        op,
+        lhs: format!("{:?}", lhs),
+        rhs: format!("{:?}", rhs),
+        span: None,
+    }
+    .into()
+}
+
+fn type_mismatch(expected: &str, got: &Value) -> SoldierError {
+    SoldierError::TypeMismatch {
+        expected: expected.to_owned(),
+        got: format!("{:?}", got),
+        span: None,
+    }
+}
+
+fn checked_div(lhs: i128, rhs: i128) -> anyhow::Result<i128> {
+    if rhs == 0 {
+        return Err(division_by_zero("div", lhs));
+    }
+    checked_int_op("div", lhs, rhs, i128::checked_div_euclid)
+}
+
+fn get_arg(args: &[Value], n: usize) -> anyhow::Result<&Value> {
+    args.get(n).ok_or_else(|| {
+        SoldierError::Arity {
+            needed: n + 1,
+            got: args.len(),
+            span: None,
+        }
+        .into()
+    })
+}
+
+#[derive(Debug, Clone)]
+struct EqBuiltin {}
+impl Function for EqBuiltin {
+    fn name(&self) -> &str {
+        "eq"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let lhs = get_arg(args, 0)?;
+        let rhs = get_arg(args, 1)?;
+        Ok(Value::Bool(lhs == rhs))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct GtBuiltin {}
+impl Function for GtBuiltin {
+    fn name(&self) -> &str {
+        "gt"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let lhs = get_arg(args, 0)?.as_num()?;
+        let rhs = get_arg(args, 1)?.as_num()?;
+        Ok(Value::Bool(lhs > rhs))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct LtBuiltin {}
+impl Function for LtBuiltin {
+    fn name(&self) -> &str {
+        "lt"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let lhs = get_arg(args, 0)?.as_num()?;
+        let rhs = get_arg(args, 1)?.as_num()?;
+        //println!("{:?} < {:?}", lhs, rhs);
+        Ok(Value::Bool(lhs < rhs))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct NotBuiltin {}
+impl Function for NotBuiltin {
+    fn name(&self) -> &str {
+        "not"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let val = get_arg(args, 0)?.as_bool()?;
+        Ok(Value::Bool(!val))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct AndBuiltin {}
+impl Function for AndBuiltin {
+    fn name(&self) -> &str {
+        "and"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let lhs = get_arg(args, 0)?.as_bool()?;
+        let rhs = get_arg(args, 1)?.as_bool()?;
+        Ok(Value::Bool(lhs && rhs))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct OrBuiltin {}
+impl Function for OrBuiltin {
+    fn name(&self) -> &str {
+        "or"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let lhs = get_arg(args, 0)?.as_bool()?;
+        let rhs = get_arg(args, 1)?.as_bool()?;
+        Ok(Value::Bool(lhs || rhs))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct PrintBuiltin {}
+impl Function for PrintBuiltin {
+    fn name(&self) -> &str {
+        "print"
+    }
+
+    fn doc(&self) -> &str {
+        "print(x, ...) writes its arguments on one line with spaces between them, and returns x"
+    }
+
+    fn call(&self, interp: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let line = args.iter().map(|arg| arg.to_string()).join(" ");
+        writeln!(interp.output.borrow_mut().0, "{}", line)?;
+        Ok(args.first().cloned().unwrap_or(Value::Null))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct DebugBuiltin {}
+impl Function for DebugBuiltin {
+    fn name(&self) -> &str {
+        "debug"
+    }
+
+    fn doc(&self) -> &str {
+        "debug(x) writes x the way it's stored, like String(\"5\") or Int(5), and returns x"
+    }
+
+    fn call(&self, interp: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let val = get_arg(args, 0)?;
+        writeln!(interp.output.borrow_mut().0, "{:?}", val)?;
+        Ok(val.clone())
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CatBuiltin {}
+impl Function for CatBuiltin {
+    fn name(&self) -> &str {
+        "cat"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let mut acc = String::new();
+        for arg in args {
+            let str = arg.as_str()?;
+            acc.push_str(str);
+        }
+        Ok(Value::String(acc))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ChrBuiltin {}
+impl Function for ChrBuiltin {
+    fn name(&self) -> &str {
+        "chr"
+    }
+
+    fn doc(&self) -> &str {
+        "chr(n) returns a string of the one character with the unicode code point n, so chr(65) \
+        is \"A\" and chr(233) is \"é\". It's an error if there's no such character"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let code = get_arg(args, 0)?.as_num()?;
+        let c = u32::try_from(code)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or_else(|| anyhow!("{} isn't a character code", code))?;
+        Ok(Value::String(c.to_string()))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct OrdBuiltin {}
+impl Function for OrdBuiltin {
+    fn name(&self) -> &str {
+        "ord"
+    }
+
+    fn doc(&self) -> &str {
+        "ord(s) returns the unicode code point of the one character in the string s, so ord(\"A\") \
+        is 65. It's the opposite of chr"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let s = get_arg(args, 0)?.as_str()?;
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => Ok(Value::Int(c as i128)),
+            _ => bail!("ord needs a string of one character, got {:?}", s),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ShowBuiltin {}
+impl Function for ShowBuiltin {
+    fn name(&self) -> &str {
+        "show"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let val = get_arg(args, 0)?;
+        Ok(Value::String(val.to_string()))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ToStrBuiltin {}
+impl Function for ToStrBuiltin {
+    fn name(&self) -> &str {
+        "to_str"
+    }
+
+    fn doc(&self) -> &str {
+        "to_str(x) returns x as a string, the same as show(x)"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let val = get_arg(args, 0)?;
+        Ok(Value::String(val.to_string()))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ParseIntBuiltin {}
+impl Function for ParseIntBuiltin {
+    fn name(&self) -> &str {
+        "parse_int"
+    }
+
+    fn doc(&self) -> &str {
+        "parse_int(s) returns the whole number written in the string s, like \"42\" or \" -7 \". \
+        Spaces around it are fine, but anything else that isn't a digit, like the x in 0x1F, is \
+        an error"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let s = get_arg(args, 0)?.as_str()?;
+        let trimmed = s.trim();
+        let digits = trimmed.strip_prefix('-').unwrap_or(trimmed);
+        if digits.is_empty() || !digits.chars().all(|c| c.is_ascii_digit()) {
+            bail!("parse_int: {:?} isn't a whole number", s);
+        }
+        let n = trimmed
+            .parse()
+            .map_err(|_| anyhow!("parse_int: {:?} is out of range for i128", s))?;
+        Ok(Value::Int(n))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ToIntBuiltin {}
+impl Function for ToIntBuiltin {
+    fn name(&self) -> &str {
+        "to_int"
+    }
+
+    fn doc(&self) -> &str {
+        "to_int(x) returns 1 for true and 0 for false, and an integer as it is. Use parse_int \
+        for a string"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        match get_arg(args, 0)? {
+            Value::Bool(b) => Ok(Value::Int(*b as i128)),
+            Value::Int(n) => Ok(Value::Int(*n)),
+            otherwise => Err(type_mismatch("a bool or an integer", otherwise).into()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct DocBuiltin {}
+impl Function for DocBuiltin {
+    fn name(&self) -> &str {
+        "doc"
+    }
+
+    fn doc(&self) -> &str {
+        "doc(f) returns the documentation for the function f"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let func = get_arg(args, 0)?.as_func()?;
+        Ok(Value::String(func.doc().into()))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ToPairsBuiltin {}
+impl Function for ToPairsBuiltin {
+    fn name(&self) -> &str {
+        "to_pairs"
+    }
+
+    fn doc(&self) -> &str {
+        "to_pairs(m) returns the entries of the map m as a list of [key, value] lists, in key \
+        order. to_pairs(from_pairs([[\"a\", 1]])) is [[\"a\", 1]]"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let map = get_arg(args, 0)?.as_map()?;
+        Ok(Value::List(
+            map.iter()
+                .map(|(k, v)| Value::List(vec![k.clone(), v.clone()]))
+                .collect(),
+        ))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct FromPairsBuiltin {}
+impl Function for FromPairsBuiltin {
+    fn name(&self) -> &str {
+        "from_pairs"
+    }
+
+    fn doc(&self) -> &str {
+        "from_pairs(pairs) builds a map from a list of [key, value] lists. A key showing up \
+        twice is an error, unless from_pairs(pairs, true) is used, then the last one wins. \
+        from_pairs([[\"a\", 1], [\"a\", 2]], true) is a map of \"a\" to 2"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let pairs = get_arg(args, 0)?.as_list()?;
+        let last_wins = match args.get(1) {
+            Some(flag) => flag.as_bool()?,
+            None => false,
+        };
+        let mut map = BTreeMap::new();
+        for (i, pair) in pairs.iter().enumerate() {
+            let (k, v) = match pair {
+                Value::List(kv) if kv.len() == 2 => (&kv[0], &kv[1]),
+                otherwise => bail!(
+                    "from_pairs: element {} should be a [key, value] list but was {:?}",
+                    i,
+                    otherwise
+                ),
+            };
+            if map.insert(k.clone(), v.clone()).is_some() && !last_wins {
+                bail!("from_pairs: duplicate key {:?} at element {}", k, i);
+            }
+        }
+        Ok(Value::Map(map))
+    }
+}
+
+const BUILDER_TYPE_NAME: &str = "builder";
+
+#[derive(Debug, Clone)]
+struct KeysBuiltin {}
+impl Function for KeysBuiltin {
+    fn name(&self) -> &str {
+        "keys"
+    }
+
+    fn doc(&self) -> &str {
+        "keys(m) returns a list of the keys of the map m, in order"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let map = get_arg(args, 0)?.as_map()?;
+        Ok(Value::List(map.keys().cloned().collect()))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ValuesBuiltin {}
+impl Function for ValuesBuiltin {
+    fn name(&self) -> &str {
+        "values"
+    }
+
+    fn doc(&self) -> &str {
+        "values(m) returns a list of the values in the map m, in the order of their keys"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let map = get_arg(args, 0)?.as_map()?;
+        Ok(Value::List(map.values().cloned().collect()))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct HasBuiltin {}
+impl Function for HasBuiltin {
+    fn name(&self) -> &str {
+        "has"
+    }
+
+    fn doc(&self) -> &str {
+        "has(m, k) returns true if the map m has the key k, even if its value is null or false"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let map = get_arg(args, 0)?.as_map()?;
+        let key = get_arg(args, 1)?;
+        Ok(Value::Bool(map.contains_key(key)))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct RemoveBuiltin {}
+impl Function for RemoveBuiltin {
+    fn name(&self) -> &str {
+        "remove"
+    }
+
+    fn doc(&self) -> &str {
+        "remove(m, k) returns a copy of the map m without the key k. m itself is left alone, so \
+        it's used like let m = remove(m, k)"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let mut map = get_arg(args, 0)?.as_map()?.clone();
+        map.remove(get_arg(args, 1)?);
+        Ok(Value::Map(map))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct MergeBuiltin {}
+impl Function for MergeBuiltin {
+    fn name(&self) -> &str {
+        "merge"
+    }
+
+    fn doc(&self) -> &str {
+        "merge(a, b) returns a map with the entries of both the maps a and b. When they both have \
+        a key, the value from b wins"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let mut merged = get_arg(args, 0)?.as_map()?.clone();
+        let b = get_arg(args, 1)?.as_map()?;
+        merged.extend(b.iter().map(|(k, v)| (k.clone(), v.clone())));
+        Ok(Value::Map(merged))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct EntriesBuiltin {}
+impl Function for EntriesBuiltin {
+    fn name(&self) -> &str {
+        "entries"
+    }
+
+    fn doc(&self) -> &str {
+        "entries(m) returns the entries of the map m as a list of [key, value] lists, in key \
+        order, the same as to_pairs(m)"
+    }
+
+    fn call(&self, interp: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        ToPairsBuiltin {}.call(interp, args)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct BuilderBuiltin {}
+impl Function for BuilderBuiltin {
+    fn name(&self) -> &str {
+        "builder"
+    }
+
+    fn doc(&self) -> &str {
+        "builder() makes an empty string builder. Pushing onto a builder doesn't copy what's \
+        already in it, unlike cat"
+    }
+
+    fn call(&self, _: &mut Interpreter, _: &[Value]) -> anyhow::Result<Value> {
+        Ok(Value::Opaque(Opaque::new(BUILDER_TYPE_NAME, String::new())))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct BuilderPushBuiltin {}
+impl Function for BuilderPushBuiltin {
+    fn name(&self) -> &str {
+        "builder_push"
+    }
+
+    fn doc(&self) -> &str {
+        "builder_push(b, s) appends the string s to the builder b, and returns b"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let builder = get_arg(args, 0)?.as_builder()?;
+        let s = get_arg(args, 1)?.as_str()?;
+        builder.with(|acc: &mut String| acc.push_str(s))?;
+        Ok(Value::Opaque(builder.clone()))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct BuilderFinishBuiltin {}
+impl Function for BuilderFinishBuiltin {
+    fn name(&self) -> &str {
+        "builder_finish"
+    }
+
+    fn doc(&self) -> &str {
+        "builder_finish(b) returns everything pushed onto the builder b as a string"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let builder = get_arg(args, 0)?.as_builder()?;
+        Ok(Value::String(builder.with(|acc: &mut String| acc.clone())?))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ReadFileBuiltin {}
+impl Function for ReadFileBuiltin {
+    fn name(&self) -> &str {
+        "read_file"
+    }
+
+    fn doc(&self) -> &str {
+        "read_file(path) returns the contents of the file at path as a string"
+    }
+
+    fn call(&self, interp: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let path = get_arg(args, 0)?.as_str()?;
+        Ok(Value::String(interp.fs.borrow().read(path)?))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct WriteFileBuiltin {}
+impl Function for WriteFileBuiltin {
+    fn name(&self) -> &str {
+        "write_file"
+    }
+
+    fn doc(&self) -> &str {
+        "write_file(path, s) replaces the contents of the file at path with the string s, and \
+        returns s"
+    }
+
+    fn call(&self, interp: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let path = get_arg(args, 0)?.as_str()?;
+        let contents = get_arg(args, 1)?.as_str()?;
+        interp.fs.borrow_mut().write(path, contents)?;
+        Ok(Value::String(contents.into()))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct AppendFileBuiltin {}
+impl Function for AppendFileBuiltin {
+    fn name(&self) -> &str {
+        "append_file"
+    }
+
+    fn doc(&self) -> &str {
+        "append_file(path, s) adds the string s to the end of the file at path, making the file \
+        if there isn't one, and returns s"
+    }
+
+    fn call(&self, interp: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let path = get_arg(args, 0)?.as_str()?;
+        let contents = get_arg(args, 1)?.as_str()?;
+        interp.fs.borrow_mut().append(path, contents)?;
+        Ok(Value::String(contents.into()))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct FileExistsBuiltin {}
+impl Function for FileExistsBuiltin {
+    fn name(&self) -> &str {
+        "file_exists"
+    }
+
+    fn doc(&self) -> &str {
+        "file_exists(path) returns whether there's a file or directory at path"
+    }
+
+    fn call(&self, interp: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let path = get_arg(args, 0)?.as_str()?;
+        Ok(Value::Bool(interp.fs.borrow().exists(path)?))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ListDirBuiltin {}
+impl Function for ListDirBuiltin {
+    fn name(&self) -> &str {
+        "list_dir"
+    }
+
+    fn doc(&self) -> &str {
+        "list_dir(path) returns the names of everything in the directory at path, sorted"
+    }
+
+    fn call(&self, interp: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let path = get_arg(args, 0)?.as_str()?;
+        let names = interp.fs.borrow().list(path)?;
+        Ok(Value::List(names.into_iter().map(Value::String).collect()))
+    }
+}
+
+// rand() is never this or more
+const RAND_LIMIT: i128 = 1 << 31;
+
+#[derive(Debug, Clone)]
+struct RandBuiltin {}
+impl Function for RandBuiltin {
+    fn name(&self) -> &str {
+        "rand"
+    }
+
+    fn doc(&self) -> &str {
+        "rand() returns a random integer from 0 up to but not including 2147483648 (2^31). The \
+        numbers are different every run unless rand_seed is called first"
+    }
+
+    fn call(&self, interp: &mut Interpreter, _args: &[Value]) -> anyhow::Result<Value> {
+        let n = interp.rng.borrow_mut().below(RAND_LIMIT as u128);
+        Ok(Value::Int(n as i128))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct RandRangeBuiltin {}
+impl Function for RandRangeBuiltin {
+    fn name(&self) -> &str {
+        "rand_range"
+    }
+
+    fn doc(&self) -> &str {
+        "rand_range(lo, hi) returns a random integer from lo up to but not including hi, so \
+        rand_range(1, 7) rolls a die"
+    }
+
+    fn call(&self, interp: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let lo = get_arg(args, 0)?.as_num()?;
+        let hi = get_arg(args, 1)?.as_num()?;
+        if lo >= hi {
+            bail!(
+                "rand_range({}, {}) has nothing to pick from, hi has to be more than lo",
+                lo,
+                hi
+            );
+        }
+        // hi - lo can be too big for an i128, but never for a u128
+        let offset = interp.rng.borrow_mut().below(hi.wrapping_sub(lo) as u128);
+        Ok(Value::Int(lo.wrapping_add(offset as i128)))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct RandSeedBuiltin {}
+impl Function for RandSeedBuiltin {
+    fn name(&self) -> &str {
+        "rand_seed"
+    }
+
+    fn doc(&self) -> &str {
+        "rand_seed(n) makes the random numbers after it the same every run, for the same n"
+    }
+
+    fn call(&self, interp: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let seed = get_arg(args, 0)?.as_num()?;
+        let seed = seed as u64 ^ (seed >> 64) as u64;
+        *interp.rng.borrow_mut() = Rng::from_seed(seed);
+        Ok(Value::Null)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct NowBuiltin {}
+impl Function for NowBuiltin {
+    fn name(&self) -> &str {
+        "now"
+    }
+
+    fn doc(&self) -> &str {
+        "now() returns the time in milliseconds since the start of 1970, UTC. It can go backwards \
+        if the clock is changed, so use now_mono for timing things"
+    }
+
+    fn call(&self, _: &mut Interpreter, _args: &[Value]) -> anyhow::Result<Value> {
+        let since_epoch = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|_| anyhow!("the clock is set to before 1970"))?;
+        Ok(Value::Int(since_epoch.as_millis() as i128))
+    }
+}
+
+lazy_static! {
+    // what now_mono counts from, the first time anything asks for it
+    static ref MONO_START: Instant = Instant::now();
+}
+
+#[derive(Debug, Clone)]
+struct NowMonoBuiltin {}
+impl Function for NowMonoBuiltin {
+    fn name(&self) -> &str {
+        "now_mono"
+    }
+
+    fn doc(&self) -> &str {
+        "now_mono() returns a count of milliseconds that only ever goes up, for timing things: \
+        take one now_mono() from another to see how long passed between them"
+    }
+
+    fn call(&self, _: &mut Interpreter, _args: &[Value]) -> anyhow::Result<Value> {
+        Ok(Value::Int(MONO_START.elapsed().as_millis() as i128))
+    }
+}
+
+// the longest one sleep can take, so a host that limits a program's fuel isn't left waiting on
+// it for ever
+const MAX_SLEEP_MS: u64 = 10_000;
+
+#[derive(Debug, Clone)]
+struct SleepBuiltin {}
+impl Function for SleepBuiltin {
+    fn name(&self) -> &str {
+        "sleep"
+    }
+
+    fn doc(&self) -> &str {
+        "sleep(ms) waits for ms milliseconds, then returns null. It waits for 10 seconds at most"
+    }
+
+    // the call costs fuel like any other, and no more for sleeping longer. a program with none
+    // left stops before it sleeps rather than after
+    fn call(&self, interp: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let ms = get_arg(args, 0)?.as_num()?;
+        let ms = u64::try_from(ms)
+            .ok()
+            .filter(|&ms| ms <= MAX_SLEEP_MS)
+            .ok_or_else(|| anyhow!("can't sleep for {}ms, the most is {}ms", ms, MAX_SLEEP_MS))?;
+        if interp.fuel_remaining() == Some(0) {
+            interp.burn_fuel()?;
+        }
+        std::thread::sleep(Duration::from_millis(ms));
+        Ok(Value::Null)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct EnvBuiltin {}
+impl Function for EnvBuiltin {
+    fn name(&self) -> &str {
+        "env"
+    }
+
+    fn doc(&self) -> &str {
+        "env(name) returns the environment variable name, or null if it isn't set"
+    }
+
+    fn call(&self, interp: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let name = get_arg(args, 0)?.as_str()?;
+        let lookup = &interp.env.borrow().0;
+        Ok(lookup(name).map(Value::String).unwrap_or(Value::Null))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ArgsBuiltin {}
+impl Function for ArgsBuiltin {
+    fn name(&self) -> &str {
+        "args"
+    }
+
+    fn doc(&self) -> &str {
+        "args() returns the list of arguments the program was run with, the strings after -- in \
+        zac file.zac -- a b c"
+    }
+
+    fn call(&self, interp: &mut Interpreter, _args: &[Value]) -> anyhow::Result<Value> {
+        let args = interp.args.borrow();
+        Ok(Value::List(
+            args.iter().cloned().map(Value::String).collect(),
+        ))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct LenBuiltin {}
+impl Function for LenBuiltin {
+    fn name(&self) -> &str {
+        "len"
+    }
+
+    fn doc(&self) -> &str {
+        "len(x) returns the number of characters in a string, items in a list or entries in a map"
+    }
+
+    fn call(&self, interp: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let len = match get_arg(args, 0)? {
+            Value::String(s) => text::char_indices(s, interp.graphemes()).len(),
+            Value::List(vals) => vals.len(),
+            Value::Map(map) => map.len(),
+            otherwise => return Err(type_mismatch("a String, List or Map", otherwise).into()),
+        };
+        Ok(Value::Int(len as i128))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SliceBuiltin {}
+impl Function for SliceBuiltin {
+    fn name(&self) -> &str {
+        "slice"
+    }
+
+    fn doc(&self) -> &str {
+        "slice(x, start, end) returns the characters of a string, or the items of a list, from \
+        index start up to but not including end. Negative indexes count back from the end, and \
+        indexes past either end are treated as that end, so slice(\"abc\", 1, 10) is \"bc\" and \
+        slice(\"abc\", -2, 3) is \"bc\" too"
+    }
+
+    fn call(&self, interp: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let start = get_arg(args, 1)?.as_num()?;
+        let end = get_arg(args, 2)?.as_num()?;
+        Ok(match get_arg(args, 0)? {
+            Value::String(s) => {
+                let chars = text::char_indices(s, interp.graphemes());
+                let range = clamp_range(start, end, chars.len());
+                Value::String(chars[range].iter().map(|(_, c)| *c).collect())
+            }
+            Value::List(vals) => Value::List(vals[clamp_range(start, end, vals.len())].to_vec()),
+            otherwise => bail!("can't slice {:?}", otherwise),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SubstrBuiltin {}
+impl Function for SubstrBuiltin {
+    fn name(&self) -> &str {
+        "substr"
+    }
+
+    fn doc(&self) -> &str {
+        "substr(s, start, len) returns len characters of the string s starting from index start, \
+        or as many as there are. A negative start counts back from the end, so substr(\"hello\", \
+        -3, 2) is \"ll\""
+    }
+
+    fn call(&self, interp: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let s = get_arg(args, 0)?.as_str()?;
+        let start = get_arg(args, 1)?.as_num()?;
+        let len = get_arg(args, 2)?.as_num()?;
+        let chars = text::char_indices(s, interp.graphemes());
+        let start = clamp_index(start, chars.len());
+        let end = start.saturating_add(usize::try_from(len).unwrap_or(0));
+        let range = start..end.min(chars.len());
+        Ok(Value::String(
+            chars[range].iter().map(|(_, c)| *c).collect(),
+        ))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct SplitBuiltin {}
+impl Function for SplitBuiltin {
+    fn name(&self) -> &str {
+        "split"
+    }
+
+    fn doc(&self) -> &str {
+        "split(s, sep) returns a list of the pieces of the string s between each sep, so \
+        split(\"a,,b\", \",\") is [\"a\", \"\", \"b\"]. An empty sep splits s into its characters"
+    }
+
+    fn call(&self, interp: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let s = get_arg(args, 0)?.as_str()?;
+        let sep = get_arg(args, 1)?.as_str()?;
+        let pieces = if sep.is_empty() {
+            text::char_indices(s, interp.graphemes())
+                .into_iter()
+                .map(|(_, c)| Value::String(c.to_owned()))
+                .collect()
+        } else {
+            s.split(sep)
+                .map(|piece| Value::String(piece.to_owned()))
+                .collect()
+        };
+        Ok(Value::List(pieces))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct JoinBuiltin {}
+impl Function for JoinBuiltin {
+    fn name(&self) -> &str {
+        "join"
+    }
+
+    fn doc(&self) -> &str {
+        "join(xs, sep) returns the items of the list xs with sep between each one, as a string. \
+        Items that aren't strings are shown like show would, so join([1, \"a\"], \"-\") is \"1-a\""
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let vals = get_arg(args, 0)?.as_list()?;
+        let sep = get_arg(args, 1)?.as_str()?;
+        Ok(Value::String(
+            vals.iter().map(|val| val.to_string()).join(sep),
+        ))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ReplaceBuiltin {}
+impl Function for ReplaceBuiltin {
+    fn name(&self) -> &str {
+        "replace"
+    }
+
+    fn doc(&self) -> &str {
+        "replace(s, from, to) returns the string s with every from in it replaced with to. from \
+        can't be empty"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let s = get_arg(args, 0)?.as_str()?;
+        let from = get_arg(args, 1)?.as_str()?;
+        let to = get_arg(args, 2)?.as_str()?;
+        if from.is_empty() {
+            bail!("can't replace an empty string");
+        }
+        Ok(Value::String(s.replace(from, to)))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ContainsBuiltin {}
+impl Function for ContainsBuiltin {
+    fn name(&self) -> &str {
+        "contains"
+    }
+
+    fn doc(&self) -> &str {
+        "contains(x, y) returns true if the string x has the string y in it, the map x has the key \
+        y, or the list x has the item y"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let needle = get_arg(args, 1)?;
+        Ok(Value::Bool(match get_arg(args, 0)? {
+            Value::String(s) => s.contains(needle.as_str()?),
+            Value::Map(map) => map.contains_key(needle),
+            Value::List(vals) => vals.contains(needle),
+            otherwise => return Err(type_mismatch("a String, List or Map", otherwise).into()),
+        }))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct StartsWithBuiltin {}
+impl Function for StartsWithBuiltin {
+    fn name(&self) -> &str {
+        "starts_with"
+    }
+
+    fn doc(&self) -> &str {
+        "starts_with(s, prefix) returns true if the string s starts with the string prefix"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let s = get_arg(args, 0)?.as_str()?;
+        let prefix = get_arg(args, 1)?.as_str()?;
+        Ok(Value::Bool(s.starts_with(prefix)))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct EndsWithBuiltin {}
+impl Function for EndsWithBuiltin {
+    fn name(&self) -> &str {
+        "ends_with"
+    }
+
+    fn doc(&self) -> &str {
+        "ends_with(s, suffix) returns true if the string s ends with the string suffix"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let s = get_arg(args, 0)?.as_str()?;
+        let suffix = get_arg(args, 1)?.as_str()?;
+        Ok(Value::Bool(s.ends_with(suffix)))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct FindBuiltin {}
+impl Function for FindBuiltin {
+    fn name(&self) -> &str {
+        "find"
+    }
+
+    fn doc(&self) -> &str {
+        "find(x, y) returns the index of the first y in the string or list x, or the first key in \
+        the map x whose value is y. It's null if there isn't one, since 0 is an index. \
+        find(x, y, start) starts looking in a string or list from index start, so \
+        find(\"abab\", \"b\", 2) is 3"
+    }
+
+    fn call(&self, interp: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let needle = get_arg(args, 1)?;
+        let start = match args.get(2) {
+            Some(start) => start.as_num()?,
+            None => 0,
+        };
+        let found = match get_arg(args, 0)? {
+            Value::String(s) => {
+                let needle = needle.as_str()?;
+                let chars = text::char_indices(s, interp.graphemes());
+                let start = clamp_index(start, chars.len());
+                chars[start..]
+                    .iter()
+                    .position(|(byte_index, _)| s[*byte_index..].starts_with(needle))
+                    .map(|i| Value::Int((start + i) as i128))
+            }
+            Value::List(vals) => {
+                let start = clamp_index(start, vals.len());
+                vals[start..]
+                    .iter()
+                    .position(|val| val == needle)
+                    .map(|i| Value::Int((start + i) as i128))
+            }
+            Value::Map(map) => {
+                if args.len() > 2 {
+                    bail!("find can't start partway through a map");
+                }
+                map.iter()
+                    .find(|(_, val)| *val == needle)
+                    .map(|(key, _)| key.clone())
+            }
+            otherwise => return Err(type_mismatch("a String, List or Map", otherwise).into()),
+        };
+        Ok(found.unwrap_or(Value::Null))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct PushBuiltin {}
+impl Function for PushBuiltin {
+    fn name(&self) -> &str {
+        "push"
+    }
+
+    fn doc(&self) -> &str {
+        "push(xs, x) returns a copy of the list xs with x added to the end"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let mut vals = get_arg(args, 0)?.as_list()?.to_vec();
+        vals.push(get_arg(args, 1)?.clone());
+        Ok(Value::List(vals))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct PopBuiltin {}
+impl Function for PopBuiltin {
+    fn name(&self) -> &str {
+        "pop"
+    }
+
+    fn doc(&self) -> &str {
+        "pop(xs) returns [rest, last], where last is the last item of the list xs and rest is the \
+        items before it, so it's used like let [xs, x] = pop(xs). xs can't be empty"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let mut vals = get_arg(args, 0)?.as_list()?.to_vec();
+        let last = vals
+            .pop()
+            .ok_or_else(|| anyhow!("can't pop from an empty list"))?;
+        Ok(Value::List(vec![Value::List(vals), last]))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct InsertBuiltin {}
+impl Function for InsertBuiltin {
+    fn name(&self) -> &str {
+        "insert"
+    }
+
+    fn doc(&self) -> &str {
+        "insert(xs, i, x) returns a copy of the list xs with x added so that it's at index i. A \
+        negative i counts back from the end, so insert([1, 2], -1, 3) is [1, 2, 3]"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let mut vals = get_arg(args, 0)?.as_list()?.to_vec();
+        let i = get_arg(args, 1)?.as_num()?;
+        let len = vals.len();
+        // one more place than there are items, since x can go after the last one
+        let at = resolve_index(i, len + 1)
+            .ok_or_else(|| anyhow!("index {} out of range for a list of length {}", i, len))?;
+        vals.insert(at, get_arg(args, 2)?.clone());
+        Ok(Value::List(vals))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct RemoveAtBuiltin {}
+impl Function for RemoveAtBuiltin {
+    fn name(&self) -> &str {
+        "remove_at"
+    }
+
+    fn doc(&self) -> &str {
+        "remove_at(xs, i) returns a copy of the list xs without the item at index i. A negative \
+        i counts back from the end"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let mut vals = get_arg(args, 0)?.as_list()?.to_vec();
+        let i = get_arg(args, 1)?.as_num()?;
+        let len = vals.len();
+        let at = resolve_index(i, len)
+            .ok_or_else(|| anyhow!("index {} out of range for a list of length {}", i, len))?;
+        vals.remove(at);
+        Ok(Value::List(vals))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ConcatBuiltin {}
+impl Function for ConcatBuiltin {
+    fn name(&self) -> &str {
+        "concat"
+    }
+
+    fn doc(&self) -> &str {
+        "concat(a, b) returns a list of the items of the list a followed by the items of the list \
+        b, the same as a + b"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let a = get_arg(args, 0)?.as_list()?;
+        let b = get_arg(args, 1)?.as_list()?;
+        Ok(Value::List(a.iter().chain(b).cloned().collect()))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct MapBuiltin {}
+impl Function for MapBuiltin {
+    fn name(&self) -> &str {
+        "map"
+    }
+
+    fn doc(&self) -> &str {
+        "map(xs, f) returns a list of f(x) for each item x of the list xs, or each character x of \
+        the string xs"
+    }
+
+    fn call(&self, interp: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        run_to_end(self.call_resumable(interp, args))
+    }
+
+    fn call_resumable<'a>(
+        &'a self,
+        interp: &'a mut Interpreter,
+        args: &'a [Value],
+    ) -> Evaluation<'a> {
+        Box::pin(async move {
+            let items = iterable_items(get_arg(args, 0)?, interp.graphemes())?;
+            let f = get_arg(args, 1)?.as_func()?;
+            let mut mapped = Vec::with_capacity(items.len());
+            for (i, item) in items.into_iter().enumerate() {
+                mapped.push(call_for_item(interp, f, "map", i, &[item]).await?);
+            }
+            Ok(Value::List(mapped))
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+struct FilterBuiltin {}
+impl Function for FilterBuiltin {
+    fn name(&self) -> &str {
+        "filter"
+    }
+
+    fn doc(&self) -> &str {
+        "filter(xs, pred) returns the items x of the list xs that pred(x) is true for. For a \
+        string it's a string of the characters pred is true for"
+    }
+
+    fn call(&self, interp: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        run_to_end(self.call_resumable(interp, args))
+    }
+
+    fn call_resumable<'a>(
+        &'a self,
+        interp: &'a mut Interpreter,
+        args: &'a [Value],
+    ) -> Evaluation<'a> {
+        Box::pin(async move {
+            let xs = get_arg(args, 0)?;
+            let items = iterable_items(xs, interp.graphemes())?;
+            let pred = get_arg(args, 1)?.as_func()?;
+            let mut kept = vec![];
+            for (i, item) in items.into_iter().enumerate() {
+                let keep = call_for_item(interp, pred, "filter", i, std::slice::from_ref(&item))
+                    .await?
+                    .as_bool()
+                    .map_err(|err| anyhow!("filter: element {}: {}", i, err))?;
+                if keep {
+                    kept.push(item);
+                }
+            }
+            Ok(match xs {
+                Value::String(_) => Value::String(
+                    kept.iter()
+                        .map(|c| c.as_str())
+                        .collect::<anyhow::Result<_>>()?,
+                ),
+                _ => Value::List(kept),
+            })
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ReduceBuiltin {}
+impl Function for ReduceBuiltin {
+    fn name(&self) -> &str {
+        "reduce"
+    }
+
+    fn doc(&self) -> &str {
+        "reduce(xs, f, init) calls f(acc, x) for each item x of the list xs, or each character of \
+        the string xs, where acc starts as init and is then what f returned last time. \
+        reduce([1, 2, 3], add, 0) is 6"
+    }
+
+    fn call(&self, interp: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        run_to_end(self.call_resumable(interp, args))
+    }
+
+    fn call_resumable<'a>(
+        &'a self,
+        interp: &'a mut Interpreter,
+        args: &'a [Value],
+    ) -> Evaluation<'a> {
+        Box::pin(async move {
+            let items = iterable_items(get_arg(args, 0)?, interp.graphemes())?;
+            let f = get_arg(args, 1)?.as_func()?;
+            let mut acc = get_arg(args, 2)?.clone();
+            for (i, item) in items.into_iter().enumerate() {
+                acc = call_for_item(interp, f, "reduce", i, &[acc, item]).await?;
+            }
+            Ok(acc)
+        })
+    }
+}
+
+// the items of a list, or the characters of a string, for the builtins that go through them
+fn iterable_items(xs: &Value, graphemes: bool) -> anyhow::Result<Vec<Value>> {
+    match xs {
+        Value::List(vals) => Ok(vals.clone()),
+        Value::String(s) => Ok(text::char_indices(s, graphemes)
+            .into_iter()
+            .map(|(_, c)| Value::String(c.to_owned()))
+            .collect()),
+        otherwise => Err(type_mismatch("a List or String", otherwise).into()),
+    }
+}
+
+// calls f for item i of what builtin is going through, and says which item it was if it fails
+async fn call_for_item(
+    interp: &mut Interpreter,
+    f: &dyn Function,
+    builtin: &str,
+    i: usize,
+    args: &[Value],
+) -> anyhow::Result<Value> {
+    f.call_resumable(interp, args)
+        .await
+        .map_err(|err| about(err, format!("{}: element {}", builtin, i)))
+}
+
+// an error from a function a builtin called, starting with what it was called for. running out
+// of fuel or depth, or exiting, isn't about any one call, so those are left as they are, and so
+// are failed assertions, which already point at the assert
+fn about(err: anyhow::Error, what: String) -> anyhow::Error {
+    match SoldierError::from(err) {
+        err @ (SoldierError::FuelExhausted { .. }
+        | SoldierError::MaxDepthExceeded { .. }
+        | SoldierError::AssertionFailed { .. }
+        | SoldierError::Exited(_)) => err.into(),
+        err => anyhow!("{}: {}", what, err),
+    }
+}
+
+// a stable merge sort by the function f, like sort_by's, that stops at the first error from it.
+// slice::sort_by can't stop early, and can panic when cmp isn't a consistent order, which a
+// function from a program needn't be
+fn merge_sort_by<'a>(
+    interp: &'a mut Interpreter,
+    f: &'a dyn Function,
+    mut vals: Vec<Value>,
+) -> Evaluation<'a, Vec<Value>> {
+    Box::pin(async move {
+        if vals.len() <= 1 {
+            return Ok(vals);
+        }
+        let right = merge_sort_by(interp, f, vals.split_off(vals.len() / 2)).await?;
+        let left = merge_sort_by(interp, f, vals).await?;
+        let mut merged = Vec::with_capacity(left.len() + right.len());
+        let mut left = left.into_iter().peekable();
+        let mut right = right.into_iter().peekable();
+        while let (Some(l), Some(r)) = (left.peek(), right.peek()) {
+            let order = f
+                .call_resumable(interp, &[l.clone(), r.clone()])
+                .await
+                .and_then(|order| order.as_num())
+                .map_err(|err| about(err, format!("sort_by: comparing {:?} and {:?}", l, r)))?;
+            // ties go to the left, which is what keeps it stable
+            let next = if order > 0 { right.next() } else { left.next() };
+            merged.extend(next);
+        }
+        merged.extend(left.chain(right));
+        Ok(merged)
+    })
+}
+
+#[derive(Debug, Clone)]
+struct SumBuiltin {}
+impl Function for SumBuiltin {
+    fn name(&self) -> &str {
+        "sum"
+    }
+
+    fn doc(&self) -> &str {
+        "sum(xs) returns the integers in the list xs added together, or 0 if it's empty"
+    }
+
+    fn call(&self, interp: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let items = iterable_items(get_arg(args, 0)?, interp.graphemes())?;
+        let mut total: i128 = 0;
+        for (i, item) in items.iter().enumerate() {
+            let n = item
+                .as_num()
+                .map_err(|err| anyhow!("sum: element {}: {}", i, err))?;
+            total = total
+                .checked_add(n)
+                .ok_or_else(|| integer_overflow(format!("sum, adding element {}", i)))?;
+        }
+        Ok(Value::Int(total))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct CountBuiltin {}
+impl Function for CountBuiltin {
+    fn name(&self) -> &str {
+        "count"
+    }
+
+    fn doc(&self) -> &str {
+        "count(xs, pred) returns how many items x of the list xs, or characters of the string xs, \
+        pred(x) is true for"
+    }
+
+    fn call(&self, interp: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        run_to_end(self.call_resumable(interp, args))
+    }
+
+    fn call_resumable<'a>(
+        &'a self,
+        interp: &'a mut Interpreter,
+        args: &'a [Value],
+    ) -> Evaluation<'a> {
+        Box::pin(async move {
+            let items = iterable_items(get_arg(args, 0)?, interp.graphemes())?;
+            let pred = get_arg(args, 1)?.as_func()?;
+            let mut count = 0;
+            for (i, item) in items.into_iter().enumerate() {
+                let matched = call_for_item(interp, pred, "count", i, &[item])
+                    .await?
+                    .as_bool()
+                    .map_err(|err| anyhow!("count: element {}: {}", i, err))?;
+                if matched {
+                    count += 1;
+                }
+            }
+            Ok(Value::Int(count))
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+struct EnumerateBuiltin {}
+impl Function for EnumerateBuiltin {
+    fn name(&self) -> &str {
+        "enumerate"
+    }
+
+    fn doc(&self) -> &str {
+        "enumerate(xs) returns a list of [index, x] for each item x of the list xs, or each \
+        character of the string xs, so enumerate(\"ab\") is [[0, \"a\"], [1, \"b\"]]"
+    }
+
+    fn call(&self, interp: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let items = iterable_items(get_arg(args, 0)?, interp.graphemes())?;
+        Ok(Value::List(
+            items
+                .into_iter()
+                .enumerate()
+                .map(|(i, item)| Value::List(vec![Value::Int(i as i128), item]))
+                .collect(),
+        ))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ZipBuiltin {}
+impl Function for ZipBuiltin {
+    fn name(&self) -> &str {
+        "zip"
+    }
+
+    fn doc(&self) -> &str {
+        "zip(a, b) returns a list of [x, y] pairs of the items of the lists a and b, or the \
+        characters of strings, at the same index. It stops at the end of the shorter one, so \
+        zip([1, 2], \"abc\") is [[1, \"a\"], [2, \"b\"]]"
+    }
+
+    fn call(&self, interp: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let a = iterable_items(get_arg(args, 0)?, interp.graphemes())?;
+        let b = iterable_items(get_arg(args, 1)?, interp.graphemes())?;
+        Ok(Value::List(
+            a.into_iter()
+                .zip(b)
+                .map(|(x, y)| Value::List(vec![x, y]))
+                .collect(),
+        ))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct ZipLongestBuiltin {}
+impl Function for ZipLongestBuiltin {
+    fn name(&self) -> &str {
+        "zip_longest"
+    }
+
+    fn doc(&self) -> &str {
+        "zip_longest(a, b, fill) is like zip(a, b), but goes on to the end of the longer one, with \
+        fill standing in for the items the shorter one doesn't have"
+    }
+
+    fn call(&self, interp: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let a = iterable_items(get_arg(args, 0)?, interp.graphemes())?;
+        let b = iterable_items(get_arg(args, 1)?, interp.graphemes())?;
+        let fill = get_arg(args, 2)?;
+        Ok(Value::List(
+            a.into_iter()
+                .zip_longest(b)
+                .map(|pair| {
+                    let (x, y) = match pair {
+                        EitherOrBoth::Both(x, y) => (x, y),
+                        EitherOrBoth::Left(x) => (x, fill.clone()),
+                        EitherOrBoth::Right(y) => (fill.clone(), y),
+                    };
+                    Value::List(vec![x, y])
+                })
+                .collect(),
+        ))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct RepeatBuiltin {}
+impl Function for RepeatBuiltin {
+    fn name(&self) -> &str {
+        "repeat"
+    }
+
+    fn doc(&self) -> &str {
+        "repeat(s, n) returns the string s n times over, so repeat(\"-\", 3) is \"---\". For \
+        anything other than a string, repeat(x, n) returns a list of n xs"
+    }
+
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let val = get_arg(args, 0)?;
+        let n = get_arg(args, 1)?.as_num()?;
+        if n < 0 {
+            bail!("can't repeat something {} times", n);
+        }
+        match val {
+            Value::String(s) => {
+                let len = (s.chars().count() as i128).checked_mul(n);
+                if len.is_none_or(|len| len > MAX_REPEAT_LEN as i128) {
+                    bail!(
+                        "repeat({:?}, {}) would be too long, the most is {}",
+                        val,
+                        n,
+                        MAX_REPEAT_LEN
+                    );
+                }
+            }
+            _ => {
+                let size = (value_size(val, MAX_REPEAT_LIST_SIZE) as i128).checked_mul(n);
+                if size.is_none_or(|size| size > MAX_REPEAT_LIST_SIZE as i128) {
+                    bail!(
+                        "repeat({:?}, {}) would make too big a list, the most is {} values in all",
+                        val,
+                        n,
+                        MAX_REPEAT_LIST_SIZE
+                    );
+                }
+            }
         }
+        let count = n as usize;
+        Ok(match val {
+            Value::String(s) => Value::String(s.repeat(count)),
+            _ => Value::List(vec![val.clone(); count]),
+        })
     }
 }
 
-#[derive(Debug)]
-struct Scope {
-    prev: Option<Rc<RefCell<Scope>>>,
-    this: BTreeMap<String, Value>,
+// how many values val is made of, counting itself, everything inside it, and each character of
+// a string as one more. it stops counting once it's past limit
+fn value_size(val: &Value, limit: usize) -> usize {
+    let mut size = 0;
+    let mut unvisited = vec![val];
+    while let Some(val) = unvisited.pop() {
+        size += 1;
+        match val {
+            Value::String(s) => size += s.len(),
+            Value::List(items) => unvisited.extend(items),
+            Value::Map(map) => unvisited.extend(map.iter().flat_map(|(k, v)| [k, v])),
+            _ => (),
+        }
+        if size > limit {
+            break;
+        }
+    }
+    size
 }
 
-impl Scope {
-    fn new(prev: Option<Rc<RefCell<Scope>>>) -> Self {
-        Self {
-            prev,
-            this: Default::default(),
-        }
+#[derive(Debug, Clone)]
+struct FormatBuiltin {}
+impl Function for FormatBuiltin {
+    fn name(&self) -> &str {
+        "format"
     }
 
-    pub fn insert(&mut self, name: String, val: Value) {
-        self.this.insert(name, val);
+    fn doc(&self) -> &str {
+        "format(template, ...) returns the string template with each {} in it replaced by the \
+        next value after it, shown like show would. {0}, {1} and so on are the first, second... \
+        value instead, and {{ and }} are a { and a } on their own. format(\"{} of {}\", 1, 2) is \
+        \"1 of 2\", and every value has to be used"
     }
 
-    pub fn get(&self, name: &str) -> Option<Value> {
-        if let Some(val) = self.this.get(name) {
-            return Some(val.clone());
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let template = get_arg(args, 0)?.as_str()?;
+        let vals = &args[1..];
+        let mut formatted = String::new();
+        let mut used = BTreeSet::new();
+        let mut next = 0;
+        let mut chars = template.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    formatted.push('{');
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    let end = rest
+                        .find('}')
+                        .ok_or_else(|| anyhow!("format: a {{ isn't closed, use {{{{ for a {{"))?;
+                    let index = match &rest[..end] {
+                        "" => {
+                            next += 1;
+                            next - 1
+                        }
+                        digits => digits.parse().map_err(|_| {
+                            anyhow!(
+                                "format: {{{}}} isn't a placeholder, use {{{{ for a {{",
+                                digits
+                            )
+                        })?,
+                    };
+                    used.insert(index);
+                    if let Some(val) = vals.get(index) {
+                        formatted.push_str(&val.to_string());
+                    }
+                    chars = rest[end + 1..].chars();
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    formatted.push('}');
+                }
+                '}' => bail!("format: a }} doesn't close a {{, use }}}} for a }}"),
+                c => formatted.push(c),
+            }
         }
-
-        self.prev
-            .as_ref()
-            .and_then(|scope| scope.borrow().get(name))
+        // too many when there's a placeholder past the last value, otherwise too few when a
+        // value isn't used
+        let past_last = used.iter().next_back().map_or(0, |last| last + 1);
+        let wanted = if past_last > vals.len() {
+            past_last
+        } else {
+            used.len()
+        };
+        if wanted != vals.len() {
+            bail!(
+                "format: the template has placeholders for {} values, but {} were given",
+                wanted,
+                vals.len()
+            );
+        }
+        Ok(Value::String(formatted))
     }
 }
 
-#[dyn_partial_eq]
-pub trait Function: Debug + DynClone + Send {
-    fn call(&self, interp: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value>;
-}
+#[derive(Debug, Clone)]
+struct SortBuiltin {}
+impl Function for SortBuiltin {
+    fn name(&self) -> &str {
+        "sort"
+    }
 
-dyn_clone::clone_trait_object!(Function);
+    fn doc(&self) -> &str {
+        "sort(xs) returns the list xs sorted from smallest to largest. The items all have to be \
+        integers, or all strings"
+    }
 
-#[derive(Debug, Clone, PartialEq)]
-pub enum Value {
-    String(String),
-    Map(BTreeMap<Value, Value>),
-    Int(i128),
-    Function(Box<dyn Function>),
-    Bool(bool),
-    List(Vec<Value>),
+    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let mut vals = get_arg(args, 0)?.as_list()?.to_vec();
+        if let Some(val) = vals
+            .iter()
+            .find(|val| !matches!(val, Value::Int(_) | Value::String(_)))
+        {
+            bail!("can't sort {:?}, only integers or strings", val);
+        }
+        if let Some(pair) = vals
+            .windows(2)
+            .find(|pair| pair[0].kind() != pair[1].kind())
+        {
+            bail!("can't sort {:?} and {:?} together", pair[0], pair[1]);
+        }
+        vals.sort();
+        Ok(Value::List(vals))
+    }
 }
 
-impl Eq for Value {}
+#[derive(Debug, Clone)]
+struct SortByBuiltin {}
+impl Function for SortByBuiltin {
+    fn name(&self) -> &str {
+        "sort_by"
+    }
 
-impl PartialOrd<Self> for Value {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        match (self, other) {
-            (Value::Int(a), Value::Int(b)) => a.partial_cmp(b),
-            (Value::String(a), Value::String(b)) => a.partial_cmp(b),
-            (Value::Bool(a), Value::Bool(b)) => a.partial_cmp(b),
-            (Value::List(a), Value::List(b)) => a.partial_cmp(b),
-            (Value::Map(a), Value::Map(b)) => a.partial_cmp(b),
-            _ => None,
-        }
+    fn doc(&self) -> &str {
+        "sort_by(xs, f) returns the list xs sorted using f(a, b), which returns a negative number \
+        if a goes before b, a positive one if it goes after, and 0 if either way is fine. Items f \
+        says are the same stay in the order they were in"
     }
-}
 
-impl Ord for Value {
-    fn cmp(&self, other: &Self) -> Ordering {
-        match (self, other) {
-            (Value::Int(a), Value::Int(b)) => a.cmp(b),
-            (Value::String(a), Value::String(b)) => a.cmp(b),
-            (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
-            (Value::List(a), Value::List(b)) => a.cmp(b),
-            (Value::Map(a), Value::Map(b)) => a.cmp(b),
-            _ => Ordering::Less,
-        }
+    fn call(&self, interp: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        run_to_end(self.call_resumable(interp, args))
     }
-}
 
-#[derive(Debug, Clone, PartialEq, DynPartialEq)]
-struct FuncDef {
-    block: Block,
-    arg_names: Vec<String>,
+    fn call_resumable<'a>(
+        &'a self,
+        interp: &'a mut Interpreter,
+        args: &'a [Value],
+    ) -> Evaluation<'a> {
+        Box::pin(async move {
+            let vals = get_arg(args, 0)?.as_list()?.to_vec();
+            let f = get_arg(args, 1)?.as_func()?;
+            Ok(Value::List(merge_sort_by(interp, f, vals).await?))
+        })
+    }
 }
 
-impl FuncDef {
-    fn from_expr(func_def: parser::FuncDef) -> Self {
-        Self {
-            block: func_def.block,
-            arg_names: func_def.arg_names,
-        }
+#[derive(Debug, Clone)]
+struct ReverseBuiltin {}
+impl Function for ReverseBuiltin {
+    fn name(&self) -> &str {
+        "reverse"
+    }
+
+    fn doc(&self) -> &str {
+        "reverse(x) returns a string with its characters in reverse order, or a list with its \
+        items in reverse order"
     }
-}
 
-impl Function for FuncDef {
     fn call(&self, interp: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
-        let mut new_interp = interp.new_scope();
-        for (name, val) in self.arg_names.iter().zip(args) {
-            new_interp
-                .scope
-                .borrow_mut()
-                .insert(name.to_owned(), val.clone());
-        }
-        new_interp.interp(&Expr::Block(self.block.clone()))
+        Ok(match get_arg(args, 0)? {
+            Value::String(s) => Value::String(
+                text::char_indices(s, interp.graphemes())
+                    .iter()
+                    .rev()
+                    .map(|(_, c)| *c)
+                    .collect(),
+            ),
+            Value::List(vals) => Value::List(vals.iter().rev().cloned().collect()),
+            otherwise => bail!("can't reverse {:?}", otherwise),
+        })
     }
 }
 
-impl Value {
-    fn as_func(&self) -> anyhow::Result<&dyn Function> {
-        match self {
-            Value::Function(f) => Ok(f.as_ref()),
-            otherwise => bail!("{:?} is not a function", otherwise),
-        }
+#[derive(Debug, Clone)]
+struct PadBuiltin {}
+impl Function for PadBuiltin {
+    fn name(&self) -> &str {
+        "pad"
     }
 
-    fn as_num(&self) -> anyhow::Result<i128> {
-        match self {
-            Value::Int(i) => Ok(*i),
-            otherwise => bail!("{:?} is not an integer", otherwise),
-        }
+    fn doc(&self) -> &str {
+        "pad(s, width) adds spaces to the end of the string s until it's width columns wide, for \
+        lining up tables. Strings that are already wider are left alone"
     }
 
-    fn as_bool(&self) -> anyhow::Result<bool> {
-        match self {
-            Value::Bool(b) => Ok(*b),
-            otherwise => bail!("{:?} is not a bool", otherwise),
+    fn call(&self, interp: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let s = get_arg(args, 0)?.as_str()?;
+        let width = get_arg(args, 1)?.as_num()?;
+        if width > MAX_PAD_WIDTH as i128 {
+            bail!(
+                "can't pad to {} columns, the most is {}",
+                width,
+                MAX_PAD_WIDTH
+            );
         }
+        let width = width.max(0) as usize;
+        let padding = width.saturating_sub(text::width(s, interp.graphemes()));
+        Ok(Value::String(format!("{}{}", s, " ".repeat(padding))))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct GlobalsBuiltin {}
+impl Function for GlobalsBuiltin {
+    fn name(&self) -> &str {
+        "globals"
     }
 
-    fn as_str(&self) -> anyhow::Result<&str> {
-        match self {
-            Value::String(s) => Ok(s),
-            otherwise => bail!("{:?} is not a String", otherwise),
-        }
+    fn doc(&self) -> &str {
+        "globals() returns a map of the top level variables, even ones hidden by a variable with the same name inside a function"
+    }
+
+    fn call(&self, interp: &mut Interpreter, _args: &[Value]) -> anyhow::Result<Value> {
+        let root = Scope::root(&interp.scope);
+        let globals = root
+            .borrow()
+            .this
+            .iter()
+            .map(|(name, val)| (Value::String(name.clone()), val.clone()))
+            .collect();
+        Ok(Value::Map(globals))
     }
 }
 
-#[derive(Debug, Clone, DynPartialEq, PartialEq)]
-struct SetBuiltin {}
-impl Function for SetBuiltin {
-    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
-        let str = get_arg(args, 0)?.as_str()?;
-        let index = get_arg(args, 1)?.as_num()?;
-        let new = get_arg(args, 2)?.as_str()?;
-        let (left, right) = str.split_at(index as usize);
-        Ok(Value::String(format!("{}{}{}", left, new, &right[1..])))
+type HostFnBody = dyn Fn(&[Value]) -> anyhow::Result<Value> + Send + Sync;
+
+// a closure registered with register_fn
+#[derive(Clone)]
+struct HostFn {
+    name: String,
+    f: Arc<HostFnBody>,
+}
+
+impl Debug for HostFn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HostFn").field("name", &self.name).finish()
     }
 }
 
-#[derive(Debug, Clone, DynPartialEq, PartialEq)]
-struct AddBuiltin {}
-impl Function for AddBuiltin {
-    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
-        let lhs = get_arg(args, 0)?.as_num()?;
-        let rhs = get_arg(args, 1)?.as_num()?;
-        Ok(Value::Int(lhs + rhs))
+impl Function for HostFn {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn call(&self, _interp: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        (self.f)(args)
     }
 }
 
-#[derive(Debug, Clone, DynPartialEq, PartialEq)]
-struct MulBuiltin {}
-impl Function for MulBuiltin {
+#[derive(Debug, Clone)]
+struct ExitBuiltin {}
+impl Function for ExitBuiltin {
+    fn name(&self) -> &str {
+        "exit"
+    }
+
+    fn doc(&self) -> &str {
+        "exit(code) stops the program right away, and zac exits with code. exit() is exit(0)"
+    }
+
     fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
-        let lhs = get_arg(args, 0)?.as_num()?;
-        let rhs = get_arg(args, 1)?.as_num()?;
-        Ok(Value::Int(lhs * rhs))
+        let code = match args.first() {
+            Some(code) => code.as_num()?,
+            None => 0,
+        };
+        let code = i32::try_from(code)
+            .map_err(|_| anyhow!("can't exit with {}, exit codes fit in an i32", code))?;
+        Err(SoldierError::Exited(code).into())
     }
 }
 
-fn get_arg(args: &[Value], n: usize) -> anyhow::Result<&Value> {
-    args.get(n).ok_or_else(|| {
-        anyhow!(
-            "not enough arguments, was looking for {} but only {} were provided",
-            n,
-            args.len()
-        )
-    })
-}
+#[derive(Debug, Clone)]
+struct EvalBuiltin {}
+impl Function for EvalBuiltin {
+    fn name(&self) -> &str {
+        "eval"
+    }
 
-#[derive(Debug, Clone, DynPartialEq, PartialEq)]
-struct EqBuiltin {}
-impl Function for EqBuiltin {
-    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
-        let lhs = get_arg(args, 0)?;
-        let rhs = get_arg(args, 1)?;
-        Ok(Value::Bool(lhs == rhs))
+    fn doc(&self) -> &str {
+        "eval(code) runs the string code as a program and returns its last value, so eval(#snippet) \
+        runs a comment. It runs where it's called, so it can use and change the variables there"
+    }
+
+    fn call(&self, interp: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        run_to_end(self.call_resumable(interp, args))
+    }
+
+    fn call_resumable<'a>(
+        &'a self,
+        interp: &'a mut Interpreter,
+        args: &'a [Value],
+    ) -> Evaluation<'a> {
+        Box::pin(async move {
+            let mut code = get_arg(args, 0)?.as_str()?.to_owned();
+            if !code.ends_with('\n') {
+                code.push('\n');
+            }
+            let program =
+                parse_program(&code).map_err(|diagnostic| anyhow!("eval: {}", diagnostic))?;
+            // it burns fuel and goes deeper like any other block, so eval inside eval inside eval
+            // stops at the same limits as anything else
+            interp
+                .eval_block(&program.block)
+                .await
+                .map_err(|err| from_eval(&code, err))
+        })
     }
 }
 
-#[derive(Debug, Clone, DynPartialEq, PartialEq)]
-struct GtBuiltin {}
-impl Function for GtBuiltin {
-    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
-        let lhs = get_arg(args, 0)?.as_num()?;
-        let rhs = get_arg(args, 1)?.as_num()?;
-        Ok(Value::Bool(lhs > rhs))
+// an error from code eval ran. its span is into that code rather than the program, so it's
+// taken off for the error to get the span of the eval call instead. the ones that aren't about
+// the code itself keep their kind, and the rest say where in the code they happened
+fn from_eval(code: &str, err: anyhow::Error) -> anyhow::Error {
+    match SoldierError::from(err) {
+        err @ (SoldierError::FuelExhausted { .. }
+        | SoldierError::MaxDepthExceeded { .. }
+        | SoldierError::AssertionFailed { .. }
+        | SoldierError::Exited(_)) => err.without_span().into(),
+        err => match err.span() {
+            Some(span) => {
+                let (line, col) = parser::line_col(code, span);
+                anyhow!("eval: line {}, col {}: {}", line, col, err)
+            }
+            None => anyhow!("eval: {}", err),
+        },
     }
 }
 
-#[derive(Debug, Clone, DynPartialEq, PartialEq)]
-struct LtBuiltin {}
-impl Function for LtBuiltin {
+// json_parse and json_show go through JSON this deep and no deeper, so neither can run out of
+// stack on it
+const MAX_JSON_DEPTH: usize = 128;
+
+#[derive(Debug, Clone)]
+struct JsonParseBuiltin {}
+impl Function for JsonParseBuiltin {
+    fn name(&self) -> &str {
+        "json_parse"
+    }
+
+    fn doc(&self) -> &str {
+        "json_parse(s) turns the JSON s into values: objects become maps with string keys, arrays \
+        become lists, and strings, bools and null stay what they are. Zac only has integers, so \
+        a number with a fraction or an exponent is an error"
+    }
+
     fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
-        let lhs = get_arg(args, 0)?.as_num()?;
-        let rhs = get_arg(args, 1)?.as_num()?;
-        //println!("{:?} < {:?}", lhs, rhs);
-        Ok(Value::Bool(lhs < rhs))
+        let s = get_arg(args, 0)?.as_str()?;
+        if Json::nesting(s) > MAX_JSON_DEPTH {
+            bail!(
+                "json_parse: the JSON is nested more than {} deep",
+                MAX_JSON_DEPTH
+            );
+        }
+        let json = Json::parse(s).map_err(|err| anyhow!("json_parse: {}", err))?;
+        Ok(json_to_value(json))
     }
 }
 
-#[derive(Debug, Clone, DynPartialEq, PartialEq)]
-struct NotBuiltin {}
-impl Function for NotBuiltin {
-    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
-        let val = get_arg(args, 0)?.as_bool()?;
-        Ok(Value::Bool(!val))
+fn json_to_value(json: Json) -> Value {
+    match json {
+        Json::Null => Value::Null,
+        Json::Bool(b) => Value::Bool(b),
+        Json::Int(n) => Value::Int(n),
+        Json::String(s) => Value::String(s),
+        Json::Array(items) => Value::List(items.into_iter().map(json_to_value).collect()),
+        Json::Object(fields) => Value::Map(
+            fields
+                .into_iter()
+                .map(|(k, v)| (Value::String(k), json_to_value(v)))
+                .collect(),
+        ),
     }
 }
 
-#[derive(Debug, Clone, DynPartialEq, PartialEq)]
-struct AndBuiltin {}
-impl Function for AndBuiltin {
+#[derive(Debug, Clone)]
+struct JsonShowBuiltin {}
+impl Function for JsonShowBuiltin {
+    fn name(&self) -> &str {
+        "json_show"
+    }
+
+    fn doc(&self) -> &str {
+        "json_show(x) returns x as JSON, the other way around from json_parse. Maps need string \
+        keys, and functions can't be JSON at all"
+    }
+
     fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
-        let lhs = get_arg(args, 0)?.as_bool()?;
-        let rhs = get_arg(args, 1)?.as_bool()?;
-        Ok(Value::Bool(lhs && rhs))
+        let json = value_to_json(get_arg(args, 0)?, 0)?;
+        Ok(Value::String(json.to_string()))
     }
 }
 
-#[derive(Debug, Clone, DynPartialEq, PartialEq)]
-struct OrBuiltin {}
-impl Function for OrBuiltin {
-    fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
-        let lhs = get_arg(args, 0)?.as_bool()?;
-        let rhs = get_arg(args, 1)?.as_bool()?;
-        Ok(Value::Bool(lhs || rhs))
+fn value_to_json(val: &Value, depth: usize) -> anyhow::Result<Json> {
+    if depth > MAX_JSON_DEPTH {
+        bail!(
+            "json_show: the value is nested more than {} deep",
+            MAX_JSON_DEPTH
+        );
     }
+    Ok(match val {
+        Value::Null => Json::Null,
+        Value::Bool(b) => Json::Bool(*b),
+        Value::Int(n) => Json::Int(*n),
+        Value::String(s) => Json::String(s.clone()),
+        Value::List(items) => Json::Array(
+            items
+                .iter()
+                .map(|item| value_to_json(item, depth + 1))
+                .collect::<anyhow::Result<_>>()?,
+        ),
+        Value::Map(map) => Json::Object(
+            map.iter()
+                .map(|(k, v)| match k {
+                    Value::String(k) => Ok((k.clone(), value_to_json(v, depth + 1)?)),
+                    k => bail!(
+                        "json_show: {:?} can't be a key in JSON, only strings can",
+                        k
+                    ),
+                })
+                .collect::<anyhow::Result<_>>()?,
+        ),
+        Value::Function(_) | Value::Opaque(_) => {
+            bail!("json_show: {:?} can't be shown as JSON", val)
+        }
+    })
 }
 
-#[derive(Debug, Clone, DynPartialEq, PartialEq)]
-struct PrintBuiltin {}
-impl Function for PrintBuiltin {
+#[derive(Debug, Clone)]
+struct AssertBuiltin {}
+impl Function for AssertBuiltin {
+    fn name(&self) -> &str {
+        "assert"
+    }
+
+    fn doc(&self) -> &str {
+        "assert(cond) stops the program with an error if cond is false, and otherwise does \
+        nothing. assert(cond, message) puts message in the error"
+    }
+
     fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
-        let val = get_arg(args, 0)?;
-        println!("{:?}", val);
-        Ok(val.clone())
+        if get_arg(args, 0)?.as_bool()? {
+            return Ok(Value::Null);
+        }
+        Err(SoldierError::AssertionFailed {
+            message: args.get(1).map(|message| message.to_string()),
+            span: None,
+        }
+        .into())
     }
 }
 
-#[derive(Debug, Clone, DynPartialEq, PartialEq)]
-struct CatBuiltin {}
-impl Function for CatBuiltin {
+#[derive(Debug, Clone)]
+struct AssertEqBuiltin {}
+impl Function for AssertEqBuiltin {
+    fn name(&self) -> &str {
+        "assert_eq"
+    }
+
+    fn doc(&self) -> &str {
+        "assert_eq(a, b) stops the program with an error showing a and b if they aren't equal"
+    }
+
     fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
-        let mut acc = String::new();
-        for arg in args {
-            let str = arg.as_str()?;
-            acc.push_str(str);
+        let a = get_arg(args, 0)?;
+        let b = get_arg(args, 1)?;
+        if a == b {
+            return Ok(Value::Null);
         }
-        Ok(Value::String(acc))
+        Err(SoldierError::AssertionFailed {
+            message: Some(format!("{} isn't equal to {}", a, b)),
+            span: None,
+        }
+        .into())
     }
 }
 
-#[derive(Debug, Clone, DynPartialEq, PartialEq)]
-struct ChrBuiltin {}
-impl Function for ChrBuiltin {
+#[derive(Debug, Clone)]
+struct TypeofBuiltin {}
+impl Function for TypeofBuiltin {
+    fn name(&self) -> &str {
+        "typeof"
+    }
+
+    fn doc(&self) -> &str {
+        "typeof(x) returns what kind of value x is: \"bool\", \"int\", \"string\", \"list\", \
+        \"map\", \"function\", \"opaque\" or \"null\""
+    }
+
     fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
-        let val = get_arg(args, 0)?.as_num()?.to_le_bytes()[0];
-        Ok(Value::String(from_utf8(&[val])?.to_string()))
+        Ok(Value::String(get_arg(args, 0)?.kind().name().to_owned()))
     }
 }
 
-#[derive(Debug, Clone, DynPartialEq, PartialEq)]
-struct ShowBuiltin {}
-impl Function for ShowBuiltin {
+// is_string(x) is typeof(x) == "string", and so on for every kind
+#[derive(Debug, Clone)]
+struct IsKindBuiltin {
+    kind: ValueKind,
+}
+impl Function for IsKindBuiltin {
+    fn name(&self) -> &str {
+        self.kind.describe().1
+    }
+
+    fn doc(&self) -> &str {
+        self.kind.describe().2
+    }
+
     fn call(&self, _: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
-        let val = get_arg(args, 0)?;
-        Ok(Value::String(wrapping::stringify(val)))
+        Ok(Value::Bool(get_arg(args, 0)?.kind() == self.kind))
     }
 }
 
@@ -574,14 +4678,22 @@ const WELCOME_TEXT: &str = r#"Help for the Zac Programming Language (https://git
 
 Define a comment with the first line set to an identifier (like #help) and it
 will be a string usable inside of your program. You can read from it, and if
-you write to it, the change will be reflected inside the source file."#;
+you write to it, the change will be reflected inside the source file.
+
+`loop { ... }` runs its body over and over. There's no `break` yet, so the
+only ways out are exit() and errors. To stop when something becomes false,
+use `while (something) { ... }` instead."#;
 
 fn generate_help_text(interp: &Interpreter) -> String {
     let mut function_names = vec![];
+    let mut function_docs = vec![];
     let mut variable_names = vec![];
     for (name, global_var_value) in &interp.scope.borrow().this {
-        if global_var_value.as_func().is_ok() {
+        if let Ok(func) = global_var_value.as_func() {
             function_names.push(name.to_string());
+            if !func.doc().is_empty() {
+                function_docs.push((name.to_string(), func.doc().to_string()));
+            }
         } else {
             if !BUILTIN_CONSTANTS.lock().unwrap().contains_key(name) {
                 variable_names.push(name.to_string());
@@ -621,6 +4733,16 @@ fn generate_help_text(interp: &Interpreter) -> String {
         txt.push_str("\nAvailable comments:\n");
         txt.push_str(&tableize(non_builtin_comment_names.iter().map(|s| s.as_str())).to_string());
     }
+    if !function_docs.is_empty() {
+        // one paragraph each, so they get refilled separately
+        txt.push_str("\nFunction docs:\n");
+        txt.push_str(
+            &function_docs
+                .iter()
+                .map(|(name, doc)| format!("{}: {}", name, doc))
+                .join("\n\n"),
+        );
+    }
     txt.trim_end().into()
 }
 
@@ -0,0 +1,268 @@
+use itertools::Itertools;
+use std::fmt::{Display, Formatter};
+
+// just enough JSON for the program description format. numbers are integers only, the same as
+// Zac's own numbers
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    Int(i128),
+    String(String),
+    Array(Vec<Json>),
+    // keeps the keys in the order they were written
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    pub fn parse(s: &str) -> anyhow::Result<Json> {
+        Ok(json::document(s)?)
+    }
+
+    // how deep s nests arrays and objects, not counting brackets in strings. the parser goes a
+    // level deeper into the stack for each one, so JSON from a program is checked before it's
+    // parsed
+    pub fn nesting(s: &str) -> usize {
+        let mut depth = 0usize;
+        let mut deepest = 0;
+        let mut in_string = false;
+        let mut escaped = false;
+        for c in s.chars() {
+            if in_string {
+                match c {
+                    _ if escaped => escaped = false,
+                    '\\' => escaped = true,
+                    '"' => in_string = false,
+                    _ => (),
+                }
+                continue;
+            }
+            match c {
+                '"' => in_string = true,
+                '[' | '{' => {
+                    depth += 1;
+                    deepest = deepest.max(depth);
+                }
+                ']' | '}' => depth = depth.saturating_sub(1),
+                _ => (),
+            }
+        }
+        deepest
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Json::Null => "null",
+            Json::Bool(_) => "bool",
+            Json::Int(_) => "number",
+            Json::String(_) => "string",
+            Json::Array(_) => "array",
+            Json::Object(_) => "object",
+        }
+    }
+}
+
+impl Display for Json {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            Json::Int(n) => write!(f, "{}", n),
+            Json::String(s) => write_json_string(f, s),
+            Json::Array(items) => write!(f, "[{}]", items.iter().join(",")),
+            Json::Object(fields) => {
+                write!(f, "{{")?;
+                for (i, (k, v)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_json_string(f, k)?;
+                    write!(f, ":{}", v)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_json_string(f: &mut Formatter<'_>, s: &str) -> std::fmt::Result {
+    write!(f, "\"")?;
+    for c in s.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+peg::parser! {
+    grammar json() for str {
+        pub rule document() -> Json
+            = _ v:value() _ { v }
+
+        rule value() -> Json
+            = "null" { Json::Null } /
+              "true" { Json::Bool(true) } /
+              "false" { Json::Bool(false) } /
+              n:number() { Json::Int(n) } /
+              s:string() { Json::String(s) } /
+              "[" _ items:(value() ** comma()) _ "]" { Json::Array(items) } /
+              "{" _ fields:(field() ** comma()) _ "}" { Json::Object(fields) }
+
+        rule field() -> (String, Json)
+            = k:string() _ ":" _ v:value() { (k, v) }
+
+        // only the first digit is listed as expected, so a number that isn't an integer is
+        // reported with just why
+        rule number() -> i128
+            = n:$("-"? ['0'..='9'] quiet!{
+                ['0'..='9']* ("." ['0'..='9']+)? (['e' | 'E'] ['+' | '-']? ['0'..='9']+)?
+            }) {?
+                n.parse().or(Err("an integer that fits in 128 bits"))
+            }
+
+        rule string() -> String
+            = "\"" chars:string_char()* "\"" { chars.into_iter().collect() }
+
+        rule string_char() -> char
+            = "\\u" hi:hex4() "\\u" lo:hex4() {?
+                char::decode_utf16([hi, lo]).next().and_then(|c| c.ok()).ok_or("a valid surrogate pair")
+            } /
+              "\\u" n:hex4() {? char::from_u32(n as u32).ok_or("a unicode scalar value") } /
+              "\\" c:['"' | '\\' | '/' | 'b' | 'f' | 'n' | 'r' | 't'] {
+                match c {
+                    'b' => '\u{8}',
+                    'f' => '\u{c}',
+                    'n' => '\n',
+                    'r' => '\r',
+                    't' => '\t',
+                    c => c,
+                }
+            } /
+              c:[^ '"' | '\\'] { c }
+
+        rule hex4() -> u16
+            = h:$(['0'..='9' | 'a'..='f' | 'A'..='F']*<4>) { u16::from_str_radix(h, 16).unwrap() }
+
+        rule comma() = _ "," _
+        rule _() = quiet!{ [' ' | '\t' | '\r' | '\n']* }
+    }
+}
+
+// the description format's serde support goes through these, see description.rs
+#[cfg(feature = "serde")]
+mod serde_impls {
+    use super::Json;
+    use serde::de::{Error, MapAccess, SeqAccess, Visitor};
+    use serde::ser::{SerializeMap, SerializeSeq};
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    impl Serialize for Json {
+        fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            match self {
+                Json::Null => serializer.serialize_unit(),
+                Json::Bool(b) => serializer.serialize_bool(*b),
+                Json::Int(n) => match i64::try_from(*n) {
+                    Ok(small) => serializer.serialize_i64(small),
+                    Err(_) => serializer.serialize_i128(*n),
+                },
+                Json::String(s) => serializer.serialize_str(s),
+                Json::Array(items) => {
+                    let mut seq = serializer.serialize_seq(Some(items.len()))?;
+                    for item in items {
+                        seq.serialize_element(item)?;
+                    }
+                    seq.end()
+                }
+                Json::Object(fields) => {
+                    let mut map = serializer.serialize_map(Some(fields.len()))?;
+                    for (k, v) in fields {
+                        map.serialize_entry(k, v)?;
+                    }
+                    map.end()
+                }
+            }
+        }
+    }
+
+    impl<'de> Deserialize<'de> for Json {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            deserializer.deserialize_any(JsonVisitor)
+        }
+    }
+
+    struct JsonVisitor;
+
+    impl<'de> Visitor<'de> for JsonVisitor {
+        type Value = Json;
+
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(f, "JSON without fractions")
+        }
+
+        fn visit_bool<E: Error>(self, b: bool) -> Result<Json, E> {
+            Ok(Json::Bool(b))
+        }
+
+        fn visit_i64<E: Error>(self, n: i64) -> Result<Json, E> {
+            Ok(Json::Int(n.into()))
+        }
+
+        fn visit_u64<E: Error>(self, n: u64) -> Result<Json, E> {
+            Ok(Json::Int(n.into()))
+        }
+
+        fn visit_i128<E: Error>(self, n: i128) -> Result<Json, E> {
+            Ok(Json::Int(n))
+        }
+
+        fn visit_str<E: Error>(self, s: &str) -> Result<Json, E> {
+            Ok(Json::String(s.to_owned()))
+        }
+
+        fn visit_string<E: Error>(self, s: String) -> Result<Json, E> {
+            Ok(Json::String(s))
+        }
+
+        fn visit_unit<E: Error>(self) -> Result<Json, E> {
+            Ok(Json::Null)
+        }
+
+        fn visit_none<E: Error>(self) -> Result<Json, E> {
+            Ok(Json::Null)
+        }
+
+        fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Json, D::Error> {
+            Json::deserialize(deserializer)
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Json, A::Error> {
+            let mut items = vec![];
+            while let Some(item) = seq.next_element()? {
+                items.push(item);
+            }
+            Ok(Json::Array(items))
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Json, A::Error> {
+            let mut fields = vec![];
+            while let Some(field) = map.next_entry()? {
+                fields.push(field);
+            }
+            Ok(Json::Object(fields))
+        }
+    }
+}
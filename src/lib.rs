@@ -1,48 +1,90 @@
+//! Zac runs programs that can read and change their own comments. To run one from Rust, values
+//! go in and come out as [`interp::Value`]s, which convert to and from the usual Rust types:
+//!
+//! ```
+//! use std::collections::BTreeMap;
+//! use zac_lib::interp::{Interpreter, Value};
+//!
+//! let mut interp = Interpreter::new();
+//! interp.register_fn("config", |_| {
+//!     let mut config = BTreeMap::new();
+//!     config.insert(Value::from("name"), Value::from("server"));
+//!     config.insert(Value::from("retries"), Value::from(3u32));
+//!     Ok(Value::from(config))
+//! });
+//! let result = interp
+//!     .run_source("let c = config()\nlet c(\"retries\") = c.retries * 2\nc\n")
+//!     .unwrap();
+//!
+//! let config = BTreeMap::<Value, Value>::try_from(result).unwrap();
+//! let name: &str = (&config[&Value::from("name")]).try_into().unwrap();
+//! let retries = u32::try_from(&config[&Value::from("retries")]).unwrap();
+//! assert_eq!((name, retries), ("server", 6));
+//! ```
+
 #![feature(exclusive_range_pattern)]
 #![feature(map_try_insert)]
 #![feature(in_band_lifetimes)]
 #![feature(box_syntax)]
 
+use crate::error::render_error;
 use crate::interp::builtin_comment;
-use crate::parser::{find_comments_mut, Expr, Program};
+use crate::parser::{find_comments_mut, CommentStyle, Program};
 use crate::wrapping::rewrap;
 use anyhow::anyhow;
 use interp::Interpreter;
+use itertools::Itertools;
 
+pub mod comment_changes;
+pub mod comment_index;
+pub mod debugger;
+pub mod description;
+pub mod error;
 pub mod interp;
+mod json;
 pub mod parser;
+pub mod playground;
 pub mod reassemble;
+mod rng;
+mod text;
+pub mod trace;
+#[cfg(feature = "serde")]
+pub mod value_serde;
+pub mod vfs;
+#[cfg(feature = "wasm")]
+pub mod wasm;
 mod wrapping;
 
 pub fn run(code: &str) -> anyhow::Result<String> {
-    let mut program = parser::parser::program(code)?;
+    let program = parser::parse_program(code)?;
     let mut interp = Interpreter::new();
-    for (_, comment) in find_comments_mut(&mut program)? {
-        interp.add_comment(comment)?;
-    }
-
-    let block = Expr::Block(program.block.clone());
-    interp.interp(&block)?;
-
-    replace_comments_in_source_code(&mut program, &mut interp)?;
-
-    Ok(reassemble::output_code(&program, &interp))
+    interp.set_source(code);
+    interp
+        .run_program(&program)
+        .map_err(|err| anyhow!(render_error(code, &err)))?;
+    interp.writeback(&program)
 }
 
 pub fn replace_comments_in_source_code(
     mut program: &mut Program,
-    interp: &mut Interpreter,
+    interp: &Interpreter,
 ) -> anyhow::Result<()> {
     let mut comments = find_comments_mut(&mut program)?;
     for (name, body) in interp.comments().iter() {
         let code_comment = comments
             .get_mut(name)
             .ok_or_else(|| anyhow!("original code didn't contain comment {}", name))?;
-        code_comment.body = rewrap(&if let Some(builtin) = builtin_comment(interp, name) {
+        let body = if let Some(builtin) = builtin_comment(interp, name) {
             builtin
         } else {
             body.to_string()
-        });
+        };
+        // block comments are kept exactly as written, only line comments get refilled
+        code_comment.body = match code_comment.style {
+            CommentStyle::Line => rewrap(&body),
+            CommentStyle::Block => body,
+            CommentStyle::Trailing => body.lines().join(" "),
+        };
     }
     Ok(())
 }
@@ -4,46 +4,328 @@
 #![feature(box_syntax)]
 
 use anyhow::anyhow;
+use std::cell::RefCell;
 use std::fs::{read_to_string, File};
-use std::io::{stdout, Write};
-use zac_lib::replace_comments_in_source_code;
+use std::io::{stderr, stdin, stdout, Write};
+use std::rc::Rc;
+use zac_lib::comment_changes;
 
-use zac_lib::interp::Interpreter;
+use zac_lib::error::{render_error, SoldierError};
+use zac_lib::interp::{Interpreter, InterpreterBuilder};
 use zac_lib::parser;
-use zac_lib::parser::{find_comments_mut, Expr};
+use zac_lib::parser::Program;
 use zac_lib::reassemble;
+use zac_lib::reassemble::AssembleOptions;
+use zac_lib::trace::{TraceFormat, Tracer, DEFAULT_MAX_VALUE_LEN};
+use zac_lib::vfs::RealFs;
 
-pub fn main() -> anyhow::Result<()> {
-    let (filename, is_dry_run) = parse_args()?;
+mod repl;
+
+enum Command {
+    Run {
+        filename: String,
+        options: RunOptions,
+    },
+    RunDescription {
+        filename: String,
+    },
+    Ast {
+        filename: String,
+    },
+    // the program written back out without running it, the way it would be after a run that
+    // didn't change anything
+    Reassembled {
+        filename: String,
+    },
+    Comments {
+        filename: String,
+    },
+    Repl,
+    Fmt {
+        filename: String,
+        // only say whether the file would change
+        check: bool,
+        options: AssembleOptions,
+    },
+}
+
+#[derive(Default)]
+struct RunOptions {
+    is_dry_run: bool,
+    allow_fs: bool,
+    // ask before writing each changed comment back
+    confirm_writes: bool,
+    trace: Option<TraceOptions>,
+    // everything after --, for the program's args()
+    args: Vec<String>,
+}
 
-    let input = read_to_string(&filename)?;
-    let mut program = parser::parser::program(&input)?;
+struct TraceOptions {
+    format: TraceFormat,
+    // stderr if there's no file
+    out: Option<String>,
+    max_value_len: usize,
+}
 
-    let mut interp = Interpreter::new();
-    for (_, comment) in find_comments_mut(&mut program)? {
-        interp.add_comment(comment)?;
+pub fn main() -> anyhow::Result<()> {
+    match parse_args()? {
+        Command::Run { filename, options } => {
+            let input = read_to_string(&filename)?;
+            let program = parse(&input)?;
+            let is_dry_run = options.is_dry_run;
+            let assembled = run(program, Some(&input), options)?;
+            if is_dry_run {
+                stdout().lock().write_all(assembled.as_bytes())?;
+            } else {
+                File::create(&filename)?.write_all(assembled.as_bytes())?;
+            }
+        }
+        // there's no source file to write comments back into, so the result always goes to stdout
+        Command::RunDescription { filename } => {
+            let program = parser::from_description(&read_to_string(&filename)?)?;
+            let options = RunOptions {
+                is_dry_run: true,
+                ..RunOptions::default()
+            };
+            let assembled = run(program, None, options)?;
+            stdout().lock().write_all(assembled.as_bytes())?;
+        }
+        Command::Ast { filename } => {
+            let program = parse(&read_to_string(&filename)?)?;
+            println!("{}", parser::to_description(&program));
+        }
+        Command::Reassembled { filename } => {
+            let program = parse(&read_to_string(&filename)?)?;
+            let assembled = reassemble::output_code(&program, &Interpreter::new());
+            stdout().lock().write_all(assembled.as_bytes())?;
+        }
+        Command::Comments { filename } => {
+            for comment in parser::load_or_build_index(&filename)?.comments {
+                println!("#{}", comment.name);
+            }
+        }
+        Command::Fmt {
+            filename,
+            check,
+            options,
+        } => {
+            let source = read_to_string(&filename)?;
+            // nothing is written unless the formatted program is the same program
+            let formatted = reassemble::format_source(&source, &options)
+                .map_err(|err| anyhow!("{}: {}", filename, err))?;
+            if check {
+                if formatted != source {
+                    eprintln!("{} isn't formatted", filename);
+                    std::process::exit(1);
+                }
+            } else if formatted != source {
+                File::create(&filename)?.write_all(formatted.as_bytes())?;
+            }
+        }
+        Command::Repl => {
+            let mut repl = repl::Repl::new(cli_interpreter());
+            repl.run(stdin().lock(), &mut stdout())?;
+        }
     }
+    Ok(())
+}
 
-    let block = Expr::Block(program.block.clone());
-    interp.interp(&block)?;
+// on a parse error, reports every error in the file rather than only the first
+fn parse(source: &str) -> anyhow::Result<Program> {
+    match parser::parse_program(source) {
+        Ok(program) => Ok(program),
+        Err(first) => {
+            let (_, mut diagnostics) = parser::parse_program_lenient(source);
+            if diagnostics.is_empty() {
+                diagnostics.push(first);
+            }
+            Err(anyhow!(
+                "{}",
+                diagnostics
+                    .iter()
+                    .map(|d| d.to_string())
+                    .collect::<Vec<_>>()
+                    .join("\n\n")
+            ))
+        }
+    }
+}
 
-    replace_comments_in_source_code(&mut program, &mut interp)?;
+// the command line has the environment, time and file builtins too, though the file ones still
+// need --allow-fs to get to any files
+fn cli_interpreter() -> Interpreter {
+    InterpreterBuilder::new()
+        .default_builtins()
+        .env_builtins()
+        .time_builtins()
+        .fs_builtins()
+        .build()
+}
 
-    let assembled = reassemble::output_code(&program, &interp);
-    if is_dry_run {
-        stdout().lock().write_all(assembled.as_bytes())?;
-    } else {
-        File::create(&filename)?.write_all(assembled.as_bytes())?;
+// source is what the program was parsed from, if it came from source code
+fn run(program: Program, source: Option<&str>, options: RunOptions) -> anyhow::Result<String> {
+    let mut interp = cli_interpreter();
+    if let Some(source) = source {
+        interp.set_source(source);
     }
-    Ok(())
+    interp.set_fs(Box::new(RealFs::new(options.allow_fs)));
+    interp.set_args(options.args);
+    // errors point at the line they happened on, when there's a source to show
+    let located = |err: SoldierError| match source {
+        Some(source) => anyhow!(render_error(source, &err)),
+        None => err.into(),
+    };
+    let tracer = options
+        .trace
+        .as_ref()
+        .map(|trace| Rc::new(RefCell::new(Tracer::new(trace.max_value_len))));
+    if let Some(tracer) = &tracer {
+        interp.set_tracer(Rc::clone(tracer));
+    }
+
+    let result = interp.run_program(&program);
+    // the trace is written even when the program fails, that's when it's most useful
+    if let (Some(trace), Some(tracer)) = (options.trace, tracer) {
+        let rendered = tracer.borrow().render(trace.format);
+        match trace.out {
+            Some(out) => File::create(out)?.write_all(rendered.as_bytes())?,
+            None => stderr().lock().write_all(rendered.as_bytes())?,
+        }
+    }
+    match result {
+        // any other code is a failure, and like an error leaves the file as it was
+        Err(SoldierError::Exited(code)) if code != 0 => std::process::exit(code),
+        Err(SoldierError::Exited(_)) => (),
+        result => {
+            result.map_err(located)?;
+        }
+    }
+    for warning in interp.warnings() {
+        eprintln!("warning: {}", warning);
+    }
+
+    let mutations = interp.comment_mutations();
+    if !options.is_dry_run && !mutations.is_empty() {
+        eprint!("{}", comment_changes::render_table(&mutations));
+        if options.confirm_writes {
+            let accepted =
+                comment_changes::confirm_writes(&mutations, &mut stdin().lock(), &mut stderr())?;
+            for mutation in &mutations {
+                if !accepted.contains(&mutation.name) {
+                    interp.revert_comment_mutation(&mutation.name)?;
+                }
+            }
+        }
+    }
+
+    interp.writeback(&program)
 }
 
-fn parse_args() -> anyhow::Result<(String, bool)> {
+fn parse_args() -> anyhow::Result<Command> {
     let mut args = std::env::args();
     let cmd_name = args.next().unwrap();
-    let filename = args
-        .next()
-        .ok_or_else(|| anyhow!("usage: {} <code.zac> [--dry]", cmd_name))?;
-    let dry_run = args.next() == Some("--dry".to_string());
-    Ok((filename, dry_run))
+    let usage = || {
+        anyhow!(
+            "usage: {0} <code.zac> [--dry] [--confirm-writes] [--allow-fs] [--trace-format=human|jsonl] [--trace-out=<file>] [--trace-value-len=<n>] [-- <args>...]\n       {0} run --from-json <program.json>\n       {0} run --dump-ast|--dump-reassembled <code.zac>\n       {0} ast [--json] <code.zac>\n       {0} comments <code.zac>\n       {0} fmt [--check] [--indent=<n>] [--max-width=<n>] <code.zac>\n       {0} [repl]",
+            cmd_name
+        )
+    };
+    let first = match args.next() {
+        Some(first) => first,
+        None => return Ok(Command::Repl),
+    };
+    match first.as_str() {
+        "repl" => Ok(Command::Repl),
+        "fmt" => parse_fmt_args(args).ok_or_else(usage),
+        "run" => match args.next().as_deref() {
+            Some("--from-json") => Ok(Command::RunDescription {
+                filename: args.next().ok_or_else(usage)?,
+            }),
+            Some("--dump-ast") => Ok(Command::Ast {
+                filename: args.next().ok_or_else(usage)?,
+            }),
+            Some("--dump-reassembled") => Ok(Command::Reassembled {
+                filename: args.next().ok_or_else(usage)?,
+            }),
+            Some(filename) => parse_run_args(filename.to_owned(), args),
+            None => Err(usage()),
+        },
+        // the syntax tree is always JSON, --json is there for anyone who'd rather say so
+        "ast" => Ok(Command::Ast {
+            filename: match args.next() {
+                Some(flag) if flag == "--json" => args.next(),
+                filename => filename,
+            }
+            .ok_or_else(usage)?,
+        }),
+        "comments" => Ok(Command::Comments {
+            filename: args.next().ok_or_else(usage)?,
+        }),
+        _ => parse_run_args(first, args),
+    }
+}
+
+fn parse_fmt_args(args: impl Iterator<Item = String>) -> Option<Command> {
+    let mut filename = None;
+    let mut check = false;
+    let mut options = AssembleOptions::default();
+    for arg in args {
+        if arg == "--check" {
+            check = true;
+        } else if let Some(width) = arg.strip_prefix("--indent=") {
+            options.indent_width = width.parse().ok()?;
+        } else if let Some(width) = arg.strip_prefix("--max-width=") {
+            options.max_line_width = Some(width.parse().ok()?);
+        } else if arg.starts_with("--") || filename.is_some() {
+            return None;
+        } else {
+            filename = Some(arg);
+        }
+    }
+    Some(Command::Fmt {
+        filename: filename?,
+        check,
+        options,
+    })
+}
+
+fn parse_run_args(
+    filename: String,
+    mut args: impl Iterator<Item = String>,
+) -> anyhow::Result<Command> {
+    let mut options = RunOptions::default();
+    let mut format = None;
+    let mut out = None;
+    let mut max_value_len = DEFAULT_MAX_VALUE_LEN;
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            options.args = args.collect();
+            break;
+        } else if arg == "--dry" {
+            options.is_dry_run = true;
+        } else if arg == "--allow-fs" {
+            options.allow_fs = true;
+        } else if arg == "--confirm-writes" {
+            options.confirm_writes = true;
+        } else if let Some(name) = arg.strip_prefix("--trace-format=") {
+            format = Some(TraceFormat::from_name(name)?);
+        } else if let Some(path) = arg.strip_prefix("--trace-out=") {
+            out = Some(path.to_owned());
+        } else if let Some(len) = arg.strip_prefix("--trace-value-len=") {
+            max_value_len = len
+                .parse()
+                .map_err(|_| anyhow!("--trace-value-len needs a number, got {:?}", len))?;
+        } else {
+            return Err(anyhow!("unknown option {}", arg));
+        }
+    }
+    // asking for either a format or a file turns tracing on
+    if format.is_some() || out.is_some() {
+        options.trace = Some(TraceOptions {
+            format: format.unwrap_or(TraceFormat::Human),
+            out,
+            max_value_len,
+        });
+    }
+    Ok(Command::Run { filename, options })
 }
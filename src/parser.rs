@@ -7,13 +7,16 @@ use std::fmt::{Debug, Display};
 use std::hash::Hash;
 use std::sync::Mutex;
 
+pub use crate::comment_index::load_or_build_index;
+pub use crate::description::{from_description, parse_json, to_description, DescError};
+
 pub type ExprID = usize;
 
 lazy_static! {
     static ref NEXT_ID: Mutex<ExprID> = Mutex::new(0);
 }
 
-fn next_id() -> usize {
+pub(crate) fn next_id() -> usize {
     let mut next_id = NEXT_ID.lock().unwrap();
     let this_id = *next_id;
     *next_id += 1;
@@ -25,6 +28,27 @@ pub struct Program {
     pub block: Block,
 }
 
+// Whether two programs are the same apart from where they came from, so the positions in them and
+// the ids handed out to result comments are left out of the comparison
+pub fn ast_eq(a: &Program, b: &Program) -> bool {
+    without_ids_or_spans(&a.block) == without_ids_or_spans(&b.block)
+}
+
+fn without_ids_or_spans(block: &Block) -> Block {
+    let mut block = block.clone();
+    let mut stack = block.exprs_mut().collect::<Vec<_>>();
+    while let Some(expr) = stack.pop() {
+        if let Expr::ResultComment(id, _, _) = expr {
+            *id = 0;
+        }
+        for span in expr.spans_mut() {
+            *span = Span::default();
+        }
+        stack.extend(expr.children_mut());
+    }
+    block
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Block(pub Vec<BlockEl>);
 
@@ -50,23 +74,61 @@ pub enum BlockEl {
     NewLine,
 }
 
-// TODO: should probably put a concept of newline into here because newlines from the programmer
-// are important
 #[derive(Debug, Clone, PartialEq)]
 pub enum Expr {
     Block(Block),
     Ref(Ref),
     Comment(Comment),
     Assignment(Assignment),
-    IntLiteral(i128),
-    StringLiteral(String),
-    ListLiteral(Vec<Expr>),
+    IndexAssignment(IndexAssignment),
+    Destructure(Destructure),
+    IntLiteral(i128, Span),
+    StringLiteral(String, Span),
+    Interpolation(Vec<InterpolationPart>, Span),
+    // """triple quoted""" strings are kept verbatim: no escapes, no interpolation, and leading
+    // indentation isn't stripped, so what's between the quotes is exactly the string
+    MultiLineString(String, Span),
+    ListLiteral(Vec<Expr>, Span),
     FuncDef(FuncDef),
     FunctionCall(FunctionCall),
+    FieldAccess(FieldAccess),
     While(While),
+    // `loop { ... }`, which runs until exit() or an error stops it
+    Loop(Block, Span),
     If(If),
     BinOp(BinOp),
-    ResultComment(ExprID, Box<Expr>),
+    // the span is where the `// #...` after the expression is
+    ResultComment(ExprID, Box<Expr>, Span),
+    TrailingComment(Box<Expr>, Comment),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum InterpolationPart {
+    Literal(String),
+    Expr(Expr),
+}
+
+// adjacent literal parts come out of the parser separately (e.g. around an escaped `\${`), so
+// glue them back together
+fn merge_literals(parts: Vec<InterpolationPart>) -> Vec<InterpolationPart> {
+    let mut merged: Vec<InterpolationPart> = vec![];
+    for part in parts {
+        match (merged.last_mut(), part) {
+            (Some(InterpolationPart::Literal(acc)), InterpolationPart::Literal(s)) => {
+                acc.push_str(&s)
+            }
+            (_, part) => merged.push(part),
+        }
+    }
+    merged
+}
+
+fn unescape_string_chunk(chunk: &str) -> Result<String, &'static str> {
+    let quoted = format!("\"{}\"", chunk);
+    Ok(StringLit::parse(quoted.as_str())
+        .map_err(|_| "string_lit")?
+        .value()
+        .to_owned())
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -90,6 +152,7 @@ pub struct BinOp {
     pub op: Op,
     pub lhs: Box<Expr>,
     pub rhs: Box<Expr>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -97,19 +160,55 @@ pub struct FuncDef {
     pub name: String,
     pub arg_names: Vec<String>,
     pub block: Block,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Comment {
     pub name: Option<String>,
     pub body: String,
+    pub style: CommentStyle,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CommentStyle {
+    Line,
+    // /* ... */ comments, the body is kept byte for byte
+    Block,
+    // a comment at the end of a line of code, its body is always a single line
+    Trailing,
+}
+
+impl CommentStyle {
+    pub fn name(&self) -> &'static str {
+        match self {
+            CommentStyle::Line => "line",
+            CommentStyle::Block => "block",
+            CommentStyle::Trailing => "trailing",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "line" => Some(CommentStyle::Line),
+            "block" => Some(CommentStyle::Block),
+            "trailing" => Some(CommentStyle::Trailing),
+            _ => None,
+        }
+    }
+}
+
+enum Trailing {
+    Result(Span),
+    Comment(Comment),
 }
 
 pub fn find_comments_mut(
     program: &'a mut Program,
 ) -> anyhow::Result<HashMap<String, &'a mut Comment>> {
     let mut comments = HashMap::new();
-    for expr in &mut program.block.exprs_mut() {
+    for expr in program.block.exprs_mut() {
         try_extend(&mut comments, &mut find_expr_comments_mut(expr)?)?;
     }
     Ok(comments)
@@ -117,52 +216,163 @@ pub fn find_comments_mut(
 
 fn find_expr_comments_mut(expr: &'a mut Expr) -> anyhow::Result<HashMap<String, &'a mut Comment>> {
     let mut comments = HashMap::new();
-    match expr {
-        Expr::Block(block) => {
-            for expr in block.exprs_mut() {
-                try_extend(&mut comments, &mut find_expr_comments_mut(expr)?)?;
+    let mut stack = vec![expr];
+    while let Some(expr) = stack.pop() {
+        let comment = match expr {
+            Expr::Comment(c) => Some(c),
+            Expr::TrailingComment(expr, c) => {
+                stack.push(expr);
+                Some(c)
             }
-        }
-        Expr::Comment(c) => {
-            let name = c.name.clone();
-            if let Some(name) = name {
+            expr => {
+                stack.extend(expr.children_mut().into_iter().rev());
+                None
+            }
+        };
+        if let Some(c) = comment {
+            if let Some(name) = c.name.clone() {
                 try_insert(&mut comments, name, c)?;
             }
         }
-        Expr::Assignment(Assignment { r#ref: _, expr }) => {
-            try_extend(&mut comments, &mut find_expr_comments_mut(expr)?)?;
+    }
+    Ok(comments)
+}
+
+// calls f on expr and everything inside it, parents before children and otherwise in source order
+pub fn walk_exprs(expr: &Expr, f: &mut impl FnMut(&Expr)) {
+    let mut stack = vec![expr];
+    while let Some(expr) = stack.pop() {
+        f(expr);
+        stack.extend(expr.children().into_iter().rev());
+    }
+}
+
+impl Expr {
+    // where the expression is in the source, for the kinds of expression that keep track
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Expr::Ref(r#ref) => Some(r#ref.span()),
+            Expr::Comment(Comment { span, .. })
+            | Expr::Assignment(Assignment { span, .. })
+            | Expr::Destructure(Destructure { span, .. })
+            | Expr::IntLiteral(_, span)
+            | Expr::StringLiteral(_, span)
+            | Expr::Interpolation(_, span)
+            | Expr::MultiLineString(_, span)
+            | Expr::ListLiteral(_, span)
+            | Expr::FuncDef(FuncDef { span, .. })
+            | Expr::FunctionCall(FunctionCall { span, .. })
+            | Expr::FieldAccess(FieldAccess { span, .. })
+            | Expr::While(While { span, .. })
+            | Expr::Loop(_, span)
+            | Expr::If(If { span, .. })
+            | Expr::BinOp(BinOp { span, .. }) => Some(*span),
+            Expr::IndexAssignment(IndexAssignment { r#ref, .. }) => Some(r#ref.span()),
+            Expr::ResultComment(_, expr, _) | Expr::TrailingComment(expr, _) => expr.span(),
+            // a block is either the whole program or the inside of something else that has one
+            Expr::Block(_) => None,
         }
-        Expr::FunctionCall(FunctionCall { r#ref: _, args }) => {
-            for expr in args {
-                try_extend(&mut comments, &mut find_expr_comments_mut(expr)?)?;
+    }
+
+    // every span kept on this expression itself, not counting the ones inside its children
+    fn spans_mut(&mut self) -> Vec<&mut Span> {
+        match self {
+            Expr::Ref(r#ref) | Expr::IndexAssignment(IndexAssignment { r#ref, .. }) => {
+                vec![r#ref.span_mut()]
             }
+            Expr::Assignment(Assignment { r#ref, span, .. })
+            | Expr::FunctionCall(FunctionCall { r#ref, span, .. }) => vec![r#ref.span_mut(), span],
+            Expr::Comment(Comment { span, .. })
+            | Expr::Destructure(Destructure { span, .. })
+            | Expr::IntLiteral(_, span)
+            | Expr::StringLiteral(_, span)
+            | Expr::Interpolation(_, span)
+            | Expr::MultiLineString(_, span)
+            | Expr::ListLiteral(_, span)
+            | Expr::FuncDef(FuncDef { span, .. })
+            | Expr::FieldAccess(FieldAccess { span, .. })
+            | Expr::While(While { span, .. })
+            | Expr::Loop(_, span)
+            | Expr::If(If { span, .. })
+            | Expr::BinOp(BinOp { span, .. })
+            | Expr::ResultComment(_, _, span)
+            | Expr::TrailingComment(_, Comment { span, .. }) => vec![span],
+            Expr::Block(_) => vec![],
         }
-        Expr::While(While { cond, block }) | Expr::If(If { cond, block }) => {
-            try_extend(&mut comments, &mut find_expr_comments_mut(cond)?)?;
-            for expr in block.exprs_mut() {
-                try_extend(&mut comments, &mut find_expr_comments_mut(expr)?)?;
+    }
+
+    // the expressions directly inside this one, in source order. passes over the whole program
+    // (finding comments, walk_exprs) go through this and children_mut, so a new kind of
+    // expression only has to be added to these two
+    pub fn children(&self) -> Vec<&Expr> {
+        match self {
+            Expr::Block(block) | Expr::Loop(block, _) | Expr::FuncDef(FuncDef { block, .. }) => {
+                block.exprs().collect()
             }
-        }
-        Expr::Ref(_) | Expr::IntLiteral(_) | Expr::BinOp(_) | Expr::StringLiteral(_) => {}
-        Expr::FuncDef(FuncDef {
-            name: _,
-            arg_names: _,
-            block,
-        }) => {
-            for expr in block.exprs_mut() {
-                try_extend(&mut comments, &mut find_expr_comments_mut(expr)?)?;
+            Expr::Assignment(Assignment { expr, .. })
+            | Expr::Destructure(Destructure { expr, .. })
+            | Expr::FieldAccess(FieldAccess { expr, .. })
+            | Expr::ResultComment(_, expr, _)
+            | Expr::TrailingComment(expr, _) => vec![expr],
+            Expr::IndexAssignment(IndexAssignment { index, expr, .. }) => vec![index, expr],
+            Expr::FunctionCall(FunctionCall { args: exprs, .. }) | Expr::ListLiteral(exprs, _) => {
+                exprs.iter().collect()
             }
-        }
-        Expr::ListLiteral(exprs) => {
-            for expr in exprs {
-                try_extend(&mut comments, &mut find_expr_comments_mut(expr)?)?;
+            Expr::While(While { cond, block, .. }) | Expr::If(If { cond, block, .. }) => {
+                std::iter::once(cond.as_ref())
+                    .chain(block.exprs())
+                    .collect()
             }
+            Expr::BinOp(BinOp { lhs, rhs, .. }) => vec![lhs, rhs],
+            Expr::Interpolation(parts, _) => parts
+                .iter()
+                .filter_map(|part| match part {
+                    InterpolationPart::Expr(expr) => Some(expr),
+                    InterpolationPart::Literal(_) => None,
+                })
+                .collect(),
+            Expr::Ref(_)
+            | Expr::Comment(_)
+            | Expr::IntLiteral(..)
+            | Expr::StringLiteral(..)
+            | Expr::MultiLineString(..) => vec![],
         }
-        Expr::ResultComment(_, expr) => {
-            try_extend(&mut comments, &mut find_expr_comments_mut(expr)?)?;
+    }
+
+    pub fn children_mut(&mut self) -> Vec<&mut Expr> {
+        match self {
+            Expr::Block(block) | Expr::Loop(block, _) | Expr::FuncDef(FuncDef { block, .. }) => {
+                block.exprs_mut().collect()
+            }
+            Expr::Assignment(Assignment { expr, .. })
+            | Expr::Destructure(Destructure { expr, .. })
+            | Expr::FieldAccess(FieldAccess { expr, .. })
+            | Expr::ResultComment(_, expr, _)
+            | Expr::TrailingComment(expr, _) => vec![expr],
+            Expr::IndexAssignment(IndexAssignment { index, expr, .. }) => vec![index, expr],
+            Expr::FunctionCall(FunctionCall { args: exprs, .. }) | Expr::ListLiteral(exprs, _) => {
+                exprs.iter_mut().collect()
+            }
+            Expr::While(While { cond, block, .. }) | Expr::If(If { cond, block, .. }) => {
+                std::iter::once(cond.as_mut())
+                    .chain(block.exprs_mut())
+                    .collect()
+            }
+            Expr::BinOp(BinOp { lhs, rhs, .. }) => vec![lhs, rhs],
+            Expr::Interpolation(parts, _) => parts
+                .iter_mut()
+                .filter_map(|part| match part {
+                    InterpolationPart::Expr(expr) => Some(expr),
+                    InterpolationPart::Literal(_) => None,
+                })
+                .collect(),
+            Expr::Ref(_)
+            | Expr::Comment(_)
+            | Expr::IntLiteral(..)
+            | Expr::StringLiteral(..)
+            | Expr::MultiLineString(..) => vec![],
         }
     }
-    Ok(comments)
 }
 
 pub fn try_extend<K: Eq + Hash + Send + Sync + Debug + Display, V: Send + Sync + Debug>(
@@ -187,34 +397,374 @@ fn try_insert<K: Eq + Hash + Send + Sync + Debug + Display, V: Send + Sync + Deb
     Ok(())
 }
 
+// where a node came from in the source, as byte offsets. nodes built some other way (from a
+// program description, say) have an empty span at 0
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+// 1-based line and column (counted in chars) of where span starts
+pub fn line_col(source: &str, span: Span) -> (usize, usize) {
+    let before = &source[..span.start.min(source.len())];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let col = before[line_start..].chars().count() + 1;
+    (line, col)
+}
+
+// a parse error, with what's needed to show it next to the line it happened on
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseDiagnostic {
+    // 1-based, and the column counts chars
+    pub line: usize,
+    pub column: usize,
+    pub line_text: String,
+    pub expected: Vec<String>,
+}
+
+impl ParseDiagnostic {
+    fn new(source: &str, err: peg::error::ParseError<peg::str::LineCol>) -> Self {
+        let all_expected = err.expected.tokens().collect::<Vec<_>>();
+        // whitespace can go almost anywhere, so it's only worth listing if it's all there is
+        let expected = all_expected
+            .iter()
+            .filter(|token| !WHITESPACE_TOKENS.contains(token))
+            .map(|token| token.to_string())
+            .collect::<Vec<_>>();
+        let mut expected = if expected.is_empty() {
+            all_expected.iter().map(|token| token.to_string()).collect()
+        } else {
+            expected
+        };
+        // peg has given these back in different orders from one version to the next
+        expected.sort();
+        expected.dedup();
+        Self {
+            line: err.location.line,
+            column: err.location.column,
+            line_text: source
+                .lines()
+                .nth(err.location.line - 1)
+                .unwrap_or("")
+                .to_owned(),
+            expected,
+        }
+    }
+}
+
+const WHITESPACE_TOKENS: &[&str] = &["[' ' | '\\t']", "\"\\n\"", "\"\\r\\n\""];
+
+// line 2, col 12: expected one of "(", ...
+//   let x = foo(1
+//              ^
+impl Display for ParseDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, col {}: expected ", self.line, self.column)?;
+        match self.expected.as_slice() {
+            [] => write!(f, "something else")?,
+            [one] => write!(f, "{}", one)?,
+            many => write!(f, "one of {}", many.join(", "))?,
+        }
+        // tabs stay tabs so the caret lines up however wide they're shown
+        let padding = self
+            .line_text
+            .chars()
+            .take(self.column - 1)
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect::<String>();
+        write!(f, "\n  {}\n  {}^", self.line_text, padding)
+    }
+}
+
+impl std::error::Error for ParseDiagnostic {}
+
+pub fn parse_program(source: &str) -> Result<Program, ParseDiagnostic> {
+    check_nesting(source)?;
+    parser::program(source).map_err(|err| ParseDiagnostic::new(source, err))
+}
+
+// the parser goes one level deeper into the stack for every bracket, string and interpolation an
+// expression is inside of, and copying or dropping an expression goes one level deeper for
+// everything it's made of, so past this they'd run out of stack instead of giving an error
+const MAX_NESTING: usize = 200;
+
+// Finds the first place that's nested more than MAX_NESTING deep, before the parser gets to it.
+// Brackets inside strings and comments don't count, but each string and each ${...} inside one
+// does, and so does each `let` until the end of its line, since `let a = let b = ...` nests too.
+// Brackets that don't match are left for the parser to complain about.
+fn check_nesting(source: &str) -> Result<(), ParseDiagnostic> {
+    #[derive(PartialEq)]
+    enum Level {
+        Bracket,
+        String,
+        // a ${...} in a string, which ends at its }
+        Interpolation,
+        Let,
+    }
+    let is_ident_char = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-';
+    let mut levels = vec![];
+    let mut prev = None;
+    let mut chars = source.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let in_string = levels.last() == Some(&Level::String);
+        let next = chars.peek().map(|&(_, next)| next);
+        let rest = &source[i..];
+        match (c, next) {
+            ('\\', _) if in_string => {
+                chars.next();
+            }
+            ('$', Some('{')) if in_string => {
+                chars.next();
+                levels.push(Level::Interpolation);
+            }
+            ('"', _) if in_string => {
+                levels.pop();
+            }
+            _ if in_string => (),
+            // a multi-line string has no escapes or ${...} in it, so it all gets skipped
+            _ if rest.starts_with("\"\"\"") => {
+                let len = rest[3..].find("\"\"\"").map_or(rest.len(), |end| end + 6);
+                while chars.next_if(|&(j, _)| j < i + len).is_some() {}
+            }
+            ('"', _) => levels.push(Level::String),
+            ('(' | '[' | '{', _) => levels.push(Level::Bracket),
+            (')' | ']' | '}', _) => {
+                while levels.last() == Some(&Level::Let) {
+                    levels.pop();
+                }
+                levels.pop();
+            }
+            ('\n', _) => {
+                while levels.last() == Some(&Level::Let) {
+                    levels.pop();
+                }
+            }
+            ('l', _)
+                if rest.starts_with("let")
+                    && !prev.is_some_and(is_ident_char)
+                    && !rest[3..].starts_with(is_ident_char) =>
+            {
+                levels.push(Level::Let);
+            }
+            // `// #name` can have more code after it on the same line, any other comment goes
+            // to the end of the line
+            ('/', Some('/')) => {
+                let after = rest[2..].trim_start_matches([' ', '\t']);
+                let name_len = after
+                    .strip_prefix('#')
+                    .filter(|name| name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_'))
+                    .map(|name| 1 + name.find(|c| !is_ident_char(c)).unwrap_or(name.len()));
+                let end = match name_len {
+                    Some(name_len) => source.len() - after.len() + name_len,
+                    None => rest.find('\n').map_or(source.len(), |end| i + end),
+                };
+                while chars.next_if(|&(j, _)| j < end).is_some() {}
+            }
+            ('/', Some('*')) => {
+                chars.next();
+                while let Some((_, c)) = chars.next() {
+                    if c == '*' && chars.next_if(|&(_, c)| c == '/').is_some() {
+                        break;
+                    }
+                }
+            }
+            _ => (),
+        }
+        prev = Some(c);
+        if levels.len() > MAX_NESTING {
+            let (line, column) = line_col(source, Span { start: i, end: i });
+            return Err(ParseDiagnostic {
+                line,
+                column,
+                line_text: source.lines().nth(line - 1).unwrap_or("").to_owned(),
+                expected: vec![format!("at most {} levels of nesting", MAX_NESTING)],
+            });
+        }
+    }
+    Ok(())
+}
+
+// Keeps going after a parse error, to report every error in a file at once. The statement with
+// the error is blanked out (so line numbers stay the same) and the program is parsed again.
+// A statement is a top level line together with the lines after it up to where its braces are
+// balanced again, so an error anywhere in a function skips the whole function.
+//
+// The program is whatever was left after taking out the statements with errors. It's only None
+// when nothing could be parsed at all.
+pub fn parse_program_lenient(source: &str) -> (Option<Program>, Vec<ParseDiagnostic>) {
+    if let Err(diagnostic) = check_nesting(source) {
+        return (None, vec![diagnostic]);
+    }
+    let original_lines = source.lines().collect::<Vec<_>>();
+    let mut lines = original_lines.clone();
+    let statements = statement_lines(&original_lines);
+    let mut diagnostics: Vec<ParseDiagnostic> = vec![];
+    // each time round, at least one statement is blanked, so this can't go on forever
+    loop {
+        let text = lines.join("\n") + "\n";
+        let err = match parser::program(&text) {
+            Ok(program) => return (Some(program), diagnostics),
+            Err(err) => err,
+        };
+        let mut diagnostic = ParseDiagnostic::new(&text, err);
+        diagnostic.line_text = original_lines
+            .get(diagnostic.line - 1)
+            .unwrap_or(&"")
+            .to_string();
+        let error_line = diagnostic.line - 1;
+        let is_repeat = diagnostics
+            .last()
+            .is_some_and(|last| (last.line, last.column) == (diagnostic.line, diagnostic.column));
+        if !is_repeat {
+            diagnostics.push(diagnostic.clone());
+        }
+        let is_blank = |statement: &&std::ops::Range<usize>| {
+            lines[statement.start..statement.end]
+                .iter()
+                .all(|line| line.trim().is_empty())
+        };
+        // an error at the start of a line usually means the statement before it didn't end
+        let blames_previous = diagnostic.column == 1 && !is_repeat;
+        let blamed = statements
+            .iter()
+            .filter(|statement| {
+                if blames_previous {
+                    statement.start < error_line
+                } else {
+                    statement.start <= error_line
+                }
+            })
+            .rfind(|statement| !is_blank(statement))
+            .or_else(|| statements.iter().find(|statement| !is_blank(statement)));
+        match blamed {
+            Some(statement) => {
+                for line in &mut lines[statement.clone()] {
+                    *line = "";
+                }
+            }
+            None => return (None, diagnostics),
+        }
+    }
+}
+
+// the line numbers of each top level statement
+fn statement_lines(lines: &[&str]) -> Vec<std::ops::Range<usize>> {
+    let mut statements = vec![];
+    let mut start = 0;
+    let mut depth = 0;
+    for (i, line) in lines.iter().enumerate() {
+        depth += brace_depth_change(line);
+        if depth <= 0 {
+            statements.push(start..i + 1);
+            start = i + 1;
+            depth = 0;
+        }
+    }
+    if start < lines.len() {
+        statements.push(start..lines.len());
+    }
+    statements
+}
+
+// braces in strings and comments don't count
+fn brace_depth_change(line: &str) -> i32 {
+    let mut change = 0;
+    let mut chars = line.chars().peekable();
+    let mut in_string = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if in_string => {
+                chars.next();
+            }
+            '"' => in_string = !in_string,
+            '/' if !in_string && chars.peek() == Some(&'/') => break,
+            '{' if !in_string => change += 1,
+            '}' if !in_string => change -= 1,
+            _ => {}
+        }
+    }
+    change
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub enum Ref {
-    CommentRef(String),
-    VarRef(String),
+    CommentRef(String, Span),
+    VarRef(String, Span),
+}
+
+impl Ref {
+    pub fn span(&self) -> Span {
+        match self {
+            Ref::CommentRef(_, span) | Ref::VarRef(_, span) => *span,
+        }
+    }
+
+    fn span_mut(&mut self) -> &mut Span {
+        match self {
+            Ref::CommentRef(_, span) | Ref::VarRef(_, span) => span,
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct Assignment {
     pub r#ref: Ref,
     pub expr: Box<Expr>,
+    pub span: Span,
+}
+
+// let m(key) = value
+#[derive(Debug, Clone, PartialEq)]
+pub struct IndexAssignment {
+    pub r#ref: Ref,
+    pub index: Box<Expr>,
+    pub expr: Box<Expr>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern {
+    Map(Vec<String>),
+    List(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Destructure {
+    pub pattern: Pattern,
+    pub expr: Box<Expr>,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct FunctionCall {
     pub r#ref: Ref,
     pub args: Vec<Expr>,
+    pub span: Span,
+}
+
+// person.name, sugar for person("name")
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldAccess {
+    pub expr: Box<Expr>,
+    pub field: String,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct While {
     pub cond: Box<Expr>,
     pub block: Block,
+    // false for `do { ... } while (cond)`, where the body runs once before cond is checked
+    pub check_first: bool,
+    pub span: Span,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct If {
     pub cond: Box<Expr>,
     pub block: Block,
+    pub span: Span,
 }
 
 // usage of peg stolen from https://github.com/A1Liu/gone/blob/master/src/parser.rs
@@ -236,52 +786,119 @@ peg::parser! {
             = newline() { BlockEl::NewLine }
 
         rule func_decl() -> Expr
-            = "defn" _? name:ident() _? "(" _? arg_names:(ident() ** comma()) _? ")" _* "{" _? block:block() _? "}" {
+            = start:position!() "defn" _? name:ident() _? "(" _? arg_names:(ident() ** comma()) _? ")" _* "{"
+              block_start() block:block() _? "}" end:position!() {
                 Expr::FuncDef(FuncDef {
                     name: name.to_string(),
                     arg_names: arg_names.iter().map(|n| n.to_string()).collect(),
                     block,
+                    span: Span { start, end },
                 })
             }
 
         rule if_statement() -> Expr
-            = "if" _? "(" _? cond:expr() _? ")" _* "{" _? block:block() _? "}" {
+            = start:position!() "if" _? "(" _? cond:expr() _? ")" _* "{" block_start() block:block() _? "}"
+              end:position!() {
                 Expr::If(If {
                     cond: Box::new(cond),
                     block,
+                    span: Span { start, end },
                 })
             }
 
         rule while_loop() -> Expr
-            = "while" _? "(" _? cond:expr() _? ")" _* "{" _? block:block() _? "}" {
+            = start:position!() "while" _? "(" _? cond:expr() _? ")" _* "{" block_start() block:block() _? "}"
+              end:position!() {
+                Expr::While(While {
+                    cond: Box::new(cond),
+                    block,
+                    check_first: true,
+                    span: Span { start, end },
+                })
+            }
+
+        rule infinite_loop() -> Expr
+            = start:position!() "loop" _* "{" block_start() block:block() _? "}" end:position!() {
+                Expr::Loop(block, Span { start, end })
+            }
+
+        rule do_while_loop() -> Expr
+            = start:position!() "do" _* "{" block_start() block:block() _? "}" _? "while" _? "(" _? cond:expr()
+              _? ")" end:position!() {
                 Expr::While(While {
                     cond: Box::new(cond),
                     block,
+                    check_first: false,
+                    span: Span { start, end },
                 })
             }
 
         rule expr() -> Expr
             = comment() /
-              expr:(while_loop() / if_statement() / func_decl() / assignment()
-                    / bin_op_expr() / term()) (nbspace()? / newline()) result_comment:result_comment()? {
-                if result_comment.is_some() {
-                    Expr::ResultComment(next_id(), Box::new(expr))
-                } else {
-                    expr
+              expr:(while_loop() / do_while_loop() / infinite_loop() / if_statement() / func_decl() / destructure() / index_assignment() / assignment()
+                    / bin_op_expr() / term()) nbspace()? trailing:trailing()? {
+                match trailing {
+                    None => expr,
+                    Some(Trailing::Result(span)) => {
+                        Expr::ResultComment(next_id(), Box::new(expr), span)
+                    }
+                    Some(Trailing::Comment(c)) => Expr::TrailingComment(Box::new(expr), c),
+                }
+            }
+
+        rule trailing() -> Trailing
+            = c:named_trailing_comment() { Trailing::Comment(c) } /
+              start:position!() result_comment() end:position!() {
+                  Trailing::Result(Span { start, end })
+              } /
+              c:anon_trailing_comment() { Trailing::Comment(c) }
+
+        // `// #name` right after code is a named comment, but `// #` followed by a space or
+        // anything that isn't an identifier is a result comment
+        rule named_trailing_comment() -> Comment
+            = start:position!() "//" onespace()? name:comment_ident()
+              body:(onespace()+ b:comment_inner_text() { b })? end:position!() {
+                Comment {
+                    name: Some(name),
+                    body: body.unwrap_or("").into(),
+                    style: CommentStyle::Trailing,
+                    span: Span { start, end },
+                }
+            }
+
+        rule anon_trailing_comment() -> Comment
+            = start:position!() "//" onespace()? body:comment_inner_text() end:position!() {
+                Comment {
+                    name: None,
+                    body: body.into(),
+                    style: CommentStyle::Trailing,
+                    span: Span { start, end },
                 }
             }
 
         rule result_comment() -> ()
-            = "//" _? "#" comment_inner_text()? following_comment()* { () }
+            = "//" nbspace()? "#" comment_inner_text()? following_comment()* { () }
 
         #[cache_left_rec]
         rule term() -> Expr
-            = string_literal_expr() / list_literal() / int() / func_call() / r#ref() / bin_op_expr()
+            = multi_line_string_expr() / string_literal_expr() / list_literal() / int() / field_access() / func_call() / r#ref()
+              / parenthesized() / bin_op_expr()
+
+        // only for grouping, `(a + b) * c`, so there's no node for the parentheses
+        rule parenthesized() -> Expr
+            = "(" _? e:(bin_op_expr() / term()) _? ")" { e }
 
+        // a line can end after an operator but not before one, otherwise a line starting with a
+        // negative number would be taken as a subtraction from the line above
         #[cache_left_rec]
         rule bin_op_expr() -> Expr
-            = left:term() _? op:op() _? right:term() {
-                Expr::BinOp(BinOp { lhs: Box::new(left), op: op, rhs: Box::new(right) })
+            = start:position!() left:term() nbspace()? op:op() _? right:term() end:position!() {
+                Expr::BinOp(BinOp {
+                    lhs: Box::new(left),
+                    op,
+                    rhs: Box::new(right),
+                    span: Span { start, end },
+                })
             }
 
         rule op() -> Op
@@ -290,11 +907,30 @@ peg::parser! {
                "<=" { Op::Lte } / ">" { Op::Gt } / "<" { Op::Lt } / "&&" { Op::And } /
                "||" { Op::Or })
 
+        // `a.b.c` is (a.b).c, so each field is nested inside the ones before it, and the chain
+        // counts towards MAX_NESTING. it's folded here rather than with left recursion, which
+        // would go one level deeper into the stack for every field
+        rule field_access() -> Expr
+            = start:position!() expr:(func_call() / r#ref())
+              fields:("." field:name() end:position!() { (field, end) })+ {?
+                if fields.len() > MAX_NESTING {
+                    return Err("fewer fields in a row");
+                }
+                Ok(fields.into_iter().fold(expr, |expr, (field, end)| {
+                    Expr::FieldAccess(FieldAccess {
+                        expr: Box::new(expr),
+                        field: field.into(),
+                        span: Span { start, end },
+                    })
+                }))
+            }
+
         rule func_call() -> Expr
-            = r#ref:ref_ref() "(" _? args:(expr() ** comma()) _? ")" {
+            = start:position!() r#ref:ref_ref() "(" _? args:(expr() ** comma()) _? ")" end:position!() {
                 Expr::FunctionCall(FunctionCall {
                     r#ref,
                     args,
+                    span: Span { start, end },
                 })
             }
 
@@ -302,38 +938,132 @@ peg::parser! {
             = r:ref_ref() { Expr::Ref(r) }
         rule ref_ref() -> Ref
             = var_ref() / comment_ref()
+        // what a let can assign to, which leaves out the constants
+        rule target_ref() -> Ref
+            = start:position!() r:ident() end:position!() { Ref::VarRef(r.into(), Span { start, end }) }
+              / comment_ref()
         rule var_ref() -> Ref
-            = r:ident() { Ref::VarRef(r.into()) }
+            = start:position!() r:(constant() / ident()) end:position!() {
+                Ref::VarRef(r.into(), Span { start, end })
+            }
         rule comment_ref() -> Ref
-            = r:comment_ident() { Ref::CommentRef(r) }
+            = start:position!() r:comment_ident() end:position!() {
+                Ref::CommentRef(r, Span { start, end })
+            }
         rule comment_ident() -> String
-            = "#" i:ident() { i.into() }
+            = "#" i:name() { i.into() }
 
         rule assignment() -> Expr
-            = "let" _ r:ref_ref() _ "=" _ expr:expr() { Expr::Assignment(Assignment {
-                r#ref: r,
-                expr: Box::new(expr),
-            })}
+            = start:position!() "let" _ r:target_ref() _ "=" _ expr:expr() end:position!() {
+                Expr::Assignment(Assignment {
+                    r#ref: r,
+                    expr: Box::new(expr),
+                    span: Span { start, end },
+                })
+            }
 
+        rule index_assignment() -> Expr
+            = "let" _ r:target_ref() "(" _? index:expr() _? ")" _ "=" _ expr:expr() {
+                Expr::IndexAssignment(IndexAssignment {
+                    r#ref: r,
+                    index: Box::new(index),
+                    expr: Box::new(expr),
+                })
+            }
+
+        rule destructure() -> Expr
+            = start:position!() "let" _ pattern:pattern() _ "=" _ expr:expr() end:position!() {
+                Expr::Destructure(Destructure {
+                    pattern,
+                    expr: Box::new(expr),
+                    span: Span { start, end },
+                })
+            }
+
+        rule pattern() -> Pattern
+            = "{" _? names:(pattern_name() ** comma()) _? "}" {
+                Pattern::Map(names.iter().map(|n| n.to_string()).collect())
+            } /
+              "[" _? names:(pattern_name() ** comma()) _? "]" {
+                Pattern::List(names.iter().map(|n| n.to_string()).collect())
+            }
+
+        // `_` in a list pattern skips that item. a nested pattern fails right where it starts,
+        // so that's where the error points
+        rule pattern_name() -> &'input str
+            = ident() /
+              &("[" / "{") {? Err("a name, nested patterns not yet supported") }
 
         rule list_literal() -> Expr
-            = "[" _? exprs:(expr() ** comma()) _? "]" { Expr::ListLiteral(exprs) }
+            = start:position!() "[" _? exprs:(expr() ** comma()) _? "]" end:position!() {
+                Expr::ListLiteral(exprs, Span { start, end })
+            }
+
+        rule multi_line_string_expr() -> Expr
+            = start:position!() "\"\"\"" body:$((!"\"\"\"" [_])*) "\"\"\"" end:position!() {
+                Expr::MultiLineString(body.into(), Span { start, end })
+            }
 
         rule string_literal_expr() -> Expr
-            = string_lit:string_lit() { Expr::StringLiteral(string_lit) }
+            = start:position!() "\"" parts:interpolation_part()* "\"" end:position!() {
+                let span = Span { start, end };
+                let parts = merge_literals(parts);
+                match parts.as_slice() {
+                    [] => Expr::StringLiteral("".into(), span),
+                    [InterpolationPart::Literal(s)] => Expr::StringLiteral(s.clone(), span),
+                    _ => Expr::Interpolation(parts, span),
+                }
+            }
 
+        rule interpolation_part() -> InterpolationPart
+            = "${" _? e:expr() _? "}" { InterpolationPart::Expr(e) } /
+              "\\$" { InterpolationPart::Literal("$".into()) } /
+              chunk:$(("\\" !"$" [_] / !("${" / "\"" / "\\") [_])+) {?
+                  Ok(InterpolationPart::Literal(unescape_string_chunk(chunk)?))
+              }
+
+        // the digits after the first aren't listed as expected, so that a literal that doesn't fit
+        // is reported with just why, after its last digit like a keyword used as a name
         rule int() -> Expr
-            = num:$("0" / "-"? ['1' ..= '9']+ ['0' ..= '9']*) { Expr::IntLiteral(num.parse().unwrap()) }
+            = start:position!() num:$("0" / "-"? ['1' ..= '9'] quiet!{ ['0' ..= '9']* }) end:position!() {?
+                num.parse()
+                    .map(|n| Expr::IntLiteral(n, Span { start, end }))
+                    .or(Err("a smaller number, integer literal out of range for i128"))
+            }
 
-        rule comment() -> Expr = named_comment() / anon_comment()
+        rule comment() -> Expr = block_comment() / named_comment() / anon_comment()
 
         rule named_comment() -> Expr
-            = "/" "/" _? name:comment_ident() body:following_comment()?  {
-                Expr::Comment(Comment { name: Some(name), body: body.unwrap_or_else(|| "".into()) })
+            = start:position!() "/" "/" nbspace()? name:comment_ident() body:following_comment()?
+              end:position!() {
+                Expr::Comment(Comment {
+                    name: Some(name),
+                    body: body.unwrap_or_else(|| "".into()),
+                    style: CommentStyle::Line,
+                    span: Span { start, end },
+                })
             }
 
         rule anon_comment() -> Expr
-            = body:comment_string() { Expr::Comment(Comment { name: None, body })}
+            = start:position!() body:comment_string() end:position!() {
+                Expr::Comment(Comment {
+                    name: None,
+                    body,
+                    style: CommentStyle::Line,
+                    span: Span { start, end },
+                })
+            }
+
+        rule block_comment() -> Expr
+            = start:position!() "/*" name:(onespace()* n:comment_ident() (newline() / &"*/") { n })?
+              body:$((!"*/" [_])*) "*/" end:position!() {
+                Expr::Comment(Comment {
+                    name,
+                    body: body.into(),
+                    style: CommentStyle::Block,
+                    span: Span { start, end },
+                })
+            }
 
         rule comment_string() -> String
             = "/" "/" onespace()? body:comment_inner_text()? following:following_comment()*  {
@@ -343,30 +1073,41 @@ peg::parser! {
         rule comment_inner_text() -> &'input str
             = body:$([^ '\r' | '\n']*) { body }
 
+        // continuation lines can be indented, for comments inside a block
         rule following_comment() -> String
-            = newline() c:comment_string() {
-                if c.starts_with("//") {
-                    let c = c.trim_start_matches("//");
-                    let c = c.strip_prefix(' ').unwrap_or(c);
-                    format!("\n{}", c)
-                } else {
-                    c
-                }
-            }
+            = newline() nbspace()? c:comment_string() { c }
 
-        rule ident() -> &'input str = $(ident_start()+ ['a'..='z' | 'A'..='Z' | '_' | '-' | '0'..='9']*)
+        // variable and function names can't be keywords, but comment names and fields can.
+        // true, false and null can be read like variables but not assigned to
+        rule ident() -> &'input str
+            = !keyword() n:name() { n }
+            / e:keyword() {? Err(e) }
+        rule name() -> &'input str = $(ident_start()+ ident_char()*)
+        rule ident_char() = ['a'..='z' | 'A'..='Z' | '_' | '-' | '0'..='9']
+        rule constant() -> &'input str = $(("true" / "false" / "null") !ident_char())
+        // the error for using each keyword as a name
+        rule keyword() -> &'static str
+            = k:(
+                "while" { "a name, `while` is a keyword" }
+                / "if" { "a name, `if` is a keyword" }
+                / "do" { "a name, `do` is a keyword" }
+                / "loop" { "a name, `loop` is a keyword" }
+                / "defn" { "a name, `defn` is a keyword" }
+                / "let" { "a name, `let` is a keyword" }
+                / "true" { "a name, `true` is a constant" }
+                / "false" { "a name, `false` is a constant" }
+                / "null" { "a name, `null` is a constant" }
+            ) !ident_char() { k }
         rule ident_start() -> &'input str = $(['a'..='z' | 'A'..='Z' | '_']+)
 
-        rule string_lit() -> String
-            = str:$("\"" (!['"'][_] / "\"\"")* "\"") {?
-                Ok(StringLit::parse(str).or_else(|e| { dbg!(str, e) ; Err("string_lit: " ) })?.value().to_owned())
-            }
-
         rule comma() -> () = _? "," _?
         rule nbspace() = onespace()+
         rule onespace() = [' ' | '\t']
         rule newline() = "\n" / "\r\n"
         rule whitespace() = (nbspace() / newline())+
+        // only the line break after a `{`, so blank lines at the start of a block are kept
+        rule block_start() = nbspace()? newline()?
+
         rule _() = quiet!{ whitespace() };
     }
 }
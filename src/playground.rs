@@ -0,0 +1,82 @@
+// Running a program the way a web page does: print goes into the result instead of stdout, and
+// there are no file or environment builtins. The wasm feature exports these to
+// JavaScript, see wasm.rs.
+
+use crate::error::render_error;
+use crate::interp::InterpreterBuilder;
+use crate::parser::parse_program;
+use crate::reassemble::{format_source, AssembleOptions};
+use std::cell::RefCell;
+use std::io::Write;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PlaygroundRun {
+    // everything print and debug wrote
+    pub output: String,
+    // what the program came to, the way show shows it, or None if it failed
+    pub result: Option<String>,
+    // the error the program stopped with, pointing at where it happened
+    pub error: Option<String>,
+    // every named comment and its body after the run
+    pub comments: Vec<(String, String)>,
+    // the program with its comments written back, None if it didn't parse or failed
+    pub source: Option<String>,
+}
+
+// print writes into this, and the run reads it back afterwards
+#[derive(Clone, Default)]
+struct Captured(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+pub fn run(source: &str) -> PlaygroundRun {
+    let output = Captured::default();
+    let mut interp = InterpreterBuilder::new()
+        .core_builtins()
+        .io_builtins()
+        .build();
+    interp.set_output(Box::new(output.clone()));
+    let outcome = parse_program(source)
+        .map_err(|diagnostic| diagnostic.to_string())
+        .and_then(|program| {
+            interp.set_source(source);
+            let result = interp
+                .run_program(&program)
+                .map_err(|err| render_error(source, &err))?;
+            let written_back = interp.writeback(&program).map_err(|err| err.to_string())?;
+            Ok((result, written_back))
+        });
+    let output = String::from_utf8_lossy(&output.0.borrow()).into_owned();
+    let comments = interp.comments();
+    match outcome {
+        Ok((result, written_back)) => PlaygroundRun {
+            output,
+            result: Some(interp.show(&result)),
+            error: None,
+            comments,
+            source: Some(written_back),
+        },
+        Err(error) => PlaygroundRun {
+            output,
+            result: None,
+            error: Some(error),
+            comments,
+            source: None,
+        },
+    }
+}
+
+// `zac fmt` for a web page
+pub fn format(source: &str) -> Result<String, String> {
+    format_source(source, &AssembleOptions::default()).map_err(|err| err.to_string())
+}
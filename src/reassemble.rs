@@ -1,123 +1,515 @@
+use crate::interp::builtin_comment;
+use crate::parser::{ast_eq, find_comments_mut, parse_program, walk_exprs, ExprID, Span};
 use crate::parser::{
-    Assignment, BinOp, Block, BlockEl, Comment, Expr, FuncDef, FunctionCall, If, Op, Program, Ref,
+    Assignment, BinOp, Block, BlockEl, Comment, CommentStyle, Destructure, Expr, FieldAccess,
+    FuncDef, FunctionCall, If, IndexAssignment, InterpolationPart, Op, Pattern, Program, Ref,
     While,
 };
 use crate::{wrapping, Interpreter};
+use anyhow::{anyhow, bail};
 use itertools::Itertools;
+use std::collections::HashMap;
 use std::fmt::Write;
+use std::io;
+use std::rc::Rc;
+
+// How output_code lays out the program. The default is the layout the programs in this repo use.
+#[derive(Debug, Clone)]
+pub struct AssembleOptions {
+    // spaces per level of nesting
+    pub indent_width: usize,
+    // `f(a, b)` rather than `f(a,b)`, also for lists, parameters and destructuring
+    pub space_after_comma: bool,
+    // function calls that would go past this many characters get one argument per line
+    pub max_line_width: Option<usize>,
+    // `// text` rather than `//text`. Lines that start with a space always get one, otherwise
+    // the space would be lost when the comment is read back in
+    pub comment_prefix_space: bool,
+    // the source the program was parsed from. result comments for expressions that haven't run
+    // say what they said there, rather than being left empty
+    pub original_source: Option<Rc<str>>,
+}
+
+impl Default for AssembleOptions {
+    fn default() -> Self {
+        Self {
+            indent_width: 2,
+            space_after_comma: true,
+            max_line_width: None,
+            comment_prefix_space: true,
+            original_source: None,
+        }
+    }
+}
+
+impl AssembleOptions {
+    fn separator(&self) -> &'static str {
+        if self.space_after_comma {
+            ", "
+        } else {
+            ","
+        }
+    }
+
+    // what goes between `//` and the text of a comment line
+    fn comment_prefix(&self, line: &str) -> &'static str {
+        if self.comment_prefix_space || line.starts_with(' ') {
+            " "
+        } else {
+            ""
+        }
+    }
+}
 
 pub fn output_code(program: &Program, interp: &Interpreter) -> String {
-    let mut assembled = String::new();
-    assemble_expr(&mut assembled, &Expr::Block(program.block.clone()), interp);
-    assembled
+    output_code_with(program, interp, &AssembleOptions::default())
 }
 
-fn assemble_expr(assembled: &mut String, expr: &Expr, interp: &Interpreter) {
-    match expr {
-        Expr::Block(block) => {
-            for block_el in &block.0 {
-                match block_el {
-                    BlockEl::Expr(expr) => {
-                        assemble_expr(assembled, expr, interp);
-                    }
-                    BlockEl::NewLine => assembled.push_str("\n"),
+pub fn output_code_with(
+    program: &Program,
+    interp: &Interpreter,
+    options: &AssembleOptions,
+) -> String {
+    let mut assembled = vec![];
+    // writing into a Vec can't fail
+    output_code_to(program, interp, options, &mut assembled).unwrap();
+    String::from_utf8(assembled).unwrap()
+}
+
+// Like output_code_with, but the program is written to w a piece at a time rather than being put
+// together in memory first. Nothing is buffered, so a file or socket should be wrapped in a
+// BufWriter.
+pub fn output_code_to(
+    program: &Program,
+    interp: &Interpreter,
+    options: &AssembleOptions,
+    w: impl io::Write,
+) -> io::Result<()> {
+    let mut stack = vec![];
+    push_block(&mut stack, &program.block);
+    let mut assembled = Output::new(w, options.indent_width);
+    assemble(&mut assembled, stack, interp, options)?;
+    assembled.finish()
+}
+
+// Like output_code, but instead of writing the whole program out again, only the named comments
+// the program changed and the result comments are written over their place in original_src, so
+// the rest of the file stays exactly as it was. program has to be the one parsed from
+// original_src, since that's where the positions come from.
+pub fn patch_program(original_src: &str, program: &Program, interp: &Interpreter) -> String {
+    let original_bodies = match parse_program(original_src) {
+        Ok(mut original) => find_comments_mut(&mut original)
+            .map(|comments| {
+                comments
+                    .into_iter()
+                    .map(|(name, comment)| (name, comment.body.clone()))
+                    .collect::<HashMap<_, _>>()
+            })
+            .unwrap_or_default(),
+        Err(_) => HashMap::new(),
+    };
+    let current_bodies = interp.comments().into_iter().collect::<HashMap<_, _>>();
+    let is_changed = |name: &String| {
+        builtin_comment(interp, name).is_some()
+            || original_bodies.get(name) != current_bodies.get(name)
+    };
+
+    let mut patches = vec![];
+    for expr in program.block.exprs() {
+        walk_exprs(expr, &mut |expr| match expr {
+            Expr::Comment(comment) | Expr::TrailingComment(_, comment) => {
+                if comment.name.as_ref().is_some_and(is_changed) {
+                    let mut assembled = Output::new(vec![], 0);
+                    assemble_comment(&mut assembled, comment, &AssembleOptions::default());
+                    assembled.finish().unwrap();
+                    patches.push((comment.span, String::from_utf8(assembled.w).unwrap()));
                 }
             }
+            Expr::ResultComment(id, _, span) => patches.push((*span, result_comment(*id, interp))),
+            _ => {}
+        });
+    }
+    patches.sort_by_key(|(span, _)| span.start);
+
+    let mut patched = String::new();
+    let mut copied_up_to = 0;
+    for (span, replacement) in patches {
+        patched.push_str(&original_src[copied_up_to..span.start]);
+        // lines after the first line up with the line the comment starts on
+        let line_start = original_src[..span.start].rfind('\n').map_or(0, |i| i + 1);
+        let line_indent = original_src[line_start..span.start]
+            .chars()
+            .take_while(|c| c.is_whitespace())
+            .collect::<String>();
+        patched.push_str(&replacement.replace('\n', &format!("\n{}", line_indent)));
+        copied_up_to = span.end;
+    }
+    patched.push_str(&original_src[copied_up_to..]);
+    patched
+}
+
+// Generated programs can nest expressions thousands of levels deep, so instead of recursing the
+// assembler keeps its own stack of what's left to write. Pieces are pushed in reverse, since the
+// last one pushed is written first.
+enum Work<'a> {
+    Expr(&'a Expr),
+    Comment(&'a Comment),
+    Str(&'a str),
+    Owned(String),
+    // everything written between these two gets indented, for the inside of a block
+    StartIndent,
+    EndIndent,
+}
+
+// Where assembled code goes. Lines inside blocks are indented as they're written: a line that's
+// only whitespace is left alone, and the newline ending a block's contents is dropped, since the
+// closing brace starts its own line. A failed write is kept until the assembler checks for it
+// after its current piece of work, and everything written after that is skipped.
+struct Output<W: io::Write> {
+    w: W,
+    indent_width: usize,
+    depth: usize,
+    // held back until something comes after it, to find out whether it ends a block
+    pending_newline: bool,
+    // held back until the line turns out to have something other than whitespace on it
+    pending_whitespace: String,
+    line_started: bool,
+    // where the line has got to, counting the indentation
+    column: usize,
+    error: Option<io::Error>,
+}
+
+impl<W: io::Write> Output<W> {
+    fn new(w: W, indent_width: usize) -> Self {
+        Self {
+            w,
+            indent_width,
+            depth: 0,
+            pending_newline: false,
+            pending_whitespace: String::new(),
+            line_started: false,
+            column: 0,
+            error: None,
         }
-        Expr::Comment(Comment { name, body }) => {
-            if let Some(name) = name {
-                assembled.push_str("// #");
-                assembled.push_str(name);
+    }
 
-                if body.is_empty() {
-                    return;
-                }
+    fn write(&mut self, s: &str) {
+        if self.error.is_none() && !s.is_empty() {
+            if let Err(e) = self.w.write_all(s.as_bytes()) {
+                self.error = Some(e);
+            }
+        }
+    }
 
-                assembled.push_str("\n");
+    fn flush_newline(&mut self) {
+        if self.pending_newline {
+            self.pending_newline = false;
+            self.write("\n");
+        }
+    }
+
+    fn push_str(&mut self, s: &str) {
+        for (i, line) in s.split('\n').enumerate() {
+            if i > 0 {
+                self.flush_newline();
+                let whitespace = std::mem::take(&mut self.pending_whitespace);
+                self.write(&whitespace);
+                self.pending_newline = true;
+                self.line_started = false;
+                self.column = 0;
             }
+            self.push_line_part(line);
+        }
+    }
 
-            if body.is_empty() {
-                assembled.push_str("//");
-                return;
+    fn push_line_part(&mut self, part: &str) {
+        if self.line_started {
+            self.write(part);
+            self.column += part.chars().count();
+            return;
+        }
+        let text = part.trim_start();
+        self.pending_whitespace
+            .push_str(&part[..part.len() - text.len()]);
+        if text.is_empty() {
+            return;
+        }
+        self.flush_newline();
+        let indentation = " ".repeat(self.depth * self.indent_width);
+        let whitespace = std::mem::take(&mut self.pending_whitespace);
+        self.write(&indentation);
+        self.write(&whitespace);
+        self.write(text);
+        self.line_started = true;
+        self.column = indentation.len() + whitespace.chars().count() + text.chars().count();
+    }
+
+    // text that has to come out exactly as it is, like the inside of a """string""", so none of
+    // its lines get indented
+    fn push_verbatim(&mut self, s: &str) {
+        if !self.line_started {
+            self.flush_newline();
+            let whitespace = std::mem::take(&mut self.pending_whitespace);
+            self.write(&whitespace);
+            self.line_started = true;
+            self.column = whitespace.chars().count();
+        }
+        for (i, line) in s.split('\n').enumerate() {
+            if i > 0 {
+                self.write("\n");
+                self.column = 0;
             }
+            self.write(line);
+            self.column += line.chars().count();
+        }
+    }
 
-            let mut lines = body.split("\n").peekable();
-            while let Some(line) = lines.next() {
-                assembled.push_str("//");
-                if !line.is_empty() {
-                    assembled.push_str(" ");
-                    assembled.push_str(line);
-                }
+    // the column the next thing written would start at
+    fn column(&self) -> usize {
+        if self.line_started {
+            self.column
+        } else {
+            self.depth * self.indent_width + self.pending_whitespace.chars().count()
+        }
+    }
 
-                if let Some(_) = lines.peek() {
-                    assembled.push_str("\n");
-                }
+    fn start_indent(&mut self) {
+        // this newline comes before the block's contents, so it stays
+        self.flush_newline();
+        self.depth += 1;
+    }
+
+    fn end_indent(&mut self) {
+        if self.pending_whitespace.is_empty() {
+            self.pending_newline = false;
+        }
+        self.depth -= 1;
+    }
+
+    fn check(&mut self) -> io::Result<()> {
+        match self.error.take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.flush_newline();
+        let whitespace = std::mem::take(&mut self.pending_whitespace);
+        self.write(&whitespace);
+        self.check()?;
+        self.w.flush()
+    }
+}
+
+fn assemble<'a, W: io::Write>(
+    assembled: &mut Output<W>,
+    mut stack: Vec<Work<'a>>,
+    interp: &Interpreter,
+    options: &AssembleOptions,
+) -> io::Result<()> {
+    while let Some(work) = stack.pop() {
+        match work {
+            Work::Expr(expr) => {
+                let column = assembled.column();
+                assemble_expr(assembled, &mut stack, expr, interp, options, column)
             }
+            Work::Comment(comment) => assemble_comment(assembled, comment, options),
+            Work::Str(s) => assembled.push_str(s),
+            Work::Owned(s) => assembled.push_str(&s),
+            Work::StartIndent => assembled.start_indent(),
+            Work::EndIndent => assembled.end_indent(),
+        }
+        assembled.check()?;
+    }
+    Ok(())
+}
+
+fn push_block<'a>(stack: &mut Vec<Work<'a>>, block: &'a Block) {
+    for block_el in block.0.iter().rev() {
+        match block_el {
+            BlockEl::Expr(expr) => stack.push(Work::Expr(expr)),
+            BlockEl::NewLine => stack.push(Work::Str("\n")),
         }
-        Expr::Assignment(Assignment { r#ref, expr }) => {
+    }
+}
+
+fn push_inner_block<'a>(stack: &mut Vec<Work<'a>>, block: &'a Block) {
+    stack.push(Work::Str("\n}"));
+    stack.push(Work::EndIndent);
+    push_block(stack, block);
+    stack.push(Work::StartIndent);
+}
+
+// items with separator between them
+fn push_separated<'a>(stack: &mut Vec<Work<'a>>, items: &'a [Expr], separator: &'static str) {
+    for (i, item) in items.iter().enumerate().rev() {
+        stack.push(Work::Expr(item));
+        if i > 0 {
+            stack.push(Work::Str(separator));
+        }
+    }
+}
+
+// operators all bind the same, so one that's an operand of another needs parentheses
+fn push_operand<'a>(stack: &mut Vec<Work<'a>>, operand: &'a Expr) {
+    if let Expr::BinOp(_) = operand {
+        stack.push(Work::Str(")"));
+        stack.push(Work::Expr(operand));
+        stack.push(Work::Str("("));
+    } else {
+        stack.push(Work::Expr(operand));
+    }
+}
+
+// the first line of expr written out on its own, for showing where something happened
+pub(crate) fn expr_source(expr: &Expr, interp: &Interpreter) -> String {
+    let options = AssembleOptions::default();
+    let mut assembled = Output::new(vec![], options.indent_width);
+    // writing into a Vec can't fail
+    assemble(&mut assembled, vec![Work::Expr(expr)], interp, &options).unwrap();
+    assembled.finish().unwrap();
+    let assembled = String::from_utf8(assembled.w).unwrap();
+    assembled.lines().next().unwrap_or("").to_owned()
+}
+
+// whether a call starting at column would go past max_line_width if it were written on one line
+fn is_too_long(
+    call: &Expr,
+    column: usize,
+    interp: &Interpreter,
+    options: &AssembleOptions,
+) -> bool {
+    let max_line_width = match options.max_line_width {
+        Some(max_line_width) => max_line_width,
+        None => return false,
+    };
+    let one_line_options = AssembleOptions {
+        max_line_width: None,
+        ..options.clone()
+    };
+    let mut one_line = Output::new(vec![], options.indent_width);
+    // writing into a Vec can't fail
+    assemble(
+        &mut one_line,
+        vec![Work::Expr(call)],
+        interp,
+        &one_line_options,
+    )
+    .unwrap();
+    one_line.finish().unwrap();
+    let one_line = String::from_utf8(one_line.w).unwrap();
+    let first_line = one_line.lines().next().unwrap_or("");
+    column + first_line.chars().count() > max_line_width
+}
+
+fn assemble_expr<'a, W: io::Write>(
+    assembled: &mut Output<W>,
+    stack: &mut Vec<Work<'a>>,
+    expr: &'a Expr,
+    interp: &Interpreter,
+    options: &AssembleOptions,
+    column: usize,
+) {
+    match expr {
+        Expr::Block(block) => push_block(stack, block),
+        Expr::Comment(comment) => assemble_comment(assembled, comment, options),
+        Expr::Assignment(Assignment { r#ref, expr, .. }) => {
             assembled.push_str("let ");
             assemble_ref(r#ref, assembled);
             assembled.push_str(" = ");
-            assemble_expr(assembled, expr, interp);
+            stack.push(Work::Expr(expr));
+        }
+        Expr::IndexAssignment(IndexAssignment { r#ref, index, expr }) => {
+            assembled.push_str("let ");
+            assemble_ref(r#ref, assembled);
+            assembled.push_str("(");
+            stack.push(Work::Expr(expr));
+            stack.push(Work::Str(") = "));
+            stack.push(Work::Expr(index));
         }
-        Expr::IntLiteral(n) => assembled.push_str(&n.to_string()),
+        Expr::Destructure(Destructure { pattern, expr, .. }) => {
+            assembled.push_str("let ");
+            let (open, names, close) = match pattern {
+                Pattern::Map(names) => ("{", names, "}"),
+                Pattern::List(names) => ("[", names, "]"),
+            };
+            assembled.push_str(open);
+            assembled.push_str(&names.join(options.separator()));
+            assembled.push_str(close);
+            assembled.push_str(" = ");
+            stack.push(Work::Expr(expr));
+        }
+        Expr::IntLiteral(n, _) => assembled.push_str(&n.to_string()),
         Expr::Ref(r#ref) => assemble_ref(r#ref, assembled),
-        Expr::FunctionCall(FunctionCall { r#ref, args }) => {
+        Expr::FunctionCall(FunctionCall { r#ref, args, .. })
+            if !args.is_empty() && is_too_long(expr, column, interp, options) =>
+        {
+            assemble_ref(r#ref, assembled);
+            assembled.push_str("(\n");
+            stack.push(Work::Str("\n)"));
+            stack.push(Work::EndIndent);
+            push_separated(stack, args, ",\n");
+            stack.push(Work::StartIndent);
+        }
+        Expr::FunctionCall(FunctionCall { r#ref, args, .. }) => {
             assemble_ref(r#ref, assembled);
             assembled.push_str("(");
-            if let Some((last, init)) = args.split_last() {
-                for arg in init {
-                    assemble_expr(assembled, arg, interp);
-                    assembled.push_str(", ");
-                }
-                assemble_expr(assembled, last, interp);
-            }
-            assembled.push_str(")");
+            stack.push(Work::Str(")"));
+            push_separated(stack, args, options.separator());
+        }
+        Expr::FieldAccess(FieldAccess { expr, field, .. }) => {
+            stack.push(Work::Str(field));
+            stack.push(Work::Str("."));
+            stack.push(Work::Expr(expr));
+        }
+        Expr::While(While {
+            cond,
+            block,
+            check_first: false,
+            ..
+        }) => {
+            assembled.push_str("do {\n");
+            stack.push(Work::Str(")"));
+            stack.push(Work::Expr(cond));
+            stack.push(Work::Str(" while ("));
+            push_inner_block(stack, block);
         }
-        e @ (Expr::While(While { cond, block }) | Expr::If(If { cond, block })) => {
+        Expr::Loop(block, _) => {
+            assembled.push_str("loop {\n");
+            push_inner_block(stack, block);
+        }
+        e @ (Expr::While(While { cond, block, .. }) | Expr::If(If { cond, block, .. })) => {
             assembled.push_str(match e {
                 Expr::While(_) => "while (",
                 Expr::If(_) => "if (",
                 _ => unreachable!(),
             });
-            assemble_expr(assembled, cond, interp);
-            assembled.push_str(") {\n");
-            assemble_inner_block(assembled, block, interp);
-            assembled.push_str("\n}");
+            push_inner_block(stack, block);
+            stack.push(Work::Str(") {\n"));
+            stack.push(Work::Expr(cond));
         }
         Expr::FuncDef(FuncDef {
             name,
             arg_names,
             block,
+            ..
         }) => {
             assembled.push_str("defn ");
             assembled.push_str(name);
             assembled.push_str("(");
-            if let Some((last, init)) = arg_names.split_last() {
-                for arg_name in init {
-                    assembled.push_str(arg_name);
-                    assembled.push_str(", ");
-                }
-                assembled.push_str(last);
-            }
+            assembled.push_str(&arg_names.join(options.separator()));
             assembled.push_str(") {\n");
-            assemble_inner_block(assembled, block, interp);
-            assembled.push_str("\n}");
+            push_inner_block(stack, block);
         }
-        Expr::ListLiteral(list) => {
+        Expr::ListLiteral(list, _) => {
             assembled.push_str("[");
-            if let Some((last, init)) = list.split_last() {
-                for item in init {
-                    assemble_expr(assembled, item, interp);
-                    assembled.push_str(", ");
-                }
-                assemble_expr(assembled, last, interp);
-            }
-            assembled.push_str("]");
+            stack.push(Work::Str("]"));
+            push_separated(stack, list, options.separator());
         }
-        Expr::BinOp(BinOp { op, lhs, rhs }) => {
-            assemble_expr(assembled, lhs, interp);
-            assembled.push_str(match op {
+        Expr::BinOp(BinOp { op, lhs, rhs, .. }) => {
+            push_operand(stack, rhs);
+            stack.push(Work::Str(match op {
                 Op::Add => " + ",
                 Op::Sub => " - ",
                 Op::Mul => " * ",
@@ -130,57 +522,187 @@ fn assemble_expr(assembled: &mut String, expr: &Expr, interp: &Interpreter) {
                 Op::Gte => " >= ",
                 Op::And => " && ",
                 Op::Or => " || ",
-            });
-            assemble_expr(assembled, rhs, interp);
-        }
-        Expr::StringLiteral(s) => {
-            write!(assembled, "{:?}", s.as_str()).unwrap();
-        }
-        Expr::ResultComment(id, expr) => {
-            assemble_expr(assembled, expr, interp);
-            assembled.push_str(" // #");
-            let result_comments = interp.result_comments.borrow();
-            if let Some(value) = result_comments.get(id) {
-                let comment = wrapping::stringify(value);
-                let lines = comment.lines().collect_vec();
-                if let Some((first, rest)) = lines.split_first() {
-                    assembled.push_str(first);
-                    if !rest.is_empty() {
-                        for line in rest {
-                            assembled.push_str("\n");
-                            assembled.push_str("// ");
-                            assembled.push_str(line);
-                        }
+            }));
+            push_operand(stack, lhs);
+        }
+        Expr::StringLiteral(s, _) => {
+            assembled.push_str("\"");
+            assembled.push_str(&escape_string_chunk(s));
+            assembled.push_str("\"");
+        }
+        Expr::MultiLineString(s, _) => {
+            assembled.push_str("\"\"\"");
+            assembled.push_verbatim(s);
+            assembled.push_str("\"\"\"");
+        }
+        Expr::Interpolation(parts, _) => {
+            assembled.push_str("\"");
+            stack.push(Work::Str("\""));
+            for part in parts.iter().rev() {
+                match part {
+                    InterpolationPart::Literal(s) => {
+                        stack.push(Work::Owned(escape_string_chunk(s)))
+                    }
+                    InterpolationPart::Expr(expr) => {
+                        stack.push(Work::Str("}"));
+                        stack.push(Work::Expr(expr));
+                        stack.push(Work::Str("${"));
                     }
                 }
             }
         }
+        Expr::TrailingComment(expr, comment) => {
+            stack.push(Work::Comment(comment));
+            stack.push(Work::Str(" "));
+            stack.push(Work::Expr(expr));
+        }
+        Expr::ResultComment(id, expr, span) => {
+            // the result is written now, and goes after everything the expression writes
+            let comment = match &options.original_source {
+                Some(source) if !interp.result_comments.borrow().contains_key(id) => {
+                    original_result_comment(source, *span)
+                }
+                _ => result_comment(*id, interp),
+            };
+            stack.push(Work::Owned(format!(" {}", comment)));
+            stack.push(Work::Expr(expr));
+        }
     }
 }
 
-fn assemble_inner_block(assembled: &mut String, block: &Block, interp: &Interpreter) {
-    let mut inner = String::new();
-    assemble_expr(&mut inner, &Expr::Block(block.clone()), interp);
-    let inner = inner
+// `// #` and the result of the expression with that id, if it ran
+fn result_comment(id: ExprID, interp: &Interpreter) -> String {
+    let mut result = String::from("// #");
+    let result_comments = interp.result_comments.borrow();
+    if let Some(value) = result_comments.get(&id) {
+        let comment = wrapping::stringify(value);
+        let lines = comment.lines().collect_vec();
+        if let Some((first, rest)) = lines.split_first() {
+            // otherwise the result would read back as a named trailing comment
+            if first.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+                result.push_str(" ");
+            }
+            result.push_str(first);
+            for line in rest {
+                result.push_str("\n");
+                result.push_str("// ");
+                result.push_str(line);
+            }
+        }
+    }
+    result
+}
+
+// a result comment as it was in source, with the lines after the first one unindented so they
+// line up wherever the expression ends up
+fn original_result_comment(source: &str, span: Span) -> String {
+    source
+        .get(span.start..span.end)
+        .unwrap_or("// #")
         .lines()
-        .map(|line| {
-            if line.trim().is_empty() {
-                line.to_string()
-            } else {
-                // indentation
-                format!("  {}", line)
+        .map(str::trim)
+        .join("\n")
+}
+
+// Parses source and writes it out again with options, keeping what the result comments say. The
+// formatted program is parsed again and has to come out the same as the original, so a bug in the
+// formatter gives an error rather than a different program.
+pub fn format_source(source: &str, options: &AssembleOptions) -> anyhow::Result<String> {
+    let program = parse_program(source)?;
+    let options = AssembleOptions {
+        original_source: Some(source.into()),
+        ..options.clone()
+    };
+    let formatted = output_code_with(&program, &Interpreter::new(), &options);
+    let reparsed = parse_program(&formatted)
+        .map_err(|err| anyhow!("the formatted program doesn't parse:\n{}", err))?;
+    if !ast_eq(&program, &reparsed) {
+        bail!("the formatted program isn't the same program as the original");
+    }
+    Ok(formatted)
+}
+
+fn assemble_comment<W: io::Write>(
+    assembled: &mut Output<W>,
+    comment: &Comment,
+    options: &AssembleOptions,
+) {
+    let Comment {
+        name, body, style, ..
+    } = comment;
+    match style {
+        CommentStyle::Block => {
+            assembled.push_str("/*");
+            if let Some(name) = name {
+                assembled.push_str(" #");
+                assembled.push_str(name);
+                assembled.push_str("\n");
+            }
+            assembled.push_verbatim(body);
+            assembled.push_str("*/");
+        }
+        CommentStyle::Trailing => {
+            assembled.push_str("//");
+            if let Some(name) = name {
+                assembled.push_str(options.comment_prefix(""));
+                assembled.push_str("#");
+                assembled.push_str(name);
+                if !body.is_empty() {
+                    assembled.push_str(" ");
+                }
+            } else if !body.is_empty() {
+                assembled.push_str(options.comment_prefix(body));
+            }
+            assembled.push_str(body);
+        }
+        CommentStyle::Line => {
+            if let Some(name) = name {
+                assembled.push_str("//");
+                assembled.push_str(options.comment_prefix(""));
+                assembled.push_str("#");
+                assembled.push_str(name);
+
+                if body.is_empty() {
+                    return;
+                }
+
+                assembled.push_str("\n");
+            }
+
+            if body.is_empty() {
+                assembled.push_str("//");
+                return;
+            }
+
+            let mut lines = body.split("\n").peekable();
+            while let Some(line) = lines.next() {
+                assembled.push_str("//");
+                if !line.is_empty() {
+                    assembled.push_str(options.comment_prefix(line));
+                    assembled.push_str(line);
+                }
+
+                if let Some(_) = lines.peek() {
+                    assembled.push_str("\n");
+                }
             }
-        })
-        .join("\n");
-    assembled.push_str(&inner);
+        }
+    }
+}
+
+// a literal `${` has to be escaped, otherwise it would parse back as an interpolation
+fn escape_string_chunk(s: &str) -> String {
+    let mut quoted = String::new();
+    write!(quoted, "{:?}", s).unwrap();
+    quoted[1..quoted.len() - 1].replace("${", "\\${")
 }
 
-fn assemble_ref(r#ref: &Ref, assembled: &mut String) {
+fn assemble_ref<W: io::Write>(r#ref: &Ref, assembled: &mut Output<W>) {
     match r#ref {
-        Ref::CommentRef(s) => {
+        Ref::CommentRef(s, _) => {
             assembled.push_str("#");
             assembled.push_str(s);
         }
-        Ref::VarRef(s) => assembled.push_str(s),
+        Ref::VarRef(s, _) => assembled.push_str(s),
     }
 }
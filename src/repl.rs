@@ -0,0 +1,201 @@
+// `zac repl`, and what running zac with no arguments does: reads a line at a time, runs it once
+// it's a whole program (see Interpreter::eval_line) and shows what it came to. Lines starting
+// with a colon are commands, see HELP.
+
+use std::collections::BTreeMap;
+use std::io::{BufRead, Write};
+use zac_lib::error::render_error;
+use zac_lib::interp::{builtin_comment, EvalOutcome, Interpreter, Value};
+
+const PROMPT: &str = "> ";
+// while eval_line is waiting for the rest of something
+const CONTINUATION_PROMPT: &str = ".. ";
+
+const HELP: &str = ":help     the builtins and what they do
+:vars     the variables set so far
+:history  what's been run so far, numbered
+:N        run number N from :history again
+:quit     leave, and so does end of input
+";
+
+pub struct Repl {
+    interp: Interpreter,
+    // the global scope before anything ran, so :vars can leave out the builtins
+    builtins: BTreeMap<String, Value>,
+    // the lines eval_line is waiting to finish, to show errors in and keep in the history
+    pending: String,
+    history: Vec<String>,
+}
+
+impl Repl {
+    pub fn new(interp: Interpreter) -> Self {
+        Self {
+            builtins: interp.vars().into_iter().collect(),
+            interp,
+            pending: String::new(),
+            history: vec![],
+        }
+    }
+
+    pub fn prompt(&self) -> &'static str {
+        if self.pending.is_empty() {
+            PROMPT
+        } else {
+            CONTINUATION_PROMPT
+        }
+    }
+
+    // forgets the input that isn't finished yet, for Ctrl-C
+    pub fn cancel(&mut self) {
+        self.pending.clear();
+        self.interp.discard_input();
+    }
+
+    // runs all of input, showing a prompt before each line. stops at the end of input or :quit
+    pub fn run(&mut self, input: impl BufRead, out: &mut impl Write) -> anyhow::Result<()> {
+        interrupt::install();
+        write!(out, "{}", self.prompt())?;
+        out.flush()?;
+        for line in input.lines() {
+            let line = line?;
+            if interrupt::take() {
+                self.cancel();
+            }
+            interrupt::set_running(true);
+            let keep_going = self.line(&line, out)?;
+            interrupt::set_running(false);
+            if !keep_going {
+                return Ok(());
+            }
+            write!(out, "{}", self.prompt())?;
+            out.flush()?;
+        }
+        writeln!(out)?;
+        Ok(())
+    }
+
+    // false after :quit
+    pub fn line(&mut self, line: &str, out: &mut impl Write) -> anyhow::Result<bool> {
+        if self.pending.is_empty() {
+            match line.trim() {
+                ":quit" => return Ok(false),
+                ":help" => {
+                    write!(out, "{}", HELP)?;
+                    writeln!(out)?;
+                    writeln!(out, "{}", builtin_comment(&self.interp, "help").unwrap())?;
+                    return Ok(true);
+                }
+                ":vars" => {
+                    for (name, val) in self.interp.vars() {
+                        if self.builtins.get(&name) != Some(&val) {
+                            writeln!(out, "{} = {}", name, val)?;
+                        }
+                    }
+                    return Ok(true);
+                }
+                ":history" => {
+                    for (i, entry) in self.history.iter().enumerate() {
+                        let entry = entry.trim_end().replace('\n', "\n   ");
+                        writeln!(out, "{:>2} {}", i + 1, entry)?;
+                    }
+                    return Ok(true);
+                }
+                command if command.starts_with(':') => {
+                    let entry = command[1..]
+                        .parse::<usize>()
+                        .ok()
+                        .and_then(|n| self.history.get(n.wrapping_sub(1)))
+                        .cloned();
+                    match entry {
+                        Some(entry) => {
+                            for line in entry.lines() {
+                                self.eval(line, out)?;
+                            }
+                        }
+                        None => writeln!(out, "unknown command {}, try :help", command)?,
+                    }
+                    return Ok(true);
+                }
+                _ => (),
+            }
+        }
+        self.eval(line, out)?;
+        Ok(true)
+    }
+
+    fn eval(&mut self, line: &str, out: &mut impl Write) -> anyhow::Result<()> {
+        self.pending.push_str(line);
+        self.pending.push('\n');
+        let outcome = self.interp.eval_line(line);
+        if let Ok(EvalOutcome::NeedMoreInput) = outcome {
+            return Ok(());
+        }
+        let input = std::mem::take(&mut self.pending);
+        if !input.trim().is_empty() {
+            self.history.push(input.clone());
+        }
+        match outcome {
+            Ok(EvalOutcome::Value(Value::Null)) | Ok(EvalOutcome::NeedMoreInput) => (),
+            Ok(EvalOutcome::Value(val)) => writeln!(out, "{}", self.interp.show(&val))?,
+            Err(err) => writeln!(out, "error: {}", render_error(&input, &err))?,
+        }
+        Ok(())
+    }
+}
+
+// Ctrl-C while typing throws away what's been typed so far and starts again at a new prompt. The
+// terminal has already dropped the line it was on, and the lines before it are dropped when the
+// next one comes in. Ctrl-C while a program runs stops zac, since there's no other way out of an
+// endless loop.
+#[cfg(unix)]
+mod interrupt {
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    const SIGINT: i32 = 2;
+
+    static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+    static RUNNING: AtomicBool = AtomicBool::new(false);
+
+    extern "C" {
+        fn signal(signum: i32, handler: extern "C" fn(i32)) -> usize;
+        fn write(fd: i32, buf: *const u8, count: usize) -> isize;
+        fn _exit(status: i32) -> !;
+    }
+
+    // only async-signal-safe calls in here
+    extern "C" fn on_sigint(_: i32) {
+        if RUNNING.load(Ordering::SeqCst) {
+            unsafe { _exit(130) }
+        }
+        INTERRUPTED.store(true, Ordering::SeqCst);
+        let fresh_prompt = b"\n> ";
+        unsafe {
+            write(1, fresh_prompt.as_ptr(), fresh_prompt.len());
+        }
+    }
+
+    pub fn install() {
+        unsafe {
+            signal(SIGINT, on_sigint);
+        }
+    }
+
+    pub fn take() -> bool {
+        INTERRUPTED.swap(false, Ordering::SeqCst)
+    }
+
+    pub fn set_running(running: bool) {
+        RUNNING.store(running, Ordering::SeqCst);
+    }
+}
+
+#[cfg(not(unix))]
+mod interrupt {
+    pub fn install() {}
+
+    pub fn take() -> bool {
+        false
+    }
+
+    pub fn set_running(_: bool) {}
+}
@@ -0,0 +1,44 @@
+// The random numbers rand and rand_range give. It's splitmix64, which is small, fast and good
+// enough for programs that want dice rolls and shuffles, but not for anything secret.
+
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+#[derive(Debug, Clone)]
+pub(crate) struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    // the same seed always gives the same numbers
+    pub(crate) fn from_seed(seed: u64) -> Self {
+        Rng { state: seed }
+    }
+
+    // a different seed every time. RandomState is seeded by the OS, which saves a dependency
+    pub(crate) fn from_entropy() -> Self {
+        Rng::from_seed(RandomState::new().build_hasher().finish())
+    }
+
+    pub(crate) fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    // a number from 0 up to but not including n, each as likely as the others. taking x % n of
+    // any x would make the small ones a little more likely, so the xs past the last whole
+    // multiple of n are drawn again
+    pub(crate) fn below(&mut self, n: u128) -> u128 {
+        assert!(n > 0);
+        let zone = u128::MAX - (u128::MAX - n + 1) % n;
+        loop {
+            let x = (self.next_u64() as u128) << 64 | self.next_u64() as u128;
+            if x <= zone {
+                return x % n;
+            }
+        }
+    }
+}
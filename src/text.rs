@@ -0,0 +1,36 @@
+// What counts as one character of a string. By default that's a char (a unicode scalar value),
+// and with the `unicode` feature and Interpreter::set_grapheme_strings(true) it's an extended
+// grapheme cluster instead, so "é" written as e + a combining accent, or a family emoji made of
+// several people, is still one character.
+
+#[cfg(feature = "unicode")]
+use unicode_segmentation::UnicodeSegmentation;
+#[cfg(feature = "unicode")]
+use unicode_width::UnicodeWidthChar;
+
+// each character of s with the byte offset it starts at
+pub(crate) fn char_indices(s: &str, graphemes: bool) -> Vec<(usize, &str)> {
+    #[cfg(feature = "unicode")]
+    if graphemes {
+        return s.grapheme_indices(true).collect();
+    }
+    let _ = graphemes;
+    s.char_indices()
+        .map(|(i, c)| (i, &s[i..i + c.len_utf8()]))
+        .collect()
+}
+
+// how many columns s takes up in a terminal. in grapheme mode wide characters (like most CJK
+// ones) take up 2, otherwise every char is 1. a grapheme is as wide as the widest char in it,
+// since versions of unicode-width before 0.1.11 add up the people in a family emoji separately
+pub(crate) fn width(s: &str, graphemes: bool) -> usize {
+    #[cfg(feature = "unicode")]
+    if graphemes {
+        return s
+            .graphemes(true)
+            .map(|g| g.chars().filter_map(|c| c.width()).max().unwrap_or(0))
+            .sum();
+    }
+    let _ = graphemes;
+    s.chars().count()
+}
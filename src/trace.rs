@@ -0,0 +1,207 @@
+// Execution traces. The interpreter records a flat stream of events while it runs, and the stream
+// is rendered either for people (`--trace-format=human`) or as JSON lines for external tools
+// (`--trace-format=jsonl`).
+//
+// Version 1 of the JSON lines format has one object per event:
+//
+//   {"version": 1, "seq": 0, "kind": "expr_start", "span": null, "expr": "block", "value": null}
+//
+// - "seq" counts up from 0 in the order events happened
+// - "kind" is one of expr_start, expr_end, var_write, comment_write, call_enter, call_exit and
+//   loop_iteration
+// - "span" is always null for now, the parser doesn't record source positions yet
+// - "expr" is only on expr_start and expr_end, and is the expression's type from the program
+//   description format (see description.rs)
+// - "name" is only on var_write, comment_write, call_enter and call_exit, and is the variable,
+//   comment or function name
+// - "value" is the value shown the same way as `show`, cut off after --trace-value-len chars and
+//   ending in "..." if it was. it's null for events that don't have a value
+//
+// For example, the `let x = 1` in a program traces as (seq numbers depend on what came before):
+//
+//   {"version":1,"seq":0,"kind":"expr_start","span":null,"expr":"assign","value":null}
+//   {"version":1,"seq":1,"kind":"expr_start","span":null,"expr":"int","value":null}
+//   {"version":1,"seq":2,"kind":"expr_end","span":null,"expr":"int","value":"1"}
+//   {"version":1,"seq":3,"kind":"var_write","span":null,"name":"x","value":"1"}
+//   {"version":1,"seq":4,"kind":"expr_end","span":null,"expr":"assign","value":"1"}
+
+use crate::interp::Value;
+use crate::json::Json;
+use crate::wrapping;
+use anyhow::bail;
+use itertools::Itertools;
+
+pub const TRACE_VERSION: i128 = 1;
+pub const DEFAULT_MAX_VALUE_LEN: usize = 80;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TraceFormat {
+    Human,
+    Jsonl,
+}
+
+impl TraceFormat {
+    pub fn from_name(name: &str) -> anyhow::Result<Self> {
+        Ok(match name {
+            "human" => TraceFormat::Human,
+            "jsonl" => TraceFormat::Jsonl,
+            _ => bail!("unknown trace format {:?}, expected human or jsonl", name),
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TraceKind {
+    ExprStart { expr: &'static str },
+    ExprEnd { expr: &'static str },
+    VarWrite { name: String },
+    CommentWrite { name: String },
+    CallEnter { name: String },
+    CallExit { name: String },
+    LoopIteration,
+}
+
+impl TraceKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            TraceKind::ExprStart { .. } => "expr_start",
+            TraceKind::ExprEnd { .. } => "expr_end",
+            TraceKind::VarWrite { .. } => "var_write",
+            TraceKind::CommentWrite { .. } => "comment_write",
+            TraceKind::CallEnter { .. } => "call_enter",
+            TraceKind::CallExit { .. } => "call_exit",
+            TraceKind::LoopIteration => "loop_iteration",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TraceEvent {
+    pub seq: usize,
+    pub kind: TraceKind,
+    pub value: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct Tracer {
+    max_value_len: usize,
+    events: Vec<TraceEvent>,
+}
+
+impl Tracer {
+    pub fn new(max_value_len: usize) -> Self {
+        Self {
+            max_value_len,
+            events: vec![],
+        }
+    }
+
+    pub fn events(&self) -> &[TraceEvent] {
+        &self.events
+    }
+
+    pub(crate) fn record(&mut self, kind: TraceKind, value: Option<&Value>) {
+        let value = value.map(|value| truncate(&wrapping::stringify(value), self.max_value_len));
+        self.events.push(TraceEvent {
+            seq: self.events.len(),
+            kind,
+            value,
+        });
+    }
+
+    /// ```
+    /// use std::cell::RefCell;
+    /// use std::rc::Rc;
+    /// use zac_lib::interp::Interpreter;
+    /// use zac_lib::parser::{parser, Expr};
+    /// use zac_lib::trace::{TraceFormat, Tracer};
+    ///
+    /// let tracer = Rc::new(RefCell::new(Tracer::new(80)));
+    /// let mut interp = Interpreter::new();
+    /// interp.set_tracer(Rc::clone(&tracer));
+    /// let program = parser::program("let x = 1\n").unwrap();
+    /// interp.interp(&Expr::Block(program.block)).unwrap();
+    ///
+    /// let jsonl = tracer.borrow().render(TraceFormat::Jsonl);
+    /// let var_write = jsonl.lines().find(|line| line.contains("var_write")).unwrap();
+    /// assert!(var_write.starts_with(r#"{"version":1,"seq":"#));
+    /// assert!(var_write.ends_with(r#""name":"x","value":"1"}"#));
+    /// ```
+    pub fn render(&self, format: TraceFormat) -> String {
+        match format {
+            TraceFormat::Human => render_human(&self.events),
+            TraceFormat::Jsonl => render_jsonl(&self.events),
+        }
+    }
+}
+
+pub(crate) fn truncate(s: &str, max_len: usize) -> String {
+    if s.chars().count() <= max_len {
+        s.to_owned()
+    } else {
+        format!("{}...", s.chars().take(max_len).collect::<String>())
+    }
+}
+
+// indented by nesting, so calls and the expressions inside them read like a tree
+fn render_human(events: &[TraceEvent]) -> String {
+    let mut depth = 0;
+    let mut lines = vec![];
+    for event in events {
+        if let TraceKind::ExprEnd { .. } | TraceKind::CallExit { .. } = event.kind {
+            depth -= 1;
+        }
+        let what = match &event.kind {
+            TraceKind::ExprStart { expr } => expr.to_string(),
+            TraceKind::ExprEnd { expr } => format!("{} =>", expr),
+            TraceKind::VarWrite { name } => format!("let {} =", name),
+            TraceKind::CommentWrite { name } => format!("let #{} =", name),
+            TraceKind::CallEnter { name } => format!("call {}", name),
+            TraceKind::CallExit { name } => format!("return from {}", name),
+            TraceKind::LoopIteration => "loop".to_string(),
+        };
+        let line = match &event.value {
+            Some(value) => format!("{} {}", what, value),
+            None => what,
+        };
+        lines.push(format!("{}{}", "  ".repeat(depth), line));
+        if let TraceKind::ExprStart { .. } | TraceKind::CallEnter { .. } = event.kind {
+            depth += 1;
+        }
+    }
+    lines.into_iter().map(|line| line + "\n").join("")
+}
+
+fn render_jsonl(events: &[TraceEvent]) -> String {
+    events
+        .iter()
+        .map(|event| event_to_json(event).to_string() + "\n")
+        .join("")
+}
+
+fn event_to_json(event: &TraceEvent) -> Json {
+    let mut fields = vec![
+        ("version".to_string(), Json::Int(TRACE_VERSION)),
+        ("seq".to_string(), Json::Int(event.seq as i128)),
+        ("kind".to_string(), Json::String(event.kind.name().into())),
+        ("span".to_string(), Json::Null),
+    ];
+    match &event.kind {
+        TraceKind::ExprStart { expr } | TraceKind::ExprEnd { expr } => {
+            fields.push(("expr".to_string(), Json::String(expr.to_string())))
+        }
+        TraceKind::VarWrite { name }
+        | TraceKind::CommentWrite { name }
+        | TraceKind::CallEnter { name }
+        | TraceKind::CallExit { name } => {
+            fields.push(("name".to_string(), Json::String(name.clone())))
+        }
+        TraceKind::LoopIteration => {}
+    }
+    let value = match &event.value {
+        Some(value) => Json::String(value.clone()),
+        None => Json::Null,
+    };
+    fields.push(("value".to_string(), value));
+    Json::Object(fields)
+}
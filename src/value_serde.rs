@@ -0,0 +1,628 @@
+// serde support for Value, with the `serde` feature. Strings, ints, bools, lists and null map onto
+// the serde data model the obvious way, and so do maps whose keys are all strings. A map with any
+// other kind of key is written as a list of [key, value] pairs, which reads back in as a list.
+// Functions and opaque values can't be written at all, and nothing reads back in as one.
+//
+// Value is also a serde format of its own: to_value turns anything Serialize into a Value, and
+// from_value turns a Value into anything Deserialize, so a host program can hand its own types to
+// a Zac program and take them back out.
+
+use crate::error::SoldierError;
+use crate::interp::Value;
+use anyhow::anyhow;
+use serde::de::value::{MapDeserializer, SeqDeserializer};
+use serde::de::{self, DeserializeOwned, IntoDeserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{self, Serialize, SerializeMap, SerializeSeq, Serializer};
+use serde::{Deserialize, Deserializer};
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+pub fn to_value<T: Serialize + ?Sized>(value: &T) -> Result<Value, SoldierError> {
+    value.serialize(ValueSerializer)
+}
+
+pub fn from_value<T: DeserializeOwned>(value: Value) -> Result<T, SoldierError> {
+    T::deserialize(value)
+}
+
+impl ser::Error for SoldierError {
+    fn custom<T: Display>(msg: T) -> Self {
+        SoldierError::Other {
+            error: anyhow!("{}", msg),
+            span: None,
+        }
+    }
+}
+
+impl de::Error for SoldierError {
+    fn custom<T: Display>(msg: T) -> Self {
+        <SoldierError as ser::Error>::custom(msg)
+    }
+}
+
+impl Serialize for Value {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Value::String(s) => serializer.serialize_str(s),
+            // most formats only go up to 64 bits, so that's used whenever it's enough
+            Value::Int(n) => match i64::try_from(*n) {
+                Ok(small) => serializer.serialize_i64(small),
+                Err(_) => serializer.serialize_i128(*n),
+            },
+            Value::Bool(b) => serializer.serialize_bool(*b),
+            Value::Null => serializer.serialize_unit(),
+            Value::List(vals) => {
+                let mut seq = serializer.serialize_seq(Some(vals.len()))?;
+                for val in vals {
+                    seq.serialize_element(val)?;
+                }
+                seq.end()
+            }
+            Value::Map(map) if map.keys().all(|k| matches!(k, Value::String(_))) => {
+                let mut out = serializer.serialize_map(Some(map.len()))?;
+                for (k, v) in map {
+                    out.serialize_entry(k, v)?;
+                }
+                out.end()
+            }
+            Value::Map(map) => {
+                let mut seq = serializer.serialize_seq(Some(map.len()))?;
+                for (k, v) in map {
+                    seq.serialize_element(&[k, v])?;
+                }
+                seq.end()
+            }
+            Value::Function(f) => Err(ser::Error::custom(format!(
+                "can't serialize the function {}",
+                f.name()
+            ))),
+            Value::Opaque(opaque) => Err(ser::Error::custom(format!(
+                "can't serialize a {}",
+                opaque.type_name()
+            ))),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Value {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = Value;
+
+    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "a string, integer, bool, list, map or null")
+    }
+
+    fn visit_bool<E: de::Error>(self, b: bool) -> Result<Value, E> {
+        Ok(Value::Bool(b))
+    }
+
+    fn visit_i64<E: de::Error>(self, n: i64) -> Result<Value, E> {
+        Ok(Value::Int(n.into()))
+    }
+
+    fn visit_u64<E: de::Error>(self, n: u64) -> Result<Value, E> {
+        Ok(Value::Int(n.into()))
+    }
+
+    fn visit_i128<E: de::Error>(self, n: i128) -> Result<Value, E> {
+        Ok(Value::Int(n))
+    }
+
+    fn visit_u128<E: de::Error>(self, n: u128) -> Result<Value, E> {
+        i128::try_from(n)
+            .map(Value::Int)
+            .map_err(|_| E::custom(format!("{} is too big for an integer", n)))
+    }
+
+    fn visit_f64<E: de::Error>(self, n: f64) -> Result<Value, E> {
+        Err(E::custom(format!("{} isn't an integer", n)))
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<Value, E> {
+        Ok(Value::String(s.to_owned()))
+    }
+
+    fn visit_string<E: de::Error>(self, s: String) -> Result<Value, E> {
+        Ok(Value::String(s))
+    }
+
+    fn visit_unit<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_none<E: de::Error>(self) -> Result<Value, E> {
+        Ok(Value::Null)
+    }
+
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Value, D::Error> {
+        Value::deserialize(deserializer)
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Value, A::Error> {
+        let mut vals = vec![];
+        while let Some(val) = seq.next_element()? {
+            vals.push(val);
+        }
+        Ok(Value::List(vals))
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Value, A::Error> {
+        let mut map = BTreeMap::new();
+        while let Some((k, v)) = access.next_entry()? {
+            map.insert(k, v);
+        }
+        Ok(Value::Map(map))
+    }
+}
+
+// Turns Rust values into Values. Structs become maps from field names, and enum variants with
+// data become a map from the variant's name to the data, like {"Circle": {"radius": 2}}.
+struct ValueSerializer;
+
+impl Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = SoldierError;
+    type SerializeSeq = ListSerializer;
+    type SerializeTuple = ListSerializer;
+    type SerializeTupleStruct = ListSerializer;
+    type SerializeTupleVariant = ListSerializer;
+    type SerializeMap = MapSerializer;
+    type SerializeStruct = MapSerializer;
+    type SerializeStructVariant = MapSerializer;
+
+    fn serialize_bool(self, b: bool) -> Result<Value, SoldierError> {
+        Ok(Value::Bool(b))
+    }
+
+    fn serialize_i8(self, n: i8) -> Result<Value, SoldierError> {
+        Ok(n.into())
+    }
+
+    fn serialize_i16(self, n: i16) -> Result<Value, SoldierError> {
+        Ok(n.into())
+    }
+
+    fn serialize_i32(self, n: i32) -> Result<Value, SoldierError> {
+        Ok(n.into())
+    }
+
+    fn serialize_i64(self, n: i64) -> Result<Value, SoldierError> {
+        Ok(n.into())
+    }
+
+    fn serialize_i128(self, n: i128) -> Result<Value, SoldierError> {
+        Ok(n.into())
+    }
+
+    fn serialize_u8(self, n: u8) -> Result<Value, SoldierError> {
+        Ok(n.into())
+    }
+
+    fn serialize_u16(self, n: u16) -> Result<Value, SoldierError> {
+        Ok(n.into())
+    }
+
+    fn serialize_u32(self, n: u32) -> Result<Value, SoldierError> {
+        Ok(n.into())
+    }
+
+    fn serialize_u64(self, n: u64) -> Result<Value, SoldierError> {
+        Ok(n.into())
+    }
+
+    fn serialize_u128(self, n: u128) -> Result<Value, SoldierError> {
+        ValueVisitor.visit_u128(n)
+    }
+
+    fn serialize_f32(self, n: f32) -> Result<Value, SoldierError> {
+        ValueVisitor.visit_f64(n.into())
+    }
+
+    fn serialize_f64(self, n: f64) -> Result<Value, SoldierError> {
+        ValueVisitor.visit_f64(n)
+    }
+
+    fn serialize_char(self, c: char) -> Result<Value, SoldierError> {
+        Ok(Value::String(c.to_string()))
+    }
+
+    fn serialize_str(self, s: &str) -> Result<Value, SoldierError> {
+        Ok(s.into())
+    }
+
+    fn serialize_bytes(self, bytes: &[u8]) -> Result<Value, SoldierError> {
+        Ok(bytes.to_vec().into())
+    }
+
+    fn serialize_none(self) -> Result<Value, SoldierError> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value, SoldierError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, SoldierError> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_struct(self, _: &'static str) -> Result<Value, SoldierError> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+    ) -> Result<Value, SoldierError> {
+        Ok(variant.into())
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        value: &T,
+    ) -> Result<Value, SoldierError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        value: &T,
+    ) -> Result<Value, SoldierError> {
+        Ok(tagged(variant, to_value(value)?))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<ListSerializer, SoldierError> {
+        Ok(ListSerializer {
+            variant: None,
+            vals: Vec::with_capacity(len.unwrap_or(0)),
+        })
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<ListSerializer, SoldierError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _: &'static str,
+        len: usize,
+    ) -> Result<ListSerializer, SoldierError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        len: usize,
+    ) -> Result<ListSerializer, SoldierError> {
+        Ok(ListSerializer {
+            variant: Some(variant),
+            vals: Vec::with_capacity(len),
+        })
+    }
+
+    fn serialize_map(self, _: Option<usize>) -> Result<MapSerializer, SoldierError> {
+        Ok(MapSerializer {
+            variant: None,
+            map: BTreeMap::new(),
+            key: None,
+        })
+    }
+
+    fn serialize_struct(self, _: &'static str, _: usize) -> Result<MapSerializer, SoldierError> {
+        self.serialize_map(None)
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _: &'static str,
+        _: u32,
+        variant: &'static str,
+        _: usize,
+    ) -> Result<MapSerializer, SoldierError> {
+        Ok(MapSerializer {
+            variant: Some(variant),
+            map: BTreeMap::new(),
+            key: None,
+        })
+    }
+}
+
+fn tagged(variant: &str, value: Value) -> Value {
+    Value::Map(BTreeMap::from([(variant.into(), value)]))
+}
+
+struct ListSerializer {
+    // set for a tuple variant, which ends up inside a map from the variant's name
+    variant: Option<&'static str>,
+    vals: Vec<Value>,
+}
+
+impl ListSerializer {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SoldierError> {
+        self.vals.push(to_value(value)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Value, SoldierError> {
+        let list = Value::List(self.vals);
+        Ok(match self.variant {
+            Some(variant) => tagged(variant, list),
+            None => list,
+        })
+    }
+}
+
+impl SerializeSeq for ListSerializer {
+    type Ok = Value;
+    type Error = SoldierError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SoldierError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, SoldierError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTuple for ListSerializer {
+    type Ok = Value;
+    type Error = SoldierError;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SoldierError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, SoldierError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleStruct for ListSerializer {
+    type Ok = Value;
+    type Error = SoldierError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SoldierError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, SoldierError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeTupleVariant for ListSerializer {
+    type Ok = Value;
+    type Error = SoldierError;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SoldierError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Value, SoldierError> {
+        self.finish()
+    }
+}
+
+struct MapSerializer {
+    // set for a struct variant, which ends up inside a map from the variant's name
+    variant: Option<&'static str>,
+    map: BTreeMap<Value, Value>,
+    // serialize_key's key, waiting for serialize_value
+    key: Option<Value>,
+}
+
+impl MapSerializer {
+    fn finish(self) -> Result<Value, SoldierError> {
+        let map = Value::Map(self.map);
+        Ok(match self.variant {
+            Some(variant) => tagged(variant, map),
+            None => map,
+        })
+    }
+}
+
+impl SerializeMap for MapSerializer {
+    type Ok = Value;
+    type Error = SoldierError;
+
+    fn serialize_key<T: Serialize + ?Sized>(&mut self, key: &T) -> Result<(), SoldierError> {
+        self.key = Some(to_value(key)?);
+        Ok(())
+    }
+
+    fn serialize_value<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), SoldierError> {
+        let key = self.key.take().ok_or_else(|| {
+            <SoldierError as ser::Error>::custom("a map value came before its key")
+        })?;
+        self.map.insert(key, to_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, SoldierError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStruct for MapSerializer {
+    type Ok = Value;
+    type Error = SoldierError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<(), SoldierError> {
+        self.map.insert(name.into(), to_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, SoldierError> {
+        self.finish()
+    }
+}
+
+impl ser::SerializeStructVariant for MapSerializer {
+    type Ok = Value;
+    type Error = SoldierError;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        name: &'static str,
+        value: &T,
+    ) -> Result<(), SoldierError> {
+        self.map.insert(name.into(), to_value(value)?);
+        Ok(())
+    }
+
+    fn end(self) -> Result<Value, SoldierError> {
+        self.finish()
+    }
+}
+
+// Reads Rust values out of Values, the other way round from ValueSerializer.
+impl<'de> Deserializer<'de> for Value {
+    type Error = SoldierError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SoldierError> {
+        match self {
+            Value::String(s) => visitor.visit_string(s),
+            Value::Int(n) => match (i64::try_from(n), u64::try_from(n)) {
+                (Ok(small), _) => visitor.visit_i64(small),
+                (_, Ok(big)) => visitor.visit_u64(big),
+                _ => visitor.visit_i128(n),
+            },
+            Value::Bool(b) => visitor.visit_bool(b),
+            Value::Null => visitor.visit_unit(),
+            Value::List(vals) => visitor.visit_seq(SeqDeserializer::new(vals.into_iter())),
+            Value::Map(map) => visitor.visit_map(MapDeserializer::new(map.into_iter())),
+            Value::Function(f) => Err(de::Error::custom(format!(
+                "can't deserialize from the function {}",
+                f.name()
+            ))),
+            Value::Opaque(opaque) => Err(de::Error::custom(format!(
+                "can't deserialize from a {}",
+                opaque.type_name()
+            ))),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, SoldierError> {
+        match self {
+            Value::Null => visitor.visit_none(),
+            otherwise => visitor.visit_some(otherwise),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, SoldierError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    // a variant is either its name, or a map from its name to its data
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _: &'static str,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SoldierError> {
+        match self {
+            Value::String(variant) => visitor.visit_enum(variant.into_deserializer()),
+            Value::Map(map) if map.len() == 1 => {
+                let (variant, data) = map.into_iter().next().unwrap();
+                visitor.visit_enum(VariantDeserializer { variant, data })
+            }
+            otherwise => Err(de::Error::custom(format!(
+                "expected an enum variant, got {:?}",
+                otherwise
+            ))),
+        }
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
+
+impl<'de> IntoDeserializer<'de, SoldierError> for Value {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self {
+        self
+    }
+}
+
+// a variant with data, from {"Name": data}
+struct VariantDeserializer {
+    variant: Value,
+    data: Value,
+}
+
+impl<'de> de::EnumAccess<'de> for VariantDeserializer {
+    type Error = SoldierError;
+    type Variant = Value;
+
+    fn variant_seed<V: de::DeserializeSeed<'de>>(
+        self,
+        seed: V,
+    ) -> Result<(V::Value, Value), SoldierError> {
+        Ok((seed.deserialize(self.variant)?, self.data))
+    }
+}
+
+impl<'de> de::VariantAccess<'de> for Value {
+    type Error = SoldierError;
+
+    fn unit_variant(self) -> Result<(), SoldierError> {
+        match self {
+            Value::Null => Ok(()),
+            otherwise => Err(de::Error::custom(format!(
+                "expected no data for a unit variant, got {:?}",
+                otherwise
+            ))),
+        }
+    }
+
+    fn newtype_variant_seed<T: de::DeserializeSeed<'de>>(
+        self,
+        seed: T,
+    ) -> Result<T::Value, SoldierError> {
+        seed.deserialize(self)
+    }
+
+    fn tuple_variant<V: Visitor<'de>>(
+        self,
+        _: usize,
+        visitor: V,
+    ) -> Result<V::Value, SoldierError> {
+        self.deserialize_any(visitor)
+    }
+
+    fn struct_variant<V: Visitor<'de>>(
+        self,
+        _: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, SoldierError> {
+        self.deserialize_any(visitor)
+    }
+}
@@ -0,0 +1,170 @@
+// Everything a program can do to files and the environment goes through here, so a host can
+// hand the interpreter a virtual filesystem (like MemFs) and its own environment instead of the
+// real ones. See Interpreter::set_fs and Interpreter::set_env.
+//
+// Writing comments back into the source file isn't done by the program, it's done afterwards by
+// whoever is running it (main.rs for the command line), so it never goes through a VirtualFs.
+
+use anyhow::{anyhow, bail};
+use itertools::Itertools;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{Debug, Formatter};
+use std::fs;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::rc::Rc;
+
+pub trait VirtualFs: Debug {
+    fn read(&self, path: &str) -> anyhow::Result<String>;
+    fn write(&mut self, path: &str, contents: &str) -> anyhow::Result<()>;
+    // adds contents to the end of the file, or makes it if it doesn't exist
+    fn append(&mut self, path: &str, contents: &str) -> anyhow::Result<()> {
+        let existing = if self.exists(path)? {
+            self.read(path)?
+        } else {
+            String::new()
+        };
+        self.write(path, &(existing + contents))
+    }
+    fn exists(&self, path: &str) -> anyhow::Result<bool>;
+    // names of the entries directly inside the directory, sorted
+    fn list(&self, path: &str) -> anyhow::Result<Vec<String>>;
+}
+
+// the real filesystem, only if the host allowed it (`--allow-fs` on the command line)
+#[derive(Debug)]
+pub struct RealFs {
+    allowed: bool,
+}
+
+impl RealFs {
+    pub fn new(allowed: bool) -> Self {
+        Self { allowed }
+    }
+
+    fn check_allowed(&self, path: &str) -> anyhow::Result<()> {
+        if !self.allowed {
+            bail!(
+                "can't access {}, file access isn't allowed (see --allow-fs)",
+                path
+            );
+        }
+        Ok(())
+    }
+}
+
+impl VirtualFs for RealFs {
+    fn read(&self, path: &str) -> anyhow::Result<String> {
+        self.check_allowed(path)?;
+        fs::read_to_string(path).map_err(|err| anyhow!("can't read {}: {}", path, err))
+    }
+
+    fn write(&mut self, path: &str, contents: &str) -> anyhow::Result<()> {
+        self.check_allowed(path)?;
+        fs::write(path, contents).map_err(|err| anyhow!("can't write {}: {}", path, err))
+    }
+
+    fn append(&mut self, path: &str, contents: &str) -> anyhow::Result<()> {
+        self.check_allowed(path)?;
+        OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(path)
+            .and_then(|mut file| file.write_all(contents.as_bytes()))
+            .map_err(|err| anyhow!("can't append to {}: {}", path, err))
+    }
+
+    fn exists(&self, path: &str) -> anyhow::Result<bool> {
+        self.check_allowed(path)?;
+        Ok(fs::metadata(path).is_ok())
+    }
+
+    fn list(&self, path: &str) -> anyhow::Result<Vec<String>> {
+        self.check_allowed(path)?;
+        let list_err = |err| anyhow!("can't list {}: {}", path, err);
+        let mut names = vec![];
+        for entry in fs::read_dir(path).map_err(list_err)? {
+            let entry = entry.map_err(list_err)?;
+            names.push(entry.file_name().to_string_lossy().into_owned());
+        }
+        names.sort();
+        Ok(names)
+    }
+}
+
+// files kept in a map from path to contents, for tests and for running where there's no real
+// filesystem. clones share the same files, so the host can keep one to look at what the
+// program wrote
+#[derive(Debug, Clone, Default)]
+pub struct MemFs {
+    files: Rc<RefCell<HashMap<String, String>>>,
+}
+
+impl MemFs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_files(files: HashMap<String, String>) -> Self {
+        Self {
+            files: Rc::new(RefCell::new(files)),
+        }
+    }
+
+    pub fn files(&self) -> HashMap<String, String> {
+        self.files.borrow().clone()
+    }
+}
+
+impl VirtualFs for MemFs {
+    fn read(&self, path: &str) -> anyhow::Result<String> {
+        match self.files.borrow().get(path) {
+            Some(contents) => Ok(contents.clone()),
+            None => bail!("no such file: {}", path),
+        }
+    }
+
+    fn write(&mut self, path: &str, contents: &str) -> anyhow::Result<()> {
+        self.files
+            .borrow_mut()
+            .insert(path.to_owned(), contents.to_owned());
+        Ok(())
+    }
+
+    // directories only exist as the prefixes of file paths
+    fn exists(&self, path: &str) -> anyhow::Result<bool> {
+        let files = self.files.borrow();
+        Ok(files.contains_key(path) || !self.list(path)?.is_empty())
+    }
+
+    fn list(&self, path: &str) -> anyhow::Result<Vec<String>> {
+        let prefix = format!("{}/", path.trim_end_matches('/'));
+        Ok(self
+            .files
+            .borrow()
+            .keys()
+            .filter_map(|file| file.strip_prefix(&prefix))
+            .map(|rest| rest.split('/').next().unwrap_or(rest).to_owned())
+            .sorted()
+            .dedup()
+            .collect())
+    }
+}
+
+// what env(name) gives back for a name, see Interpreter::set_env
+pub type EnvFn = dyn Fn(&str) -> Option<String>;
+
+pub struct EnvLookup(pub Box<EnvFn>);
+
+impl EnvLookup {
+    pub fn real() -> Self {
+        EnvLookup(Box::new(|name| std::env::var(name).ok()))
+    }
+}
+
+impl Debug for EnvLookup {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "EnvLookup")
+    }
+}
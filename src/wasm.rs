@@ -0,0 +1,42 @@
+// The JavaScript side of playground.rs, for a web page running Zac. Build with
+//
+//     cargo build --lib --release --target wasm32-unknown-unknown --features wasm
+//
+// and run wasm-bindgen over the .wasm file to get the module to import.
+
+use crate::playground;
+use js_sys::{Object, Reflect};
+use wasm_bindgen::prelude::*;
+
+// {output, result, error, comments, source}, where comments is an object from each comment's
+// name to its body, and result, error and source are null when there isn't one
+#[wasm_bindgen]
+pub fn run(source: &str) -> JsValue {
+    let run = playground::run(source);
+    let comments = Object::new();
+    for (name, body) in &run.comments {
+        set(&comments, name, body.into());
+    }
+    let obj = Object::new();
+    set(&obj, "output", run.output.into());
+    set(&obj, "result", optional(run.result));
+    set(&obj, "error", optional(run.error));
+    set(&obj, "comments", comments.into());
+    set(&obj, "source", optional(run.source));
+    obj.into()
+}
+
+// the formatted program, or throws the reason it can't be formatted
+#[wasm_bindgen]
+pub fn format(source: &str) -> Result<String, JsValue> {
+    playground::format(source).map_err(|err| JsValue::from(js_sys::Error::new(&err)))
+}
+
+fn set(obj: &Object, key: &str, value: JsValue) {
+    // setting a property on a plain object can't fail
+    Reflect::set(obj, &key.into(), &value).unwrap();
+}
+
+fn optional(s: Option<String>) -> JsValue {
+    s.map_or(JsValue::NULL, JsValue::from)
+}
@@ -84,8 +84,10 @@ fn to_doc(val: &Value) -> RcDoc<()> {
             )
             .append(RcDoc::text("}")),
         Value::Int(n) => RcDoc::as_string(n),
-        Value::Function(_) => RcDoc::as_string("<function>"),
+        Value::Function(func) => RcDoc::as_string(format!("<function {}>", func.name())),
         Value::Bool(b) => RcDoc::as_string(b),
+        Value::Opaque(opaque) => RcDoc::as_string(opaque.display()),
+        Value::Null => RcDoc::text("null"),
         Value::List(vals) => RcDoc::text("[")
             .append(
                 RcDoc::intersperse(
@@ -20,6 +20,7 @@ syntax keyword zacLoopKeywords while if
 
 " Comments
 syntax region zacCommentLine start="//" end="$"   contains=zacTodos,zacCommentIdent
+syntax region zacCommentBlock start="/\*" end="\*/" contains=zacTodos,zacCommentIdent
 syntax region zacDirective start="%" end=" "
 
 syntax match zacCommentIdent		"#[a-z_-][a-z0-9_-]*\>"
@@ -44,6 +45,7 @@ highlight default link zacKeywords Keyword
 " comments are highlighted as strings in this lang
 " TODO: only some strings treated as values highlighted this way
 " highlight default link zacCommentLine Comment
+highlight default link zacCommentBlock Comment
 highlight default link zacLoopKeywords Repeat
 highlight default link zacDecInt Number
 highlight default link zacHexInt Number
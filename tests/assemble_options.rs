@@ -0,0 +1,97 @@
+// Each of the AssembleOptions, and that what they write reads back in as the same program.
+
+use zac_lib::interp::Interpreter;
+use zac_lib::parser::parse_program;
+use zac_lib::reassemble::{output_code_with, AssembleOptions};
+
+fn assemble(source: &str, options: &AssembleOptions) -> String {
+    let program = parse_program(source).unwrap();
+    let assembled = output_code_with(&program, &Interpreter::new(), options);
+    let reparsed = parse_program(&assembled).unwrap();
+    assert_eq!(
+        output_code_with(&reparsed, &Interpreter::new(), options),
+        assembled
+    );
+    assembled
+}
+
+#[test]
+fn indent_width() {
+    let options = AssembleOptions {
+        indent_width: 3,
+        ..AssembleOptions::default()
+    };
+    assert_eq!(
+        assemble("defn f() {\nif (true) {\n1\n}\n}\n", &options),
+        "defn f() {\n   if (true) {\n      1\n   }\n}\n"
+    );
+}
+
+#[test]
+fn space_after_comma() {
+    let source = "defn f(a, b) {\nlet [x, y] = [a, b]\n}\nf(1, 2)\n";
+    let options = AssembleOptions {
+        space_after_comma: false,
+        ..AssembleOptions::default()
+    };
+    assert_eq!(
+        assemble(source, &options),
+        "defn f(a,b) {\n  let [x,y] = [a,b]\n}\nf(1,2)\n"
+    );
+    assert_eq!(
+        assemble(source, &AssembleOptions::default()),
+        "defn f(a, b) {\n  let [x, y] = [a, b]\n}\nf(1, 2)\n"
+    );
+}
+
+#[test]
+fn max_line_width_wraps_long_calls() {
+    let options = AssembleOptions {
+        max_line_width: Some(20),
+        ..AssembleOptions::default()
+    };
+    assert_eq!(
+        assemble("cat(\"first\", \"second\", f(1))\n", &options),
+        "cat(\n  \"first\",\n  \"second\",\n  f(1)\n)\n"
+    );
+    // a call that fits stays on one line, even inside one that doesn't
+    assert_eq!(assemble("print(1, 2)\n", &options), "print(1, 2)\n");
+}
+
+#[test]
+fn max_line_width_counts_indentation() {
+    let options = AssembleOptions {
+        max_line_width: Some(14),
+        ..AssembleOptions::default()
+    };
+    assert_eq!(
+        assemble("f(123, 456)\nif (true) {\nf(123, 456)\nwhile (false) {\nf(123, 456)\n}\n}\n", &options),
+        "f(123, 456)\nif (true) {\n  f(123, 456)\n  while (false) {\n    f(\n      123,\n      456\n    )\n  }\n}\n"
+    );
+}
+
+#[test]
+fn wraps_nested_calls_that_are_still_too_long() {
+    let options = AssembleOptions {
+        max_line_width: Some(12),
+        ..AssembleOptions::default()
+    };
+    assert_eq!(
+        assemble("f(g(123, 456), 7)\n", &options),
+        "f(\n  g(\n    123,\n    456\n  ),\n  7\n)\n"
+    );
+}
+
+#[test]
+fn comment_prefix_space() {
+    let source = "// #notes\n// some notes\n//  indented\nlet x = 1 // #x after\nx // trailing\n";
+    let options = AssembleOptions {
+        comment_prefix_space: false,
+        ..AssembleOptions::default()
+    };
+    assert_eq!(
+        assemble(source, &options),
+        "//#notes\n//some notes\n//  indented\nlet x = 1 //#x after\nx //trailing\n"
+    );
+    assert_eq!(assemble(source, &AssembleOptions::default()), source);
+}
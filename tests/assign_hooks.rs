@@ -0,0 +1,49 @@
+// Hearing about assignments from outside the program.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use zac_lib::interp::{Interpreter, Value};
+use zac_lib::parser::Ref;
+
+fn name(r#ref: &Ref) -> String {
+    match r#ref {
+        Ref::VarRef(name, _) => name.clone(),
+        Ref::CommentRef(name, _) => format!("#{}", name),
+    }
+}
+
+#[test]
+fn assignments_in_a_loop_are_reported_in_order() {
+    let seen = Rc::new(RefCell::new(vec![]));
+    let mut interp = Interpreter::new();
+    let record = Rc::clone(&seen);
+    interp.on_assign(move |r#ref, value| record.borrow_mut().push((name(r#ref), value.clone())));
+    interp
+        .run_source("// #status\n// idle\n\nlet i = 0\nwhile (i < 2) {\n  let i = i + 1\n  let #status = cat(\"step \", show(i))\n}\n")
+        .unwrap();
+    assert_eq!(
+        *seen.borrow(),
+        vec![
+            ("i".to_owned(), Value::Int(0)),
+            ("i".to_owned(), Value::Int(1)),
+            ("#status".to_owned(), Value::from("step 1")),
+            ("i".to_owned(), Value::Int(2)),
+            ("#status".to_owned(), Value::from("step 2")),
+        ]
+    );
+}
+
+#[test]
+fn every_callback_is_called() {
+    let count = Rc::new(RefCell::new(0));
+    let mut interp = Interpreter::new();
+    for _ in 0..2 {
+        let count = Rc::clone(&count);
+        interp.on_assign(move |_, _| *count.borrow_mut() += 1);
+    }
+    interp
+        .run_source("let x = 1\nlet m = [1]\nlet m(0) = 2\n")
+        .unwrap();
+    // two assignments, heard by both callbacks, and the index assignment isn't one
+    assert_eq!(*count.borrow(), 4);
+}
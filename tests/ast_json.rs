@@ -0,0 +1,53 @@
+// The syntax tree as JSON, for tools outside Rust: a program sent out as JSON and read back in
+// reassembles to the same source as the program itself.
+
+use std::fs;
+use std::path::Path;
+use zac_lib::interp::Interpreter;
+use zac_lib::parser::{parse_json, parse_program, to_description};
+use zac_lib::reassemble::output_code;
+
+#[test]
+fn every_example_round_trips() {
+    let root = Path::new(env!("CARGO_MANIFEST_DIR"));
+    let mut checked = 0;
+    for dir in ["examples", "spec", "golden"] {
+        for entry in fs::read_dir(root.join(dir)).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().map_or(true, |ext| ext != "zac") {
+                continue;
+            }
+            let program = match parse_program(&fs::read_to_string(&path).unwrap()) {
+                Ok(program) => program,
+                // the specs for parse errors
+                Err(_) => continue,
+            };
+            let back = parse_json(&to_description(&program)).unwrap();
+            let interp = Interpreter::new();
+            // blank lines aren't kept, so compare without them
+            let without_blank_lines = |s: String| {
+                s.lines()
+                    .filter(|l| !l.is_empty())
+                    .collect::<Vec<_>>()
+                    .join("\n")
+            };
+            assert_eq!(
+                without_blank_lines(output_code(&back, &interp)),
+                without_blank_lines(output_code(&program, &interp)),
+                "{}",
+                path.display()
+            );
+            checked += 1;
+        }
+    }
+    assert!(checked > 50, "{}", checked);
+}
+
+#[test]
+fn errors_say_where() {
+    let err = parse_json(
+        r#"{"version": 1, "block": [{"type": "assign", "target": {"type": "var", "name": "x"}}]}"#,
+    )
+    .unwrap_err();
+    assert_eq!(err.to_string(), "$.block[0]: missing field \"value\"");
+}
@@ -0,0 +1,56 @@
+// The bitwise builtins, mostly for putting bytes together and taking them apart.
+
+use zac_lib::interp::{Interpreter, Value};
+use zac_lib::parser::{parser, Expr};
+
+fn run(source: &str) -> Result<Value, String> {
+    let program = parser::program(source).unwrap();
+    Interpreter::new()
+        .interp(&Expr::Block(program.block))
+        .map_err(|err| err.to_string())
+}
+
+#[test]
+fn a_byte_from_bits_and_back() {
+    let source = "let bits = [0, 1, 0, 0, 0, 0, 0, 1]
+let byte = 0
+let i = 0
+while (i < 8) {
+  let byte = bor(shl(byte, 1), bits(i))
+  let i = i + 1
+}
+let decoded = \"\"
+let i = 7
+while (i >= 0) {
+  let decoded = cat(decoded, show(band(shr(byte, i), 1)))
+  let i = i - 1
+}
+[byte, chr(byte), decoded]
+";
+    assert_eq!(
+        run(source),
+        Ok(Value::List(vec![
+            Value::Int(65),
+            Value::String("A".into()),
+            Value::String("01000001".into())
+        ]))
+    );
+}
+
+#[test]
+fn each_operation() {
+    assert_eq!(
+        run("[band(12, 10), bor(12, 10), bxor(12, 10), bnot(0), shl(1, 4), shr(8, 2), shr(-8, 1)]\n"),
+        Ok(Value::List(
+            [8, 14, 6, -1, 16, 2, -4].into_iter().map(Value::Int).collect()
+        ))
+    );
+}
+
+#[test]
+fn shifts_out_of_range() {
+    for source in ["shl(1, 128)\n", "shr(1, -1)\n"] {
+        let err = run(source).unwrap_err();
+        assert!(err.contains("the shift has to be from 0 to 127"), "{}", err);
+    }
+}
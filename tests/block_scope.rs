@@ -0,0 +1,75 @@
+// The bodies of while and if have their own scope, which can still change the variables outside.
+
+use zac_lib::interp::{Interpreter, Value};
+use zac_lib::parser::{parser, Expr};
+
+fn run(source: &str) -> Result<Value, String> {
+    let program = parser::program(source).unwrap();
+    Interpreter::new()
+        .interp(&Expr::Block(program.block))
+        .map_err(|err| err.to_string())
+}
+
+#[test]
+fn let_changes_variables_from_outside() {
+    let source = "let total = 0
+let i = 0
+while (i < 3) {
+  let total = total + i
+  let i = i + 1
+}
+if (total == 3) {
+  let total = total * 10
+}
+[total, i]
+";
+    assert_eq!(
+        run(source),
+        Ok(Value::List(vec![Value::Int(30), Value::Int(3)]))
+    );
+}
+
+#[test]
+fn new_variables_end_with_the_block() {
+    for (source, name) in [
+        (
+            "let i = 0\nwhile (i < 1) {\n  let doubled = 2\n  let i = i + 1\n}\ndoubled\n",
+            "doubled",
+        ),
+        ("if (true) {\n  let inside = 1\n}\ninside\n", "inside"),
+    ] {
+        let err = run(source).unwrap_err();
+        assert!(err.contains(name), "{}", err);
+    }
+}
+
+#[test]
+fn a_block_sees_variables_from_outside() {
+    let source = "let x = 5
+let seen = 0
+if (true) {
+  let seen = x
+}
+seen
+";
+    assert_eq!(run(source), Ok(Value::Int(5)));
+}
+
+#[test]
+fn a_function_call_inside_a_block_keeps_its_own_variables() {
+    let source = "let x = 1
+defn set_x() {
+  let x = 2
+  x
+}
+let got = 0
+if (true) {
+  let got = set_x()
+}
+[x, got]
+";
+    assert_eq!(
+        run(source),
+        Ok(Value::List(vec![Value::Int(1), Value::Int(2)]))
+    );
+}
@@ -0,0 +1,54 @@
+// Building strings with cat in a loop, and the warning that suggests a builder instead.
+
+use zac_lib::interp::{Interpreter, Value};
+use zac_lib::parser::{parser, Expr};
+
+fn run(interp: &mut Interpreter, source: &str) -> Value {
+    let program = parser::program(source).unwrap();
+    interp.interp(&Expr::Block(program.block)).unwrap()
+}
+
+const WARNING: &str = "`let s = cat(s, ...)` ran 10000 times, and copies all of s each time. \
+    builder(), builder_push(b, s) and builder_finish(b) build strings faster";
+
+// runs body n times in a loop, with s and t starting out empty
+fn warnings_after(n: usize, body: &str) -> Vec<String> {
+    let mut interp = Interpreter::new();
+    let source = format!(
+        "let s = \"\"\nlet t = \"\"\nlet b = builder()\nlet i = 0\nwhile (i < {}) {{\n  {}\n  let i = i + 1\n}}\n",
+        n, body
+    );
+    run(&mut interp, &source);
+    interp.warnings()
+}
+
+#[test]
+fn cat_onto_itself_many_times_is_warned_about() {
+    assert_eq!(warnings_after(10_000, "let s = cat(s, \"x\")"), [WARNING]);
+    // only the once
+    assert_eq!(warnings_after(25_000, "let s = cat(s, \"x\")"), [WARNING]);
+}
+
+#[test]
+fn other_cats_are_not() {
+    for body in [
+        // putting it in front copies s too, but isn't the pattern being looked for
+        "let s = cat(\"x\", s)",
+        "let t = cat(s, \"x\")",
+        "builder_push(b, \"x\")",
+    ] {
+        assert!(warnings_after(10_000, body).is_empty(), "{}", body);
+    }
+    assert!(warnings_after(9_999, "let s = cat(s, \"x\")").is_empty());
+}
+
+#[test]
+fn a_builder_makes_the_same_string() {
+    let mut interp = Interpreter::new();
+    let built = run(
+        &mut interp,
+        "let b = builder()\nlet s = \"\"\nlet i = 0\nwhile (i < 12) {\n  builder_push(b, show(i))\n  let s = cat(s, show(i))\n  let i = i + 1\n}\n[builder_finish(b), s]\n",
+    );
+    let expected = Value::String("01234567891011".into());
+    assert_eq!(built, Value::List(vec![expected.clone(), expected]));
+}
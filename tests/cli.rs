@@ -0,0 +1,142 @@
+// Running zac from the command line.
+
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+// a file of its own for each test, since they run at the same time
+fn program_file(name: &str, source: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("zac-cli-{}-{}.zac", std::process::id(), name));
+    std::fs::write(&path, source).unwrap();
+    path
+}
+
+fn zac(args: &[&str], path: &PathBuf) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_zac"))
+        .args(args)
+        .arg(path)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+fn stderr(output: &Output) -> String {
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
+
+#[test]
+fn run_writes_the_result_back() {
+    let path = program_file("run", "// #out\n// old\n\nlet #out = \"new\"\n");
+    let output = zac(&["run"], &path);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "// #out\n// new\n\nlet #out = \"new\"\n"
+    );
+}
+
+#[test]
+fn dump_ast_doesnt_run_the_program() {
+    let path = program_file("ast", "let x    = nope\n");
+    let output = zac(&["run", "--dump-ast"], &path);
+    assert!(output.status.success(), "{}", stderr(&output));
+    let ast = stdout(&output);
+    assert!(ast.contains("\"assign\""), "{}", ast);
+    assert!(ast.contains("\"nope\""), "{}", ast);
+}
+
+#[test]
+fn ast_json() {
+    let path = program_file("ast_json", "let x = 1\n");
+    let output = zac(&["ast", "--json"], &path);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "{\"version\":1,\"block\":[{\"type\":\"assign\",\"target\":{\"type\":\"var\",\"name\":\"x\"},\"value\":{\"type\":\"int\",\"value\":1}}]}\n"
+    );
+}
+
+#[test]
+fn dump_reassembled() {
+    let path = program_file("reassembled", "let x    = add(1,2)\nnope\n");
+    let output = zac(&["run", "--dump-reassembled"], &path);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(stdout(&output), "let x = add(1, 2)\nnope\n");
+    // and the file is left alone
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "let x    = add(1,2)\nnope\n"
+    );
+}
+
+#[test]
+fn errors_exit_nonzero() {
+    let path = program_file("runtime_error", "let x = 1\nlet y = x(2)\n");
+    let output = zac(&["run"], &path);
+    assert!(!output.status.success());
+    assert!(
+        stderr(&output)
+            .contains("line 2, col 9: tried to call a Int(1)\n  let y = x(2)\n          ^^^^"),
+        "{}",
+        stderr(&output)
+    );
+
+    let path = program_file("parse_error", "let x = )\n");
+    for args in [
+        &["run"][..],
+        &["run", "--dump-ast"],
+        &["run", "--dump-reassembled"],
+    ] {
+        let output = zac(args, &path);
+        assert!(!output.status.success(), "{:?}", args);
+        assert!(
+            stderr(&output).contains("line 1, col 9"),
+            "{}",
+            stderr(&output)
+        );
+    }
+}
+
+#[test]
+fn args_after_double_dash() {
+    let path = program_file(
+        "args",
+        "// #out\n// old\n\nlet #out = join(args(), \",\")\n",
+    );
+    let output = Command::new(env!("CARGO_BIN_EXE_zac"))
+        .arg("run")
+        .arg(&path)
+        .args(["--dry", "--", "a", "--dry", "b c"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        stdout(&output),
+        "// #out\n// a,--dry,b c\n\nlet #out = join(args(), \",\")\n"
+    );
+}
+
+#[test]
+fn exit_code() {
+    let path = program_file("exit", "// #out\n// old\n\nlet #out = \"new\"\nexit(3)\n");
+    let output = zac(&["run"], &path);
+    assert_eq!(output.status.code(), Some(3), "{}", stderr(&output));
+    // exiting with anything but 0 leaves the file alone, like an error does
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "// #out\n// old\n\nlet #out = \"new\"\nexit(3)\n"
+    );
+
+    let path = program_file(
+        "exit_0",
+        "// #out\n// old\n\nlet #out = \"new\"\nexit()\nnope\n",
+    );
+    let output = zac(&["run"], &path);
+    assert!(output.status.success(), "{}", stderr(&output));
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "// #out\n// new\n\nlet #out = \"new\"\nexit()\nnope\n"
+    );
+}
@@ -0,0 +1,121 @@
+// The comments a run changed, and choosing which of the changes to write back.
+
+use std::io::{Cursor, Write};
+use std::process::{Command, Stdio};
+use zac_lib::comment_changes::{confirm_writes, render_table};
+use zac_lib::interp::{CommentMutation, Interpreter};
+use zac_lib::parser::{find_comments_mut, parser, Expr, Program};
+use zac_lib::reassemble::output_code;
+use zac_lib::replace_comments_in_source_code;
+
+const SOURCE: &str = "// #kept\n// old kept\n\n// #dropped\n// old dropped\n\n// #untouched\n// same\n\nlet #kept = \"new kept\"\nlet #dropped = \"first\"\nlet #dropped = \"new dropped\"\n";
+
+fn ran() -> (Interpreter, Program) {
+    let mut program = parser::program(SOURCE).unwrap();
+    let mut interp = Interpreter::new();
+    for (_, comment) in find_comments_mut(&mut program).unwrap() {
+        interp.add_comment(comment).unwrap();
+    }
+    interp.interp(&Expr::Block(program.block.clone())).unwrap();
+    (interp, program)
+}
+
+// the answers given, one per line, and what was asked
+fn confirm(mutations: &[CommentMutation], answers: &str) -> (Vec<String>, String) {
+    let mut asked = vec![];
+    let accepted = confirm_writes(mutations, &mut Cursor::new(answers), &mut asked).unwrap();
+    (
+        accepted.into_iter().collect(),
+        String::from_utf8(asked).unwrap(),
+    )
+}
+
+#[test]
+fn mutations_are_listed_by_name() {
+    let mutations = ran().0.comment_mutations();
+    assert_eq!(
+        mutations,
+        [
+            CommentMutation {
+                name: "dropped".into(),
+                old_body: "old dropped".into(),
+                new_body: "new dropped".into(),
+                writes: 2,
+            },
+            CommentMutation {
+                name: "kept".into(),
+                old_body: "old kept".into(),
+                new_body: "new kept".into(),
+                writes: 1,
+            },
+        ]
+    );
+    assert_eq!(
+        render_table(&mutations),
+        "comment   writes  before       after\n#dropped  2       old dropped  new dropped\n#kept     1       old kept     new kept\n"
+    );
+}
+
+#[test]
+fn accepting_one_and_rejecting_another() {
+    let (mut interp, mut program) = ran();
+    let mutations = interp.comment_mutations();
+    // anything that isn't an answer is asked again
+    let (accepted, asked) = confirm(&mutations, "n\nmaybe\ny\n");
+    assert_eq!(accepted, ["kept"]);
+    assert_eq!(
+        asked,
+        "write #dropped? [y]es/[n]o/[a]ll: write #kept? [y]es/[n]o/[a]ll: write #kept? [y]es/[n]o/[a]ll: "
+    );
+    for mutation in &mutations {
+        if !accepted.contains(&mutation.name) {
+            interp.revert_comment_mutation(&mutation.name).unwrap();
+        }
+    }
+    replace_comments_in_source_code(&mut program, &mut interp).unwrap();
+    let written = output_code(&program, &interp);
+    assert!(
+        written.starts_with(
+            "// #kept\n// new kept\n\n// #dropped\n// old dropped\n\n// #untouched\n// same\n"
+        ),
+        "{}",
+        written
+    );
+    assert_eq!(interp.comment_mutations().len(), 1);
+}
+
+#[test]
+fn all_and_running_out_of_answers() {
+    let mutations = ran().0.comment_mutations();
+    assert_eq!(confirm(&mutations, "a\n").0, ["dropped", "kept"]);
+    assert_eq!(confirm(&mutations, "y\n").0, ["dropped"]);
+    assert!(confirm(&mutations, "").0.is_empty());
+}
+
+#[test]
+fn confirming_from_the_command_line() {
+    let path = std::env::temp_dir().join(format!("zac-confirm-{}.zac", std::process::id()));
+    std::fs::write(
+        &path,
+        "// #kept\n// old\n\n// #dropped\n// old\n\nlet #kept = \"new\"\nlet #dropped = \"new\"\n",
+    )
+    .unwrap();
+    let mut child = Command::new(env!("CARGO_BIN_EXE_zac"))
+        .arg(&path)
+        .arg("--confirm-writes")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // asked about #dropped first, they're in name order
+    child.stdin.take().unwrap().write_all(b"n\ny\n").unwrap();
+    let output = child.wait_with_output().unwrap();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    assert!(output.status.success(), "{}", stderr);
+    assert!(stderr.contains("write #kept?"), "{}", stderr);
+    assert_eq!(
+        std::fs::read_to_string(&path).unwrap(),
+        "// #kept\n// new\n\n// #dropped\n// old\n\nlet #kept = \"new\"\nlet #dropped = \"new\"\n"
+    );
+}
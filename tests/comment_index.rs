@@ -0,0 +1,69 @@
+// The comment index kept next to a source file.
+
+use std::fs;
+use std::path::PathBuf;
+use zac_lib::comment_index::{index_path, CommentIndex};
+use zac_lib::parser::load_or_build_index;
+
+const SOURCE: &str = "// #beta\n// b\n\n// #alpha\n// a\nlet x = 1\n";
+
+// a fresh source file, with no index yet
+fn source_file(name: &str, source: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("zac-index-{}-{}.zac", std::process::id(), name));
+    fs::write(&path, source).unwrap();
+    let _ = fs::remove_file(index_path(&path));
+    path
+}
+
+fn names(index: &CommentIndex) -> Vec<&str> {
+    index.comments.iter().map(|c| c.name.as_str()).collect()
+}
+
+#[test]
+fn built_then_used() {
+    let path = source_file("hit", SOURCE);
+    let built = load_or_build_index(&path).unwrap();
+    assert_eq!(names(&built), ["alpha", "beta"]);
+    let written = fs::read_to_string(index_path(&path)).unwrap();
+    assert!(written.contains("alpha"), "{}", written);
+
+    // the source hasn't changed, so what's in the index is believed without looking at the
+    // source again
+    fs::write(index_path(&path), written.replace("alpha", "gamma")).unwrap();
+    assert_eq!(
+        names(&load_or_build_index(&path).unwrap()),
+        ["gamma", "beta"]
+    );
+}
+
+#[test]
+fn rebuilt_when_the_source_changes() {
+    let path = source_file("stale", SOURCE);
+    let before = load_or_build_index(&path).unwrap();
+    fs::write(&path, format!("{}// #delta\n// d\n", SOURCE)).unwrap();
+    let after = load_or_build_index(&path).unwrap();
+    assert_ne!(after.source_hash, before.source_hash);
+    assert_eq!(names(&after), ["alpha", "beta", "delta"]);
+    // and written out again for next time
+    assert!(fs::read_to_string(index_path(&path))
+        .unwrap()
+        .contains("delta"));
+}
+
+#[test]
+fn rebuilt_when_it_cant_be_read() {
+    let path = source_file("corrupt", SOURCE);
+    let built = load_or_build_index(&path).unwrap();
+    let written = fs::read_to_string(index_path(&path)).unwrap();
+    for corrupt in [
+        "not json at all".to_owned(),
+        written[..written.len() / 2].to_owned(),
+        written.replace("\"version\":1", "\"version\":99"),
+        written.replace("\"style\":\"line\"", "\"style\":\"wavy\""),
+    ] {
+        assert_ne!(corrupt, written);
+        fs::write(index_path(&path), &corrupt).unwrap();
+        assert_eq!(load_or_build_index(&path).unwrap(), built, "{}", corrupt);
+        assert_eq!(fs::read_to_string(index_path(&path)).unwrap(), written);
+    }
+}
@@ -0,0 +1,86 @@
+// Converting between Values and Rust types.
+
+use std::collections::BTreeMap;
+use zac_lib::error::SoldierError;
+use zac_lib::interp::Value;
+
+#[test]
+fn into_values() {
+    assert_eq!(Value::from(5i64), Value::Int(5));
+    assert_eq!(Value::from(-5i8), Value::Int(-5));
+    assert_eq!(Value::from(u64::MAX), Value::Int(u64::MAX as i128));
+    assert_eq!(Value::from(true), Value::Bool(true));
+    assert_eq!(Value::from("a"), Value::String("a".into()));
+    assert_eq!(Value::from(String::from("a")), Value::String("a".into()));
+    assert_eq!(
+        Value::from(vec![1, 2]),
+        Value::List(vec![Value::Int(1), Value::Int(2)])
+    );
+    let map = BTreeMap::from([(Value::from("k"), Value::from(1))]);
+    assert_eq!(Value::from(map.clone()), Value::Map(map));
+}
+
+#[test]
+fn out_of_values() {
+    assert_eq!(i128::try_from(Value::Int(5)).unwrap(), 5);
+    assert_eq!(u8::try_from(&Value::Int(255)).unwrap(), 255);
+    assert!(bool::try_from(Value::Bool(true)).unwrap());
+    assert_eq!(String::try_from(Value::from("a")).unwrap(), "a");
+    let value = Value::from("borrowed");
+    let s: &str = (&value).try_into().unwrap();
+    assert_eq!(s, "borrowed");
+    assert_eq!(
+        Vec::<Value>::try_from(Value::from(vec![true])).unwrap(),
+        vec![Value::Bool(true)]
+    );
+    assert_eq!(
+        BTreeMap::<Value, Value>::try_from(&Value::Map(BTreeMap::new())).unwrap(),
+        BTreeMap::new()
+    );
+}
+
+#[test]
+fn ints_that_dont_fit() {
+    let err = u8::try_from(Value::Int(256)).unwrap_err();
+    assert!(
+        matches!(&err, SoldierError::TypeMismatch { got, .. } if got == "Int(256)"),
+        "{:?}",
+        err
+    );
+    assert_eq!(
+        err.to_string(),
+        "Int(256) is not an integer that fits in a u8"
+    );
+    assert!(u64::try_from(Value::Int(-1)).is_err());
+    assert!(i64::try_from(Value::Int(i128::from(i64::MAX) + 1)).is_err());
+    assert_eq!(
+        i64::try_from(Value::Int(i64::MIN.into())).unwrap(),
+        i64::MIN
+    );
+}
+
+#[test]
+fn the_wrong_kind_of_value() {
+    assert_eq!(
+        i32::try_from(Value::from("1")).unwrap_err().to_string(),
+        "String(\"1\") is not an integer"
+    );
+    assert_eq!(
+        String::try_from(Value::Int(1)).unwrap_err().to_string(),
+        "Int(1) is not a String"
+    );
+    assert!(matches!(
+        bool::try_from(Value::Null),
+        Err(SoldierError::TypeMismatch { .. })
+    ));
+    assert!(BTreeMap::<Value, Value>::try_from(Value::from(vec![1])).is_err());
+    assert!(Vec::<Value>::try_from(Value::from(1)).is_err());
+}
+
+#[test]
+fn accessors() {
+    assert_eq!(Value::Int(3).as_num().unwrap(), 3);
+    assert_eq!(Value::from("x").as_str().unwrap(), "x");
+    assert!(!Value::Bool(false).as_bool().unwrap());
+    assert!(Value::Null.as_num().is_err());
+}
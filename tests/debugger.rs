@@ -0,0 +1,207 @@
+// Stepping through a program with a DebugSession.
+
+use std::cell::RefCell;
+use std::io::Write;
+use std::rc::Rc;
+use zac_lib::debugger::{Breakpoint, DebugSession};
+use zac_lib::interp::{Interpreter, Value};
+use zac_lib::parser::parse_program;
+
+const SOURCE: &str = "let x = 1
+// #checkpoint
+// here
+
+let y = x + 1
+while (y < 4) {
+  let y = y + 1
+}
+y
+";
+
+fn with_source(source: &str) -> Interpreter {
+    let mut interp = Interpreter::new();
+    interp.set_source(source);
+    interp
+}
+
+fn debug<'a>(interp: &'a mut Interpreter, source: &str) -> DebugSession<'a> {
+    interp.debug(&parse_program(source).unwrap())
+}
+
+#[test]
+fn steps_go_through_every_expression_in_order() {
+    let mut interp = with_source(SOURCE);
+    let mut session = debug(&mut interp, SOURCE);
+    let first = session.step().unwrap();
+    assert_eq!(first.expr, "assign");
+    assert_eq!(first.source, "let x = 1");
+    assert_eq!(first.line, Some(1));
+    assert!(session.scope().is_empty(), "{:?}", session.scope());
+    let second = session.step().unwrap();
+    assert_eq!(second.expr, "int");
+    let mut count = 2;
+    while session.step().is_some() {
+        count += 1;
+    }
+    assert!(count > 10, "{}", count);
+    assert!(matches!(session.result(), Some(Ok(Value::Int(4)))));
+}
+
+#[test]
+fn breaking_on_a_named_comment() {
+    let mut interp = with_source(SOURCE);
+    let mut session = debug(&mut interp, SOURCE);
+    session.add_breakpoint(Breakpoint::Comment("checkpoint".into()));
+    let step = session.continue_to_breakpoint().unwrap();
+    assert_eq!(step.comment.as_deref(), Some("checkpoint"));
+    assert_eq!(step.line, Some(2));
+    // x is set, but y isn't yet, and the builtins aren't shown
+    let scope = session.scope();
+    assert_eq!(scope.get("x"), Some(&Value::Int(1)));
+    assert_eq!(scope.get("y"), None);
+    assert_eq!(scope.get("add"), None);
+    assert!(session.continue_to_breakpoint().is_none());
+}
+
+#[test]
+fn breaking_on_a_line_stops_once_per_visit() {
+    let mut interp = with_source(SOURCE);
+    let mut session = debug(&mut interp, SOURCE);
+    session.add_breakpoint(Breakpoint::Line(7));
+    let mut ys = vec![];
+    while let Some(step) = session.continue_to_breakpoint() {
+        assert_eq!(step.source, "let y = y + 1");
+        ys.push(session.scope()["y"].clone());
+    }
+    assert_eq!(ys, vec![Value::Int(2), Value::Int(3)]);
+}
+
+#[test]
+fn errors_end_the_session() {
+    let mut interp = Interpreter::new();
+    let mut session = debug(&mut interp, "let x = 1\nnope\n");
+    let mut last = None;
+    while let Some(step) = session.step() {
+        last = Some(step);
+    }
+    // the step that failed is the last one
+    assert_eq!(last.unwrap().source, "nope");
+    assert!(matches!(session.result(), Some(Err(_))));
+}
+
+#[derive(Clone, Default)]
+struct Captured(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Captured {
+    fn text(&self) -> String {
+        String::from_utf8(self.0.borrow().clone()).unwrap()
+    }
+}
+
+#[test]
+fn nothing_runs_until_its_stepped_to() {
+    let captured = Captured::default();
+    let mut interp = Interpreter::new();
+    interp.set_output(Box::new(captured.clone()));
+    let mut session = debug(&mut interp, "print(\"one\")\nprint(\"two\")\n");
+    assert_eq!(captured.text(), "");
+    assert!(session.result().is_none());
+    // print(\"one\"), then the string inside it
+    session.step().unwrap();
+    session.step().unwrap();
+    assert_eq!(captured.text(), "");
+    assert_eq!(session.step().unwrap().source, "print(\"two\")");
+    assert_eq!(captured.text(), "one\n");
+    while session.step().is_some() {}
+    assert_eq!(captured.text(), "one\ntwo\n");
+}
+
+#[test]
+fn programs_that_never_end_can_be_stepped_through() {
+    let mut interp = Interpreter::new();
+    let mut session = debug(&mut interp, "let i = 0\nloop {\n  let i = i + 1\n}\n");
+    for _ in 0..1000 {
+        assert!(session.step().is_some());
+    }
+    match session.scope().get("i") {
+        Some(Value::Int(i)) => assert!(*i > 100, "{}", i),
+        other => panic!("{:?}", other),
+    }
+    assert!(session.result().is_none());
+    // dropping the session stops the program
+}
+
+#[test]
+fn the_interpreter_carries_on_after_a_session() {
+    let mut interp = Interpreter::new();
+    {
+        let mut session = debug(&mut interp, "let x = 1\nlet y = 2\n");
+        while session.step().unwrap().source != "let y = 2" {}
+    }
+    // stopped part way through, so there's an x but no y
+    assert_eq!(interp.get_var("x"), Some(Value::Int(1)));
+    assert_eq!(interp.get_var("y"), None);
+    assert_eq!(interp.run_source("x + 1\n").unwrap(), Value::Int(2));
+}
+
+#[test]
+fn breaking_inside_a_function_a_builtin_calls() {
+    let source = "defn double(n) {\n  // #inside\n  n * 2\n}\nmap([1, 2, 3], double)\n";
+    let mut interp = with_source(source);
+    let mut session = debug(&mut interp, source);
+    session.add_breakpoint(Breakpoint::Comment("inside".into()));
+    let mut ns = vec![];
+    while session.continue_to_breakpoint().is_some() {
+        ns.push(session.scope()["n"].clone());
+    }
+    assert_eq!(ns, [Value::Int(1), Value::Int(2), Value::Int(3)]);
+    assert_eq!(
+        session.result().unwrap().as_ref().unwrap().to_string(),
+        "[2, 4, 6]"
+    );
+}
+
+#[test]
+fn the_program_runs_on_the_thread_stepping_it() {
+    let threads = Rc::new(RefCell::new(vec![]));
+    let mut interp = Interpreter::new();
+    let seen = Rc::clone(&threads);
+    interp.on_assign(move |_, _| seen.borrow_mut().push(std::thread::current().id()));
+    let mut session = debug(
+        &mut interp,
+        "let x = 1\nlet y = map([1, 2], defn f(n) { n })\n",
+    );
+    while session.step().is_some() {}
+    drop(session);
+    assert_eq!(*threads.borrow(), [std::thread::current().id(); 2]);
+}
+
+#[test]
+fn stopping_inside_a_function_leaves_nothing_behind() {
+    let source =
+        "defn deeper(n) {\n  // #bottom\n  if (n > 0) {\n    deeper(n - 1)\n  }\n}\ndeeper(5)\n";
+    let mut interp = with_source(source);
+    // just deep enough for deeper(5)
+    interp.set_max_depth(26);
+    {
+        let mut session = debug(&mut interp, source);
+        session.add_breakpoint(Breakpoint::Comment("bottom".into()));
+        for _ in 0..6 {
+            session.continue_to_breakpoint().unwrap();
+        }
+    }
+    // which it wouldn't be if the calls the session stopped in were still counted
+    assert!(interp.run_source("deeper(5)\n").is_ok());
+    interp.set_max_depth(25);
+    assert!(interp.run_source("deeper(5)\n").is_err());
+}
@@ -0,0 +1,124 @@
+// Programs and values nested far deeper than the parser allows, built directly. Writing them
+// out and putting them in error messages mustn't overflow the stack.
+
+use std::collections::BTreeMap;
+use std::thread;
+use zac_lib::interp::{Interpreter, Value};
+use zac_lib::parser::{parser, Block, BlockEl, Expr, FunctionCall, Program, Ref, Span};
+use zac_lib::reassemble::output_code;
+
+const DEPTH: usize = 100_000;
+// far less than recursing 100_000 deep would take
+const STACK_SIZE: usize = 4 << 20;
+
+fn on_small_stack<T: Send + 'static>(f: impl FnOnce() -> T + Send + 'static) -> T {
+    thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(f)
+        .unwrap()
+        .join()
+        .unwrap()
+}
+
+// f(f(f(...f(1)...)))
+fn nested_calls(depth: usize) -> Expr {
+    let mut expr = Expr::IntLiteral(1, Span::default());
+    for _ in 0..depth {
+        expr = Expr::FunctionCall(FunctionCall {
+            r#ref: Ref::VarRef("f".into(), Span::default()),
+            args: vec![expr],
+            span: Span::default(),
+        });
+    }
+    expr
+}
+
+fn nested_lists(depth: usize) -> Value {
+    let mut val = Value::Int(1);
+    for _ in 0..depth {
+        val = Value::List(vec![val]);
+    }
+    val
+}
+
+fn nested_maps(depth: usize) -> Value {
+    let mut val = Value::Int(1);
+    for _ in 0..depth {
+        val = Value::Map(BTreeMap::from([(Value::String("k".into()), val)]));
+    }
+    val
+}
+
+#[test]
+fn assembling_deep_calls() {
+    let assembled = on_small_stack(|| {
+        let program = Program {
+            block: Block(vec![BlockEl::Expr(nested_calls(DEPTH)), BlockEl::NewLine]),
+        };
+        let assembled = output_code(&program, &Interpreter::new());
+        // dropping it recursively takes stack too, and that isn't what's being tested
+        std::mem::forget(program);
+        assembled
+    });
+    assert_eq!(
+        assembled,
+        format!("{}1{}\n", "f(".repeat(DEPTH), ")".repeat(DEPTH))
+    );
+}
+
+#[test]
+fn assembling_deep_lists() {
+    let assembled = on_small_stack(|| {
+        let mut expr = Expr::IntLiteral(1, Span::default());
+        for _ in 0..DEPTH {
+            expr = Expr::ListLiteral(vec![expr], Span::default());
+        }
+        let program = Program {
+            block: Block(vec![BlockEl::Expr(expr), BlockEl::NewLine]),
+        };
+        let assembled = output_code(&program, &Interpreter::new());
+        std::mem::forget(program);
+        assembled
+    });
+    assert_eq!(
+        assembled,
+        format!("{}1{}\n", "[".repeat(DEPTH), "]".repeat(DEPTH))
+    );
+}
+
+#[test]
+fn debug_printing_deep_values() {
+    let (lists, maps) = on_small_stack(|| {
+        let lists = nested_lists(DEPTH);
+        let maps = nested_maps(DEPTH);
+        let shown = (format!("{:?}", lists), format!("{:?}", maps));
+        std::mem::forget((lists, maps));
+        shown
+    });
+    // cut off well before the bottom
+    assert!(lists.starts_with("List([List([List(["), "{}", lists);
+    assert!(lists.contains("..."), "{}", lists);
+    assert!(lists.len() < 1000, "{}", lists.len());
+    assert!(maps.starts_with("Map({String(\"k\"): Map({"), "{}", maps);
+    assert!(maps.len() < 1000, "{}", maps.len());
+}
+
+// a builtin given a deep value it can't use says what it got. (a program can't be given one:
+// reading a variable copies its value, which recurses as deep as the value goes)
+#[test]
+fn errors_about_deep_values() {
+    let err = on_small_stack(|| {
+        let mut interp = Interpreter::new();
+        let program = parser::program("sub\n").unwrap();
+        let sub = match interp.interp(&Expr::Block(program.block)) {
+            Ok(Value::Function(sub)) => sub,
+            other => panic!("{:?}", other),
+        };
+        let args = [nested_lists(DEPTH), Value::Int(1)];
+        let err = sub.call(&mut interp, &args).unwrap_err().to_string();
+        std::mem::forget(args);
+        err
+    });
+    assert!(err.contains("List([List(["), "{}", err);
+    assert!(err.len() < 1000, "{}", err.len());
+}
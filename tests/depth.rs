@@ -0,0 +1,91 @@
+// Programs nested too deeply to evaluate on the Rust stack.
+
+use std::thread;
+use zac_lib::error::SoldierError;
+use zac_lib::interp::{Interpreter, Value};
+use zac_lib::parser::{Block, BlockEl, Expr, Span};
+
+// far less than evaluating 100_000 nested blocks would take without the limit
+const STACK_SIZE: usize = 4 << 20;
+
+// a block holding a block holding a block ... holding a 1
+fn nested_blocks(depth: usize) -> Expr {
+    let mut expr = Expr::IntLiteral(1, Span::default());
+    for _ in 0..depth {
+        expr = Expr::Block(Block(vec![BlockEl::Expr(expr)]));
+    }
+    expr
+}
+
+fn eval_on_small_stack(depth: usize, max_depth: Option<usize>) -> Result<Value, SoldierError> {
+    thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(move || {
+            let expr = nested_blocks(depth);
+            let mut interp = Interpreter::new();
+            if let Some(max_depth) = max_depth {
+                interp.set_max_depth(max_depth);
+            }
+            let result = interp.interp(&expr);
+            // dropping an expression recursively takes stack too, and that isn't what's
+            // being tested
+            std::mem::forget(expr);
+            result
+        })
+        .unwrap()
+        .join()
+        .unwrap()
+}
+
+#[test]
+fn deep_nesting_is_an_error() {
+    let err = eval_on_small_stack(100_000, None).unwrap_err();
+    assert!(
+        matches!(err, SoldierError::MaxDepthExceeded { max: 256, .. }),
+        "{:?}",
+        err
+    );
+    assert_eq!(err.to_string(), "expressions are nested more than 256 deep");
+}
+
+#[test]
+fn the_limit_can_be_changed() {
+    assert!(matches!(eval_on_small_stack(200, None), Ok(Value::Int(1))));
+    assert!(matches!(
+        eval_on_small_stack(200, Some(100)),
+        Err(SoldierError::MaxDepthExceeded { max: 100, .. })
+    ));
+}
+
+#[test]
+fn the_depth_goes_back_down_after_an_error() {
+    let mut interp = Interpreter::new();
+    interp.set_max_depth(10);
+    let deep = nested_blocks(20);
+    let shallow = nested_blocks(5);
+    assert!(interp.interp(&deep).is_err());
+    assert!(matches!(interp.interp(&shallow), Ok(Value::Int(1))));
+}
+
+#[test]
+fn eval_inside_eval() {
+    let result = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(|| {
+            let mut interp = Interpreter::new();
+            let nested = interp.run_source("eval(\"eval(\\\"eval(\\\\\\\"1 + 1\\\\\\\")\\\")\")\n");
+            // code that evals itself goes on until it's too deep
+            let endless = interp.run_source("let code = \"eval(code)\"\neval(code)\n");
+            (nested, endless)
+        })
+        .unwrap()
+        .join()
+        .unwrap();
+    assert!(matches!(result.0, Ok(Value::Int(2))), "{:?}", result.0);
+    let err = result.1.unwrap_err();
+    assert!(
+        matches!(err, SoldierError::MaxDepthExceeded { max: 256, span: Some(span) } if span.start == 24),
+        "{:?}",
+        err
+    );
+}
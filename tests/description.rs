@@ -0,0 +1,89 @@
+// Programs described in JSON instead of written as source: mistakes in a description say where
+// they are, and a description runs just like the source it describes.
+
+use zac_lib::interp::{Interpreter, Value};
+use zac_lib::parser::{find_comments_mut, from_description, parser, Expr, Program};
+use zac_lib::reassemble::output_code;
+use zac_lib::replace_comments_in_source_code;
+
+#[test]
+fn a_misspelled_type_says_where() {
+    let err = from_description(
+        r#"{"version": 1, "block": [
+            {"type": "int", "value": 1},
+            {"type": "while", "cond": {"type": "var", "name": "x"}, "body": [
+                {"type": "call", "target": {"type": "var", "name": "print"}, "args": [
+                    {"type": "strnig", "value": "hi"}
+                ]}
+            ]}
+        ]}"#,
+    )
+    .unwrap_err();
+    assert_eq!(err.path, "$.block[1].body[0].args[0].type");
+    assert_eq!(
+        err.to_string(),
+        "$.block[1].body[0].args[0].type: unknown expression type \"strnig\""
+    );
+}
+
+#[test]
+fn other_errors_say_where() {
+    for (json, path) in [
+        (r#"{"version": 2, "block": []}"#, "$.version"),
+        (r#"{"block": []}"#, "$"),
+        (r#"{"version": 1, "block": [1]}"#, "$.block[0]"),
+        (
+            r#"{"version": 1, "block": [{"type": "bin_op", "op": "%%", "lhs": {"type": "int", "value": 1}, "rhs": {"type": "int", "value": 2}}]}"#,
+            "$.block[0].op",
+        ),
+        (r#"{"version": 1, "block": ["#, "$"),
+    ] {
+        assert_eq!(from_description(json).unwrap_err().path, path, "{}", json);
+    }
+}
+
+// what the program gives back, and its source with the comments it changed
+fn run(mut program: Program) -> (Value, String) {
+    let mut interp = Interpreter::new();
+    for (_, comment) in find_comments_mut(&mut program).unwrap() {
+        interp.add_comment(comment).unwrap();
+    }
+    let value = interp.interp(&Expr::Block(program.block.clone())).unwrap();
+    replace_comments_in_source_code(&mut program, &mut interp).unwrap();
+    (value, output_code(&program, &interp))
+}
+
+#[test]
+fn runs_the_same_as_the_source() {
+    let source = "// #total\n// 0\ndefn double(n) {\n  n * 2\n}\nlet i = 0\nwhile (i < 3) {\n  let #total = \"${double(i)}\"\n  let i = i + 1\n}\n[i, #total]\n";
+    let description = r#"{"version": 1, "block": [
+        {"type": "comment", "name": "total", "body": "0"},
+        {"type": "defn", "name": "double", "args": ["n"], "body": [
+            {"type": "bin_op", "op": "*", "lhs": {"type": "var", "name": "n"}, "rhs": {"type": "int", "value": 2}}
+        ]},
+        {"type": "assign", "target": {"type": "var", "name": "i"}, "value": {"type": "int", "value": 0}},
+        {"type": "while",
+         "cond": {"type": "bin_op", "op": "<", "lhs": {"type": "var", "name": "i"}, "rhs": {"type": "int", "value": 3}},
+         "body": [
+            {"type": "assign", "target": {"type": "comment_ref", "name": "total"}, "value":
+                {"type": "interpolation", "parts": [
+                    {"type": "call", "target": {"type": "var", "name": "double"}, "args": [{"type": "var", "name": "i"}]}
+                ]}},
+            {"type": "assign", "target": {"type": "var", "name": "i"}, "value":
+                {"type": "bin_op", "op": "+", "lhs": {"type": "var", "name": "i"}, "rhs": {"type": "int", "value": 1}}}
+        ]},
+        {"type": "list", "items": [{"type": "var", "name": "i"}, {"type": "comment_ref", "name": "total"}]}
+    ]}"#;
+    let from_source = run(parser::program(source).unwrap());
+    let from_description = run(from_description(description).unwrap());
+    assert_eq!(from_description, from_source);
+    assert_eq!(
+        from_source.0,
+        Value::List(vec![Value::Int(3), Value::String("4".into())])
+    );
+    assert!(
+        from_source.1.starts_with("// #total\n// 4\n"),
+        "{}",
+        from_source.1
+    );
+}
@@ -0,0 +1,102 @@
+// let [a, b] = ... and let {a, b} = ..., which bind every name or none of them.
+
+use zac_lib::interp::{Interpreter, Value};
+use zac_lib::parser::{parse_program, parser, Expr};
+
+fn run(interp: &mut Interpreter, source: &str) -> Result<Value, String> {
+    let program = parser::program(source).map_err(|err| err.to_string())?;
+    interp
+        .interp(&Expr::Block(program.block))
+        .map_err(|err| err.to_string())
+}
+
+fn eval(source: &str) -> Result<Value, String> {
+    run(&mut Interpreter::new(), source)
+}
+
+fn ints(ints: &[i128]) -> Result<Value, String> {
+    Ok(Value::List(ints.iter().copied().map(Value::Int).collect()))
+}
+
+#[test]
+fn lists() {
+    assert_eq!(eval("let [a, b] = [1, 2]\n[b, a]\n"), ints(&[2, 1]));
+    assert_eq!(eval("let [q, r] = divmod(7, 2)\n[q, r]\n"), ints(&[3, 1]));
+}
+
+#[test]
+fn maps() {
+    assert_eq!(
+        eval("let {y, x} = from_pairs([[\"x\", 1], [\"y\", 2], [\"z\", 3]])\n[x, y]\n"),
+        ints(&[1, 2])
+    );
+}
+
+#[test]
+fn skipping_items() {
+    assert_eq!(eval("let [_, b, _] = [1, 2, 3]\n[b]\n"), ints(&[2]));
+    let err = eval("let [_, b, _] = [1, 2, 3]\n_\n").unwrap_err();
+    assert!(err.contains('_'), "{}", err);
+}
+
+#[test]
+fn failures() {
+    for (source, message) in [
+        (
+            "let [a, b] = [1, 2, 3]\n",
+            "expected a list of 2 items but got 3",
+        ),
+        ("let [a, b] = [1]\n", "expected a list of 2 items but got 1"),
+        (
+            "let {x, y} = from_pairs([[\"x\", 1]])\n",
+            "map has no key \"y\"",
+        ),
+        ("let [a, b] = 5\n", "5"),
+        ("let {a} = [1]\n", "[Int(1)]"),
+    ] {
+        let err = eval(source).unwrap_err();
+        assert!(err.contains(message), "{}: {}", source, err);
+    }
+}
+
+#[test]
+fn nothing_is_bound_when_it_fails() {
+    let mut interp = Interpreter::new();
+    run(&mut interp, "let a = 1\nlet x = 1\n").unwrap();
+    run(&mut interp, "let [a, b, c] = [5, 6]\n").unwrap_err();
+    run(&mut interp, "let {x, y} = from_pairs([[\"x\", 5]])\n").unwrap_err();
+    assert_eq!(run(&mut interp, "[a, x]\n"), ints(&[1, 1]));
+    let err = run(&mut interp, "b\n").unwrap_err();
+    assert!(err.contains('b'), "{}", err);
+}
+
+#[test]
+fn nested_patterns_are_a_syntax_error() {
+    for (source, column) in [
+        ("let x = 1\nlet [[a]] = [[1]]\n", 6),
+        ("let x = 1\nlet {a, {b}} = [1]\n", 9),
+    ] {
+        let err = parse_program(source).unwrap_err();
+        // pointing at the inner pattern
+        assert_eq!((err.line, err.column), (2, column), "{}", err);
+        assert!(
+            err.to_string()
+                .contains("nested patterns not yet supported"),
+            "{}",
+            err
+        );
+    }
+}
+
+#[test]
+fn divmod() {
+    // the remainder is never negative, like mod's
+    assert_eq!(eval("divmod(-7, 2)\n"), ints(&[-4, 1]));
+    assert_eq!(eval("divmod(7, -2)\n"), ints(&[-3, 1]));
+    let err = eval("divmod(7, 0)\n").unwrap_err();
+    assert!(err.contains("the divisor can't be zero"), "{}", err);
+    // i128::MIN, which can't be written as a literal
+    let min = format!("sub({}, 1)", i128::MIN + 1);
+    let err = eval(&format!("divmod({}, -1)\n", min)).unwrap_err();
+    assert!(err.contains("integer overflow in divmod"), "{}", err);
+}
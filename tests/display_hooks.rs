@@ -0,0 +1,68 @@
+// Values made by the host, shown the way the host says to show them.
+
+use std::cell::RefCell;
+use std::rc::Rc;
+use zac_lib::error::SoldierError;
+use zac_lib::interp::{Interpreter, Opaque, Value};
+use zac_lib::trace::{TraceFormat, Tracer, DEFAULT_MAX_VALUE_LEN};
+
+struct Point {
+    x: i64,
+    y: i64,
+}
+
+fn eval(interp: &mut Interpreter, source: &str) -> Result<Value, SoldierError> {
+    interp.run_source(source)
+}
+
+fn with_point() -> Interpreter {
+    let mut interp = Interpreter::new();
+    interp.register_display(|p: &Point| format!("Point({}, {})", p.x, p.y));
+    interp.set_var(
+        "p",
+        Value::Opaque(Opaque::new("Point", Point { x: 1, y: 2 })),
+    );
+    interp
+}
+
+#[test]
+fn display_hooks_are_used_by_show_traces_and_errors() {
+    let mut interp = with_point();
+    let shown = eval(&mut interp, "show([p])\n").unwrap();
+    assert_eq!(shown, Value::from("[Point(1, 2)]"));
+    assert_eq!(interp.show(&interp.get_var("p").unwrap()), "Point(1, 2)");
+
+    let tracer = Rc::new(RefCell::new(Tracer::new(DEFAULT_MAX_VALUE_LEN)));
+    interp.set_tracer(Rc::clone(&tracer));
+    eval(&mut interp, "let q = p\n").unwrap();
+    let trace = tracer.borrow().render(TraceFormat::Human);
+    assert!(trace.contains("let q = Point(1, 2)"), "{}", trace);
+
+    let err = eval(&mut interp, "p + 1\n").unwrap_err();
+    assert!(err.to_string().contains("Point(1, 2)"), "{}", err);
+}
+
+#[test]
+fn without_a_hook_the_type_name_is_shown() {
+    struct Secret;
+    let mut interp = Interpreter::new();
+    interp.set_var("s", Value::Opaque(Opaque::new("Secret", Secret)));
+    assert_eq!(
+        eval(&mut interp, "show(s)\n").unwrap(),
+        Value::from("<Secret>")
+    );
+}
+
+#[test]
+fn display_hooks_belong_to_one_interpreter() {
+    let interp = with_point();
+    let p = interp.get_var("p").unwrap();
+    let mut other = Interpreter::new();
+    other.set_var("p", p.clone());
+    assert_eq!(
+        eval(&mut other, "show(p)\n").unwrap(),
+        Value::from("<Point>")
+    );
+    assert_eq!(other.show(&p), "<Point>");
+    assert_eq!(interp.show(&p), "Point(1, 2)");
+}
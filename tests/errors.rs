@@ -0,0 +1,262 @@
+// The kind of error a program stops with, for code that handles them differently.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use zac_lib::error::{render_error, SoldierError};
+use zac_lib::interp::{Interpreter, Value};
+use zac_lib::parser::{find_comments_mut, parse_program, Op};
+
+fn run(source: &str) -> Result<Value, SoldierError> {
+    Interpreter::new().run_source(source)
+}
+
+#[test]
+fn parse_errors() {
+    let err = run("let x = )\n").unwrap_err();
+    assert!(
+        matches!(&err, SoldierError::Parse(diagnostic) if diagnostic.line == 1),
+        "{:?}",
+        err
+    );
+}
+
+#[test]
+fn undefined_names_say_where_they_are() {
+    let err = run("let x = 1\nlet y = nope\n").unwrap_err();
+    assert!(
+        matches!(
+            &err,
+            SoldierError::UndefinedName { name, span } if name == "nope" && span.start == 18
+        ),
+        "{:?}",
+        err
+    );
+    assert_eq!(err.to_string(), "undefined name nope");
+}
+
+#[test]
+fn undefined_comments() {
+    let err = run("let x = #nope\n").unwrap_err();
+    assert!(
+        matches!(&err, SoldierError::UndefinedComment { name, .. } if name == "nope"),
+        "{:?}",
+        err
+    );
+}
+
+#[test]
+fn duplicate_comments() {
+    let mut program = parse_program("// #a\n// one\n1\n").unwrap();
+    let comments = find_comments_mut(&mut program).unwrap();
+    let comment = comments.get("a").unwrap();
+    let mut interp = Interpreter::new();
+    interp.add_comment(comment).unwrap();
+    let err = interp.add_comment(comment).unwrap_err();
+    assert!(
+        matches!(&err, SoldierError::DuplicateComment { name, .. } if name == "a"),
+        "{:?}",
+        err
+    );
+}
+
+#[test]
+fn type_mismatches() {
+    let err = run("if (1) {\n  2\n}\n").unwrap_err();
+    assert!(
+        matches!(
+            &err,
+            SoldierError::TypeMismatch { expected, got, .. } if expected == "a bool" && got == "Int(1)"
+        ),
+        "{:?}",
+        err
+    );
+    // the same error from inside a builtin
+    let err = run("chr(\"a\")\n").unwrap_err();
+    assert!(
+        matches!(&err, SoldierError::TypeMismatch { expected, .. } if expected == "an integer"),
+        "{:?}",
+        err
+    );
+}
+
+#[test]
+fn bad_operands() {
+    let err = run("1 + [2]\n").unwrap_err();
+    assert!(
+        matches!(&err, SoldierError::BadOperands { op: Op::Add, .. }),
+        "{:?}",
+        err
+    );
+    assert_eq!(err.to_string(), "can't add Int(1) and List([Int(2)])");
+}
+
+#[test]
+fn calling_something_that_isnt_a_function() {
+    let err = run("let x = 1\nx(2)\n").unwrap_err();
+    assert!(
+        matches!(
+            &err,
+            SoldierError::NotCallable { span, .. } if span.start == 10
+        ),
+        "{:?}",
+        err
+    );
+}
+
+#[test]
+fn missing_arguments() {
+    let err = run("chr()\n").unwrap_err();
+    assert!(
+        matches!(
+            err,
+            SoldierError::Arity {
+                needed: 1,
+                got: 0,
+                ..
+            }
+        ),
+        "{:?}",
+        err
+    );
+}
+
+#[test]
+fn arithmetic() {
+    let err = run("div(7, 0)\n").unwrap_err();
+    assert!(
+        matches!(&err, SoldierError::DivisionByZero { name, dividend: 7, .. } if name == "div"),
+        "{:?}",
+        err
+    );
+    let err = run("7 / 0\n").unwrap_err();
+    assert!(
+        matches!(err, SoldierError::DivisionByZero { .. }),
+        "{:?}",
+        err
+    );
+    let err = run("pow(10, 100)\n").unwrap_err();
+    assert!(
+        matches!(&err, SoldierError::IntegerOverflow { call, .. } if call == "pow(10, 100)"),
+        "{:?}",
+        err
+    );
+}
+
+#[test]
+fn errors_pass_through_functions_unchanged() {
+    let err = run("defn f(x) {\n  g(x)\n}\nf(1)\n").unwrap_err();
+    assert!(
+        matches!(&err, SoldierError::UndefinedName { name, .. } if name == "g"),
+        "{:?}",
+        err
+    );
+}
+
+#[test]
+fn converts_to_anyhow() {
+    let err: anyhow::Error = run("nope\n").unwrap_err().into();
+    assert!(matches!(
+        err.downcast_ref::<SoldierError>(),
+        Some(SoldierError::UndefinedName { .. })
+    ));
+}
+
+fn rendered(source: &str) -> String {
+    render_error(source, &run(source).unwrap_err())
+}
+
+#[test]
+fn rendering_points_at_the_expression() {
+    assert_eq!(
+        rendered("let x = 3\nlet y = x(2)\n"),
+        "line 2, col 9: tried to call a Int(3)\n  let y = x(2)\n          ^^^^"
+    );
+    assert_eq!(
+        rendered("let x = [1]\nlet y = add(x, 1)\n"),
+        "line 2, col 9: List([Int(1)]) is not an integer\n  let y = add(x, 1)\n          ^^^^^^^^^"
+    );
+    // the innermost expression with a span, inside a function that was called from elsewhere
+    assert_eq!(
+        rendered("defn f(x) {\n\tdiv(x, 0)\n}\nf(1)\n"),
+        "line 2, col 2: can't take div(1, 0), the divisor can't be zero\n  \tdiv(x, 0)\n  \t^^^^^^^^^"
+    );
+}
+
+#[test]
+fn rendering_a_loop_only_underlines_its_first_line() {
+    assert_eq!(
+        rendered("while (1) {\n  2\n}\n"),
+        "line 1, col 1: Int(1) is not a bool\n  while (1) {\n  ^^^^^^^^^^^"
+    );
+}
+
+#[test]
+fn rendering_errors_without_a_span() {
+    let err = run("let x = )\n").unwrap_err();
+    assert_eq!(render_error("let x = )\n", &err), err.to_string());
+}
+
+#[test]
+fn duplicate_comments_stop_the_program_before_it_runs() {
+    let ran = Arc::new(AtomicBool::new(false));
+    let mut interp = Interpreter::new();
+    let ran_in_program = Arc::clone(&ran);
+    interp.register_fn("mark", move |_| {
+        ran_in_program.store(true, Ordering::SeqCst);
+        Ok(Value::Null)
+    });
+    let err = interp
+        .run_source("mark()\n// #a\n// one\n\n// #a\n// two\n")
+        .unwrap_err();
+    assert!(
+        matches!(&err, SoldierError::DuplicateComment { name, .. } if name == "a"),
+        "{:?}",
+        err
+    );
+    assert!(!ran.load(Ordering::SeqCst));
+}
+
+#[test]
+fn exit_stops_the_program() {
+    let mut interp = Interpreter::new();
+    let source = "let i = 0\nwhile (i < 10) {\n  if (i == 3) {\n    exit(3)\n  }\n  let i = i + 1\n}\nlet after = true\n";
+    let err = interp.run_source(source).unwrap_err();
+    assert!(matches!(err, SoldierError::Exited(3)), "{:?}", err);
+    assert_eq!(interp.get_var("i"), Some(Value::Int(3)));
+    assert_eq!(interp.get_var("after"), None);
+
+    // from inside a function a builtin calls, too
+    let err = run("defn f(x) {\n  exit()\n}\nmap([1, 2], f)\n").unwrap_err();
+    assert!(matches!(err, SoldierError::Exited(0)), "{:?}", err);
+}
+
+#[test]
+fn assertions_say_where_they_failed() {
+    let source = "let x = 1\nassert(x == 2)\n";
+    let err = run(source).unwrap_err();
+    assert!(
+        matches!(&err, SoldierError::AssertionFailed { message: None, span: Some(span) } if span.start == 10),
+        "{:?}",
+        err
+    );
+    assert_eq!(
+        render_error(source, &err),
+        "line 2, col 1: assertion failed\n  assert(x == 2)\n  ^^^^^^^^^^^^^^"
+    );
+
+    let err = run("assert_eq(\"a\", add(1, 1))\n").unwrap_err();
+    assert!(
+        matches!(&err, SoldierError::AssertionFailed { message: Some(message), .. } if message == "a isn't equal to 2"),
+        "{:?}",
+        err
+    );
+
+    // a failure inside a function a builtin calls is still an assertion failure, at the assert
+    let err = run("defn f(x) {\n  assert(x < 2, x)\n}\nmap([1, 2], f)\n").unwrap_err();
+    assert!(
+        matches!(&err, SoldierError::AssertionFailed { span: Some(span), .. } if span.start == 14),
+        "{:?}",
+        err
+    );
+    assert_eq!(err.to_string(), "assertion failed: 2");
+}
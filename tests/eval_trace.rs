@@ -0,0 +1,73 @@
+// Tracing every expression a program evaluates, see Interpreter::set_trace.
+
+use std::cell::RefCell;
+use std::io::Write;
+use std::rc::Rc;
+use zac_lib::interp::Interpreter;
+
+#[derive(Clone, Default)]
+struct Captured(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn traced(source: &str) -> String {
+    let captured = Captured::default();
+    let mut interp = Interpreter::new();
+    interp.set_output(Box::new(captured.clone()));
+    interp.set_trace(true);
+    interp.run_source(source).unwrap();
+    let output = captured.0.borrow().clone();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn one_line_per_expression() {
+    assert_eq!(
+        traced("let x = 1\n"),
+        "    int 1 => 1\n  assign let x = 1 => 1\n"
+    );
+}
+
+#[test]
+fn every_iteration_of_a_loop() {
+    let trace = traced("let i = 0\nwhile (i < 3) {\n  let i = i + 1\n}\n");
+    let increments = trace
+        .lines()
+        .filter(|line| line.trim_start().starts_with("assign let i = i + 1 =>"))
+        .count();
+    assert_eq!(increments, 3, "{}", trace);
+    // one more condition than iterations, the one that ends the loop
+    let conditions = trace
+        .lines()
+        .filter(|line| line.trim_start().starts_with("bin_op i < 3 =>"))
+        .count();
+    assert_eq!(conditions, 4, "{}", trace);
+}
+
+#[test]
+fn trace_goes_with_print() {
+    let trace = traced("print(\"hi\")\n");
+    // the argument, then what print wrote, then the call itself
+    assert_eq!(
+        trace,
+        "    string \"hi\" => hi\nhi\n  call print(\"hi\") => hi\n"
+    );
+}
+
+#[test]
+fn off_by_default() {
+    let captured = Captured::default();
+    let mut interp = Interpreter::new();
+    interp.set_output(Box::new(captured.clone()));
+    interp.run_source("let x = 1\n").unwrap();
+    assert!(captured.0.borrow().is_empty());
+}
@@ -0,0 +1,128 @@
+// The file builtins on the real filesystem, once the host allows it.
+
+use std::fs;
+use std::path::PathBuf;
+use zac_lib::error::SoldierError;
+use zac_lib::interp::{Interpreter, InterpreterBuilder, Value};
+use zac_lib::vfs::RealFs;
+
+fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("zac-files-{}-{}", std::process::id(), name))
+}
+
+fn with_files() -> Interpreter {
+    InterpreterBuilder::new()
+        .default_builtins()
+        .fs_builtins()
+        .build()
+}
+
+fn run(source: &str) -> Result<Value, SoldierError> {
+    let mut interp = with_files();
+    interp.set_fs(Box::new(RealFs::new(true)));
+    interp.run_source(source)
+}
+
+#[test]
+fn write_append_and_read() {
+    let path = temp_path("notes.txt");
+    let _ = fs::remove_file(&path);
+    let path_str = path.to_str().unwrap();
+    let result = run(&format!(
+        "write_file(\"{0}\", \"one\\n\")\nappend_file(\"{0}\", \"two\\n\")\nread_file(\"{0}\")\n",
+        path_str
+    ))
+    .unwrap();
+    assert_eq!(result, Value::from("one\ntwo\n"));
+    assert_eq!(fs::read_to_string(&path).unwrap(), "one\ntwo\n");
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn append_makes_the_file() {
+    let path = temp_path("log.txt");
+    let _ = fs::remove_file(&path);
+    run(&format!(
+        "append_file(\"{}\", \"x\")\n",
+        path.to_str().unwrap()
+    ))
+    .unwrap();
+    assert_eq!(fs::read_to_string(&path).unwrap(), "x");
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn errors_name_the_path_and_say_why() {
+    let missing = temp_path("missing.txt");
+    let missing = missing.to_str().unwrap();
+    let err = run(&format!("read_file(\"{}\")\n", missing)).unwrap_err();
+    assert!(
+        err.to_string()
+            .starts_with(&format!("can't read {}: ", missing)),
+        "{}",
+        err
+    );
+    // the message from the OS
+    assert!(err.to_string().contains("(os error"), "{}", err);
+
+    // a directory can't be written to like a file, whoever is running the tests
+    let dir = temp_path("dir");
+    fs::create_dir_all(&dir).unwrap();
+    let dir = dir.to_str().unwrap();
+    let err = run(&format!("write_file(\"{}\", \"x\")\n", dir)).unwrap_err();
+    assert!(
+        err.to_string()
+            .starts_with(&format!("can't write {}: ", dir)),
+        "{}",
+        err
+    );
+    fs::remove_dir(dir).unwrap();
+}
+
+#[cfg(unix)]
+#[test]
+fn permission_denied() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let path = temp_path("locked.txt");
+    fs::write(&path, "x").unwrap();
+    fs::set_permissions(&path, fs::Permissions::from_mode(0o000)).unwrap();
+    // root reads it anyway, and then there's no error to check
+    if fs::read(&path).is_err() {
+        let path = path.to_str().unwrap();
+        for source in [
+            format!("read_file(\"{}\")\n", path),
+            format!("write_file(\"{}\", \"y\")\n", path),
+        ] {
+            let err = run(&source).unwrap_err().to_string();
+            assert!(err.contains(&format!("{}: ", path)), "{}", err);
+            assert!(err.contains("Permission denied"), "{}", err);
+        }
+    }
+    fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn not_in_a_new_interpreter() {
+    let err = Interpreter::new()
+        .run_source("read_file(\"a.txt\")\n")
+        .unwrap_err();
+    assert!(
+        matches!(&err, SoldierError::UndefinedName { name, .. } if name == "read_file"),
+        "{:?}",
+        err
+    );
+}
+
+#[test]
+fn still_off_without_allowing_it() {
+    let path = temp_path("secret.txt");
+    let err = with_files()
+        .run_source(&format!("read_file(\"{}\")\n", path.to_str().unwrap()))
+        .unwrap_err();
+    assert!(
+        err.to_string().contains("file access isn't allowed"),
+        "{}",
+        err
+    );
+}
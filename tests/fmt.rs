@@ -0,0 +1,86 @@
+// `zac fmt`, which rewrites a file the way reassemble lays programs out. golden/fmt/<name>.zac
+// formats to golden/fmt/<name>.formatted.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output};
+use zac_lib::reassemble::{format_source, AssembleOptions};
+
+fn golden(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("golden/fmt")
+        .join(name)
+}
+
+// a copy to format, since fmt writes over the file
+fn scratch_copy(name: &str, source: &str) -> PathBuf {
+    let path = std::env::temp_dir().join(format!("zac-fmt-{}-{}.zac", std::process::id(), name));
+    fs::write(&path, source).unwrap();
+    path
+}
+
+fn fmt(args: &[&str], path: &Path) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_zac"))
+        .arg("fmt")
+        .args(args)
+        .arg(path)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn messy_file_formats_to_golden() {
+    let source = fs::read_to_string(golden("messy.zac")).unwrap();
+    let expected = fs::read_to_string(golden("messy.formatted")).unwrap();
+    let path = scratch_copy("messy", &source);
+    let output = fmt(&[], &path);
+    assert!(output.status.success(), "{:?}", output);
+    assert_eq!(fs::read_to_string(&path).unwrap(), expected);
+    // formatting again doesn't change anything
+    assert_eq!(
+        format_source(&expected, &AssembleOptions::default()).unwrap(),
+        expected
+    );
+}
+
+#[test]
+fn check_detects_drift() {
+    let formatted = fs::read_to_string(golden("messy.formatted")).unwrap();
+    let path = scratch_copy("check_clean", &formatted);
+    assert!(fmt(&["--check"], &path).status.success());
+
+    let messy = fs::read_to_string(golden("messy.zac")).unwrap();
+    let path = scratch_copy("check_messy", &messy);
+    let output = fmt(&["--check"], &path);
+    assert_eq!(output.status.code(), Some(1));
+    // --check never writes
+    assert_eq!(fs::read_to_string(&path).unwrap(), messy);
+}
+
+#[test]
+fn options() {
+    let path = scratch_copy("options", "defn f(a,b) {\nadd(a,b)\n}\n");
+    assert!(fmt(&["--indent=4"], &path).status.success());
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        "defn f(a, b) {\n    add(a, b)\n}\n"
+    );
+}
+
+#[test]
+fn unparsable_files_are_left_alone() {
+    let source = "let x = (1\nlet y = 2\n";
+    let path = scratch_copy("unparsable", source);
+    let output = fmt(&[], &path);
+    assert!(!output.status.success());
+    assert_eq!(fs::read_to_string(&path).unwrap(), source);
+}
+
+#[test]
+fn result_comments_keep_what_they_said() {
+    let source = "add(1,2) // # 3\nlet x = [1] // #[1]\n";
+    assert_eq!(
+        format_source(source, &AssembleOptions::default()).unwrap(),
+        "add(1, 2) // # 3\nlet x = [1] // #[1]\n"
+    );
+}
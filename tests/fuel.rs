@@ -0,0 +1,61 @@
+// Stopping programs that run for too long.
+
+use zac_lib::error::SoldierError;
+use zac_lib::interp::{Interpreter, Value};
+
+fn eval(interp: &mut Interpreter, source: &str) -> Result<Value, SoldierError> {
+    interp.run_source(source)
+}
+
+#[test]
+fn infinite_loops_stop() {
+    for source in ["while (true) {\n  1\n}\n", "loop {\n  1\n}\n"] {
+        let mut interp = Interpreter::new();
+        interp.set_fuel(Some(1000));
+        let err = eval(&mut interp, source).unwrap_err();
+        assert!(
+            matches!(err, SoldierError::FuelExhausted { used: 1000, .. }),
+            "{:?}",
+            err
+        );
+        assert_eq!(err.to_string(), "ran out of fuel after 1000 steps");
+        assert_eq!(interp.fuel_remaining(), Some(0));
+    }
+}
+
+#[test]
+fn fuel_runs_out_inside_functions_too() {
+    let mut interp = Interpreter::new();
+    interp.set_fuel(Some(50));
+    let err = eval(&mut interp, "defn f() {\n  f()\n}\nf()\n").unwrap_err();
+    assert!(
+        matches!(err, SoldierError::FuelExhausted { .. }),
+        "{:?}",
+        err
+    );
+}
+
+#[test]
+fn programs_under_the_limit_finish() {
+    let mut interp = Interpreter::new();
+    interp.set_fuel(Some(1000));
+    let source = "let i = 0\nwhile (i < 10) {\n  let i = i + 1\n}\ni\n";
+    assert!(matches!(eval(&mut interp, source), Ok(Value::Int(10))));
+    let remaining = interp.fuel_remaining().unwrap();
+    assert!(remaining > 0 && remaining < 1000, "{}", remaining);
+
+    // the same program again, on what's left
+    let mut again = Interpreter::new();
+    again.set_fuel(Some(1000 - remaining));
+    assert!(matches!(eval(&mut again, source), Ok(Value::Int(10))));
+    assert_eq!(again.fuel_remaining(), Some(0));
+}
+
+#[test]
+fn no_limit_by_default() {
+    let mut interp = Interpreter::new();
+    assert_eq!(interp.fuel_remaining(), None);
+    let source = "let i = 0\nwhile (i < 10000) {\n  let i = i + 1\n}\ni\n";
+    assert!(matches!(eval(&mut interp, source), Ok(Value::Int(10000))));
+    assert_eq!(interp.fuel_remaining(), None);
+}
@@ -0,0 +1,82 @@
+// Functions as values: they're compared, ordered and hashed by name.
+
+use std::collections::{BTreeMap, HashSet};
+use zac_lib::interp::{Interpreter, Value};
+use zac_lib::parser::{parser, Expr};
+
+fn run(interp: &mut Interpreter, source: &str) -> Value {
+    let program = parser::program(source).unwrap();
+    interp.interp(&Expr::Block(program.block)).unwrap()
+}
+
+fn name(val: &Value) -> &str {
+    match val {
+        Value::Function(func) => func.name(),
+        _ => panic!("{:?} isn't a function", val),
+    }
+}
+
+#[test]
+fn equal_across_clones() {
+    let mut interp = Interpreter::new();
+    run(
+        &mut interp,
+        "defn double(n) {\n  mul(n, 2)\n}\nlet g = double\nlet h = add\n",
+    );
+    assert_eq!(run(&mut interp, "g\n"), run(&mut interp, "double\n"));
+    assert_eq!(run(&mut interp, "h\n"), run(&mut interp, "add\n"));
+    let add = run(&mut interp, "add\n");
+    assert_eq!(add.clone(), add);
+    assert_ne!(add, run(&mut interp, "mul\n"));
+    assert_eq!(
+        run(&mut interp, "[eq(g, double), eq(h, add), eq(g, add)]\n"),
+        Value::List(vec![
+            Value::Bool(true),
+            Value::Bool(true),
+            Value::Bool(false)
+        ])
+    );
+
+    let set: HashSet<Value> = [run(&mut interp, "g\n"), run(&mut interp, "double\n")].into();
+    assert_eq!(set.len(), 1);
+}
+
+// only the name counts, so two different definitions under one name are the same function
+#[test]
+fn different_definitions_with_the_same_name_are_equal() {
+    let mut first = Interpreter::new();
+    let mut second = Interpreter::new();
+    let f = run(&mut first, "defn f(n) {\n  add(n, 1)\n}\nf\n");
+    let other_f = run(&mut second, "defn f(n) {\n  mul(n, 2)\n}\nf\n");
+    assert_eq!(f, other_f);
+    assert_ne!(run(&mut first, "f(3)\n"), run(&mut second, "f(3)\n"));
+}
+
+#[test]
+fn ordered_by_name_in_maps() {
+    let mut interp = Interpreter::new();
+    run(&mut interp, "defn double(n) {\n  mul(n, 2)\n}\n");
+    // the order they go in doesn't matter
+    for names in [
+        ["cat", "add", "double", "mul"],
+        ["mul", "double", "cat", "add"],
+    ] {
+        let map: BTreeMap<Value, usize> = names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| (run(&mut interp, &format!("{}\n", name)), i))
+            .collect();
+        let keys: Vec<&str> = map.keys().map(name).collect();
+        assert_eq!(keys, ["add", "cat", "double", "mul"]);
+    }
+}
+
+#[test]
+fn show() {
+    let mut interp = Interpreter::new();
+    run(&mut interp, "defn double(n) {\n  mul(n, 2)\n}\n");
+    for name in ["add", "double"] {
+        let shown = run(&mut interp, &format!("show({})\n", name));
+        assert_eq!(shown, Value::String(format!("<function {}>", name)));
+    }
+}
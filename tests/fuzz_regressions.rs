@@ -0,0 +1,102 @@
+// Inputs fuzz/fuzz_targets/parse_and_run.rs found that panicked or crashed, and now give errors.
+
+use std::thread;
+use zac_lib::error::SoldierError;
+use zac_lib::interp::{Interpreter, Value};
+use zac_lib::parser::{parse_program, parse_program_lenient};
+
+fn run(source: &str) -> Result<Value, SoldierError> {
+    Interpreter::new().run_source(source)
+}
+
+#[test]
+fn int_literals_too_big_for_128_bits() {
+    let err = parse_program("let x = 1234567890123456789012345678901234567890\n").unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("integer literal out of range for i128"),
+        "{}",
+        err
+    );
+    assert!(parse_program(&format!("{}\n", i128::MAX)).is_ok());
+    assert!(parse_program(&format!("{}\n", i128::MIN)).is_ok());
+}
+
+#[test]
+fn chr_of_something_that_isnt_a_character() {
+    for code in [
+        "-1",
+        "55296",
+        "1114112",
+        "170141183460469231731687303715884105727",
+    ] {
+        let err = run(&format!("chr({})\n", code)).unwrap_err();
+        assert_eq!(err.to_string(), format!("{} isn't a character code", code));
+    }
+    assert_eq!(run("chr(233)\n").unwrap(), Value::from("é"));
+}
+
+#[test]
+fn pad_to_a_huge_width() {
+    let err = run("pad(\"x\", 100000000000)\n").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "can't pad to 100000000000 columns, the most is 1048576"
+    );
+}
+
+#[test]
+fn deeply_nested_source_is_a_parse_error() {
+    let sources = [
+        format!("{}1{}\n", "(".repeat(5000), ")".repeat(5000)),
+        format!("{}{}\n", "[".repeat(5000), "]".repeat(5000)),
+        format!("{}1\n{}", "if (true) {\n".repeat(5000), "}\n".repeat(5000)),
+        format!("{}1{}\n", "\"${".repeat(5000), "}\"".repeat(5000)),
+        format!("{}1\n", "let x = ".repeat(5000)),
+        // code can go on the same line as a named comment
+        format!("// #e{}1{}\n", "(".repeat(5000), ")".repeat(5000)),
+    ];
+    for source in &sources {
+        let err = parse_program(source).unwrap_err();
+        assert_eq!(err.expected, vec!["at most 200 levels of nesting"]);
+        let (program, diagnostics) = parse_program_lenient(source);
+        assert!(program.is_none());
+        assert_eq!(diagnostics, vec![err]);
+    }
+}
+
+#[test]
+fn brackets_in_strings_and_comments_dont_count_as_nesting() {
+    let source = "print(\"(((\")\n// [[[\n/* {{{ */\n\"\"\"\n(\"\n\"\"\"\nlet x = 1\n".repeat(1000);
+    assert!(parse_program(&source).is_ok());
+}
+
+#[test]
+fn long_chains_of_fields() {
+    let err = parse_program(&format!("x{}\n", ".a".repeat(10_000))).unwrap_err();
+    assert!(err.to_string().contains("fewer fields in a row"), "{}", err);
+    let source = format!("x{}\n", ".a".repeat(200));
+    let err = thread::Builder::new()
+        .stack_size(4 << 20)
+        .spawn(move || run(&source).unwrap_err())
+        .unwrap()
+        .join()
+        .unwrap();
+    assert_eq!(err.to_string(), "undefined name x");
+}
+
+#[test]
+fn set_past_the_end_of_a_string() {
+    for index in ["-1", "3", "4"] {
+        let err = run(&format!("set(\"abc\", {}, \"x\")\n", index)).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "{} isn't where a character starts in a string of length 3",
+                index
+            )
+        );
+    }
+    assert!(run("set(\"é\", 1, \"x\")\n").is_err());
+    assert_eq!(run("set(\"éa\", 0, \"x\")\n").unwrap(), Value::from("xa"));
+}
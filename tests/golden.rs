@@ -0,0 +1,46 @@
+// Reprints every program in golden/ and compares it against the files next to it, which lock
+// down the layout: golden/<name>.indent2 and golden/<name>.indent4 are the program reprinted
+// with blocks indented by 2 and 4 spaces. Run with ZAC_BLESS=1 to write the files instead,
+// after a change to the layout that's meant to happen.
+
+use std::fs;
+use std::path::Path;
+use zac_lib::interp::Interpreter;
+use zac_lib::parser::parse_program;
+use zac_lib::reassemble::{output_code_with, AssembleOptions};
+
+#[test]
+fn golden() {
+    let bless = std::env::var_os("ZAC_BLESS").is_some();
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("golden");
+    let mut failures = vec![];
+    for entry in fs::read_dir(&dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension().map_or(true, |ext| ext != "zac") {
+            continue;
+        }
+        let program = parse_program(&fs::read_to_string(&path).unwrap()).unwrap();
+        for width in [2, 4] {
+            let options = AssembleOptions {
+                indent_width: width,
+                ..AssembleOptions::default()
+            };
+            let reprinted = output_code_with(&program, &Interpreter::new(), &options);
+            let expected_path = path.with_extension(format!("indent{}", width));
+            if bless {
+                fs::write(&expected_path, &reprinted).unwrap();
+                continue;
+            }
+            let expected = fs::read_to_string(&expected_path).unwrap_or_default();
+            if reprinted != expected {
+                failures.push(format!(
+                    "{}:\n--- expected\n{}--- got\n{}",
+                    expected_path.display(),
+                    expected,
+                    reprinted
+                ));
+            }
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
@@ -0,0 +1,101 @@
+// Strings counted in grapheme clusters instead of chars, with the unicode feature.
+#![cfg(feature = "unicode")]
+
+use zac_lib::interp::{Interpreter, Value};
+use zac_lib::parser::{parser, Expr};
+
+// a man, a woman and a girl joined into one emoji by zero width joiners
+const FAMILY: &str = "\u{1f468}\u{200d}\u{1f469}\u{200d}\u{1f467}";
+// the é is an e followed by a combining acute accent
+const CAFE: &str = "cafe\u{301}";
+
+fn eval(interp: &mut Interpreter, source: &str) -> Value {
+    let source = format!(
+        "let family = \"{}\"\nlet cafe = \"{}\"\n{}",
+        FAMILY, CAFE, source
+    );
+    let program = parser::program(&source).unwrap();
+    interp.interp(&Expr::Block(program.block)).unwrap()
+}
+
+fn run(graphemes: bool, source: &str) -> Vec<Value> {
+    let mut interp = Interpreter::new();
+    interp.set_grapheme_strings(graphemes);
+    match eval(&mut interp, source) {
+        Value::List(vals) => vals,
+        other => panic!("{:?}", other),
+    }
+}
+
+fn strings(strings: &[&str]) -> Vec<Value> {
+    strings
+        .iter()
+        .map(|s| Value::String(s.to_string()))
+        .collect()
+}
+
+#[test]
+fn len() {
+    let source = "[len(family), len(cafe)]\n";
+    assert_eq!(run(false, source), [Value::Int(5), Value::Int(5)]);
+    assert_eq!(run(true, source), [Value::Int(1), Value::Int(4)]);
+}
+
+#[test]
+fn indexing_slicing_and_reversing() {
+    let source = "[family(0), cafe(3), slice(cafe, 0, 4), slice(cafe, 3, 4), reverse(cafe)]\n";
+    assert_eq!(
+        run(false, source),
+        strings(&["\u{1f468}", "e", "cafe", "e", "\u{301}efac"])
+    );
+    assert_eq!(
+        run(true, source),
+        strings(&[FAMILY, "e\u{301}", CAFE, "e\u{301}", "e\u{301}fac"])
+    );
+}
+
+#[test]
+fn setting_a_character() {
+    let source = "let s = cafe\nlet s(3) = \"E\"\n[s]\n";
+    assert_eq!(run(false, source), strings(&["cafE\u{301}"]));
+    assert_eq!(run(true, source), strings(&["cafE"]));
+}
+
+#[test]
+fn going_through_the_characters() {
+    let source = "map(cat(family, cafe), len)\n";
+    assert_eq!(run(false, source), vec![Value::Int(1); 10]);
+    assert_eq!(run(true, source), vec![Value::Int(1); 5]);
+    let source = "[len(map(family, show)), len(map(cafe, show))]\n";
+    assert_eq!(run(true, source), [Value::Int(1), Value::Int(4)]);
+}
+
+#[test]
+fn pad_counts_columns() {
+    let source = "[pad(cafe, 6), pad(\"\u{65e5}\u{672c}\", 6), pad(family, 3)]\n";
+    assert_eq!(
+        run(false, source),
+        strings(&[
+            "cafe\u{301} ",
+            "\u{65e5}\u{672c}    ",
+            // five chars, already wider than 3
+            FAMILY
+        ])
+    );
+    // the accent takes up no room of its own, and the wide characters take 2 columns each. so
+    // does the whole family, however many people are in it
+    assert_eq!(
+        run(true, source),
+        strings(&[
+            "cafe\u{301}  ",
+            "\u{65e5}\u{672c}  ",
+            &format!("{} ", FAMILY)
+        ])
+    );
+}
+
+#[test]
+fn off_by_default() {
+    let mut interp = Interpreter::new();
+    assert_eq!(eval(&mut interp, "len(family)\n"), Value::Int(5));
+}
@@ -0,0 +1,110 @@
+// Functions provided by the program embedding the interpreter.
+
+use zac_lib::error::SoldierError;
+use zac_lib::interp::{builtin_comment, Function, Interpreter, Opaque, Value};
+
+fn eval(interp: &mut Interpreter, source: &str) -> Result<Value, SoldierError> {
+    interp.run_source(source)
+}
+
+#[test]
+fn closures_can_be_called() {
+    let mut interp = Interpreter::new();
+    interp.register_fn("double", |args| match args {
+        [Value::Int(n)] => Ok(Value::Int(n * 2)),
+        _ => anyhow::bail!("double takes a number"),
+    });
+    assert!(matches!(
+        eval(&mut interp, "double(21)\n"),
+        Ok(Value::Int(42))
+    ));
+    let err = eval(&mut interp, "double(\"x\")\n").unwrap_err();
+    assert!(err.to_string().contains("double takes a number"), "{}", err);
+}
+
+// a builtin that uses the interpreter it's called from
+#[derive(Debug, Clone)]
+struct CallTwice;
+
+impl Function for CallTwice {
+    fn name(&self) -> &str {
+        "call_twice"
+    }
+
+    fn doc(&self) -> &str {
+        "call_twice(f, x) is f(f(x))"
+    }
+
+    fn call(&self, interp: &mut Interpreter, args: &[Value]) -> anyhow::Result<Value> {
+        let f = match &args[0] {
+            Value::Function(f) => f,
+            other => anyhow::bail!("{:?} is not a function", other),
+        };
+        let once = f.call(interp, &args[1..])?;
+        f.call(interp, &[once])
+    }
+}
+
+#[test]
+fn builtins_can_use_the_interpreter() {
+    let mut interp = Interpreter::new();
+    interp.register_builtin("call_twice", CallTwice);
+    let value = eval(
+        &mut interp,
+        "defn inc(n) {\n  n + 1\n}\ncall_twice(inc, 1)\n",
+    )
+    .unwrap();
+    assert!(matches!(value, Value::Int(3)));
+}
+
+#[test]
+fn registered_functions_are_in_help() {
+    let mut interp = Interpreter::new();
+    interp.register_builtin("call_twice", CallTwice);
+    interp.register_fn("fetch_config", |_| Ok(Value::Null));
+    let help = builtin_comment(&interp, "help").unwrap();
+    assert!(help.contains("fetch_config"), "{}", help);
+    assert!(help.contains("call_twice(f, x) is f(f(x))"), "{}", help);
+}
+
+#[test]
+fn registering_replaces_a_builtin() {
+    let mut interp = Interpreter::new();
+    interp.register_fn("len", |_| Ok(Value::Int(-1)));
+    assert!(matches!(
+        eval(&mut interp, "len(\"abc\")\n"),
+        Ok(Value::Int(-1))
+    ));
+}
+
+#[test]
+fn map_and_filter_call_host_functions() {
+    let mut interp = Interpreter::new();
+    interp.register_fn("shout", |args| {
+        let s: &str = (&args[0]).try_into()?;
+        Ok(Value::from(s.to_uppercase()))
+    });
+    interp.register_fn("long", |args| {
+        let s: &str = (&args[0]).try_into()?;
+        Ok(Value::Bool(s.len() > 2))
+    });
+    let shouted = eval(&mut interp, "map(filter([\"hi\", \"hey\"], long), shout)\n").unwrap();
+    assert_eq!(shouted, Value::List(vec![Value::from("HEY")]));
+    let err = eval(&mut interp, "map([\"a\", 1], shout)\n").unwrap_err();
+    assert!(err.to_string().starts_with("map: element 1: "), "{}", err);
+}
+
+#[test]
+fn host_values_are_opaque_whatever_their_type_is_called() {
+    let mut interp = Interpreter::new();
+    interp.set_var("handle", Value::Opaque(Opaque::new("string", 5u8)));
+    assert_eq!(
+        eval(
+            &mut interp,
+            "[typeof(handle), is_string(handle), is_opaque(handle)]\n"
+        )
+        .unwrap()
+        .to_string(),
+        "[opaque, false, true]"
+    );
+}
@@ -0,0 +1,54 @@
+// json_parse and json_show.
+
+use zac_lib::interp::{Interpreter, Value};
+
+fn call(interp: &mut Interpreter, name: &str, arg: Value) -> anyhow::Result<Value> {
+    interp.set_var("arg", arg);
+    Ok(interp.run_source(&format!("{}(arg)\n", name))?)
+}
+
+#[test]
+fn round_trip() {
+    let mut interp = Interpreter::new();
+    let doc = r#"{"a":[1,-2,{"b":null,"c":[true,false,[]]}],"d":{},"e":"é\n\"q\"","f":170141183460469231731687303715884105727}"#;
+    let parsed = call(&mut interp, "json_parse", doc.into()).unwrap();
+    let shown = call(&mut interp, "json_show", parsed.clone()).unwrap();
+    assert_eq!(shown, Value::String("{\"a\":[1,-2,{\"b\":null,\"c\":[true,false,[]]}],\"d\":{},\"e\":\"é\\n\\\"q\\\"\",\"f\":170141183460469231731687303715884105727}".into()));
+    assert_eq!(call(&mut interp, "json_parse", shown).unwrap(), parsed);
+}
+
+#[test]
+fn whitespace_and_key_order_dont_matter() {
+    let mut interp = Interpreter::new();
+    let spaced = call(
+        &mut interp,
+        "json_parse",
+        " { \"b\" : [ 1 , 2 ] ,\n\"a\" : 0 } ".into(),
+    )
+    .unwrap();
+    let compact = call(&mut interp, "json_parse", "{\"a\":0,\"b\":[1,2]}".into()).unwrap();
+    assert_eq!(spaced, compact);
+}
+
+#[test]
+fn too_deep() {
+    let mut interp = Interpreter::new();
+    let deep = format!("{}{}", "[".repeat(100_000), "]".repeat(100_000));
+    let err = call(&mut interp, "json_parse", deep.into()).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "json_parse: the JSON is nested more than 128 deep"
+    );
+
+    // brackets in strings aren't nesting
+    let brackets = format!("[\"{}\"]", "[".repeat(1000));
+    assert!(call(&mut interp, "json_parse", brackets.into()).is_ok());
+
+    let err = interp
+        .run_source("let x = []\nlet i = 0\nwhile (i < 200) {\n  let x = [x]\n  let i = i + 1\n}\njson_show(x)\n")
+        .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "json_show: the value is nested more than 128 deep"
+    );
+}
@@ -0,0 +1,30 @@
+use zac_lib::parser::{line_col, Span};
+
+fn at(source: &str, start: usize) -> (usize, usize) {
+    line_col(source, Span { start, end: start })
+}
+
+#[test]
+fn first_line() {
+    assert_eq!(at("let x = 1", 0), (1, 1));
+    assert_eq!(at("let x = 1", 4), (1, 5));
+}
+
+#[test]
+fn after_newlines() {
+    let source = "let x = 1\nlet y = 2\n\nfoo";
+    assert_eq!(at(source, 10), (2, 1));
+    assert_eq!(at(source, 14), (2, 5));
+    assert_eq!(at(source, 21), (4, 1));
+}
+
+#[test]
+fn columns_count_chars_not_bytes() {
+    let source = "let s = \"héllo\" + x";
+    assert_eq!(at(source, source.find('x').unwrap()), (1, 19));
+}
+
+#[test]
+fn past_the_end_is_the_end() {
+    assert_eq!(at("ab\ncd", 100), (2, 3));
+}
@@ -0,0 +1,99 @@
+// Programs whose files are all kept in memory by the host.
+
+use std::collections::HashMap;
+use zac_lib::interp::{Interpreter, InterpreterBuilder, Value};
+use zac_lib::parser::{find_comments_mut, parser, Expr};
+use zac_lib::reassemble::output_code;
+use zac_lib::replace_comments_in_source_code;
+use zac_lib::vfs::MemFs;
+
+fn with_mem_fs(files: &[(&str, &str)]) -> (Interpreter, MemFs) {
+    let fs = MemFs::with_files(
+        files
+            .iter()
+            .map(|(path, contents)| (path.to_string(), contents.to_string()))
+            .collect(),
+    );
+    let mut interp = InterpreterBuilder::new()
+        .default_builtins()
+        .fs_builtins()
+        .build();
+    interp.set_fs(Box::new(fs.clone()));
+    (interp, fs)
+}
+
+fn run(interp: &mut Interpreter, source: &str) -> Result<Value, String> {
+    let program = parser::program(source).unwrap();
+    interp
+        .interp(&Expr::Block(program.block))
+        .map_err(|err| err.to_string())
+}
+
+fn strings(strings: &[&str]) -> Value {
+    Value::List(
+        strings
+            .iter()
+            .map(|s| Value::String(s.to_string()))
+            .collect(),
+    )
+}
+
+#[test]
+fn reads_and_writes_only_the_map() {
+    let (mut interp, fs) = with_mem_fs(&[
+        ("in/first.txt", "ada"),
+        ("in/second.txt", "grace"),
+        ("in/skip.txt", ""),
+    ]);
+    let source = "let names = [read_file(\"in/first.txt\"), read_file(\"in/second.txt\")]
+let i = 0
+while (i < 2) {
+  write_file(\"out/${names(i)}.txt\", \"hello ${names(i)}\")
+  let i = i + 1
+}
+write_file(\"in/skip.txt\", \"done\")
+[list_dir(\"out\"), file_exists(\"out/ada.txt\"), file_exists(\"out/alan.txt\")]
+";
+    assert_eq!(
+        run(&mut interp, source),
+        Ok(Value::List(vec![
+            strings(&["ada.txt", "grace.txt"]),
+            Value::Bool(true),
+            Value::Bool(false)
+        ]))
+    );
+    let expected: HashMap<String, String> = [
+        ("in/first.txt", "ada"),
+        ("in/second.txt", "grace"),
+        ("in/skip.txt", "done"),
+        ("out/ada.txt", "hello ada"),
+        ("out/grace.txt", "hello grace"),
+    ]
+    .iter()
+    .map(|(path, contents)| (path.to_string(), contents.to_string()))
+    .collect();
+    assert_eq!(fs.files(), expected);
+}
+
+#[test]
+fn missing_files_are_errors() {
+    let (mut interp, fs) = with_mem_fs(&[]);
+    let err = run(&mut interp, "read_file(\"nope.txt\")\n").unwrap_err();
+    assert!(err.contains("no such file: nope.txt"), "{}", err);
+    assert!(fs.files().is_empty());
+}
+
+// writing comments back into the program is up to the host, and doesn't go through the
+// program's files
+#[test]
+fn comments_are_not_written_to_it() {
+    let (mut interp, fs) = with_mem_fs(&[]);
+    let mut program = parser::program("// #note\n// old\n\nlet #note = \"new\"\n").unwrap();
+    for (_, comment) in find_comments_mut(&mut program).unwrap() {
+        interp.add_comment(comment).unwrap();
+    }
+    interp.interp(&Expr::Block(program.block.clone())).unwrap();
+    replace_comments_in_source_code(&mut program, &mut interp).unwrap();
+    assert!(output_code(&program, &interp).contains("// new"));
+    assert!(fs.files().is_empty());
+}
@@ -0,0 +1,74 @@
+// min and max of any number of arguments, and abs.
+
+use zac_lib::interp::{Interpreter, Value};
+use zac_lib::parser::{parser, Expr};
+
+fn run(source: &str) -> Result<Value, String> {
+    let program = parser::program(&format!("{}\n", source)).unwrap();
+    Interpreter::new()
+        .interp(&Expr::Block(program.block))
+        .map_err(|err| err.to_string())
+}
+
+#[test]
+fn numbers() {
+    for (source, expected) in [
+        ("min(3, 1)", 1),
+        ("max(3, 1)", 3),
+        ("min(4, -2, 9, 0)", -2),
+        ("max(4, -2, 9, 0)", 9),
+        ("min(5, 5, 5)", 5),
+    ] {
+        assert_eq!(run(source), Ok(Value::Int(expected)), "{}", source);
+    }
+}
+
+#[test]
+fn strings() {
+    for (source, expected) in [
+        (r#"min("pear", "apple", "fig")"#, "apple"),
+        (r#"max("pear", "apple", "fig")"#, "pear"),
+    ] {
+        assert_eq!(
+            run(source),
+            Ok(Value::String(expected.into())),
+            "{}",
+            source
+        );
+    }
+}
+
+#[test]
+fn bad_arguments() {
+    for (source, error) in [
+        ("min()", "min needs at least 2 arguments, got 0"),
+        ("max(1)", "max needs at least 2 arguments, got 1"),
+        (r#"min(1, "a")"#, "min needs all numbers or all strings"),
+        ("max([1], [2])", "max needs all numbers or all strings"),
+    ] {
+        let err = run(source).unwrap_err();
+        assert!(err.contains(error), "{}: {}", source, err);
+    }
+}
+
+#[test]
+fn abs() {
+    for (source, expected) in [("abs(-7)", 7), ("abs(7)", 7), ("abs(0)", 0)] {
+        assert_eq!(run(source), Ok(Value::Int(expected)), "{}", source);
+    }
+    // there's no positive number as big as the smallest negative one
+    let err = run(&format!("abs(sub({}, 1))", i128::MIN + 1)).unwrap_err();
+    assert!(err.contains("integer overflow in abs"), "{}", err);
+}
+
+#[test]
+fn in_the_help_text() {
+    let help = zac_lib::run("// #help\n// nothing yet\n").unwrap();
+    for line in [
+        "min: min(a, b, ...) returns the smallest",
+        "max: max(a, b, ...) returns the largest",
+        "abs: abs(n) returns n without its sign",
+    ] {
+        assert!(help.contains(line), "{}\n{}", line, help);
+    }
+}
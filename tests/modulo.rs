@@ -0,0 +1,57 @@
+// mod gives Euclidean remainders: never negative, whatever the signs of its arguments.
+
+use zac_lib::interp::{Interpreter, Value};
+use zac_lib::parser::{parser, Expr};
+
+fn run(source: &str) -> Result<Value, String> {
+    let program = parser::program(source).unwrap();
+    Interpreter::new()
+        .interp(&Expr::Block(program.block))
+        .map_err(|err| err.to_string())
+}
+
+#[test]
+fn negative_operands() {
+    for (a, b, remainder) in [
+        (7, 3, 1),
+        (-7, 3, 2),
+        (7, -3, 1),
+        (-7, -3, 2),
+        (-1, 3, 2),
+        (6, -3, 0),
+        (0, 5, 0),
+    ] {
+        assert_eq!(
+            run(&format!("mod({}, {})\n", a, b)),
+            Ok(Value::Int(remainder)),
+            "mod({}, {})",
+            a,
+            b
+        );
+    }
+}
+
+#[test]
+fn by_zero_is_an_error() {
+    let err = run("mod(5, 0)\n").unwrap_err();
+    assert!(err.contains("the divisor can't be zero"), "{}", err);
+}
+
+#[test]
+fn the_help_text_says_which_remainder() {
+    let help = zac_lib::run("// #help\n// nothing yet\n").unwrap();
+    assert!(
+        help.contains("mod: mod(a, b) returns the remainder"),
+        "{}",
+        help
+    );
+    assert_eq!(
+        run("doc(mod)\n"),
+        Ok(Value::String(
+            "mod(a, b) returns the remainder of a divided by b. It's never negative, even when a \
+            or b are, so mod(-1, 3) is 2 and mod(7, -3) is 1. It goes with div, so a is \
+            div(a, b) * b + mod(a, b)"
+                .into()
+        ))
+    );
+}
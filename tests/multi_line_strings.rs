@@ -0,0 +1,41 @@
+// """triple quoted""" strings are kept exactly as written.
+
+use zac_lib::interp::{Interpreter, Value};
+use zac_lib::parser::{parser, Expr};
+
+fn eval(source: &str) -> Value {
+    let program = parser::program(source).unwrap();
+    Interpreter::new()
+        .interp(&Expr::Block(program.block))
+        .unwrap()
+}
+
+fn string(s: &str) -> Value {
+    Value::String(s.into())
+}
+
+#[test]
+fn leading_indentation_is_kept() {
+    assert_eq!(
+        eval("let s = \"\"\"one\n  two\n    three\"\"\"\ns\n"),
+        string("one\n  two\n    three")
+    );
+    assert_eq!(
+        eval("\"\"\"\n  indented\n\"\"\"\n"),
+        string("\n  indented\n")
+    );
+}
+
+#[test]
+fn escapes_and_interpolation_are_left_alone() {
+    assert_eq!(
+        eval("let x = 1\n\"\"\"a\\nb ${x} \"quoted\" too\"\"\"\n"),
+        string("a\\nb ${x} \"quoted\" too")
+    );
+}
+
+#[test]
+fn written_back_as_they_were() {
+    let source = "let s = \"\"\"one\n  two \\n ${three}\"\"\"\n";
+    assert_eq!(zac_lib::run(source).unwrap(), source);
+}
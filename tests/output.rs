@@ -0,0 +1,33 @@
+// Where print writes to.
+
+use std::cell::RefCell;
+use std::io::Write;
+use std::rc::Rc;
+use zac_lib::interp::Interpreter;
+
+#[derive(Clone, Default)]
+struct Captured(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn print_writes_to_the_output() {
+    let captured = Captured::default();
+    let mut interp = Interpreter::new();
+    interp.set_output(Box::new(captured.clone()));
+    let source = "print(1)\ndefn f(x) {\n  debug(x)\n}\nf(\"a\\nb\")\nprint([])\n";
+    interp.run_source(source).unwrap();
+    // a debug inside a function goes to the same place as print
+    assert_eq!(
+        String::from_utf8(captured.0.borrow().clone()).unwrap(),
+        "1\nString(\"a\\nb\")\n[]\n"
+    );
+}
@@ -0,0 +1,62 @@
+// Writing a program straight to an io::Write instead of into a String.
+
+use std::io;
+use zac_lib::interp::Interpreter;
+use zac_lib::parser::parse_program;
+use zac_lib::reassemble::{output_code, output_code_to, AssembleOptions};
+
+const SOURCE: &str =
+    "// #notes\n// some notes\n\ndefn f(a) {\n\n  while (false) {\n    a\n  }\n}\nf(1)\n";
+
+#[test]
+fn writes_the_same_as_output_code() {
+    let program = parse_program(SOURCE).unwrap();
+    let interp = Interpreter::new();
+    let mut written = vec![];
+    output_code_to(&program, &interp, &AssembleOptions::default(), &mut written).unwrap();
+    assert_eq!(String::from_utf8(written).unwrap(), SOURCE);
+    assert_eq!(output_code(&program, &interp), SOURCE);
+}
+
+// takes the first `remaining` bytes, then fails
+struct FailingWriter {
+    remaining: usize,
+    written: Vec<u8>,
+}
+
+impl io::Write for FailingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.remaining == 0 {
+            return Err(io::Error::new(io::ErrorKind::Other, "disk full"));
+        }
+        let n = buf.len().min(self.remaining);
+        self.remaining -= n;
+        self.written.extend_from_slice(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn write_errors_are_returned() {
+    let program = parse_program(SOURCE).unwrap();
+    for remaining in [0, 1, 20, SOURCE.len() - 1] {
+        let mut w = FailingWriter {
+            remaining,
+            written: vec![],
+        };
+        let err = output_code_to(
+            &program,
+            &Interpreter::new(),
+            &AssembleOptions::default(),
+            &mut w,
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "disk full");
+        // everything up to the failure was written, and nothing after it
+        assert_eq!(w.written, SOURCE.as_bytes()[..remaining]);
+    }
+}
@@ -0,0 +1,82 @@
+// Arithmetic at the ends of the i128 range reports overflow instead of panicking or wrapping.
+
+use zac_lib::interp::{Interpreter, Value};
+use zac_lib::parser::{parser, Expr};
+
+const MAX: i128 = i128::MAX;
+const MIN: i128 = i128::MIN;
+
+fn run(source: &str) -> Result<Value, String> {
+    // MIN can't be written as a literal, since its digits on their own are more than MAX
+    let source = format!(
+        "let max = {}\nlet min = sub({}, 1)\n{}\n",
+        MAX,
+        MIN + 1,
+        source
+    );
+    let program = parser::program(&source).unwrap();
+    Interpreter::new()
+        .interp(&Expr::Block(program.block))
+        .map_err(|err| err.to_string())
+}
+
+fn assert_int(source: &str, expected: i128) {
+    assert_eq!(run(source), Ok(Value::Int(expected)), "{}", source);
+}
+
+// call is what the error says was being done, like add(1, 2)
+fn assert_overflows(source: &str, call: String) {
+    let err = run(source).unwrap_err();
+    assert!(
+        err.contains(&format!("integer overflow in {}", call)),
+        "{}: {}",
+        source,
+        err
+    );
+}
+
+#[test]
+fn add() {
+    assert_overflows("add(max, 1)", format!("add({}, 1)", MAX));
+    assert_overflows("add(min, -1)", format!("add({}, -1)", MIN));
+    assert_overflows("max + max", format!("add({}, {})", MAX, MAX));
+    assert_int("add(max, 0)", MAX);
+    assert_int("add(min, max)", -1);
+}
+
+#[test]
+fn sub() {
+    assert_overflows("sub(min, 1)", format!("sub({}, 1)", MIN));
+    assert_overflows("sub(max, -1)", format!("sub({}, -1)", MAX));
+    assert_overflows("sub(0, min)", format!("sub(0, {})", MIN));
+    assert_overflows("min - 1", format!("sub({}, 1)", MIN));
+    assert_int("sub(-1, min)", MAX);
+    assert_int("sub(max, max)", 0);
+}
+
+#[test]
+fn mul() {
+    assert_overflows("mul(max, 2)", format!("mul({}, 2)", MAX));
+    assert_overflows("mul(min, -1)", format!("mul({}, -1)", MIN));
+    assert_overflows("min * min", format!("mul({}, {})", MIN, MIN));
+    assert_int("mul(max, -1)", MIN + 1);
+    assert_int("mul(min, 1)", MIN);
+}
+
+#[test]
+fn div() {
+    assert_overflows("div(min, -1)", format!("div({}, -1)", MIN));
+    assert_overflows("min / -1", format!("div({}, -1)", MIN));
+    assert_int("div(max, -1)", MIN + 1);
+    assert_int("div(min, 1)", MIN);
+    // rounded down, leaving mod(min, max) = max - 1
+    assert_int("div(min, max)", -2);
+}
+
+#[test]
+fn modulo() {
+    assert_overflows("mod(min, -1)", format!("mod({}, -1)", MIN));
+    assert_int("mod(max, 2)", 1);
+    assert_int("mod(min, max)", MAX - 1);
+    assert_int("mod(min, 2)", 0);
+}
@@ -0,0 +1,69 @@
+// Going between maps and lists of [key, value] pairs.
+
+use zac_lib::interp::{Interpreter, Value};
+use zac_lib::parser::{parser, Expr};
+
+fn run(source: &str) -> Result<Value, String> {
+    let program = parser::program(source).unwrap();
+    Interpreter::new()
+        .interp(&Expr::Block(program.block))
+        .map_err(|err| err.to_string())
+}
+
+fn pair(k: &str, v: i128) -> Value {
+    Value::List(vec![Value::String(k.into()), Value::Int(v)])
+}
+
+#[test]
+fn round_trip() {
+    assert_eq!(
+        run("to_pairs(from_pairs([[\"b\", 2], [\"a\", 1]]))\n"),
+        // in key order
+        Ok(Value::List(vec![pair("a", 1), pair("b", 2)]))
+    );
+}
+
+#[test]
+fn duplicate_keys() {
+    let pairs = "[[\"a\", 1], [\"b\", 2], [\"a\", 3]]";
+    for source in [
+        format!("from_pairs({})\n", pairs),
+        format!("from_pairs({}, false)\n", pairs),
+    ] {
+        let err = run(&source).unwrap_err();
+        assert!(err.contains("duplicate key"), "{}", err);
+        assert!(err.contains("at element 2"), "{}", err);
+    }
+    assert_eq!(
+        run(&format!("to_pairs(from_pairs({}, true))\n", pairs)),
+        Ok(Value::List(vec![pair("a", 3), pair("b", 2)]))
+    );
+}
+
+#[test]
+fn empty() {
+    let empty = Ok(Value::List(vec![]));
+    assert_eq!(run("to_pairs(from_pairs([]))\n"), empty);
+    assert_eq!(run("to_pairs(from_pairs([], true))\n"), empty);
+}
+
+#[test]
+fn not_pairs() {
+    for source in ["from_pairs([1])\n", "from_pairs([[1, 2, 3]])\n"] {
+        let err = run(source).unwrap_err();
+        assert!(err.contains("should be a [key, value] list"), "{}", err);
+    }
+}
+
+#[test]
+fn documented_with_examples() {
+    for name in ["to_pairs", "from_pairs"] {
+        let doc = match run(&format!("doc({})\n", name)) {
+            Ok(Value::String(doc)) => doc,
+            otherwise => panic!("no doc for {}: {:?}", name, otherwise),
+        };
+        // an example of it being used, and what that gives back
+        assert!(doc.contains("from_pairs([["), "{}", doc);
+        assert!(doc.contains(") is "), "{}", doc);
+    }
+}
@@ -0,0 +1,137 @@
+use zac_lib::parser::{parse_program, parse_program_lenient, to_description, ParseDiagnostic};
+
+fn diagnostic(source: &str) -> ParseDiagnostic {
+    parse_program(source).expect_err("should fail to parse")
+}
+
+#[test]
+fn unterminated_call() {
+    let diagnostic = diagnostic("let x = foo(1, 2\nlet y = 3\n");
+    assert_eq!((diagnostic.line, diagnostic.column), (2, 1));
+    assert_eq!(diagnostic.line_text, "let y = 3");
+    assert!(diagnostic.expected.contains(&"\")\"".to_string()));
+    assert!(diagnostic.expected.contains(&"\",\"".to_string()));
+}
+
+#[test]
+fn expected_is_sorted_without_repeats() {
+    for source in [
+        "let x = foo(1, 2\nlet y = 3\n",
+        "let x = \n",
+        "let y = x @ 2\n",
+    ] {
+        let expected = diagnostic(source).expected;
+        let mut sorted = expected.clone();
+        sorted.sort();
+        sorted.dedup();
+        assert_eq!(expected, sorted);
+    }
+}
+
+#[test]
+fn bad_keyword() {
+    let diagnostic = diagnostic("let while = 2\n");
+    assert_eq!(
+        diagnostic.to_string(),
+        "line 1, col 10: expected a name, `while` is a keyword\n  let while = 2\n           ^"
+    );
+}
+
+#[test]
+fn illegal_character() {
+    let diagnostic = diagnostic("let x = 1\nlet y = x @ 2\n");
+    assert_eq!((diagnostic.line, diagnostic.column), (2, 11));
+    assert!(diagnostic
+        .to_string()
+        .ends_with("\n  let y = x @ 2\n            ^"));
+}
+
+#[test]
+fn whitespace_isnt_listed_as_expected() {
+    let diagnostic = diagnostic("let x = foo(1, 2\nlet y = 3\n");
+    assert!(!diagnostic
+        .expected
+        .iter()
+        .any(|token| token.contains("' '")));
+}
+
+#[test]
+fn caret_keeps_tabs() {
+    let diagnostic = diagnostic("defn f() {\n\tlet while = 1\n}\n");
+    assert!(diagnostic
+        .to_string()
+        .ends_with("\n  \tlet while = 1\n  \t         ^"));
+}
+
+#[test]
+fn lenient_reports_every_bad_statement() {
+    let source = "let a = 1 @ 2\nlet b = 2\nlet while = 3\nlet c = b\n";
+    let (program, diagnostics) = parse_program_lenient(source);
+    assert_eq!(
+        diagnostics
+            .iter()
+            .map(|d| (d.line, d.column))
+            .collect::<Vec<_>>(),
+        vec![(1, 11), (3, 10)]
+    );
+    assert_eq!(diagnostics[1].line_text, "let while = 3");
+    // what's left is the statements that did parse
+    let program = program.unwrap();
+    assert_eq!(program.block.exprs().count(), 2);
+}
+
+#[test]
+fn lenient_skips_whole_functions() {
+    let source = "defn f() {\n  let x = )\n}\nlet y = 1\nlet z = @\n";
+    let (_, diagnostics) = parse_program_lenient(source);
+    assert_eq!(
+        diagnostics.iter().map(|d| d.line).collect::<Vec<_>>(),
+        vec![2, 5]
+    );
+}
+
+#[test]
+fn lenient_blames_an_unfinished_statement() {
+    let source = "let x = foo(1, 2\nlet y = 3\nlet z = @\n";
+    let (program, diagnostics) = parse_program_lenient(source);
+    assert_eq!(
+        diagnostics
+            .iter()
+            .map(|d| (d.line, d.column))
+            .collect::<Vec<_>>(),
+        vec![(2, 1), (3, 9)]
+    );
+    assert_eq!(program.unwrap().block.exprs().count(), 1);
+}
+
+#[test]
+fn lenient_is_strict_when_nothing_is_wrong() {
+    let source = "let x = 1\nx + 1\n";
+    let (program, diagnostics) = parse_program_lenient(source);
+    assert!(diagnostics.is_empty());
+    assert_eq!(
+        to_description(&program.unwrap()),
+        to_description(&parse_program(source).unwrap())
+    );
+}
+
+#[test]
+fn int_literal_out_of_range() {
+    assert!(parse_program(&format!("let x = {}\n", i128::MAX)).is_ok());
+    assert!(parse_program(&format!("let x = {}\n", i128::MIN)).is_ok());
+    let one_past_max = "170141183460469231731687303715884105728";
+    assert_eq!(
+        diagnostic(&format!("let x = {}\n", one_past_max)).to_string(),
+        format!(
+            "line 1, col 48: expected a smaller number, integer literal out of range for i128\n  let x = {}\n{}^",
+            one_past_max,
+            " ".repeat(49)
+        )
+    );
+    let diagnostic = diagnostic(&format!("print({})\n", "9".repeat(10_000)));
+    assert_eq!((diagnostic.line, diagnostic.column), (1, 10_007));
+    assert_eq!(
+        diagnostic.expected,
+        vec!["a smaller number, integer literal out of range for i128"]
+    );
+}
@@ -0,0 +1,55 @@
+// Running programs the way the web playground does.
+
+use zac_lib::playground;
+
+#[test]
+fn print_is_captured() {
+    let run =
+        playground::run("// #greeting\n// hi\n\nprint(#greeting, 2)\nlet #greeting = \"bye\"\n");
+    assert_eq!(run.output, "hi 2\n");
+    assert_eq!(run.result.as_deref(), Some("bye"));
+    assert_eq!(run.error, None);
+    assert_eq!(
+        run.comments,
+        vec![("greeting".to_owned(), "bye".to_owned())]
+    );
+    assert_eq!(
+        run.source.as_deref(),
+        Some("// #greeting\n// bye\n\nprint(#greeting, 2)\nlet #greeting = \"bye\"\n")
+    );
+}
+
+#[test]
+fn errors() {
+    let run = playground::run("print(1)\nnope\n");
+    assert_eq!(run.output, "1\n");
+    assert_eq!(run.result, None);
+    assert_eq!(
+        run.error.as_deref(),
+        Some("line 2, col 1: undefined name nope\n  nope\n  ^^^^")
+    );
+    assert!(playground::run("let x = )\n").error.is_some());
+}
+
+#[test]
+fn no_files_or_environment() {
+    let run = playground::run("read_file(\"/etc/hostname\")\n");
+    assert_eq!(
+        run.error.as_deref().and_then(|err| err.lines().next()),
+        Some("line 1, col 1: undefined name read_file"),
+    );
+    let run = playground::run("env(\"HOME\")\n");
+    assert_eq!(
+        run.error.as_deref().and_then(|err| err.lines().next()),
+        Some("line 1, col 1: undefined name env"),
+    );
+}
+
+#[test]
+fn format() {
+    assert_eq!(
+        playground::format("let x = add(1,2)\n").unwrap(),
+        "let x = add(1, 2)\n"
+    );
+    assert!(playground::format("let x = )\n").is_err());
+}
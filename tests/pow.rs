@@ -0,0 +1,55 @@
+// pow, for whole number exponents.
+
+use zac_lib::interp::{Interpreter, Value};
+use zac_lib::parser::{parser, Expr};
+
+fn run(source: &str) -> Result<Value, String> {
+    let program = parser::program(&format!("{}\n", source)).unwrap();
+    Interpreter::new()
+        .interp(&Expr::Block(program.block))
+        .map_err(|err| err.to_string())
+}
+
+#[test]
+fn powers() {
+    for (source, expected) in [
+        ("pow(2, 10)", 1024),
+        ("pow(-3, 3)", -27),
+        ("pow(-3, 2)", 9),
+        ("pow(7, 1)", 7),
+        ("pow(7, 0)", 1),
+        ("pow(0, 5)", 0),
+        ("pow(2, 126)", 1 << 126),
+    ] {
+        assert_eq!(run(source), Ok(Value::Int(expected)), "{}", source);
+    }
+}
+
+#[test]
+fn zero_to_the_zero_is_one() {
+    assert_eq!(run("pow(0, 0)"), Ok(Value::Int(1)));
+}
+
+// exponents too big for the power to be worked out directly
+#[test]
+fn huge_exponents() {
+    let huge = 1_i128 << 40;
+    for (source, expected) in [
+        (format!("pow(0, {})", huge), 0),
+        (format!("pow(1, {})", huge), 1),
+        (format!("pow(-1, {})", huge), 1),
+        (format!("pow(-1, {})", huge + 1), -1),
+    ] {
+        assert_eq!(run(&source), Ok(Value::Int(expected)), "{}", source);
+    }
+    let err = run(&format!("pow(2, {})", huge)).unwrap_err();
+    assert!(err.contains("integer overflow in pow"), "{}", err);
+}
+
+#[test]
+fn errors() {
+    let err = run("pow(2, 127)").unwrap_err();
+    assert!(err.contains("integer overflow in pow(2, 127)"), "{}", err);
+    let err = run("pow(2, -1)").unwrap_err();
+    assert!(err.contains("the exponent can't be negative"), "{}", err);
+}
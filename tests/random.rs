@@ -0,0 +1,89 @@
+// rand, rand_range and rand_seed.
+
+use zac_lib::interp::{Interpreter, InterpreterBuilder, Value};
+
+fn ints(val: Value) -> Vec<i128> {
+    match val {
+        Value::List(items) => items
+            .into_iter()
+            .map(|item| match item {
+                Value::Int(i) => i,
+                otherwise => panic!("{:?}", otherwise),
+            })
+            .collect(),
+        otherwise => panic!("{:?}", otherwise),
+    }
+}
+
+fn draws(interp: &mut Interpreter, call: &str) -> Vec<i128> {
+    let source = format!(
+        "let got = []\nlet i = 0\nwhile (i < 200) {{\n  let got = push(got, {})\n  let i = i + 1\n}}\ngot\n",
+        call
+    );
+    ints(interp.run_source(&source).unwrap())
+}
+
+#[test]
+fn seeding_repeats_the_sequence() {
+    let mut interp = Interpreter::new();
+    interp.run_source("rand_seed(7)\n").unwrap();
+    let first = draws(&mut interp, "rand()");
+    interp.run_source("rand_seed(7)\n").unwrap();
+    assert_eq!(draws(&mut interp, "rand()"), first);
+
+    // and in another interpreter too
+    let mut other = Interpreter::new();
+    other.run_source("rand_seed(7)\n").unwrap();
+    assert_eq!(draws(&mut other, "rand()"), first);
+}
+
+#[test]
+fn unseeded_runs_differ() {
+    let first = draws(&mut Interpreter::new(), "rand()");
+    let second = draws(&mut Interpreter::new(), "rand()");
+    assert_ne!(first, second);
+}
+
+#[test]
+fn rand_is_below_2_to_the_31() {
+    let got = draws(&mut Interpreter::new(), "rand()");
+    assert!(got.iter().all(|&n| (0..1 << 31).contains(&n)), "{:?}", got);
+}
+
+#[test]
+fn rand_range_includes_lo_but_not_hi() {
+    let mut interp = Interpreter::new();
+    let got = draws(&mut interp, "rand_range(-1, 2)");
+    assert!(got.iter().all(|n| [-1, 0, 1].contains(n)), "{:?}", got);
+    for n in [-1, 0, 1] {
+        assert!(got.contains(&n), "{} never came up in {:?}", n, got);
+    }
+
+    assert!(draws(&mut interp, "rand_range(5, 6)")
+        .iter()
+        .all(|&n| n == 5));
+
+    // the whole of i128, which is more numbers than an i128 can count
+    let got = interp
+        .run_source("rand_range(-170141183460469231731687303715884105727 - 1, 170141183460469231731687303715884105727)\n")
+        .unwrap();
+    assert!(matches!(got, Value::Int(n) if n != i128::MAX), "{:?}", got);
+}
+
+#[test]
+fn rand_range_needs_hi_above_lo() {
+    let mut interp = Interpreter::new();
+    let err = interp.run_source("rand_range(2, 1)\n").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "rand_range(2, 1) has nothing to pick from, hi has to be more than lo"
+    );
+    assert!(interp.run_source("rand_range(1)\n").is_err());
+    assert!(interp.run_source("rand_range(1, \"2\")\n").is_err());
+}
+
+#[test]
+fn random_needs_random_builtins() {
+    let mut interp = InterpreterBuilder::new().core_builtins().build();
+    assert!(interp.run_source("rand()\n").is_err());
+}
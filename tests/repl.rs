@@ -0,0 +1,82 @@
+// Giving the interpreter a line at a time, like a REPL does.
+
+use zac_lib::error::SoldierError;
+use zac_lib::interp::{EvalOutcome, Interpreter, Value};
+
+fn value(val: impl Into<Value>) -> EvalOutcome {
+    EvalOutcome::Value(val.into())
+}
+
+#[test]
+fn a_loop_one_line_at_a_time() {
+    let mut interp = Interpreter::new();
+    assert_eq!(interp.eval_line("let i = 0").unwrap(), value(0));
+    assert_eq!(interp.eval_line("let total = 0").unwrap(), value(0));
+    assert_eq!(
+        interp.eval_line("while (i < 4) {").unwrap(),
+        EvalOutcome::NeedMoreInput
+    );
+    assert_eq!(
+        interp.eval_line("  let i = i + 1").unwrap(),
+        EvalOutcome::NeedMoreInput
+    );
+    assert_eq!(
+        interp.eval_line("  let total = total + i").unwrap(),
+        EvalOutcome::NeedMoreInput
+    );
+    assert!(matches!(
+        interp.eval_line("}").unwrap(),
+        EvalOutcome::Value(_)
+    ));
+    assert_eq!(interp.eval_line("total").unwrap(), value(10));
+}
+
+#[test]
+fn unfinished_calls_and_strings_wait_for_more() {
+    let mut interp = Interpreter::new();
+    assert_eq!(
+        interp.eval_line("add(1,").unwrap(),
+        EvalOutcome::NeedMoreInput
+    );
+    assert_eq!(interp.eval_line("2)").unwrap(), value(3));
+    assert_eq!(
+        interp.eval_line("let s = \"a").unwrap(),
+        EvalOutcome::NeedMoreInput
+    );
+    assert_eq!(interp.eval_line("b\"").unwrap(), value("a\nb"));
+}
+
+#[test]
+fn invalid_input_is_an_error_and_is_dropped() {
+    let mut interp = Interpreter::new();
+    let err = interp.eval_line("let x = )").unwrap_err();
+    assert!(matches!(err, SoldierError::Parse(_)), "{:?}", err);
+    // nothing is left over from the bad line
+    assert_eq!(interp.eval_line("1").unwrap(), value(1));
+}
+
+#[test]
+fn comments_defined_interactively() {
+    let mut interp = Interpreter::new();
+    assert_eq!(
+        interp.eval_line("// #greeting").unwrap(),
+        EvalOutcome::NeedMoreInput
+    );
+    assert_eq!(
+        interp.eval_line("// hello").unwrap(),
+        EvalOutcome::NeedMoreInput
+    );
+    assert!(matches!(
+        interp.eval_line("").unwrap(),
+        EvalOutcome::Value(_)
+    ));
+    assert_eq!(interp.eval_line("#greeting").unwrap(), value("hello"));
+}
+
+#[test]
+fn runtime_errors_keep_what_came_before() {
+    let mut interp = Interpreter::new();
+    interp.eval_line("let x = 5").unwrap();
+    assert!(interp.eval_line("nope").is_err());
+    assert_eq!(interp.eval_line("x").unwrap(), value(5));
+}
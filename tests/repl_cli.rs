@@ -0,0 +1,52 @@
+// The REPL zac starts with no arguments, driven through stdin.
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+fn repl(input: &str) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_zac"))
+        .arg("repl")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn results_are_shown_and_variables_persist() {
+    assert_eq!(
+        repl("let x = 2\nlet xs = [x, \"a\"]\nwhile (x < 5) {\n  let x = x + 1\n}\nx\n"),
+        "> 2\n> [2, a]\n> .. .. 5\n> 5\n> \n"
+    );
+}
+
+#[test]
+fn commands() {
+    let output = repl("let x = 1\n:vars\n:history\n:1\n:help\n:quit\nlet y = 22\n");
+    assert!(
+        output.starts_with("> 1\n> x = 1\n>  1 let x = 1\n> 1\n> :help"),
+        "{}",
+        output
+    );
+    // the generated help lists the builtins
+    assert!(output.contains("add"), "{}", output);
+    // nothing after :quit runs
+    assert!(!output.contains("22"), "{}", output);
+}
+
+#[test]
+fn errors_dont_end_the_session() {
+    assert_eq!(
+        repl("nope\nlet x = )\n1\n"),
+        "> error: line 1, col 1: undefined name nope\n  nope\n  ^^^^\n> error: line 1, col 9: expected one of \"#\", \"(\", \"-\", \"/\", \"/*\", \"0\", \"[\", \"\\\"\", \"\\\"\\\"\\\"\", \"defn\", \"do\", \"false\", \"if\", \"let\", \"loop\", \"null\", \"true\", \"while\", ['1' ..= '9'], ['a'..='z' | 'A'..='Z' | '_']\n  let x = )\n          ^\n> 1\n> \n"
+    );
+}
@@ -0,0 +1,198 @@
+// Programs that are put back together without being run should come out exactly as written.
+
+use itertools::Itertools;
+use zac_lib::interp::Interpreter;
+use zac_lib::parser::{find_comments_mut, parse_program};
+use zac_lib::reassemble::output_code;
+
+fn reassemble(source: &str) -> String {
+    let program = parse_program(source).unwrap();
+    output_code(&program, &Interpreter::new())
+}
+
+fn assert_roundtrips(source: &str) {
+    assert_eq!(reassemble(source), source);
+}
+
+#[test]
+fn blank_lines_between_sections() {
+    assert_roundtrips("let x = 1\n\n\nlet y = 2\n\nlet z = 3\n");
+}
+
+#[test]
+fn blank_lines_inside_blocks() {
+    assert_roundtrips("defn f() {\n\n  let a = 1\n\n\n  a\n\n}\nf()\n");
+    assert_roundtrips("while (false) {\n\n  let a = 1\n}\n");
+}
+
+#[test]
+fn blank_lines_around_comments() {
+    assert_roundtrips("// #notes\n// some notes\n\n\n/* block */\n\nlet x = 1\n");
+}
+
+#[test]
+fn assignments() {
+    assert_roundtrips("let x = 5\n");
+    assert_roundtrips("let #help = show(x)\n");
+}
+
+// every body of up to three lines made from lines that are easy to get wrong
+fn comment_bodies() -> Vec<String> {
+    let lines = [
+        "",
+        "word",
+        "two words",
+        "  indented",
+        "#not-a-name",
+        "// slashes",
+    ];
+    let mut bodies = vec![];
+    for len in 1..=3 {
+        for combination in (0..len).map(|_| lines.iter()).multi_cartesian_product() {
+            bodies.push(combination.into_iter().join("\n"));
+        }
+    }
+    bodies
+}
+
+fn named_comment_bodies(source: &str) -> Vec<(String, String)> {
+    let mut program = parse_program(source).unwrap();
+    find_comments_mut(&mut program)
+        .unwrap()
+        .into_iter()
+        .map(|(name, comment)| (name, comment.body.clone()))
+        .sorted()
+        .collect()
+}
+
+#[test]
+fn named_comments_keep_their_name_and_body() {
+    for body in comment_bodies() {
+        for source in [
+            "// #notes\nlet x = 1\n".to_owned(),
+            "defn f() {\n  // #notes\n  let x = 1\n}\n".to_owned(),
+        ] {
+            let mut program = parse_program(&source).unwrap();
+            let mut comments = find_comments_mut(&mut program).unwrap();
+            comments.get_mut("notes").unwrap().body = body.clone();
+            let once = output_code(&program, &Interpreter::new());
+            assert_eq!(
+                named_comment_bodies(&once),
+                vec![("notes".to_owned(), body.clone())],
+                "{:?}",
+                once
+            );
+            assert_eq!(reassemble(&once), once);
+        }
+    }
+}
+
+fn run_and_patch(source: &str) -> String {
+    let program = parse_program(source).unwrap();
+    let mut interp = Interpreter::new();
+    interp.set_source(source);
+    interp.run_program(&program).unwrap();
+    interp.writeback(&program).unwrap()
+}
+
+#[test]
+fn patching_changes_only_the_changed_comment() {
+    let source = "let   x =  1\n\n// #count\n// 0\n\n  // #untouched\n  // odd   spacing\nlet #count = \"1\"\nprint( x )\n";
+    assert_eq!(
+        run_and_patch(source),
+        "let   x =  1\n\n// #count\n// 1\n\n  // #untouched\n  // odd   spacing\nlet #count = \"1\"\nprint( x )\n"
+    );
+}
+
+#[test]
+fn patching_indents_changed_comments_like_the_original() {
+    let source = "defn f() {\n    // #c\n    // a\n    let #c = \"b\"\n}\nf()\n";
+    assert_eq!(
+        run_and_patch(source),
+        "defn f() {\n    // #c\n    // b\n    let #c = \"b\"\n}\nf()\n"
+    );
+}
+
+#[test]
+fn patching_fills_in_result_comments() {
+    assert_eq!(run_and_patch("1  +  2 // #\n"), "1  +  2 // #3\n");
+}
+
+#[test]
+fn writeback_appends_to_a_comment_in_place() {
+    let source =
+        "let  x = 1\n\n// #notes\n// started\nlet #notes = cat(#notes, \" done\")\n  print( x )\n";
+    assert_eq!(
+        run_and_patch(source),
+        "let  x = 1\n\n// #notes\n// started done\nlet #notes = cat(#notes, \" done\")\n  print( x )\n"
+    );
+}
+
+#[test]
+fn writeback_without_a_source_writes_the_whole_program() {
+    let source = "// #notes\n// started\nlet #notes = cat(#notes, \" done\")\n";
+    let program = parse_program(source).unwrap();
+    let mut interp = Interpreter::new();
+    interp.run_program(&program).unwrap();
+    assert_eq!(
+        interp.writeback(&program).unwrap(),
+        "// #notes\n// started done\nlet #notes = cat(#notes, \" done\")\n"
+    );
+}
+
+// the lines of before and after that differ, after the lines both start and end with
+fn changed_lines<'a>(before: &'a str, after: &'a str) -> (Vec<&'a str>, Vec<&'a str>) {
+    let before = before.split('\n').collect::<Vec<_>>();
+    let after = after.split('\n').collect::<Vec<_>>();
+    let prefix = before
+        .iter()
+        .zip(&after)
+        .take_while(|(b, a)| b == a)
+        .count();
+    let suffix = before[prefix..]
+        .iter()
+        .rev()
+        .zip(after[prefix..].iter().rev())
+        .take_while(|(b, a)| b == a)
+        .count();
+    (
+        before[prefix..before.len() - suffix].to_vec(),
+        after[prefix..after.len() - suffix].to_vec(),
+    )
+}
+
+#[test]
+fn writeback_grows_a_comment() {
+    let source =
+        "let  a = 1\n/* untouched */\n  // #c\n  // one\nlet #c = \"one\\n\\ntwo\"\nprint(  a )\n";
+    let after = run_and_patch(source);
+    assert_eq!(
+        changed_lines(source, &after),
+        (vec![], vec!["  //", "  // two"])
+    );
+}
+
+#[test]
+fn writeback_shrinks_a_comment() {
+    let source = "// #other\n// same\n\n// #c\n// one\n// two\n// three\nlet #c = \"one\"\nlet #other = \"same\"\n";
+    let after = run_and_patch(source);
+    assert_eq!(
+        changed_lines(source, &after),
+        (vec!["// two", "// three"], vec![])
+    );
+}
+
+#[test]
+fn writeback_at_the_end_without_a_newline() {
+    let source = "let #c = \"new\"\n// #c\n// old";
+    assert_eq!(run_and_patch(source), "let #c = \"new\"\n// #c\n// new");
+}
+
+#[test]
+fn writeback_to_a_trailing_comment() {
+    let source = "let x = 1   // #c old\nlet #c = \"new  text\"\n";
+    assert_eq!(
+        run_and_patch(source),
+        "let x = 1   // #c new  text\nlet #c = \"new  text\"\n"
+    );
+}
@@ -0,0 +1,393 @@
+// Builds random programs, writes them out and reads them back in, and checks the same program
+// comes back. The programs are ones the parser could have produced itself: a trailing comment
+// after `let x = 1` belongs to the 1, and two line comments with nothing between them would be
+// read back as one, so those are never generated. A failure prints the seed and the source.
+
+use zac_lib::interp::Interpreter;
+use zac_lib::parser::{
+    ast_eq, parse_program, Assignment, BinOp, Block, BlockEl, Comment, CommentStyle, Destructure,
+    Expr, FieldAccess, FuncDef, FunctionCall, If, IndexAssignment, InterpolationPart, Op, Pattern,
+    Program, Ref, Span, While,
+};
+use zac_lib::reassemble::output_code;
+
+const CASES: u64 = 2000;
+const MAX_DEPTH: usize = 3;
+
+// names that are easy to get wrong: the ones starting with a keyword or a constant have to stay
+// names
+const NAMES: &[&str] = &[
+    "a",
+    "b",
+    "total",
+    "x_1",
+    "item-count",
+    "done",
+    "letter",
+    "iffy",
+    "nullable",
+    "trueish",
+];
+const FIELDS: &[&str] = &["a", "name", "if", "while", "true", "let"];
+const CONSTANTS: &[&str] = &["true", "false", "null"];
+const STRING_PIECES: &[&str] = &[
+    "a", " ", "word", "\"", "\\", "$", "${", "{", "}", "\n", "\t", "é", "//", "#",
+];
+const MULTI_LINE_PIECES: &[&str] = &["a", " ", "  indented", "\n", "\"", "\\", "${x}", "é", "*/"];
+const COMMENT_LINES: &[&str] = &[
+    "",
+    "word",
+    "two words",
+    "  indented",
+    "#not-a-name",
+    "// slashes",
+    "${x}",
+    "\"quoted\"",
+];
+const BLOCK_COMMENT_PIECES: &[&str] = &["a", " ", "\n", "  indented", "*", "/", "#", "//"];
+const OPS: &[Op] = &[
+    Op::Add,
+    Op::Sub,
+    Op::Mul,
+    Op::Div,
+    Op::Eq,
+    Op::Neq,
+    Op::Lt,
+    Op::Gt,
+    Op::Lte,
+    Op::Gte,
+    Op::And,
+    Op::Or,
+];
+
+// xorshift, so a seed always makes the same program
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Self {
+        Self(seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1)
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+
+    fn one_in(&mut self, n: usize) -> bool {
+        self.below(n) == 0
+    }
+
+    fn pick<T: Clone>(&mut self, items: &[T]) -> T {
+        items[self.below(items.len())].clone()
+    }
+
+    fn name(&mut self) -> String {
+        self.pick(NAMES).to_owned()
+    }
+
+    fn names(&mut self, max: usize) -> Vec<String> {
+        (0..self.below(max + 1)).map(|_| self.name()).collect()
+    }
+
+    fn text(&mut self, pieces: &[&str], max: usize) -> String {
+        (0..self.below(max + 1))
+            .map(|_| self.pick(pieces))
+            .collect()
+    }
+}
+
+fn var(name: &str) -> Ref {
+    Ref::VarRef(name.to_owned(), Span::default())
+}
+
+fn comment(name: Option<String>, body: String, style: CommentStyle) -> Comment {
+    Comment {
+        name,
+        body,
+        style,
+        span: Span::default(),
+    }
+}
+
+fn target(rng: &mut Rng) -> Ref {
+    if rng.one_in(3) {
+        Ref::CommentRef(rng.name(), Span::default())
+    } else {
+        var(&rng.name())
+    }
+}
+
+// anything that can go on either side of an operator
+fn term(rng: &mut Rng, depth: usize) -> Expr {
+    let leaf = depth >= MAX_DEPTH;
+    match rng.below(if leaf { 5 } else { 10 }) {
+        0 => Expr::IntLiteral(rng.below(2000) as i128 - 1000, Span::default()),
+        1 => Expr::StringLiteral(rng.text(STRING_PIECES, 5), Span::default()),
+        2 => Expr::Ref(var(&rng.name())),
+        3 => Expr::Ref(var(&rng.pick(CONSTANTS))),
+        4 => {
+            let mut body = rng.text(MULTI_LINE_PIECES, 5).replace("\"\"\"", "\"");
+            if body.ends_with('"') {
+                body.push('a');
+            }
+            Expr::MultiLineString(body, Span::default())
+        }
+        5 => Expr::ListLiteral(
+            (0..rng.below(3)).map(|_| term(rng, depth + 1)).collect(),
+            Span::default(),
+        ),
+        6 => Expr::FunctionCall(FunctionCall {
+            r#ref: target(rng),
+            args: (0..rng.below(3)).map(|_| term(rng, depth + 1)).collect(),
+            span: Span::default(),
+        }),
+        7 => {
+            let expr = match rng.below(3) {
+                0 => Expr::Ref(var(&rng.name())),
+                _ => term(rng, MAX_DEPTH - 1),
+            };
+            match expr {
+                Expr::Ref(_) | Expr::FunctionCall(_) | Expr::FieldAccess(_) => {
+                    Expr::FieldAccess(FieldAccess {
+                        expr: Box::new(expr),
+                        field: rng.pick(FIELDS).to_owned(),
+                        span: Span::default(),
+                    })
+                }
+                other => other,
+            }
+        }
+        8 => Expr::BinOp(BinOp {
+            op: rng.pick(OPS),
+            lhs: Box::new(term(rng, depth + 1)),
+            rhs: Box::new(term(rng, depth + 1)),
+            span: Span::default(),
+        }),
+        _ => interpolation(rng, depth),
+    }
+}
+
+// literal parts are never empty or next to each other, since the parser would merge them
+fn interpolation(rng: &mut Rng, depth: usize) -> Expr {
+    let mut parts = vec![];
+    let exprs = rng.below(2) + 1;
+    for i in 0..exprs {
+        if rng.one_in(2) || i > 0 {
+            let literal = rng.text(STRING_PIECES, 3);
+            if !literal.is_empty() {
+                parts.push(InterpolationPart::Literal(literal));
+            }
+        }
+        parts.push(InterpolationPart::Expr(term(rng, depth + 1)));
+    }
+    if rng.one_in(2) {
+        parts.push(InterpolationPart::Literal("end".into()));
+    }
+    Expr::Interpolation(parts, Span::default())
+}
+
+// the value in a `let`, and where a trailing comment on that line ends up
+fn line_end(rng: &mut Rng, depth: usize) -> Expr {
+    let expr = term(rng, depth);
+    match rng.below(6) {
+        0 => Expr::TrailingComment(Box::new(expr), trailing_comment(rng)),
+        1 => Expr::ResultComment(0, Box::new(expr), Span::default()),
+        _ => expr,
+    }
+}
+
+fn trailing_comment(rng: &mut Rng) -> Comment {
+    if rng.one_in(2) {
+        let body = rng.pick(&["", "word", "two words", "${x}", "// slashes"]);
+        comment(Some(rng.name()), body.to_owned(), CommentStyle::Trailing)
+    } else {
+        let body = rng.pick(&[
+            "",
+            "word",
+            " indented",
+            "two words",
+            "// slashes",
+            "not #a name",
+        ]);
+        comment(None, body.to_owned(), CommentStyle::Trailing)
+    }
+}
+
+fn line_comment(rng: &mut Rng) -> Comment {
+    let lines = (0..rng.below(3) + 1)
+        .map(|_| rng.pick(COMMENT_LINES))
+        .collect::<Vec<_>>();
+    if rng.one_in(2) {
+        comment(Some(rng.name()), lines.join("\n"), CommentStyle::Line)
+    } else {
+        // the first line of an unnamed comment can't look like a name
+        let body = lines.join("\n");
+        let body = if body.starts_with('#') {
+            format!("x{}", body)
+        } else {
+            body
+        };
+        comment(None, body, CommentStyle::Line)
+    }
+}
+
+fn block_comment(rng: &mut Rng) -> Comment {
+    let body = rng.text(BLOCK_COMMENT_PIECES, 6).replace("*/", "* /");
+    let body = if body.ends_with('*') {
+        format!("{} ", body)
+    } else {
+        body
+    };
+    if rng.one_in(2) {
+        comment(Some(rng.name()), body, CommentStyle::Block)
+    } else {
+        let body = if body.trim_start().starts_with('#') {
+            format!("x{}", body)
+        } else {
+            body
+        };
+        comment(None, body, CommentStyle::Block)
+    }
+}
+
+fn statement(rng: &mut Rng, depth: usize) -> Expr {
+    let leaf = depth >= MAX_DEPTH;
+    let statement = match rng.below(if leaf { 6 } else { 11 }) {
+        0 => return Expr::Comment(line_comment(rng)),
+        1 => return Expr::Comment(block_comment(rng)),
+        2 => {
+            return Expr::Assignment(Assignment {
+                r#ref: target(rng),
+                expr: Box::new(line_end(rng, depth + 1)),
+                span: Span::default(),
+            })
+        }
+        3 => {
+            return Expr::IndexAssignment(IndexAssignment {
+                r#ref: target(rng),
+                index: Box::new(term(rng, depth + 1)),
+                expr: Box::new(line_end(rng, depth + 1)),
+            })
+        }
+        4 => {
+            let names = rng.names(3);
+            return Expr::Destructure(Destructure {
+                pattern: if rng.one_in(2) {
+                    Pattern::Map(names)
+                } else {
+                    Pattern::List(names)
+                },
+                expr: Box::new(line_end(rng, depth + 1)),
+                span: Span::default(),
+            });
+        }
+        5 => term(rng, depth),
+        6 => Expr::While(While {
+            cond: Box::new(term(rng, depth + 1)),
+            block: block(rng, depth + 1),
+            check_first: true,
+            span: Span::default(),
+        }),
+        7 => Expr::While(While {
+            cond: Box::new(term(rng, depth + 1)),
+            block: block(rng, depth + 1),
+            check_first: false,
+            span: Span::default(),
+        }),
+        8 => Expr::Loop(block(rng, depth + 1), Span::default()),
+        9 => Expr::If(If {
+            cond: Box::new(term(rng, depth + 1)),
+            block: block(rng, depth + 1),
+            span: Span::default(),
+        }),
+        _ => Expr::FuncDef(FuncDef {
+            name: rng.name(),
+            arg_names: rng.names(3),
+            block: block(rng, depth + 1),
+            span: Span::default(),
+        }),
+    };
+    match rng.below(6) {
+        0 => Expr::TrailingComment(Box::new(statement), trailing_comment(rng)),
+        1 => Expr::ResultComment(0, Box::new(statement), Span::default()),
+        _ => statement,
+    }
+}
+
+// whether a line comment right after this would be read as part of it
+fn continues_into_next_line(expr: &Expr) -> bool {
+    match expr {
+        Expr::Comment(Comment {
+            style: CommentStyle::Line,
+            ..
+        })
+        | Expr::ResultComment(..) => true,
+        Expr::Assignment(Assignment { expr, .. })
+        | Expr::IndexAssignment(IndexAssignment { expr, .. })
+        | Expr::Destructure(Destructure { expr, .. }) => continues_into_next_line(expr),
+        _ => false,
+    }
+}
+
+fn block(rng: &mut Rng, depth: usize) -> Block {
+    let mut els = vec![];
+    if rng.one_in(4) {
+        els.push(BlockEl::NewLine);
+    }
+    let mut previous: Option<Expr> = None;
+    for _ in 0..rng.below(3) + 1 {
+        let expr = statement(rng, depth);
+        let is_line_comment = matches!(
+            expr,
+            Expr::Comment(Comment {
+                style: CommentStyle::Line,
+                ..
+            })
+        );
+        if is_line_comment && previous.as_ref().map_or(false, continues_into_next_line) {
+            els.push(BlockEl::NewLine);
+        }
+        els.push(BlockEl::Expr(expr.clone()));
+        els.push(BlockEl::NewLine);
+        if rng.one_in(4) {
+            els.push(BlockEl::NewLine);
+        }
+        previous = Some(expr);
+    }
+    Block(els)
+}
+
+#[test]
+fn generated_programs_roundtrip() {
+    for seed in 0..CASES {
+        let mut rng = Rng::new(seed);
+        let program = Program {
+            block: block(&mut rng, 0),
+        };
+        let source = output_code(&program, &Interpreter::new());
+        let reparsed = match parse_program(&source) {
+            Ok(reparsed) => reparsed,
+            Err(e) => panic!("seed {}: {}\n{}", seed, e, source),
+        };
+        assert!(
+            ast_eq(&program, &reparsed),
+            "seed {} reads back differently:\n{}\n{:#?}\n{:#?}",
+            seed,
+            source,
+            program,
+            reparsed
+        );
+        assert_eq!(
+            output_code(&reparsed, &Interpreter::new()),
+            source,
+            "seed {}",
+            seed
+        );
+    }
+}
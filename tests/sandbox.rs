@@ -0,0 +1,159 @@
+// Interpreters built with only some of the builtins.
+
+use zac_lib::error::SoldierError;
+use zac_lib::interp::{builtin_comment, Interpreter, InterpreterBuilder, Value};
+
+fn eval(interp: &mut Interpreter, source: &str) -> Result<Value, SoldierError> {
+    interp.run_source(source)
+}
+
+#[test]
+fn core_builtins_leave_out_print_and_exit() {
+    let mut interp = InterpreterBuilder::new().core_builtins().build();
+    for name in ["print", "exit"] {
+        assert!(matches!(
+            eval(&mut interp, &format!("{}(1)\n", name)),
+            Err(SoldierError::UndefinedName { name: undefined, .. }) if undefined == name
+        ));
+    }
+    assert!(matches!(
+        eval(&mut interp, "add(len(\"ab\"), 1)\n"),
+        Ok(Value::Int(3))
+    ));
+}
+
+#[test]
+fn without_removes_one_builtin() {
+    for builder in [
+        InterpreterBuilder::new()
+            .core_builtins()
+            .io_builtins()
+            .env_builtins()
+            .without("print"),
+        InterpreterBuilder::new()
+            .without("print")
+            .core_builtins()
+            .io_builtins()
+            .env_builtins(),
+    ] {
+        let mut interp = builder.build();
+        assert!(matches!(
+            eval(&mut interp, "print(1)\n"),
+            Err(SoldierError::UndefinedName { name, .. }) if name == "print"
+        ));
+        assert!(matches!(
+            eval(&mut interp, "env(\"HOME\")\n"),
+            Ok(Value::String(_) | Value::Null)
+        ));
+    }
+}
+
+#[test]
+fn nothing_but_constants() {
+    let mut interp = InterpreterBuilder::new().build();
+    assert!(matches!(eval(&mut interp, "true\n"), Ok(Value::Bool(true))));
+    assert!(matches!(
+        eval(&mut interp, "add(1, 2)\n"),
+        Err(SoldierError::UndefinedName { .. })
+    ));
+}
+
+#[test]
+fn help_lists_only_what_was_built_in() {
+    let sandboxed = InterpreterBuilder::new().core_builtins().build();
+    let help = builtin_comment(&sandboxed, "help").unwrap();
+    assert!(!help.contains("read_file"), "{}", help);
+    assert!(!help.contains("list_dir"), "{}", help);
+    assert!(help.contains("from_pairs"), "{}", help);
+
+    let default = Interpreter::new();
+    let help = builtin_comment(&default, "help").unwrap();
+    assert!(!help.contains("read_file"), "{}", help);
+    assert!(help.contains("from_pairs"), "{}", help);
+
+    let with_files = InterpreterBuilder::new()
+        .default_builtins()
+        .fs_builtins()
+        .build();
+    let help = builtin_comment(&with_files, "help").unwrap();
+    assert!(help.contains("read_file"), "{}", help);
+}
+
+#[test]
+fn files_need_fs_builtins() {
+    let mut interp = InterpreterBuilder::new()
+        .core_builtins()
+        .io_builtins()
+        .env_builtins()
+        .build();
+    for name in [
+        "read_file",
+        "write_file",
+        "append_file",
+        "file_exists",
+        "list_dir",
+    ] {
+        assert!(matches!(
+            eval(&mut interp, &format!("{}(\"a.txt\")\n", name)),
+            Err(SoldierError::UndefinedName { name: undefined, .. }) if undefined == name
+        ));
+    }
+    assert!(matches!(
+        eval(&mut interp, "env(\"HOME\")\n"),
+        Ok(Value::String(_) | Value::Null)
+    ));
+}
+
+#[test]
+fn env_and_args_need_env_builtins() {
+    let mut interp = InterpreterBuilder::new()
+        .core_builtins()
+        .io_builtins()
+        .build();
+    interp.set_args(vec!["a".into()]);
+    for (source, name) in [("env(\"HOME\")\n", "env"), ("args()\n", "args")] {
+        assert!(matches!(
+            eval(&mut interp, source),
+            Err(SoldierError::UndefinedName { name: undefined, .. }) if undefined == name
+        ));
+    }
+}
+
+#[test]
+fn env_and_args_are_not_in_a_new_interpreter() {
+    let mut interp = Interpreter::new();
+    for (source, name) in [("env(\"HOME\")\n", "env"), ("args()\n", "args")] {
+        assert!(matches!(
+            eval(&mut interp, source),
+            Err(SoldierError::UndefinedName { name: undefined, .. }) if undefined == name
+        ));
+    }
+}
+
+#[test]
+fn set_args() {
+    let mut interp = InterpreterBuilder::new()
+        .core_builtins()
+        .env_builtins()
+        .build();
+    assert!(matches!(eval(&mut interp, "args()\n"), Ok(Value::List(args)) if args.is_empty()));
+    interp.set_args(vec!["a".into(), "b c".into()]);
+    assert_eq!(
+        eval(&mut interp, "join(args(), \",\")\n")
+            .unwrap()
+            .to_string(),
+        "a,b c"
+    );
+}
+
+#[test]
+fn eval_can_be_left_out() {
+    let mut interp = InterpreterBuilder::new()
+        .core_builtins()
+        .without("eval")
+        .build();
+    assert!(matches!(
+        eval(&mut interp, "eval(\"1\")\n"),
+        Err(SoldierError::UndefinedName { name, .. }) if name == "eval"
+    ));
+}
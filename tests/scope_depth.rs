@@ -0,0 +1,103 @@
+// Runaway recursion and runaway scopes: the depth limit's error lists the calls that led to it,
+// and a scope with too many bindings is warned about.
+
+use zac_lib::interp::{Interpreter, Value};
+use zac_lib::parser::{parser, Expr};
+
+fn run(interp: &mut Interpreter, source: &str) -> Result<Value, String> {
+    let program = parser::program(source).unwrap();
+    interp
+        .interp(&Expr::Block(program.block))
+        .map_err(|err| err.to_string())
+}
+
+#[test]
+fn runaway_recursion_lists_the_calls() {
+    let mut interp = Interpreter::new();
+    interp.set_max_scope_depth(20);
+    let err = run(
+        &mut interp,
+        "defn down(n) {\n  down(n + 1)\n}\ndefn start() {\n  down(0)\n}\nstart()\n",
+    )
+    .unwrap_err();
+    assert!(
+        err.contains("maximum scope depth of 20 exceeded calling down"),
+        "{}",
+        err
+    );
+    // only the most recent calls, which are all the same one
+    assert_eq!(err.matches("\n  in down").count(), 10, "{}", err);
+    assert!(!err.contains("in start"), "{}", err);
+    assert_eq!(interp.peak_depth(), 20);
+    assert_eq!(interp.stats().peak_depth, 20);
+    // and the depth goes back down
+    assert_eq!(interp.stats().depth, 1);
+}
+
+#[test]
+fn peak_depth_is_the_deepest_call() {
+    let mut interp = Interpreter::new();
+    assert_eq!(interp.peak_depth(), 1);
+    run(
+        &mut interp,
+        "defn count(n) {\n  if (n > 0) {\n    count(n - 1)\n  }\n}\ncount(5)\ncount(2)\n",
+    )
+    .unwrap();
+    assert_eq!(interp.peak_depth(), 7);
+}
+
+// 10k variables with made up names
+fn many_variables() -> String {
+    (0..10_000)
+        .map(|i| format!("let v{} = {}\n", i, i))
+        .collect()
+}
+
+#[test]
+fn a_scope_with_too_many_bindings_is_warned_about() {
+    let mut interp = Interpreter::new();
+    assert_eq!(
+        run(&mut interp, &format!("{}v9999\n", many_variables())),
+        Ok(Value::Int(9999))
+    );
+    // once, not for every binding past the limit
+    assert_eq!(
+        interp.warnings(),
+        ["a single scope has more than 1000 bindings, are names being generated in a loop?"]
+    );
+
+    let mut interp = Interpreter::new();
+    interp.set_max_scope_bindings(20_000);
+    run(&mut interp, &many_variables()).unwrap();
+    assert!(interp.warnings().is_empty(), "{:?}", interp.warnings());
+}
+
+#[test]
+fn a_scope_with_few_bindings_is_not() {
+    let mut interp = Interpreter::new();
+    run(
+        &mut interp,
+        "let i = 0\nwhile (i < 5000) {\n  let i = i + 1\n}\n",
+    )
+    .unwrap();
+    assert!(interp.warnings().is_empty(), "{:?}", interp.warnings());
+}
+
+#[test]
+fn variables_made_by_eval_are_warned_about() {
+    let mut interp = Interpreter::new();
+    let source = "let b = builder()
+let i = 0
+while (i < 10000) {
+  builder_push(b, \"let v${i} = ${i}\\n\")
+  let i = i + 1
+}
+eval(builder_finish(b))
+v9999
+";
+    assert_eq!(run(&mut interp, source), Ok(Value::Int(9999)));
+    assert_eq!(
+        interp.warnings(),
+        ["a single scope has more than 1000 bindings, are names being generated in a loop?"]
+    );
+}
@@ -0,0 +1,143 @@
+// Serializing Values and passing Rust types in and out of programs, with the serde feature.
+#![cfg(feature = "serde")]
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use zac_lib::error::SoldierError;
+use zac_lib::interp::{Interpreter, Value};
+use zac_lib::parser::{parse_program, Assignment, Expr, FunctionCall};
+use zac_lib::reassemble::output_code;
+use zac_lib::value_serde::{from_value, to_value};
+
+fn map(entries: Vec<(Value, Value)>) -> Value {
+    Value::Map(entries.into_iter().collect())
+}
+
+#[test]
+fn nested_maps_round_trip() {
+    let value = map(vec![
+        ("name".into(), "zac".into()),
+        (
+            "limits".into(),
+            map(vec![
+                ("fuel".into(), 1000.into()),
+                ("depth".into(), map(vec![("max".into(), (-5).into())])),
+            ]),
+        ),
+        (
+            "tags".into(),
+            vec![Value::from("a"), true.into(), Value::Null].into(),
+        ),
+        ("big".into(), i128::MAX.into()),
+    ]);
+    let back: Value = from_value(to_value(&value).unwrap()).unwrap();
+    assert_eq!(back, value);
+}
+
+#[test]
+fn values_go_through_as_themselves() {
+    // a Value serialized into a Value is the same Value
+    let value = map(vec![("a".into(), vec![1, 2].into())]);
+    assert_eq!(to_value(&value).unwrap(), value);
+}
+
+#[test]
+fn maps_with_other_keys_become_lists_of_pairs() {
+    let value = map(vec![(1.into(), "one".into()), ("two".into(), 2.into())]);
+    assert_eq!(
+        to_value(&value).unwrap(),
+        Value::List(vec![
+            vec![Value::from(1), "one".into()].into(),
+            vec![Value::from("two"), 2.into()].into(),
+        ])
+    );
+}
+
+#[test]
+fn functions_cant_be_serialized() {
+    let add = Interpreter::new().run_source("add\n").unwrap();
+    let err = to_value(&vec![add]).unwrap_err();
+    assert!(matches!(err, SoldierError::Other { .. }), "{:?}", err);
+    assert_eq!(err.to_string(), "can't serialize the function add");
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+enum Shape {
+    Dot,
+    Circle { radius: u32 },
+    Square(u32),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct Config {
+    name: String,
+    retries: u8,
+    verbose: bool,
+    shapes: Vec<Shape>,
+    extra: Option<BTreeMap<String, i64>>,
+}
+
+#[test]
+fn rust_types_round_trip_through_a_program() {
+    let config = Config {
+        name: "zac".to_owned(),
+        retries: 3,
+        verbose: false,
+        shapes: vec![Shape::Dot, Shape::Circle { radius: 2 }, Shape::Square(4)],
+        extra: None,
+    };
+    let mut interp = Interpreter::new();
+    let given = config.clone();
+    interp.register_fn("config", move |_| Ok(to_value(&given)?));
+    let source = "let c = config()\nlet c(\"retries\") = c.retries + 1\nc\n";
+    let back: Config = from_value(interp.run_source(source).unwrap()).unwrap();
+    assert_eq!(
+        back,
+        Config {
+            retries: 4,
+            ..config
+        }
+    );
+}
+
+#[test]
+fn deserializing_the_wrong_shape_fails() {
+    let err = from_value::<Config>(map(vec![("name".into(), 1.into())])).unwrap_err();
+    assert!(matches!(err, SoldierError::Other { .. }), "{:?}", err);
+    let err = from_value::<u8>(300.into()).unwrap_err();
+    assert!(err.to_string().contains("300"), "{}", err);
+    let add = Interpreter::new().run_source("add\n").unwrap();
+    assert!(from_value::<Value>(add).is_err());
+}
+
+#[test]
+fn programs_round_trip() {
+    let source = "// #notes\n// hi\nlet x = add(1, 2)\nwhile (x < 5) {\n  let x = x + 1\n}\nprint(x, #notes)\n";
+    let program = parse_program(source).unwrap();
+    let value = to_value(&program).unwrap();
+    // the same shape as the description format
+    let map = BTreeMap::<Value, Value>::try_from(value.clone()).unwrap();
+    assert_eq!(map[&Value::from("version")], Value::Int(1));
+    let back: zac_lib::parser::Program = from_value(value).unwrap();
+    let interp = Interpreter::new();
+    assert_eq!(output_code(&back, &interp), output_code(&program, &interp));
+}
+
+#[test]
+fn parts_of_expressions() {
+    let program = parse_program("let x = f(1)\n").unwrap();
+    let assign = match program.block.exprs().next().unwrap() {
+        Expr::Assignment(assign) => assign.clone(),
+        otherwise => panic!("{:?}", otherwise),
+    };
+    let value = to_value(&assign).unwrap();
+    let back: Assignment = from_value(value.clone()).unwrap();
+    // the description format doesn't keep where things were in the source
+    assert_eq!(to_value(&back).unwrap(), value);
+    assert!(matches!(*back.expr, Expr::FunctionCall(_)));
+    let err = from_value::<FunctionCall>(value).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "$.type: expected a FunctionCall expression, got \"assign\""
+    );
+}
@@ -0,0 +1,51 @@
+// Where each kind of expression is in the source it was parsed from.
+
+use zac_lib::parser::{ast_eq, parse_program, Expr};
+
+// the part of source the first expression in it was parsed from
+fn first(source: &str) -> &str {
+    let program = parse_program(source).unwrap();
+    let span = program.block.exprs().next().unwrap().span().unwrap();
+    &source[span.start..span.end]
+}
+
+#[test]
+fn every_kind_of_expression_has_one() {
+    for source in [
+        "123",
+        "\"hi\"",
+        "\"hi {x}\"",
+        "[1, 2]",
+        "1 + 2",
+        "a.b.c",
+        "x",
+        "f(1)",
+        "let x = 1",
+        "let [a, b] = [1, 2]",
+        "if (x) {\n  1\n}",
+        "while (x) {\n  1\n}",
+        "loop {\n  exit()\n}",
+        "defn f(a) {\n  a\n}",
+    ] {
+        assert_eq!(first(&format!("{}\n", source)), source);
+    }
+}
+
+#[test]
+fn only_up_to_each_field() {
+    let program = parse_program("a.b.c\n").unwrap();
+    let inner = match program.block.exprs().next().unwrap() {
+        Expr::FieldAccess(access) => access.expr.span().unwrap(),
+        otherwise => panic!("{:?}", otherwise),
+    };
+    assert_eq!((inner.start, inner.end), (0, 3));
+}
+
+#[test]
+fn the_same_program_elsewhere_is_still_the_same() {
+    let a = parse_program("let x = [1, 2]\nif (x) {\n  print(x + 1)\n}\n").unwrap();
+    let b = parse_program("let x   =   [1,2]\nif (x) {\n    print(x+1)\n}\n").unwrap();
+    assert!(ast_eq(&a, &b));
+    let c = parse_program("let x = [1, 2]\nif (x) {\n  print(x + 2)\n}\n").unwrap();
+    assert!(!ast_eq(&a, &c));
+}
@@ -0,0 +1,183 @@
+// Runs every program in spec/ and checks it against the expectations written in its own named
+// comments:
+//
+//   // #expect_value   the {:?} of the value of the last expression in the program
+//   // #expect_output  everything the program printed
+//   // #expect_error   text the error the program stops with has to contain, as shown by
+//                     render_error, so it can start with the line and column
+//
+// Expectations go at the top of the file, since a comment at the end would be the program's
+// last expression. A program that doesn't parse can only have an #expect_error, and its
+// expected text has to fit on one line. Programs run without the real filesystem or
+// environment: files live in a MemFs, and the only environment variable is ZAC_SPEC=1.
+
+use std::cell::RefCell;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::rc::Rc;
+use zac_lib::error::render_error;
+use zac_lib::interp::InterpreterBuilder;
+use zac_lib::parser::{find_comments_mut, parse_program};
+use zac_lib::vfs::MemFs;
+
+#[derive(Clone, Default)]
+struct Captured(Rc<RefCell<Vec<u8>>>);
+
+impl Write for Captured {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.borrow_mut().write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+struct Expectations {
+    value: Option<String>,
+    output: Option<String>,
+    error: Option<String>,
+}
+
+fn run_spec(path: &Path) -> Result<(), String> {
+    let source = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let mut program = match parse_program(&source) {
+        Ok(program) => program,
+        Err(err) => return check_parse_error(&source, &err.to_string()),
+    };
+    let mut interp = InterpreterBuilder::new()
+        .default_builtins()
+        .env_builtins()
+        .time_builtins()
+        .fs_builtins()
+        .build();
+    interp.set_source(&source);
+    interp.set_fs(Box::new(MemFs::new()));
+    interp.set_env(Box::new(|name| {
+        (name == "ZAC_SPEC").then(|| "1".to_string())
+    }));
+    let output = Captured::default();
+    interp.set_output(Box::new(output.clone()));
+
+    let mut expectations = Expectations {
+        value: None,
+        output: None,
+        error: None,
+    };
+    for (name, comment) in find_comments_mut(&mut program).map_err(|e| e.to_string())? {
+        match name.as_str() {
+            "expect_value" => expectations.value = Some(comment.body.clone()),
+            "expect_output" => expectations.output = Some(comment.body.clone()),
+            "expect_error" => expectations.error = Some(comment.body.clone()),
+            _ => {}
+        }
+    }
+    if expectations.value.is_none() && expectations.output.is_none() && expectations.error.is_none()
+    {
+        return Err("has no #expect_value, #expect_output or #expect_error comment".into());
+    }
+
+    let result = interp.run_program(&program);
+    let printed = String::from_utf8_lossy(&output.0.borrow()).into_owned();
+    let mut mismatches = vec![];
+    match (&result, &expectations.error) {
+        (Ok(value), None) => {
+            if let Some(expected) = &expectations.value {
+                let actual = format!("{:?}", value);
+                if &actual != expected {
+                    mismatches.push(("value", expected.clone(), actual));
+                }
+            }
+        }
+        (Ok(value), Some(expected)) => mismatches.push((
+            "error",
+            expected.clone(),
+            format!("no error, the value was {:?}", value),
+        )),
+        (Err(err), Some(expected)) => {
+            let rendered = render_error(&source, err);
+            if !rendered.contains(expected.as_str()) {
+                mismatches.push(("error", expected.clone(), rendered));
+            }
+        }
+        (Err(err), None) => {
+            mismatches.push(("value", "no error".into(), render_error(&source, err)))
+        }
+    }
+    if let Some(expected) = &expectations.output {
+        if printed.trim_end() != expected.trim_end() {
+            mismatches.push(("output", expected.clone(), printed));
+        }
+    }
+    if mismatches.is_empty() {
+        return Ok(());
+    }
+    let mut report = format!("program:\n{}\n", indent(&source));
+    for (what, expected, actual) in mismatches {
+        report.push_str(&format!(
+            "expected {}:\n{}\nactual {}:\n{}\n",
+            what,
+            indent(&expected),
+            what,
+            indent(&actual)
+        ));
+    }
+    Err(report)
+}
+
+// a program that doesn't parse has no comments to read expectations from, so its #expect_error
+// is found in the source text, as the line after `// #expect_error`
+fn check_parse_error(source: &str, err: &str) -> Result<(), String> {
+    let expected = source
+        .lines()
+        .skip_while(|line| line.trim() != "// #expect_error")
+        .nth(1)
+        .and_then(|line| line.strip_prefix("// "));
+    match expected {
+        Some(expected) if err.contains(expected) => Ok(()),
+        Some(expected) => Err(format!(
+            "program:\n{}\nexpected error:\n{}\nactual error:\n{}\n",
+            indent(source),
+            indent(expected),
+            indent(err)
+        )),
+        None => Err(format!("doesn't parse: {}", err)),
+    }
+}
+
+fn indent(s: &str) -> String {
+    s.lines()
+        .map(|line| format!("    {}", line))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[test]
+fn spec() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("spec");
+    let mut paths = fs::read_dir(&dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().map_or(false, |ext| ext == "zac"))
+        .collect::<Vec<_>>();
+    paths.sort();
+    assert!(!paths.is_empty(), "no programs in {}", dir.display());
+
+    let failures = paths
+        .iter()
+        .filter_map(|path| {
+            run_spec(path)
+                .err()
+                .map(|report| format!("--- {}\n{}", path.display(), report))
+        })
+        .collect::<Vec<_>>();
+    if !failures.is_empty() {
+        panic!(
+            "{} of {} spec programs failed\n\n{}",
+            failures.len(),
+            paths.len(),
+            failures.join("\n")
+        );
+    }
+}
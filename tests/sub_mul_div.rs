@@ -0,0 +1,86 @@
+// sub, mul and div, which give errors instead of overflowing or dividing by zero.
+
+use zac_lib::interp::{Interpreter, Value};
+use zac_lib::parser::{parser, Expr};
+
+fn run(source: &str) -> Result<Value, String> {
+    let program = parser::program(source).unwrap();
+    Interpreter::new()
+        .interp(&Expr::Block(program.block))
+        .map_err(|err| err.to_string())
+}
+
+fn ints(source: &str) -> Vec<i128> {
+    match run(source) {
+        Ok(Value::List(vals)) => vals
+            .iter()
+            .map(|val| match val {
+                Value::Int(n) => *n,
+                other => panic!("{:?}", other),
+            })
+            .collect(),
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn sub() {
+    assert_eq!(
+        ints("[sub(5, 3), sub(3, 5), sub(-3, -5), sub(0, 0)]\n"),
+        [2, -2, 2, 0]
+    );
+}
+
+#[test]
+fn mul() {
+    assert_eq!(
+        ints("[mul(6, 7), mul(-6, 7), mul(-6, -7), mul(0, -7)]\n"),
+        [42, -42, 42, 0]
+    );
+}
+
+#[test]
+fn div() {
+    assert_eq!(ints("[div(7, 2), div(6, 3), div(0, 5)]\n"), [3, 2, 0]);
+    // rounding so the remainder is never negative, like mod
+    assert_eq!(ints("[div(-7, 2), div(7, -2), div(-7, -2)]\n"), [-4, -3, 4]);
+}
+
+#[test]
+fn div_by_zero_is_an_error() {
+    let err = run("div(5, 0)\n").unwrap_err();
+    assert!(err.contains("the divisor can't be zero"), "{}", err);
+}
+
+#[test]
+fn overflowing_is_an_error() {
+    // i128::MIN, which can't be written as a literal
+    let min = format!("sub({}, 1)", i128::MIN + 1);
+    assert_eq!(run(&format!("{}\n", min)), Ok(Value::Int(i128::MIN)));
+    for source in [
+        format!("sub({}, 1)\n", min),
+        format!("mul({}, 2)\n", i128::MAX),
+        format!("div({}, -1)\n", min),
+    ] {
+        let err = run(&source).unwrap_err();
+        assert!(err.contains("integer overflow in"), "{}: {}", source, err);
+    }
+}
+
+#[test]
+fn in_the_help_text() {
+    let help = zac_lib::run("// #help\n// nothing yet\n").unwrap();
+    let words: Vec<&str> = help.split_whitespace().collect();
+    for name in ["sub", "mul", "div"] {
+        assert!(words.contains(&name), "{}", help);
+    }
+    assert!(help.contains("div: div(a, b) divides a by b"), "{}", help);
+    for name in ["add", "sub", "mul"] {
+        let doc = zac_lib::run(&format!("// #doc\n// x\nlet #doc = doc({})\n", name)).unwrap();
+        assert!(
+            doc.contains("It's an error if the result overflows"),
+            "{}",
+            doc
+        );
+    }
+}
@@ -0,0 +1,97 @@
+// now, now_mono and sleep, which are about real time so can't be spec programs.
+
+use std::time::{Duration, Instant};
+use zac_lib::error::SoldierError;
+use zac_lib::interp::{Interpreter, InterpreterBuilder, Value};
+
+fn with_time() -> Interpreter {
+    InterpreterBuilder::new()
+        .core_builtins()
+        .time_builtins()
+        .build()
+}
+
+#[test]
+fn now_is_after_2020() {
+    let now = with_time().run_source("now()\n").unwrap();
+    assert!(
+        matches!(now, Value::Int(ms) if ms > 1_577_836_800_000),
+        "{:?}",
+        now
+    );
+}
+
+#[test]
+fn now_mono_never_goes_backwards() {
+    let mut interp = with_time();
+    let times = interp
+        .run_source("let times = []\nlet i = 0\nwhile (i < 100) {\n  let times = push(times, now_mono())\n  let i = i + 1\n}\ntimes\n")
+        .unwrap();
+    let Value::List(times) = times else {
+        panic!("{:?}", times);
+    };
+    assert_eq!(times.len(), 100);
+    assert!(
+        times.windows(2).all(|pair| pair[0] <= pair[1]),
+        "{:?}",
+        times
+    );
+}
+
+#[test]
+fn sleep() {
+    let mut interp = with_time();
+    let started = Instant::now();
+    let slept = interp
+        .run_source("let before = now_mono()\nsleep(50)\nnow_mono() - before\n")
+        .unwrap();
+    assert!(started.elapsed() >= Duration::from_millis(50));
+    assert!(matches!(slept, Value::Int(ms) if ms >= 50), "{:?}", slept);
+}
+
+#[test]
+fn sleep_is_stopped_by_fuel() {
+    let mut interp = with_time();
+    // enough to get as far as calling sleep, but none left after
+    interp.set_fuel(Some(3));
+    let started = Instant::now();
+    let err = interp.run_source("sleep(10000)\n").unwrap_err();
+    assert!(
+        matches!(err, SoldierError::FuelExhausted { .. }),
+        "{:?}",
+        err
+    );
+    assert!(started.elapsed() < Duration::from_secs(5));
+
+    // and sleeping doesn't burn any more than other calls
+    interp.set_fuel(Some(10));
+    interp.run_source("sleep(1)\n").unwrap();
+    let after_sleep = interp.fuel_remaining().unwrap();
+    interp.set_fuel(Some(10));
+    interp.run_source("abs(1)\n").unwrap();
+    assert_eq!(after_sleep, interp.fuel_remaining().unwrap());
+}
+
+#[test]
+fn time_needs_time_builtins() {
+    let mut interp = InterpreterBuilder::new()
+        .core_builtins()
+        .io_builtins()
+        .build();
+    for name in ["now", "now_mono", "sleep"] {
+        assert!(matches!(
+            interp.run_source(&format!("{}(1)\n", name)),
+            Err(SoldierError::UndefinedName { name: undefined, .. }) if undefined == name
+        ));
+    }
+}
+
+#[test]
+fn not_in_a_new_interpreter() {
+    let err = Interpreter::new().run_source("sleep(1)\n").unwrap_err();
+    assert!(
+        matches!(&err, SoldierError::UndefinedName { name, .. } if name == "sleep"),
+        "{:?}",
+        err
+    );
+}
@@ -0,0 +1,230 @@
+// The JSON lines trace format, read the way an external tool would: as plain JSON, without any
+// help from the interpreter.
+
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fs;
+use std::iter::Peekable;
+use std::path::Path;
+use std::rc::Rc;
+use std::str::Chars;
+use zac_lib::interp::Interpreter;
+use zac_lib::parser::{find_comments_mut, parser, Expr};
+use zac_lib::trace::{TraceFormat, Tracer, DEFAULT_MAX_VALUE_LEN, TRACE_VERSION};
+
+const KINDS: [&str; 7] = [
+    "expr_start",
+    "expr_end",
+    "var_write",
+    "comment_write",
+    "call_enter",
+    "call_exit",
+    "loop_iteration",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Json {
+    Null,
+    Bool(bool),
+    Int(i128),
+    String(String),
+    Array(Vec<Json>),
+    Object(BTreeMap<String, Json>),
+}
+
+// just enough JSON for the trace: no floats, and \u escapes only for single code points
+fn parse_json(text: &str) -> Result<Json, String> {
+    let mut chars = text.chars().peekable();
+    let json = parse_value(&mut chars)?;
+    match chars.next() {
+        None => Ok(json),
+        Some(c) => Err(format!("{:?} after the value", c)),
+    }
+}
+
+fn parse_value(chars: &mut Peekable<Chars>) -> Result<Json, String> {
+    match chars.next().ok_or("ran out")? {
+        'n' => expect(chars, "ull").map(|_| Json::Null),
+        't' => expect(chars, "rue").map(|_| Json::Bool(true)),
+        'f' => expect(chars, "alse").map(|_| Json::Bool(false)),
+        '"' => parse_string(chars).map(Json::String),
+        '[' => {
+            let mut items = vec![];
+            if chars.peek() == Some(&']') {
+                chars.next();
+                return Ok(Json::Array(items));
+            }
+            loop {
+                items.push(parse_value(chars)?);
+                match chars.next() {
+                    Some(',') => {}
+                    Some(']') => return Ok(Json::Array(items)),
+                    c => return Err(format!("{:?} in an array", c)),
+                }
+            }
+        }
+        '{' => {
+            let mut fields = BTreeMap::new();
+            if chars.peek() == Some(&'}') {
+                chars.next();
+                return Ok(Json::Object(fields));
+            }
+            loop {
+                expect(chars, "\"")?;
+                let key = parse_string(chars)?;
+                expect(chars, ":")?;
+                if fields.insert(key.clone(), parse_value(chars)?).is_some() {
+                    return Err(format!("{} is there twice", key));
+                }
+                match chars.next() {
+                    Some(',') => {}
+                    Some('}') => return Ok(Json::Object(fields)),
+                    c => return Err(format!("{:?} in an object", c)),
+                }
+            }
+        }
+        c if c == '-' || c.is_ascii_digit() => {
+            let mut digits = c.to_string();
+            while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+                digits.push(c);
+            }
+            digits.parse().map(Json::Int).map_err(|err| err.to_string())
+        }
+        c => Err(format!("{:?} can't start a value", c)),
+    }
+}
+
+fn parse_string(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    let mut s = String::new();
+    loop {
+        match chars.next().ok_or("unterminated string")? {
+            '"' => return Ok(s),
+            '\\' => s.push(match chars.next().ok_or("unterminated escape")? {
+                'n' => '\n',
+                't' => '\t',
+                'r' => '\r',
+                'u' => {
+                    let hex: String = chars.by_ref().take(4).collect();
+                    let code = u32::from_str_radix(&hex, 16).map_err(|err| err.to_string())?;
+                    char::from_u32(code).ok_or("not a code point")?
+                }
+                c => c,
+            }),
+            c if (c as u32) < 0x20 => return Err(format!("unescaped {:?} in a string", c)),
+            c => s.push(c),
+        }
+    }
+}
+
+fn expect(chars: &mut Peekable<Chars>, s: &str) -> Result<(), String> {
+    for expected in s.chars() {
+        if chars.next() != Some(expected) {
+            return Err(format!("expected {:?}", s));
+        }
+    }
+    Ok(())
+}
+
+fn jsonl_trace(source: &str, max_value_len: usize) -> String {
+    let tracer = Rc::new(RefCell::new(Tracer::new(max_value_len)));
+    let mut interp = Interpreter::new();
+    interp.set_tracer(Rc::clone(&tracer));
+    let mut program = parser::program(source).unwrap();
+    for (_, comment) in find_comments_mut(&mut program).unwrap() {
+        interp.add_comment(comment).unwrap();
+    }
+    interp.interp(&Expr::Block(program.block)).unwrap();
+    let rendered = tracer.borrow().render(TraceFormat::Jsonl);
+    rendered
+}
+
+fn parse_lines(trace: &str) -> Vec<BTreeMap<String, Json>> {
+    trace
+        .lines()
+        .map(|line| match parse_json(line) {
+            Ok(Json::Object(fields)) => fields,
+            otherwise => panic!("{}: {:?}", line, otherwise),
+        })
+        .collect()
+}
+
+// a field that's a string, or "null" when it's null or not there
+fn field(event: &BTreeMap<String, Json>, name: &str) -> String {
+    match event.get(name) {
+        Some(Json::String(s)) => s.clone(),
+        None | Some(Json::Null) => "null".to_owned(),
+        Some(other) => panic!("{} is {:?}", name, other),
+    }
+}
+
+#[test]
+fn every_line_of_a_fixture_parses() {
+    let fib =
+        fs::read_to_string(Path::new(env!("CARGO_MANIFEST_DIR")).join("examples/fib.zac")).unwrap();
+    let trace = jsonl_trace(&fib, DEFAULT_MAX_VALUE_LEN);
+    assert!(trace.ends_with('\n'));
+    let events = parse_lines(&trace);
+    assert!(events.len() > 100, "{}", events.len());
+    let mut depth = 0;
+    for (seq, event) in events.iter().enumerate() {
+        assert_eq!(event["version"], Json::Int(TRACE_VERSION));
+        assert_eq!(event["seq"], Json::Int(seq as i128));
+        assert!(event.contains_key("span"));
+        let kind = field(event, "kind");
+        assert!(KINDS.contains(&kind.as_str()), "{}", kind);
+        match kind.as_str() {
+            "expr_start" => depth += 1,
+            "expr_end" => depth -= 1,
+            _ => {}
+        }
+        assert!(depth >= 0);
+    }
+    assert_eq!(depth, 0);
+}
+
+#[test]
+fn events_come_in_the_order_they_happened() {
+    let source = "// #log\n// start\n\ndefn twice(n) {\n  n * 2\n}\nlet i = 0\nwhile (i < 2) {\n  let #log = twice(i)\n  let i = i + 1\n}\n";
+    let events = parse_lines(&jsonl_trace(source, DEFAULT_MAX_VALUE_LEN));
+    let outside_expressions: Vec<String> = events
+        .iter()
+        .filter(|event| !field(event, "kind").starts_with("expr_"))
+        .map(|event| {
+            format!(
+                "{} {} {}",
+                field(event, "kind"),
+                field(event, "name"),
+                field(event, "value")
+            )
+        })
+        .collect();
+    assert_eq!(
+        outside_expressions,
+        [
+            "var_write twice <function twice>",
+            "var_write i 0",
+            "loop_iteration null 0",
+            "call_enter twice null",
+            "var_write n 0",
+            "call_exit twice 0",
+            "comment_write log 0",
+            "var_write i 1",
+            "loop_iteration null 1",
+            "call_enter twice null",
+            "var_write n 1",
+            "call_exit twice 2",
+            "comment_write log 2",
+            "var_write i 2",
+        ]
+    );
+}
+
+#[test]
+fn values_are_cut_off() {
+    let events = parse_lines(&jsonl_trace("let s = \"abcdefghij\"\n", 4));
+    let write = events
+        .iter()
+        .find(|event| field(event, "kind") == "var_write")
+        .unwrap();
+    assert_eq!(field(write, "value"), "abcd...");
+}
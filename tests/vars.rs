@@ -0,0 +1,37 @@
+// Passing values into a program and reading them back out.
+
+use zac_lib::interp::{Interpreter, Value};
+
+#[test]
+fn inject_and_read_back() {
+    let mut interp = Interpreter::new();
+    interp.set_var("input", 21.into());
+    interp.run_source("let result = input * 2\n").unwrap();
+    assert_eq!(interp.get_var("result"), Some(Value::Int(42)));
+    assert_eq!(interp.get_var("input"), Some(Value::Int(21)));
+    assert_eq!(interp.get_var("nope"), None);
+}
+
+#[test]
+fn only_top_level_variables() {
+    let mut interp = Interpreter::new();
+    interp
+        .run_source("defn f() {\n  let inner = 1\n  inner\n}\nlet outer = f()\n")
+        .unwrap();
+    assert_eq!(interp.get_var("outer"), Some(Value::Int(1)));
+    assert_eq!(interp.get_var("inner"), None);
+}
+
+#[test]
+fn listing_variables() {
+    let mut interp = Interpreter::new();
+    interp.set_var("b", "two".into());
+    interp.set_var("a", 1.into());
+    let vars = interp.vars();
+    let a = vars.iter().position(|(name, _)| name == "a").unwrap();
+    let b = vars.iter().position(|(name, _)| name == "b").unwrap();
+    assert!(a < b);
+    assert_eq!(vars[b].1, Value::from("two"));
+    // the builtins live alongside them
+    assert!(vars.iter().any(|(name, _)| name == "add"));
+}